@@ -0,0 +1,81 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use serde_json::{json, Value};
+
+/**
+ * A document with `len` records, where every tenth record differs between
+ * `before` and `after`.
+ */
+fn documents(len: usize) -> (Value, Value) {
+    let record = |i: usize, version: usize| {
+        json!({
+            "id": i,
+            "name": format!("record {i}"),
+            "tags": ["a", "b", "c"],
+            "nested": { "version": version, "payload": "x".repeat(256) },
+        })
+    };
+    let before = (0..len).map(|i| (i.to_string(), record(i, 0))).collect();
+    let after = (0..len)
+        .map(|i| (i.to_string(), record(i, usize::from(i % 10 == 0))))
+        .collect();
+    (Value::Object(before), Value::Object(after))
+}
+
+/**
+ * An object with `len` scalar members, where every tenth member differs
+ * between `before` and `after` and every hundredth is removed.
+ */
+fn flat_documents(len: usize) -> (Value, Value) {
+    let before = (0..len).map(|i| (format!("key {i}"), json!(i))).collect();
+    let after = (0..len)
+        .filter(|i| i % 100 != 1)
+        .map(|i| (format!("key {i}"), json!(i + usize::from(i % 10 == 0))))
+        .collect();
+    (Value::Object(before), Value::Object(after))
+}
+
+fn bench_flat(c: &mut Criterion) {
+    let (before, after) = flat_documents(10_000);
+    let mut group = c.benchmark_group("flat");
+
+    group.bench_function("generate", |b| {
+        b.iter(|| json_merge_patch_gen::generate(&before, &after))
+    });
+    let unchanged = before.clone();
+    group.bench_function("generate_unchanged", |b| {
+        b.iter(|| json_merge_patch_gen::generate(&before, &unchanged))
+    });
+    group.bench_function("generate_same", |b| {
+        b.iter(|| json_merge_patch_gen::generate(&before, &before))
+    });
+
+    group.finish();
+}
+
+fn bench_generate(c: &mut Criterion) {
+    let (before, after) = documents(10_000);
+    let mut group = c.benchmark_group("generate");
+
+    group.bench_function("generate", |b| {
+        b.iter(|| json_merge_patch_gen::generate(&before, &after))
+    });
+    group.bench_function("generate_ref", |b| {
+        b.iter(|| json_merge_patch_gen::generate_ref(&before, &after))
+    });
+    group.bench_function("generate_owned", |b| {
+        b.iter_batched(
+            || (before.clone(), after.clone()),
+            |(before, after)| json_merge_patch_gen::generate_owned(before, after),
+            BatchSize::LargeInput,
+        )
+    });
+    #[cfg(feature = "rayon")]
+    group.bench_function("par_generate", |b| {
+        b.iter(|| json_merge_patch_gen::par_generate(&before, &after))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_generate, bench_flat);
+criterion_main!(benches);
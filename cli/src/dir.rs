@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use json_merge_patch_gen::DocumentChange;
+use serde_json::{Map, Value};
+
+use crate::{file_text, print, read, Format};
+
+/// Diffs the `*.json` files with the same paths under `old` and `new`.
+///
+/// Without `out`, prints a manifest of the files that were `added`, with
+/// their documents, `patched`, with their patches, and `removed`. With `out`,
+/// writes the patches of the patched files and the documents of the added
+/// ones to the same paths under it, and lists the changed files like
+/// `git diff --name-status`.
+pub fn generate(old: &Path, new: &Path, out: Option<&Path>, format: &Format) -> Result<(), String> {
+    let (befores, afters) = (documents(old)?, documents(new)?);
+    let changes = json_merge_patch_gen::generate_batch_keyed(&befores, &afters);
+
+    let Some(out) = out else {
+        let (mut added, mut patched, mut removed) = (Map::new(), Map::new(), Vec::new());
+        for (path, change) in changes {
+            match change {
+                DocumentChange::Added(document) => {
+                    added.insert(path.clone(), document.clone());
+                }
+                DocumentChange::Patched(patch) => {
+                    patched.insert(path.clone(), patch);
+                }
+                DocumentChange::Removed => removed.push(Value::String(path.clone())),
+            }
+        }
+        let manifest = serde_json::json!({
+            "added": added,
+            "patched": patched,
+            "removed": removed,
+        });
+        return print(&manifest, format);
+    };
+
+    let mut stdout = io::stdout().lock();
+    for (path, change) in &changes {
+        let (status, written) = match change {
+            DocumentChange::Added(document) => ("A", Some(*document)),
+            DocumentChange::Patched(patch) => ("M", Some(patch)),
+            DocumentChange::Removed => ("D", None),
+        };
+        if let Some(value) = written {
+            write(&out.join(path), value, format)?;
+        }
+        writeln!(stdout, "{status}\t{path}")
+            .map_err(|error| format!("failed to write the output: {error}"))?;
+    }
+    Ok(())
+}
+
+/// The `*.json` files under `root`, by their paths relative to it with `/`
+/// separators. Symlinks to files are followed, but not ones to directories.
+fn documents(root: &Path) -> Result<BTreeMap<String, Value>, String> {
+    let mut documents = BTreeMap::new();
+    let mut directories = vec![PathBuf::new()];
+    while let Some(relative) = directories.pop() {
+        let directory = root.join(&relative);
+        let entries = fs::read_dir(&directory)
+            .map_err(|error| format!("failed to read {}: {error}", directory.display()))?;
+        for entry in entries {
+            let entry = entry
+                .map_err(|error| format!("failed to read {}: {error}", directory.display()))?;
+            let (path, relative) = (entry.path(), relative.join(entry.file_name()));
+            let file_type = entry
+                .file_type()
+                .map_err(|error| format!("failed to read {}: {error}", path.display()))?;
+            if file_type.is_dir() {
+                directories.push(relative);
+            } else if file_type.is_symlink() && path.is_dir() {
+                // Linked directories could lead back to where they are
+                eprintln!(
+                    "json-merge-patch: skipping {}, a symlink to a directory",
+                    path.display()
+                );
+            } else if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                let key = relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                documents.insert(key, read(&path)?);
+            }
+        }
+    }
+    Ok(documents)
+}
+
+/// Writes `value` to `path`, creating the directories it's in.
+fn write(path: &Path, value: &Value, format: &Format) -> Result<(), String> {
+    let text = file_text(value, format)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|error| format!("failed to create {}: {error}", parent.display()))?;
+    }
+    fs::write(path, text).map_err(|error| format!("failed to write {}: {error}", path.display()))
+}
@@ -0,0 +1,88 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+use json_merge_patch_gen::Error;
+use serde_json::Value;
+
+use crate::{display_pointer, human, read};
+
+/// Diff driver for `diff.<driver>.command`, which git runs with the path of
+/// the file followed by the old and new versions as `file hex mode` triples,
+/// or with just the path for unmerged files.
+///
+/// Reports files that aren't JSON instead of failing, as that would abort the
+/// whole diff.
+pub fn diff(args: &[String]) -> Result<ExitCode, String> {
+    let [path, before, _, _, after, _, _, ..] = args else {
+        if let [path] = args {
+            println!("* Unmerged path {path}");
+            return Ok(ExitCode::SUCCESS);
+        }
+        return Err(format!(
+            "expected the 7 arguments of a git diff driver, got {}",
+            args.len()
+        ));
+    };
+
+    let mut stdout = anstream::stdout().lock();
+    let new_path = args.get(7).unwrap_or(path);
+    writeln!(stdout, "diff --json-merge-patch a/{path} b/{new_path}").map_err(write_error)?;
+    let (before, after) = match (version(before), version(after)) {
+        (Ok(before), Ok(after)) => (before, after),
+        (Err(message), _) | (_, Err(message)) => {
+            writeln!(stdout, "{message}, not diffed").map_err(write_error)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+    };
+    let patch = json_merge_patch_gen::generate(&before, &after);
+    human::write(&mut stdout, &before, patch.as_ref()).map_err(write_error)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Merge driver for `merge.<driver>.driver`, to be run as
+/// `json-merge-patch git-merge %O %A %B %P`. Writes the merge to the file of
+/// `%A`, or leaves it as it is and exits with 1 on conflicts.
+pub fn merge(
+    base: &Path,
+    ours: &Path,
+    theirs: &Path,
+    path: Option<&str>,
+) -> Result<ExitCode, String> {
+    let (base, current, other) = (read(base)?, read(ours)?, read(theirs)?);
+    match json_merge_patch_gen::merge(&base, &current, &other) {
+        Ok(merged) => {
+            let mut text =
+                serde_json::to_string_pretty(&merged).map_err(|error| error.to_string())?;
+            text.push('\n');
+            fs::write(ours, text)
+                .map_err(|error| format!("failed to write {}: {error}", ours.display()))?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(Error::MergeConflict { pointers }) => {
+            let path = path.unwrap_or("the file");
+            for pointer in pointers {
+                eprintln!(
+                    "json-merge-patch: conflicting changes to {} in {path}",
+                    display_pointer(&pointer)
+                );
+            }
+            Ok(ExitCode::FAILURE)
+        }
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// Reads a version of the file in a git diff, where `/dev/null` stands for a
+/// missing file, which is diffed as `null`.
+fn version(path: &str) -> Result<Value, String> {
+    match path {
+        "/dev/null" => Ok(Value::Null),
+        path => read(Path::new(path)),
+    }
+}
+
+fn write_error(error: io::Error) -> String {
+    format!("failed to write the output: {error}")
+}
@@ -0,0 +1,108 @@
+use std::io::{self, Write};
+
+use anstyle::{AnsiColor, Style};
+use serde_json::Value;
+
+const ADDED: Style = AnsiColor::Green.on_default();
+const REMOVED: Style = AnsiColor::Red.on_default();
+const MODIFIED: Style = AnsiColor::Yellow.on_default();
+
+/// Writes `patch` as a tree of the changes it makes to `before`: added
+/// members in green, removed ones in red and modified ones in yellow.
+/// Modified objects are expanded, other values are written as compact JSON.
+pub fn write(writer: &mut impl Write, before: &Value, patch: Option<&Value>) -> io::Result<()> {
+    let (Value::Object(before), Some(Value::Object(patch))) = (before, patch) else {
+        return match patch {
+            Some(after) => line(
+                writer,
+                0,
+                MODIFIED,
+                None,
+                Some(format!("{before} → {after}")),
+            ),
+            None => Ok(()),
+        };
+    };
+
+    let mut stack = vec![(before, patch.iter())];
+    while let Some((before, members)) = stack.last_mut() {
+        let before = *before;
+        let Some((key, patch)) = members.next() else {
+            stack.pop();
+            continue;
+        };
+        let depth = stack.len() - 1;
+        match (before.get(key), patch) {
+            (None, Value::Null) => {}
+            (None, patch) => line(writer, depth, ADDED, Some(key), Some(patch.to_string()))?,
+            (Some(before), Value::Null) => {
+                line(writer, depth, REMOVED, Some(key), Some(before.to_string()))?
+            }
+            (Some(Value::Object(before)), Value::Object(patch)) => {
+                line(writer, depth, MODIFIED, Some(key), None)?;
+                stack.push((before, patch.iter()));
+            }
+            (Some(before), after) => line(
+                writer,
+                depth,
+                MODIFIED,
+                Some(key),
+                Some(format!("{before} → {after}")),
+            )?,
+        }
+    }
+    Ok(())
+}
+
+/// Writes a line for one change, marked with `+`, `-` or `~` depending on
+/// its style.
+fn line(
+    writer: &mut impl Write,
+    depth: usize,
+    style: Style,
+    key: Option<&str>,
+    value: Option<String>,
+) -> io::Result<()> {
+    let marker = match style {
+        ADDED => '+',
+        REMOVED => '-',
+        _ => '~',
+    };
+    write!(writer, "{style}{:indent$}{marker}", "", indent = depth * 2)?;
+    match (key, value) {
+        (Some(key), Some(value)) => write!(writer, " {key}: {value}")?,
+        (Some(key), None) => write!(writer, " {key}")?,
+        (None, Some(value)) => write!(writer, " {value}")?,
+        (None, None) => {}
+    }
+    writeln!(writer, "{style:#}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_write() {
+        // Removed members sort after the others, where patches put them with
+        // serde_json's `preserve_order` feature too
+        let before = json!({"a": 1, "b": {"c": 2, "y": true}, "z": "x"});
+        let after = json!({"a": 1, "b": {"c": 3}, "f": [1]});
+        let patch = json_merge_patch_gen::generate(&before, &after);
+        let mut output = Vec::new();
+        write(&mut output, &before, patch.as_ref()).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\x1b[33m~ b\x1b[0m\n\
+             \x1b[33m  ~ c: 2 → 3\x1b[0m\n\
+             \x1b[31m  - y: true\x1b[0m\n\
+             \x1b[32m+ f: [1]\x1b[0m\n\
+             \x1b[31m- z: \"x\"\x1b[0m\n"
+        );
+
+        let mut output = Vec::new();
+        write(&mut output, &before, None).unwrap();
+        assert!(output.is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use serde_json::Value;
+
+use crate::{apply_at, file_text, read, Format, PointerArg};
+
+/// Applies `patch` to the file at `path`, or to its value at `pointer`. The
+/// file is replaced by writing the result to a temporary file next to it and
+/// renaming that over it, so that it's never left half written.
+///
+/// With `backup`, the original is kept with a `.bak` suffix. With `create`, a
+/// missing file is patched as `{}`.
+pub fn apply(
+    path: &Path,
+    patch: &Value,
+    pointer: &PointerArg,
+    backup: bool,
+    create: bool,
+    format: &Format,
+) -> Result<(), String> {
+    let exists = path.try_exists().map_err(|error| read_error(path, error))?;
+    let mut document = match exists {
+        true => read(path)?,
+        false if create => Value::Object(Default::default()),
+        false => return Err(read_error(path, ErrorKind::NotFound.into())),
+    };
+    apply_at(&mut document, patch, pointer, path)?;
+
+    let text = file_text(&document, format)?;
+
+    let temporary = suffixed(path, &format!(".{}.tmp", process::id()));
+    write(&temporary, &text, path, exists)
+        .inspect_err(|_| drop(fs::remove_file(&temporary)))
+        .map_err(|error| write_error(path, error))?;
+    if backup && exists {
+        fs::copy(path, suffixed(path, ".bak"))
+            .inspect_err(|_| drop(fs::remove_file(&temporary)))
+            .map_err(|error| write_error(&suffixed(path, ".bak"), error))?;
+    }
+    fs::rename(&temporary, path)
+        .inspect_err(|_| drop(fs::remove_file(&temporary)))
+        .map_err(|error| write_error(path, error))
+}
+
+/// Writes `text` to `temporary`, with the permissions of the file at `path`
+/// if it `exists`, and waits for it to reach the disk, so that a crash after
+/// the rename can't leave it empty.
+fn write(temporary: &Path, text: &str, path: &Path, exists: bool) -> io::Result<()> {
+    let mut file = File::create(temporary)?;
+    file.write_all(text.as_bytes())?;
+    if exists {
+        file.set_permissions(fs::metadata(path)?.permissions())?;
+    }
+    file.sync_all()
+}
+
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = OsString::from(path);
+    path.push(suffix);
+    path.into()
+}
+
+fn read_error(path: &Path, error: io::Error) -> String {
+    format!("failed to read {}: {error}", path.display())
+}
+
+fn write_error(path: &Path, error: io::Error) -> String {
+    format!("failed to write {}: {error}", path.display())
+}
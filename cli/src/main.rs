@@ -0,0 +1,472 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use json_merge_patch_gen::Unpaired;
+use serde_json::Value;
+
+mod dir;
+mod git;
+mod human;
+mod in_place;
+mod watch;
+
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print the merge patch that turns BEFORE into AFTER
+    Generate {
+        /// Document before the change, `-` for stdin
+        before: PathBuf,
+        /// Document after the change, `-` for stdin
+        after: PathBuf,
+        #[command(flatten)]
+        format: Format,
+        /// Print the changes as a colored tree instead of JSON
+        #[arg(long, conflicts_with_all = ["pretty", "compact"])]
+        human: bool,
+        /// Print nothing and exit with 0 if the documents are equal, or print
+        /// the paths of the changes and exit with 1 if they differ
+        #[arg(long, conflicts_with_all = ["pretty", "compact", "human"])]
+        check: bool,
+        /// With --check, only fail if there are more than N changes
+        #[arg(long, value_name = "N", requires = "check")]
+        max_changes: Option<usize>,
+        /// Accept JSON5 and JSONC documents, e.g. with comments and trailing
+        /// commas
+        #[arg(long)]
+        json5: bool,
+        #[command(flatten)]
+        pointer: PointerArg,
+    },
+    /// Print DOCUMENT with PATCH applied
+    Apply {
+        /// Document to patch, `-` for stdin
+        document: PathBuf,
+        /// Merge patch to apply, `-` for stdin
+        patch: PathBuf,
+        #[command(flatten)]
+        format: Format,
+        /// Replace DOCUMENT with the result instead of printing it, which is
+        /// indented unless --compact is given
+        #[arg(long, conflicts_with = "pretty")]
+        in_place: bool,
+        /// With --in-place, keep the original as DOCUMENT.bak
+        #[arg(long, requires = "in_place")]
+        backup: bool,
+        /// With --in-place, patch `{}` if DOCUMENT doesn't exist
+        #[arg(long, requires = "in_place")]
+        create: bool,
+        #[command(flatten)]
+        pointer: PointerArg,
+    },
+    /// Work on directories of JSON files
+    Dir {
+        #[command(subcommand)]
+        command: DirCommand,
+    },
+    /// Check that applying PATCH to BEFORE gives AFTER, exiting with 1 if it
+    /// doesn't
+    Validate {
+        /// Document before the change, `-` for stdin
+        before: PathBuf,
+        /// Merge patch to check, `-` for stdin
+        patch: PathBuf,
+        /// Document after the change, `-` for stdin
+        after: PathBuf,
+    },
+    /// Merge the changes from BASE to OURS and from BASE to THEIRS, like
+    /// diff3, printing the merged document or the paths changed on both
+    /// sides and exiting with 1 if the changes conflict
+    Merge3 {
+        /// Common ancestor, `-` for stdin
+        base: PathBuf,
+        /// One side's version, `-` for stdin
+        ours: PathBuf,
+        /// Other side's version, `-` for stdin
+        theirs: PathBuf,
+        #[command(flatten)]
+        format: Format,
+    },
+    /// Diff two NDJSON (JSON Lines) files record by record, printing one
+    /// patch per pair of records, or `null` if they're equal
+    Ndjson {
+        /// File with the records before the change, `-` for stdin
+        before: PathBuf,
+        /// File with the records after the change, `-` for stdin
+        after: PathBuf,
+        /// What to do with records left over when the files have a
+        /// different number of records
+        #[arg(long, value_enum, default_value_t = UnpairedArg::Error)]
+        unpaired: UnpairedArg,
+    },
+    /// Git diff driver, see the README for setting it up
+    #[command(name = "git-diff")]
+    GitDiff {
+        /// Arguments passed by git
+        #[arg(num_args = 1..=9)]
+        args: Vec<String>,
+    },
+    /// Git merge driver, merging the changes from BASE to OURS and THEIRS
+    /// into OURS, see the README for setting it up
+    #[command(name = "git-merge")]
+    GitMerge {
+        /// Common ancestor (`%O`)
+        base: PathBuf,
+        /// Current version (`%A`), where the merge is written
+        ours: PathBuf,
+        /// Other branch's version (`%B`)
+        theirs: PathBuf,
+        /// Path of the merged file (`%P`), for messages
+        path: Option<String>,
+    },
+    /// Watch FILE, printing a patch from its last version each time it
+    /// changes
+    Watch {
+        /// Document to watch
+        file: PathBuf,
+        /// URL to POST each patch to, as `application/merge-patch+json`
+        #[arg(long)]
+        post: Option<String>,
+        #[command(flatten)]
+        format: Format,
+    },
+}
+
+#[derive(Subcommand)]
+enum DirCommand {
+    /// Diff the `*.json` files with the same paths under OLD and NEW,
+    /// printing a manifest of the added, patched and removed files
+    Generate {
+        /// Directory before the change
+        old: PathBuf,
+        /// Directory after the change
+        new: PathBuf,
+        /// Write the patches of the patched files and the added files to the
+        /// same paths under DIR instead, listing the changed files
+        #[arg(long, value_name = "DIR")]
+        out: Option<PathBuf>,
+        #[command(flatten)]
+        format: Format,
+    },
+}
+
+/// Output formatting, pretty when writing to a terminal and compact otherwise
+/// unless overridden
+#[derive(Args)]
+struct Format {
+    /// Indent the output
+    #[arg(long, conflicts_with = "compact")]
+    pretty: bool,
+    /// Print the output on a single line
+    #[arg(long)]
+    compact: bool,
+}
+
+/// Subtree of the documents to work on
+#[derive(Args)]
+struct PointerArg {
+    /// Only diff or patch the value at this JSON Pointer, e.g. /spec/template
+    #[arg(long)]
+    pointer: Option<String>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum UnpairedArg {
+    /// Fail
+    Error,
+    /// Ignore them
+    Skip,
+    /// Compare them with the empty object
+    Empty,
+}
+
+impl From<UnpairedArg> for Unpaired {
+    fn from(unpaired: UnpairedArg) -> Self {
+        match unpaired {
+            UnpairedArg::Error => Unpaired::Error,
+            UnpairedArg::Skip => Unpaired::Skip,
+            UnpairedArg::Empty => Unpaired::Empty,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("json-merge-patch: {message}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<ExitCode, String> {
+    match cli.command {
+        Command::Generate {
+            before,
+            after,
+            format,
+            human,
+            check,
+            max_changes,
+            json5,
+            pointer,
+        } => {
+            check_stdin(&[&before, &after])?;
+            let read = if json5 { read_json5 } else { read };
+            let before = select(read(&before)?, &pointer, &before)?;
+            let after = select(read(&after)?, &pointer, &after)?;
+            if check {
+                let Some(patch) = json_merge_patch_gen::generate(&before, &after) else {
+                    return Ok(ExitCode::SUCCESS);
+                };
+                let changes = json_merge_patch_gen::changes(&before, &patch);
+                if changes.len() <= max_changes.unwrap_or(0) {
+                    return Ok(ExitCode::SUCCESS);
+                }
+                let mut stdout = io::stdout().lock();
+                for change in &changes {
+                    writeln!(stdout, "{}", display_pointer(&change.pointer))
+                        .map_err(|error| format!("failed to write the output: {error}"))?;
+                }
+                return Ok(ExitCode::FAILURE);
+            }
+            if human {
+                let patch = json_merge_patch_gen::generate(&before, &after);
+                human::write(&mut anstream::stdout().lock(), &before, patch.as_ref())
+                    .map_err(|error| format!("failed to write the output: {error}"))?;
+                return Ok(ExitCode::SUCCESS);
+            }
+            // Equal documents still get a patch that leaves them as they are
+            let patch =
+                json_merge_patch_gen::generate(&before, &after).unwrap_or_else(|| match after {
+                    Value::Object(_) => Value::Object(Default::default()),
+                    after => after,
+                });
+            print(&patch, &format)?;
+        }
+        Command::Apply {
+            document,
+            patch,
+            format,
+            in_place,
+            backup,
+            create,
+            pointer,
+        } => {
+            check_stdin(&[&document, &patch])?;
+            if in_place {
+                if is_stdin(&document) {
+                    return Err("can't patch stdin in place".to_string());
+                }
+                in_place::apply(&document, &read(&patch)?, &pointer, backup, create, &format)?;
+                return Ok(ExitCode::SUCCESS);
+            }
+            let (mut value, patch) = (read(&document)?, read(&patch)?);
+            apply_at(&mut value, &patch, &pointer, &document)?;
+            print(&value, &format)?;
+        }
+        Command::Dir {
+            command:
+                DirCommand::Generate {
+                    old,
+                    new,
+                    out,
+                    format,
+                },
+        } => dir::generate(&old, &new, out.as_deref(), &format)?,
+        Command::Validate {
+            before,
+            patch,
+            after,
+        } => {
+            check_stdin(&[&before, &patch, &after])?;
+            let (mut document, patch, after) = (read(&before)?, read(&patch)?, read(&after)?);
+            json_merge_patch_gen::apply(&mut document, &patch);
+            if document != after {
+                eprintln!("json-merge-patch: the patch doesn't turn BEFORE into AFTER");
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Command::Merge3 {
+            base,
+            ours,
+            theirs,
+            format,
+        } => {
+            check_stdin(&[&base, &ours, &theirs])?;
+            let (base, ours, theirs) = (read(&base)?, read(&ours)?, read(&theirs)?);
+            match json_merge_patch_gen::merge(&base, &ours, &theirs) {
+                Ok(merged) => print(&merged, &format)?,
+                Err(json_merge_patch_gen::Error::MergeConflict { pointers }) => {
+                    for pointer in pointers {
+                        eprintln!(
+                            "json-merge-patch: conflicting changes to {}",
+                            display_pointer(&pointer)
+                        );
+                    }
+                    return Ok(ExitCode::FAILURE);
+                }
+                Err(error) => return Err(error.to_string()),
+            }
+        }
+        Command::Ndjson {
+            before,
+            after,
+            unpaired,
+        } => {
+            check_stdin(&[&before, &after])?;
+            json_merge_patch_gen::generate_ndjson(
+                open(&before)?,
+                open(&after)?,
+                io::stdout().lock(),
+                unpaired.into(),
+            )
+            .map_err(|error| error.to_string())?;
+        }
+        Command::GitDiff { args } => return git::diff(&args),
+        Command::GitMerge {
+            base,
+            ours,
+            theirs,
+            path,
+        } => return git::merge(&base, &ours, &theirs, path.as_deref()),
+        Command::Watch { file, post, format } => {
+            if is_stdin(&file) {
+                return Err("can't watch stdin".to_string());
+            }
+            watch::watch(&file, post.as_deref(), &format)?;
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Pointers for printing on their own line, where the empty pointer of the
+/// whole document would be an empty line.
+fn display_pointer(pointer: &str) -> &str {
+    match pointer {
+        "" => "(root)",
+        pointer => pointer,
+    }
+}
+
+/// The value of `document`, read from `path`, at the pointer if one is given.
+fn select(mut document: Value, pointer: &PointerArg, path: &Path) -> Result<Value, String> {
+    let Some(pointer) = &pointer.pointer else {
+        return Ok(document);
+    };
+    document
+        .pointer_mut(pointer)
+        .map(Value::take)
+        .ok_or_else(|| format!("no value at {pointer} in {}", name(path)))
+}
+
+/// Applies `patch` to the value of `document`, read from `path`, at the
+/// pointer if one is given.
+fn apply_at(
+    document: &mut Value,
+    patch: &Value,
+    pointer: &PointerArg,
+    path: &Path,
+) -> Result<(), String> {
+    let value = match &pointer.pointer {
+        Some(pointer) => document
+            .pointer_mut(pointer)
+            .ok_or_else(|| format!("no value at {pointer} in {}", name(path)))?,
+        None => document,
+    };
+    json_merge_patch_gen::apply(value, patch);
+    Ok(())
+}
+
+fn is_stdin(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+fn name(path: &Path) -> String {
+    match is_stdin(path) {
+        true => "stdin".to_string(),
+        false => path.display().to_string(),
+    }
+}
+
+fn check_stdin(paths: &[&Path]) -> Result<(), String> {
+    if paths.iter().filter(|path| is_stdin(path)).count() > 1 {
+        return Err("only one of the inputs can be stdin".to_string());
+    }
+    Ok(())
+}
+
+fn open(path: &Path) -> Result<Box<dyn BufRead>, String> {
+    if is_stdin(path) {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    File::open(path)
+        .map(|file| Box::new(BufReader::new(file)) as Box<dyn BufRead>)
+        .map_err(|error| format!("failed to read {}: {error}", path.display()))
+}
+
+fn read(path: &Path) -> Result<Value, String> {
+    let text = read_text(path)?;
+    serde_json::from_str(&text).map_err(|error| format!("{}: {error}", name(path)))
+}
+
+fn read_json5(path: &Path) -> Result<Value, String> {
+    let text = read_text(path)?;
+    json_merge_patch_gen::parse_json5(&text).map_err(|error| match error {
+        json_merge_patch_gen::Error::Json5 {
+            location: Some((line, column)),
+            message,
+            ..
+        } => format!("{}:{line}:{column}: {message}", name(path)),
+        error => format!("{}: {error}", name(path)),
+    })
+}
+
+fn read_text(path: &Path) -> Result<String, String> {
+    let mut text = String::new();
+    open(path)?
+        .read_to_string(&mut text)
+        .map_err(|error| format!("failed to read {}: {error}", name(path)))?;
+    Ok(text)
+}
+
+/// `value` as the text of a file, indented unless --compact is given, like
+/// editors write them.
+fn file_text(value: &Value, format: &Format) -> Result<String, String> {
+    let mut text = match format.compact {
+        true => serde_json::to_string(value),
+        false => serde_json::to_string_pretty(value),
+    }
+    .map_err(|error| error.to_string())?;
+    text.push('\n');
+    Ok(text)
+}
+
+fn print(value: &Value, format: &Format) -> Result<(), String> {
+    let mut stdout = io::stdout().lock();
+    let pretty = format.pretty || !format.compact && stdout.is_terminal();
+    write_line(&mut stdout, value, pretty)
+}
+
+/// Writes `value` to `out` on a line of its own, or indented if `pretty`.
+fn write_line(out: &mut impl Write, value: &Value, pretty: bool) -> Result<(), String> {
+    let written = if pretty {
+        serde_json::to_writer_pretty(&mut *out, value)
+    } else {
+        serde_json::to_writer(&mut *out, value)
+    };
+    written
+        .map_err(io::Error::from)
+        .and_then(|()| writeln!(out))
+        .map_err(|error| format!("failed to write the output: {error}"))
+}
@@ -0,0 +1,125 @@
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::sync::mpsc;
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde_json::Value;
+
+use crate::{name, read, write_line, Format};
+
+/// Watches the JSON file at `path`, printing the patch from its last seen
+/// version each time it changes, and POSTing it to `url` if given.
+///
+/// The directory of the file is watched rather than the file itself, so that
+/// files replaced by editors keep being watched. Versions that can't be read
+/// are reported and skipped, e.g. while the file is being written, and empty
+/// ones are skipped silently.
+pub fn watch(path: &Path, url: Option<&str>, format: &Format) -> Result<(), String> {
+    let mut last = read(path)?;
+
+    let directory = match path.parent() {
+        Some(parent) if parent != Path::new("") => parent,
+        _ => Path::new("."),
+    };
+    let file_name = path.file_name();
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)
+        .map_err(|error| format!("failed to watch {}: {error}", name(path)))?;
+    watcher
+        .watch(directory, RecursiveMode::NonRecursive)
+        .map_err(|error| format!("failed to watch {}: {error}", name(path)))?;
+
+    for event in receiver {
+        let event = event.map_err(|error| format!("failed to watch {}: {error}", name(path)))?;
+        let changed = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+            && event.paths.iter().any(|path| path.file_name() == file_name);
+        if !changed {
+            continue;
+        }
+
+        let stdout = io::stdout();
+        let pretty = format.pretty || !format.compact && stdout.is_terminal();
+        let patch = emit(path, &mut last, &mut stdout.lock(), pretty)?;
+        if let (Some(patch), Some(url)) = (patch, url) {
+            post(url, &patch);
+        }
+    }
+    Ok(())
+}
+
+/// Reads the file at `path` after it changed, writing the patch from the
+/// `last` version to `out` and returning it, and makes it the last version.
+/// Versions that can't be read are reported and skipped, and empty ones are
+/// skipped silently.
+fn emit(
+    path: &Path,
+    last: &mut Value,
+    out: &mut impl Write,
+    pretty: bool,
+) -> Result<Option<Value>, String> {
+    // Files are usually truncated before they're written
+    if !fs::metadata(path).is_ok_and(|metadata| metadata.len() > 0) {
+        return Ok(None);
+    }
+    let current = match read(path) {
+        Ok(current) => current,
+        Err(message) => {
+            eprintln!("json-merge-patch: {message}");
+            return Ok(None);
+        }
+    };
+    let patch = json_merge_patch_gen::generate(last, &current);
+    if let Some(patch) = &patch {
+        write_line(out, patch, pretty)?;
+    }
+    *last = current;
+    Ok(patch)
+}
+
+/// POSTs `patch` to `url`, reporting failures without stopping.
+fn post(url: &str, patch: &Value) {
+    let sent = ureq::post(url)
+        .header("Content-Type", "application/merge-patch+json")
+        .send(patch.to_string());
+    if let Err(error) = sent {
+        eprintln!("json-merge-patch: failed to POST the patch to {url}: {error}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_emit() {
+        let path = std::env::temp_dir().join(format!("watch-{}.json", std::process::id()));
+        let mut last = json!({"a": 1, "b": 2});
+        let mut output = Vec::new();
+
+        fs::write(&path, r#"{"a": 1, "b": 3}"#).unwrap();
+        let patch = emit(&path, &mut last, &mut output, false).unwrap();
+        assert_eq!(patch, Some(json!({"b": 3})));
+        assert_eq!(last, json!({"a": 1, "b": 3}));
+
+        // Unchanged, truncated and half written versions print nothing
+        fs::write(&path, r#"{"b": 3, "a": 1}"#).unwrap();
+        assert_eq!(emit(&path, &mut last, &mut output, false).unwrap(), None);
+        fs::write(&path, "").unwrap();
+        assert_eq!(emit(&path, &mut last, &mut output, false).unwrap(), None);
+        fs::write(&path, r#"{"a": "#).unwrap();
+        assert_eq!(emit(&path, &mut last, &mut output, false).unwrap(), None);
+        assert_eq!(last, json!({"a": 1, "b": 3}));
+
+        fs::write(&path, r#"{"a": 1}"#).unwrap();
+        let patch = emit(&path, &mut last, &mut output, true).unwrap();
+        assert_eq!(patch, Some(json!({"b": null})));
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"b\":3}\n{\n  \"b\": null\n}\n"
+        );
+    }
+}
@@ -0,0 +1,533 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde_json::{json, Value};
+
+/// A directory for the files of a test, removed with its contents when
+/// dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new() -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "json-merge-patch-test-{}-{}",
+            process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    /// Writes `text` to the file at `name`, creating the directories it's in.
+    fn file(&self, name: &str, text: &str) -> PathBuf {
+        let path = self.0.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Runs the tool with `args`, writing `stdin` to its standard input.
+fn run<S: AsRef<OsStr>>(args: &[S], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_json-merge-patch"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> &str {
+    std::str::from_utf8(&output.stdout).unwrap()
+}
+
+fn stderr(output: &Output) -> &str {
+    std::str::from_utf8(&output.stderr).unwrap()
+}
+
+/// Parses `text`, to compare output regardless of the order of its members.
+fn parsed(text: &str) -> Value {
+    serde_json::from_str(text).unwrap()
+}
+
+fn arg(path: &Path) -> &OsStr {
+    path.as_os_str()
+}
+
+#[test]
+fn test_generate_check() {
+    let dir = TempDir::new();
+    let before = dir.file("before.json", r#"{"a": 1, "b": {"c": 2}, "d": 3}"#);
+    let after = dir.file("after.json", r#"{"a": 1, "b": {"c": 3}}"#);
+    let check = |after: &Path, extra: &[&str]| {
+        let mut args = vec![OsStr::new("generate"), arg(&before), arg(after)];
+        args.push(OsStr::new("--check"));
+        args.extend(extra.iter().map(OsStr::new));
+        run(&args, "")
+    };
+
+    let output = check(&before, &[]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+
+    let output = check(&after, &[]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "/b/c\n/d\n");
+
+    // Only failing with more changes than the limit
+    let output = check(&after, &["--max-changes", "1"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "/b/c\n/d\n");
+    let output = check(&after, &["--max-changes", "2"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+
+    // Errors are told apart from differences
+    let output = check(&dir.path("missing.json"), &[]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).starts_with("json-merge-patch: failed to read"));
+    let output = run(&["generate", "a.json", "b.json", "--max-changes", "1"], "");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_git_diff() {
+    let dir = TempDir::new();
+    let old = dir.file("old.json", r#"{"a": 1, "b": 2}"#);
+    let new = dir.file("new.json", r#"{"a": 1, "b": 3, "c": [1]}"#);
+    let driver = |old: &OsStr, extra: &[&str]| {
+        let mut args = vec![OsStr::new("git-diff"), OsStr::new("f.json"), old];
+        args.extend(["aaa", "100644"].map(OsStr::new));
+        args.extend([arg(&new), OsStr::new("bbb"), OsStr::new("100644")]);
+        args.extend(extra.iter().map(OsStr::new));
+        run(&args, "")
+    };
+
+    let output = driver(arg(&old), &[]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        "diff --json-merge-patch a/f.json b/f.json\n~ b: 2 → 3\n+ c: [1]\n"
+    );
+
+    // Renames pass the new path
+    let output = driver(arg(&old), &["g.json"]);
+    assert!(stdout(&output).starts_with("diff --json-merge-patch a/f.json b/g.json\n"));
+
+    // Added files are diffed from `null`
+    let output = driver(OsStr::new("/dev/null"), &[]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        "diff --json-merge-patch a/f.json b/f.json\n~ null → {\"a\":1,\"b\":3,\"c\":[1]}\n"
+    );
+
+    // Files that aren't JSON are reported without failing the whole diff
+    let text = dir.file("old.txt", "not json");
+    let output = driver(arg(&text), &[]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).ends_with(", not diffed\n"));
+
+    let output = run(&["git-diff", "f.json"], "");
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "* Unmerged path f.json\n");
+
+    let output = run(&["git-diff", "f.json", "old.json"], "");
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_git_merge() {
+    let dir = TempDir::new();
+    let base = dir.file("base.json", r#"{"a": 1, "b": 1, "c": 1}"#);
+    let theirs = dir.file("theirs.json", r#"{"a": 1, "b": 2, "c": 1}"#);
+
+    let ours = dir.file("ours.json", r#"{"a": 2, "b": 1, "c": 1}"#);
+    let output = run(
+        &[
+            OsStr::new("git-merge"),
+            arg(&base),
+            arg(&ours),
+            arg(&theirs),
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        fs::read_to_string(&ours).unwrap(),
+        "{\n  \"a\": 2,\n  \"b\": 2,\n  \"c\": 1\n}\n"
+    );
+
+    // Conflicts leave the current version as it is
+    let conflicting = r#"{"a": 1, "b": 3, "c": 1}"#;
+    let ours = dir.file("ours.json", conflicting);
+    let output = run(
+        &[
+            OsStr::new("git-merge"),
+            arg(&base),
+            arg(&ours),
+            arg(&theirs),
+            OsStr::new("config.json"),
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        stderr(&output),
+        "json-merge-patch: conflicting changes to /b in config.json\n"
+    );
+    assert_eq!(fs::read_to_string(&ours).unwrap(), conflicting);
+}
+
+#[test]
+fn test_merge3() {
+    let dir = TempDir::new();
+    let base = dir.file("base.json", r#"{"a": 1, "b": {"c": 1, "d": 1}, "e": 1}"#);
+    let merge3 = |ours: &str, theirs: &str| {
+        let ours = dir.file("ours.json", ours);
+        let theirs = dir.file("theirs.json", theirs);
+        run(
+            &[
+                OsStr::new("merge3"),
+                arg(&base),
+                arg(&ours),
+                arg(&theirs),
+                OsStr::new("--compact"),
+            ],
+            "",
+        )
+    };
+
+    // Changes to different members are both kept
+    let output = merge3(
+        r#"{"a": 2, "b": {"c": 1, "d": 1}, "e": 1}"#,
+        r#"{"a": 1, "b": {"c": 1, "d": 2}}"#,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "{\"a\":2,\"b\":{\"c\":1,\"d\":2}}\n");
+
+    // Only one side changing gives that side
+    let ours = r#"{"a": 1, "b": {"c": 3, "d": 1}, "e": 1, "f": [1]}"#;
+    let output = merge3(ours, r#"{"a": 1, "b": {"c": 1, "d": 1}, "e": 1}"#);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        "{\"a\":1,\"b\":{\"c\":3,\"d\":1},\"e\":1,\"f\":[1]}\n"
+    );
+
+    // Overlapping changes are listed instead of printing a merge
+    let output = merge3(
+        r#"{"a": 2, "b": {"c": 2, "d": 1}, "e": 1}"#,
+        r#"{"a": 3, "b": {"c": 3, "d": 1}, "e": 1}"#,
+    );
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout(&output), "");
+    assert_eq!(
+        stderr(&output),
+        "json-merge-patch: conflicting changes to /a\n\
+         json-merge-patch: conflicting changes to /b/c\n"
+    );
+}
+
+#[test]
+fn test_apply_in_place() {
+    let dir = TempDir::new();
+    let patch = dir.file("patch.json", r#"{"a": null, "b": [2]}"#);
+    let apply = |document: &Path, extra: &[&str]| {
+        let mut args = vec![OsStr::new("apply"), arg(document), arg(&patch)];
+        args.push(OsStr::new("--in-place"));
+        args.extend(extra.iter().map(OsStr::new));
+        run(&args, "")
+    };
+
+    let config = dir.file("config.json", r#"{"a": 1, "c": 3}"#);
+    let output = apply(&config, &["--backup"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+    let text = fs::read_to_string(&config).unwrap();
+    assert_eq!(parsed(&text), json!({"b": [2], "c": 3}));
+    assert!(text.contains("\n  \"c\": 3") && text.ends_with("}\n"));
+    assert_eq!(
+        fs::read_to_string(dir.path("config.json.bak")).unwrap(),
+        r#"{"a": 1, "c": 3}"#
+    );
+
+    // Missing files are only created with --create
+    let missing = dir.path("missing.json");
+    let output = apply(&missing, &["--compact"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(!missing.exists());
+    let output = apply(&missing, &["--compact", "--create", "--backup"]);
+    assert_eq!(output.status.code(), Some(0));
+    let text = fs::read_to_string(&missing).unwrap();
+    assert_eq!(parsed(&text), json!({"b": [2]}));
+    assert_eq!(text.lines().count(), 1);
+    assert!(!dir.path("missing.json.bak").exists());
+
+    // Documents that can't be patched are left as they are
+    let invalid = dir.file("invalid.json", r#"{"a": "#);
+    let output = apply(&invalid, &["--backup"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(fs::read_to_string(&invalid).unwrap(), r#"{"a": "#);
+    let output = apply(&config, &["--pointer", "/missing"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(fs::read_to_string(&config).unwrap().contains("\"c\": 3"));
+    let mut names: Vec<_> = fs::read_dir(&dir.0)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "config.json",
+            "config.json.bak",
+            "invalid.json",
+            "missing.json",
+            "patch.json"
+        ]
+    );
+
+    let output = run(&["apply", "-", "patch.json", "--in-place"], "{}");
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        stderr(&output),
+        "json-merge-patch: can't patch stdin in place\n"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_apply_in_place_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new();
+    let patch = dir.file("patch.json", r#"{"a": 2}"#);
+    let config = dir.file("config.json", r#"{"a": 1}"#);
+    fs::set_permissions(&config, fs::Permissions::from_mode(0o600)).unwrap();
+    let output = run(
+        &[
+            OsStr::new("apply"),
+            arg(&config),
+            arg(&patch),
+            OsStr::new("--in-place"),
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(0));
+    let mode = fs::metadata(&config).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+}
+
+#[test]
+fn test_pointer() {
+    let dir = TempDir::new();
+    let document = dir.file(
+        "document.json",
+        r#"{"a": 1, "spec/v1": {"x~y": {"k": 1, "l": 2}}}"#,
+    );
+    let run_with = |args: &[&OsStr], stdin: &str| {
+        let mut args = args.to_vec();
+        args.push(OsStr::new("--compact"));
+        run(&args, stdin)
+    };
+    let generate = OsStr::new("generate");
+    let apply = OsStr::new("apply");
+    let stdin = OsStr::new("-");
+    let pointer = OsStr::new("--pointer");
+
+    // `~1` and `~0` escape `/` and `~`, and `-` reads from stdin
+    let escaped = OsStr::new("/spec~1v1/x~0y");
+    let output = run_with(
+        &[generate, arg(&document), stdin, pointer, escaped],
+        r#"{"a": 2, "spec/v1": {"x~y": {"k": 3, "l": 2}}}"#,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(parsed(stdout(&output)), json!({"k": 3}));
+    let output = run_with(&[apply, stdin, arg(&document), pointer, escaped], "");
+    assert_eq!(output.status.code(), Some(2));
+    let patch = dir.file("patch.json", r#"{"k": null, "m": 4}"#);
+    let output = run_with(
+        &[apply, stdin, arg(&patch), pointer, escaped],
+        r#"{"spec/v1": {"x~y": {"k": 1}}, "b": 2}"#,
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        parsed(stdout(&output)),
+        json!({"b": 2, "spec/v1": {"x~y": {"m": 4}}})
+    );
+
+    // Values other than objects are diffed and patched as a whole
+    let output = run_with(
+        &[generate, arg(&document), stdin, pointer, OsStr::new("/a")],
+        r#"{"a": [2]}"#,
+    );
+    assert_eq!(parsed(stdout(&output)), json!([2]));
+    let output = run_with(
+        &[
+            apply,
+            arg(&document),
+            arg(&patch),
+            pointer,
+            OsStr::new("/a"),
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        parsed(stdout(&output)),
+        json!({"a": {"m": 4}, "spec/v1": {"x~y": {"k": 1, "l": 2}}})
+    );
+
+    // Missing values fail, including ones inside values other than objects
+    for missing in ["/b", "/a/b", "spec"] {
+        let missing = OsStr::new(missing);
+        let output = run_with(
+            &[generate, arg(&document), arg(&document), pointer, missing],
+            "",
+        );
+        assert_eq!(output.status.code(), Some(2));
+        assert_eq!(
+            stderr(&output),
+            format!(
+                "json-merge-patch: no value at {} in {}\n",
+                missing.to_str().unwrap(),
+                document.display()
+            )
+        );
+        let output = run_with(&[apply, arg(&document), arg(&patch), pointer, missing], "");
+        assert_eq!(output.status.code(), Some(2));
+        assert_eq!(stdout(&output), "");
+    }
+}
+
+#[test]
+fn test_dir_generate() {
+    let dir = TempDir::new();
+    dir.file("old/changed.json", r#"{"a": 1, "b": 2}"#);
+    dir.file("new/changed.json", r#"{"a": 1, "b": 3}"#);
+    dir.file("old/sub/same.json", r#"{"k": 1}"#);
+    dir.file("new/sub/same.json", r#"{"k": 1}"#);
+    dir.file("old/sub/removed.json", "{}");
+    dir.file("new/deep/er/added.json", r#"{"z": [1]}"#);
+    dir.file("new/notes.txt", "not json");
+    let (old, new) = (dir.path("old"), dir.path("new"));
+
+    let output = run(
+        &[
+            OsStr::new("dir"),
+            OsStr::new("generate"),
+            arg(&old),
+            arg(&new),
+            OsStr::new("--compact"),
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        concat!(
+            r#"{"added":{"deep/er/added.json":{"z":[1]}},"#,
+            r#""patched":{"changed.json":{"b":3}},"#,
+            r#""removed":["sub/removed.json"]}"#,
+            "\n"
+        )
+    );
+
+    let out = dir.path("patches");
+    let output = run(
+        &[
+            OsStr::new("dir"),
+            OsStr::new("generate"),
+            arg(&old),
+            arg(&new),
+            OsStr::new("--out"),
+            arg(&out),
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        "M\tchanged.json\nA\tdeep/er/added.json\nD\tsub/removed.json\n"
+    );
+    assert_eq!(
+        fs::read_to_string(out.join("changed.json")).unwrap(),
+        "{\n  \"b\": 3\n}\n"
+    );
+    assert!(out.join("deep/er/added.json").exists());
+    assert!(!out.join("sub").exists());
+
+    let output = run(
+        &[
+            OsStr::new("dir"),
+            OsStr::new("generate"),
+            arg(&old),
+            arg(&dir.path("missing")),
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_dir_generate_symlinks() {
+    use std::os::unix::fs::symlink;
+
+    let dir = TempDir::new();
+    dir.file("old/a.json", r#"{"a": 1}"#);
+    dir.file("new/a.json", r#"{"a": 2}"#);
+    dir.file("linked.json", r#"{"l": 1}"#);
+    // A loop back to the root, and a link to a file
+    symlink(dir.path("new"), dir.path("new/loop")).unwrap();
+    symlink(dir.path("linked.json"), dir.path("new/linked.json")).unwrap();
+
+    let output = run(
+        &[
+            OsStr::new("dir"),
+            OsStr::new("generate"),
+            arg(&dir.path("old")),
+            arg(&dir.path("new")),
+            OsStr::new("--compact"),
+        ],
+        "",
+    );
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output),
+        r#"{"added":{"linked.json":{"l":1}},"patched":{"a.json":{"a":2}},"removed":[]}"#.to_owned()
+            + "\n"
+    );
+    assert_eq!(
+        stderr(&output),
+        format!(
+            "json-merge-patch: skipping {}, a symlink to a directory\n",
+            dir.path("new/loop").display()
+        )
+    );
+}
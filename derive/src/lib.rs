@@ -0,0 +1,277 @@
+/*!
+ * `#[derive(JsonMergePatch)]`, re-exported by `json_merge_patch_gen` with its
+ * `derive` feature.
+ */
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, Data, DeriveInput, Error, Fields, GenericArgument, PathArguments, Type,
+};
+
+#[proc_macro_derive(JsonMergePatch, attributes(json_merge_patch, serde))]
+pub fn derive_json_merge_patch(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/**
+ * How a field is patched.
+ */
+enum Kind<'a> {
+    /** Replaced with the value of the patch */
+    Value,
+    /** An `Option`, which the patch can also set to `None` by `null` */
+    Optional(&'a Type),
+    /** Patched with the patch of its type */
+    Nested,
+}
+
+struct Field<'a> {
+    ident: &'a syn::Ident,
+    vis: &'a syn::Visibility,
+    ty: &'a Type,
+    /** Key of the field in JSON */
+    key: String,
+    kind: Kind<'a>,
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    name,
+                    "JsonMergePatch can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                name,
+                "JsonMergePatch can only be derived for structs",
+            ))
+        }
+    };
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "JsonMergePatch can't be derived for generic structs",
+        ));
+    }
+    let fields = fields
+        .iter()
+        .map(field)
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let krate = quote!(::json_merge_patch_gen);
+    let private = quote!(#krate::__private);
+    let value = quote!(#private::serde_json::Value);
+    let vis = &input.vis;
+    let patch = format_ident!("{name}Patch");
+    let doc = format!("Typed merge patch of [`{name}`]");
+
+    let declarations = fields.iter().map(|field| {
+        let Field { ident, vis, ty, .. } = field;
+        match field.kind {
+            Kind::Value | Kind::Optional(_) => quote!(#vis #ident: ::core::option::Option<#ty>),
+            Kind::Nested => quote! {
+                #vis #ident: ::core::option::Option<<#ty as #krate::JsonMergePatch>::Patch>
+            },
+        }
+    });
+
+    let to_json = fields.iter().map(|field| {
+        let Field { ident, key, .. } = field;
+        let json = match field.kind {
+            Kind::Value => quote!(to_json(value)),
+            Kind::Optional(_) => quote!(match value {
+                ::core::option::Option::Some(value) => to_json(value),
+                ::core::option::Option::None => #value::Null,
+            }),
+            Kind::Nested => quote!(#value::from(value)),
+        };
+        quote! {
+            if let ::core::option::Option::Some(value) = patch.#ident {
+                object.insert(#private::String::from(#key), #json);
+            }
+        }
+    });
+
+    let from_json = fields.iter().map(|field| {
+        let Field { ident, ty, key, .. } = field;
+        let removed = format!("`{key}` of {name} can't be removed");
+        let parsed = match field.kind {
+            Kind::Value => quote! {
+                if value.is_null() {
+                    return ::core::result::Result::Err(error(#removed));
+                }
+                #private::serde_json::from_value(value)?
+            },
+            Kind::Optional(inner) => quote! {
+                match value {
+                    #value::Null => ::core::option::Option::None,
+                    value => ::core::option::Option::Some(
+                        #private::serde_json::from_value::<#inner>(value)?
+                    ),
+                }
+            },
+            Kind::Nested => quote! {
+                if value.is_null() {
+                    return ::core::result::Result::Err(error(#removed));
+                }
+                <<#ty as #krate::JsonMergePatch>::Patch as ::core::convert::TryFrom<#value>>::try_from(value)?
+            },
+        };
+        quote! {
+            if let ::core::option::Option::Some(value) = object.remove(#key) {
+                patch.#ident = ::core::option::Option::Some({ #parsed });
+            }
+        }
+    });
+
+    let apply = fields.iter().map(|field| {
+        let ident = field.ident;
+        match field.kind {
+            Kind::Value | Kind::Optional(_) => quote! {
+                if let ::core::option::Option::Some(value) = patch.#ident {
+                    self.#ident = value;
+                }
+            },
+            Kind::Nested => quote! {
+                if let ::core::option::Option::Some(patch) = patch.#ident {
+                    #krate::JsonMergePatch::apply_patch(&mut self.#ident, patch);
+                }
+            },
+        }
+    });
+
+    let not_object = format!("expected an object for a patch of {name}");
+    Ok(quote! {
+        #[doc = #doc]
+        #[derive(Default)]
+        #vis struct #patch {
+            #(#declarations,)*
+        }
+
+        impl ::core::convert::From<#patch> for #value {
+            fn from(patch: #patch) -> Self {
+                fn to_json<T: #private::serde::Serialize>(value: T) -> #value {
+                    #private::serde_json::to_value(value)
+                        .expect("patched fields should serialize to JSON")
+                }
+                let mut object = #private::serde_json::Map::new();
+                #(#to_json)*
+                #value::Object(object)
+            }
+        }
+
+        impl ::core::convert::TryFrom<#value> for #patch {
+            type Error = #private::serde_json::Error;
+
+            fn try_from(value: #value) -> ::core::result::Result<Self, Self::Error> {
+                fn error(message: &str) -> #private::serde_json::Error {
+                    <#private::serde_json::Error as #private::serde::de::Error>::custom(message)
+                }
+                let #value::Object(mut object) = value else {
+                    return ::core::result::Result::Err(error(#not_object));
+                };
+                let mut patch = #patch::default();
+                #(#from_json)*
+                ::core::result::Result::Ok(patch)
+            }
+        }
+
+        impl #name {
+            /** Applies `patch`, leaving the fields that it doesn't set as they are */
+            #vis fn apply_patch(&mut self, patch: #patch) {
+                #(#apply)*
+            }
+        }
+
+        impl #krate::JsonMergePatch for #name {
+            type Patch = #patch;
+
+            fn apply_patch(&mut self, patch: #patch) {
+                #name::apply_patch(self, patch)
+            }
+        }
+    })
+}
+
+fn field(field: &syn::Field) -> Result<Field<'_>, Error> {
+    // The .unwrap() is safe because the fields are named
+    let ident = field.ident.as_ref().unwrap();
+    let mut key = ident.to_string();
+    key = key.strip_prefix("r#").map(String::from).unwrap_or(key);
+    let mut nested = false;
+
+    for attr in &field.attrs {
+        if attr.path().is_ident("json_merge_patch") {
+            attr.parse_nested_meta(|meta| match meta.path.is_ident("nested") {
+                true => {
+                    nested = true;
+                    Ok(())
+                }
+                false => Err(meta.error("expected `nested`")),
+            })?;
+        } else if attr.path().is_ident("serde") {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") && meta.input.peek(syn::Token![=]) {
+                    let value: syn::LitStr = meta.value()?.parse()?;
+                    key = value.value();
+                    return Ok(());
+                }
+                // Skips the rest of other attributes, like `= "..."` or `(...)`
+                while !meta.input.is_empty() && !meta.input.peek(syn::Token![,]) {
+                    meta.input.parse::<proc_macro2::TokenTree>()?;
+                }
+                Ok(())
+            })?;
+        }
+    }
+
+    let kind = match (nested, option(&field.ty)) {
+        (true, Some(_)) => {
+            return Err(Error::new_spanned(
+                &field.ty,
+                "`nested` fields can't be `Option`s",
+            ))
+        }
+        (true, None) => Kind::Nested,
+        (false, Some(inner)) => Kind::Optional(inner),
+        (false, None) => Kind::Value,
+    };
+    Ok(Field {
+        ident,
+        vis: &field.vis,
+        ty: &field.ty,
+        key,
+        kind,
+    })
+}
+
+/**
+ * The type in `ty` if it's an `Option`.
+ */
+fn option(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if path.qself.is_some() || segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+    match arguments.args.first()? {
+        GenericArgument::Type(inner) if arguments.args.len() == 1 => Some(inner),
+        _ => None,
+    }
+}
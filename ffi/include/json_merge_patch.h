@@ -0,0 +1,30 @@
+/*
+ * Generating and applying JSON Merge Patches (RFC 7386), with documents and
+ * patches as NUL-terminated UTF-8 JSON strings.
+ *
+ * Strings returned, including errors, are owned by the caller and must be
+ * freed with json_merge_patch_free(). Functions that fail return NULL and, if
+ * error isn't NULL, set *error to a message, or to NULL on success.
+ */
+
+#ifndef JSON_MERGE_PATCH_H
+#define JSON_MERGE_PATCH_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The merge patch that turns before into after, or "{}" if they're equal */
+char *json_merge_patch_generate(const char *before, const char *after, char **error);
+
+/* document with patch applied */
+char *json_merge_patch_apply(const char *document, const char *patch, char **error);
+
+/* Frees a string returned by this library, does nothing for NULL */
+void json_merge_patch_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,8 @@
+/*!
+ * The shared and static libraries of the C ABI of json_merge_patch_gen, see
+ * `include/json_merge_patch.h`.
+ */
+
+pub use json_merge_patch_gen::ffi::{
+    json_merge_patch_apply, json_merge_patch_free, json_merge_patch_generate,
+};
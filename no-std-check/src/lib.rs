@@ -0,0 +1,23 @@
+#![no_std]
+
+extern crate alloc;
+
+use json_merge_patch_gen::{generate_at, generate_with, Error, Options, UnicodeNormalization};
+use serde_json::{json, Value};
+
+pub fn patch(before: &Value, after: &Value) -> Option<Value> {
+    let options = Options::new()
+        .numeric_equality(true)
+        .unicode_normalization(UnicodeNormalization::Nfc)
+        .ignore_path("/meta")
+        .max_depth(8);
+    generate_with(before, after, &options)
+}
+
+pub fn patch_at(before: &Value, after: &Value) -> Result<Option<Value>, Error> {
+    generate_at(before, after, "/spec")
+}
+
+pub fn example() -> Option<Value> {
+    patch(&json!({ "a": 1, "meta": 1 }), &json!({ "a": 2, "meta": 2 }))
+}
@@ -0,0 +1,114 @@
+/*!
+ * Native Node.js bindings through [napi-rs](https://napi.rs), for servers
+ * where the startup of the WebAssembly build or converting JavaScript
+ * objects costs too much. Documents and patches are JSON text, as strings or
+ * Buffers, and the results are strings.
+ */
+
+use napi::bindgen_prelude::{Buffer, Either};
+use napi::{Error, Result, Status};
+use napi_derive::napi;
+use serde_json::Value;
+
+type Json = Either<String, Buffer>;
+
+/// The merge patch that turns `before` into `after`, or `null` if they're
+/// equal.
+#[napi]
+pub fn generate(before: Json, after: Json) -> Result<Option<String>> {
+    let before = parse(&before, "before")?;
+    let after = parse(&after, "after")?;
+    Ok(json_merge_patch_gen::generate(&before, &after).map(|patch| patch.to_string()))
+}
+
+/// `document` with `patch` applied.
+#[napi]
+pub fn apply(document: Json, patch: Json) -> Result<String> {
+    let mut document = parse(&document, "document")?;
+    let patch = parse(&patch, "patch")?;
+    json_merge_patch_gen::apply(&mut document, &patch);
+    Ok(document.to_string())
+}
+
+/// Combines patches applied one after the other into one with the same
+/// effect, or `null` if there are none. Fails if a patch merges an object
+/// into a member that an earlier one removes or replaces, which can't be
+/// combined without the document.
+#[napi]
+pub fn merge_patches(patches: Vec<Json>) -> Result<Option<String>> {
+    let patches = patches
+        .iter()
+        .enumerate()
+        .map(|(index, patch)| parse(patch, &format!("patches[{index}]")))
+        .collect::<Result<Vec<_>>>()?;
+    if patches.is_empty() {
+        return Ok(None);
+    }
+    match json_merge_patch_gen::squash(&patches) {
+        Some(merged) => Ok(Some(merged.to_string())),
+        None => Err(Error::new(
+            Status::InvalidArg,
+            "patches: an object is merged into a member that an earlier patch removes or replaces",
+        )),
+    }
+}
+
+fn parse(json: &Json, name: &str) -> Result<Value> {
+    let parsed = match json {
+        Either::A(string) => serde_json::from_str(string),
+        Either::B(buffer) => serde_json::from_slice(buffer),
+    };
+    parsed.map_err(|error| Error::new(Status::InvalidArg, format!("{name}: {error}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json(text: &str) -> Json {
+        Either::A(text.to_string())
+    }
+
+    #[test]
+    fn test_generate_apply() {
+        let before = r#"{"a":1,"b":{"c":[1,2]},"d":2}"#;
+        let after = r#"{"a":1,"b":{"c":[3]},"e":"x"}"#;
+        let patch = generate(json(before), Either::B(after.as_bytes().to_vec().into()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            parsed(&patch),
+            parsed(r#"{"b":{"c":[3]},"d":null,"e":"x"}"#)
+        );
+        assert_eq!(
+            parsed(&apply(json(before), json(&patch)).unwrap()),
+            parsed(after)
+        );
+        assert_eq!(generate(json(after), json(after)).unwrap(), None);
+
+        let error = generate(json("{"), json("{}")).unwrap_err();
+        assert_eq!(error.status, Status::InvalidArg);
+        assert!(error.reason.starts_with("before: "));
+    }
+
+    #[test]
+    fn test_merge_patches() {
+        let merged = merge_patches(vec![
+            json(r#"{"a":1,"b":{"c":1}}"#),
+            json(r#"{"b":{"d":2}}"#),
+        ]);
+        assert_eq!(
+            parsed(&merged.unwrap().unwrap()),
+            parsed(r#"{"a":1,"b":{"c":1,"d":2}}"#)
+        );
+        assert_eq!(merge_patches(vec![]).unwrap(), None);
+
+        let error =
+            merge_patches(vec![json(r#"{"a":null}"#), json(r#"{"a":{"b":1}}"#)]).unwrap_err();
+        assert_eq!(error.status, Status::InvalidArg);
+    }
+
+    fn parsed(text: &str) -> Value {
+        serde_json::from_str(text).unwrap()
+    }
+}
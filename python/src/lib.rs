@@ -0,0 +1,70 @@
+/*!
+ * The `json_merge_patch` Python extension module, built with maturin. Documents
+ * and patches are plain Python values, as from `json.loads`.
+ */
+
+use json_merge_patch_gen::PlainNumbers;
+use pyo3::prelude::*;
+use pythonize::{depythonize, pythonize};
+use serde_json::Value;
+
+/// The merge patch that turns `before` into `after`, or `None` if they're
+/// equal.
+#[pyfunction]
+fn generate<'py>(
+    py: Python<'py>,
+    before: &Bound<'py, PyAny>,
+    after: &Bound<'py, PyAny>,
+) -> PyResult<Option<Bound<'py, PyAny>>> {
+    let before: Value = depythonize(before)?;
+    let after: Value = depythonize(after)?;
+    match json_merge_patch_gen::generate(&before, &after) {
+        Some(patch) => Ok(Some(pythonize(py, &PlainNumbers(&patch))?)),
+        None => Ok(None),
+    }
+}
+
+/// A copy of `document` with `patch` applied.
+#[pyfunction]
+fn apply<'py>(
+    py: Python<'py>,
+    document: &Bound<'py, PyAny>,
+    patch: &Bound<'py, PyAny>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let mut document: Value = depythonize(document)?;
+    let patch: Value = depythonize(patch)?;
+    json_merge_patch_gen::apply(&mut document, &patch);
+    Ok(pythonize(py, &PlainNumbers(&document))?)
+}
+
+#[pymodule]
+fn json_merge_patch(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(generate, module)?)?;
+    module.add_function(wrap_pyfunction!(apply, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::PyDict;
+
+    #[test]
+    fn test_generate_apply() {
+        Python::initialize();
+        Python::attach(|py| {
+            let json = py.import("json").unwrap();
+            let loads = |text: &str| json.call_method1("loads", (text,)).unwrap();
+            let before = loads(r#"{"a": 1, "b": {"c": [1, 2]}, "d": null}"#);
+            let after = loads(r#"{"a": 1, "b": {"c": [3, -4.5]}, "e": "x"}"#);
+
+            let patch = generate(py, &before, &after).unwrap().unwrap();
+            assert!(patch.is_instance_of::<PyDict>());
+            assert!(patch
+                .eq(loads(r#"{"b": {"c": [3, -4.5]}, "d": null, "e": "x"}"#))
+                .unwrap());
+            assert!(apply(py, &before, &patch).unwrap().eq(&after).unwrap());
+            assert!(generate(py, &after, &after).unwrap().is_none());
+        });
+    }
+}
@@ -0,0 +1,182 @@
+/*!
+ * An [actix-web](https://docs.rs/actix-web) extractor for merge patch request
+ * bodies.
+ */
+
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+
+use ::actix_web::body::{self, BodyStream};
+use ::actix_web::dev::Payload;
+use ::actix_web::error::PayloadError;
+use ::actix_web::http::{header, StatusCode};
+use ::actix_web::{FromRequest, HttpRequest, HttpResponse, ResponseError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::media_type::is_merge_patch;
+use crate::typed::MergePatch;
+
+/**
+ * Extracts the `application/merge-patch+json` body of a request as a
+ * [`MergePatch`] of a `T`, up to the limit of the [`JsonMergePatchConfig`] in
+ * the app data.
+ */
+pub struct JsonMergePatch<T>(pub MergePatch<T>);
+
+impl<T> JsonMergePatch<T> {
+    /** The patch as it was in the body */
+    pub fn value(&self) -> &Value {
+        self.0.as_value()
+    }
+
+    /** The patch, without the extractor */
+    pub fn into_inner(self) -> MergePatch<T> {
+        self.0
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> JsonMergePatch<T> {
+    /**
+     * Applies the patch to `target`, see [`MergePatch::apply`].
+     */
+    pub fn apply_to(&self, target: &mut T) -> Result<(), serde_json::Error> {
+        self.0.apply(target)
+    }
+}
+
+impl<T> fmt::Debug for JsonMergePatch<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("JsonMergePatch").field(&self.0).finish()
+    }
+}
+
+impl<T: 'static> FromRequest for JsonMergePatch<T> {
+    type Error = JsonMergePatchError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self, Self::Error>>>>;
+
+    fn from_request(request: &HttpRequest, payload: &mut Payload) -> Self::Future {
+        let headers = request.headers();
+        let content_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok());
+        let limit = request
+            .app_data::<JsonMergePatchConfig>()
+            .unwrap_or(&JsonMergePatchConfig::DEFAULT)
+            .limit;
+        let length = headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok()?.parse::<usize>().ok());
+
+        let error = match () {
+            _ if !content_type.is_some_and(is_merge_patch) => {
+                Some(JsonMergePatchError::UnsupportedMediaType)
+            }
+            _ if length.is_some_and(|length| length > limit) => {
+                Some(JsonMergePatchError::TooLarge { limit })
+            }
+            _ => None,
+        };
+        let stream = BodyStream::new(payload.take());
+        Box::pin(async move {
+            if let Some(error) = error {
+                return Err(error);
+            }
+            let body = body::to_bytes_limited(stream, limit)
+                .await
+                .map_err(|_| JsonMergePatchError::TooLarge { limit })?
+                .map_err(JsonMergePatchError::Payload)?;
+            let patch = serde_json::from_slice(&body)
+                .map_err(|error| JsonMergePatchError::InvalidJson(error.to_string()))?;
+            Ok(JsonMergePatch(MergePatch::new(patch)))
+        })
+    }
+}
+
+/**
+ * Configuration of the [`JsonMergePatch`] extractor, added to the app data of
+ * the app, scope or resource that it applies to.
+ */
+#[derive(Debug, Clone)]
+pub struct JsonMergePatchConfig {
+    limit: usize,
+}
+
+impl JsonMergePatchConfig {
+    const DEFAULT: JsonMergePatchConfig = JsonMergePatchConfig {
+        limit: 2 * 1024 * 1024,
+    };
+
+    /**
+     * Sets the maximum size of bodies in bytes, 2 MiB by default.
+     */
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+impl Default for JsonMergePatchConfig {
+    fn default() -> Self {
+        JsonMergePatchConfig::DEFAULT
+    }
+}
+
+/**
+ * Why a request didn't have a merge patch body, responded to with its status
+ * and a JSON body like `{"error": "..."}`.
+ */
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum JsonMergePatchError {
+    /** The `Content-Type` isn't `application/merge-patch+json` (415) */
+    UnsupportedMediaType,
+    /** The body is larger than the configured limit (413) */
+    TooLarge { limit: usize },
+    /** The body isn't JSON (400) */
+    InvalidJson(String),
+    /** The body couldn't be read (400) */
+    Payload(PayloadError),
+}
+
+impl fmt::Display for JsonMergePatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonMergePatchError::UnsupportedMediaType => {
+                write!(
+                    f,
+                    "expected a request with `Content-Type: application/merge-patch+json`"
+                )
+            }
+            JsonMergePatchError::TooLarge { limit } => {
+                write!(f, "the merge patch is larger than {limit} bytes")
+            }
+            JsonMergePatchError::InvalidJson(message) => {
+                write!(f, "failed to parse the merge patch: {message}")
+            }
+            JsonMergePatchError::Payload(error) => {
+                write!(f, "failed to read the merge patch: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for JsonMergePatchError {}
+
+impl ResponseError for JsonMergePatchError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            JsonMergePatchError::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            JsonMergePatchError::TooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            JsonMergePatchError::InvalidJson(_) | JsonMergePatchError::Payload(_) => {
+                StatusCode::BAD_REQUEST
+            }
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(json!({"error": self.to_string()}))
+    }
+}
@@ -0,0 +1,90 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use serde_json::{Map, Value};
+
+use crate::copy::deep_clone;
+use crate::pointer;
+
+/**
+ * Applies `patch` to `document` as described in RFC 7386.
+ */
+pub(crate) fn apply(document: &mut Value, patch: &Value) {
+    let mut stack = vec![(document, patch)];
+
+    while let Some((target, patch)) = stack.pop() {
+        let Value::Object(patch) = patch else {
+            *target = deep_clone(patch);
+            continue;
+        };
+        if !target.is_object() {
+            *target = Value::Object(Map::new());
+        }
+        let Value::Object(target) = target else {
+            unreachable!("target was made an object");
+        };
+
+        // Objects are merged into the target's members once the other members
+        // are done, as that needs them all borrowed at once
+        for (key, patch) in patch {
+            match patch {
+                Value::Null => {
+                    target.remove(key);
+                }
+                Value::Object(_) => {
+                    target.entry(key).or_insert(Value::Null);
+                }
+                _ => {
+                    target.insert(key.clone(), deep_clone(patch));
+                }
+            }
+        }
+        for (key, target) in target.iter_mut() {
+            if let Some(patch @ Value::Object(_)) = patch.get(key) {
+                stack.push((target, patch));
+            }
+        }
+    }
+}
+
+pub(crate) fn apply_partial(document: &mut Value, patch: &Value) -> Vec<String> {
+    let mut skipped = Vec::new();
+    let Value::Object(patch) = patch else {
+        *document = deep_clone(patch);
+        return skipped;
+    };
+    let Value::Object(document) = document else {
+        skipped.push(String::new());
+        return skipped;
+    };
+
+    // Objects are merged once the other members are done, like in `apply`
+    let mut stack = vec![(String::new(), document, patch)];
+    while let Some((path, target, patch)) = stack.pop() {
+        for (key, patch) in patch {
+            match (patch, target.get(key)) {
+                (Value::Null, _) => {
+                    target.remove(key);
+                }
+                (Value::Object(_), None) => {
+                    target.insert(key.clone(), Value::Object(Map::new()));
+                }
+                (Value::Object(_), Some(Value::Object(_))) => {}
+                (Value::Object(_), Some(_)) => {
+                    skipped.push([path.as_str(), "/", &pointer::escape(key)].concat());
+                }
+                _ => {
+                    target.insert(key.clone(), deep_clone(patch));
+                }
+            }
+        }
+        for (key, target) in target.iter_mut() {
+            if let (Some(Value::Object(patch)), Value::Object(target)) = (patch.get(key), target) {
+                let pointer = [path.as_str(), "/", &pointer::escape(key)].concat();
+                stack.push((pointer, target, patch));
+            }
+        }
+    }
+    skipped
+}
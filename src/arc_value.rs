@@ -0,0 +1,191 @@
+use alloc::borrow::ToOwned;
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use serde_json::{Number, Value};
+
+use crate::value::{JsonLike, Kind};
+
+/**
+ * A JSON value whose strings, arrays and objects are reference counted, so
+ * that clones share their contents.
+ *
+ * Patches generated for `ArcValue`s share the unchanged parts of the `after`
+ * document instead of copying them, and subtrees shared by both documents are
+ * skipped without comparing them.
+ *
+ * Conversions from and to [`Value`], serialization and [`Drop`] of this type
+ * recurse on the nesting depth of the value.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArcValue {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(Arc<str>),
+    Array(Arc<[ArcValue]>),
+    Object(Arc<BTreeMap<String, ArcValue>>),
+}
+
+impl JsonLike for ArcValue {
+    type Object = BTreeMap<String, ArcValue>;
+    type Members<'a> = core::iter::Map<
+        alloc::collections::btree_map::Iter<'a, String, ArcValue>,
+        fn((&'a String, &'a ArcValue)) -> (&'a str, &'a ArcValue),
+    >;
+
+    fn kind(&self) -> Kind<'_, Self> {
+        match self {
+            ArcValue::Null => Kind::Null,
+            ArcValue::Bool(_) => Kind::Bool,
+            ArcValue::Number(_) => Kind::Number,
+            ArcValue::String(string) => Kind::String(string),
+            ArcValue::Array(array) => Kind::Array(array),
+            ArcValue::Object(object) => Kind::Object(&**object),
+        }
+    }
+
+    fn members(object: &BTreeMap<String, ArcValue>) -> Self::Members<'_> {
+        object.iter().map(|(key, value)| (key.as_str(), value))
+    }
+
+    fn get<'a>(object: &'a BTreeMap<String, ArcValue>, key: &str) -> Option<&'a Self> {
+        object.get(key)
+    }
+
+    fn len(object: &BTreeMap<String, ArcValue>) -> usize {
+        object.len()
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        match self {
+            ArcValue::Number(number) => number.as_i64(),
+            _ => None,
+        }
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        match self {
+            ArcValue::Number(number) => number.as_u64(),
+            _ => None,
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            ArcValue::Number(number) => number.as_f64(),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    fn number_text(&self) -> Option<String> {
+        match self {
+            ArcValue::Number(number) => Some(number.as_str().to_owned()),
+            _ => None,
+        }
+    }
+
+    fn null() -> Self {
+        ArcValue::Null
+    }
+
+    fn array(values: Vec<Self>) -> Self {
+        ArcValue::Array(values.into())
+    }
+
+    fn new_object() -> BTreeMap<String, ArcValue> {
+        BTreeMap::new()
+    }
+
+    fn insert(object: &mut BTreeMap<String, ArcValue>, key: &str, value: Self) {
+        object.insert(key.to_owned(), value);
+    }
+
+    fn object(object: BTreeMap<String, ArcValue>) -> Self {
+        ArcValue::Object(Arc::new(object))
+    }
+
+    fn copy_for_patch(&self) -> Self {
+        self.clone()
+    }
+
+    fn shares_with(&self, other: &Self) -> bool {
+        match (self, other) {
+            (ArcValue::String(a), ArcValue::String(b)) => Arc::ptr_eq(a, b),
+            (ArcValue::Array(a), ArcValue::Array(b)) => Arc::ptr_eq(a, b),
+            (ArcValue::Object(a), ArcValue::Object(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl From<Value> for ArcValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => ArcValue::Null,
+            Value::Bool(bool) => ArcValue::Bool(bool),
+            Value::Number(number) => ArcValue::Number(number),
+            Value::String(string) => ArcValue::String(string.into()),
+            Value::Array(array) => ArcValue::Array(array.into_iter().map(ArcValue::from).collect()),
+            Value::Object(object) => ArcValue::Object(Arc::new(
+                object
+                    .into_iter()
+                    .map(|(key, value)| (key, ArcValue::from(value)))
+                    .collect(),
+            )),
+        }
+    }
+}
+
+impl From<&ArcValue> for Value {
+    fn from(value: &ArcValue) -> Self {
+        match value {
+            ArcValue::Null => Value::Null,
+            ArcValue::Bool(bool) => Value::Bool(*bool),
+            ArcValue::Number(number) => Value::Number(number.clone()),
+            ArcValue::String(string) => Value::String(string.as_ref().to_owned()),
+            ArcValue::Array(array) => Value::Array(array.iter().map(Value::from).collect()),
+            ArcValue::Object(object) => Value::Object(
+                object
+                    .iter()
+                    .map(|(key, value)| (key.clone(), Value::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<ArcValue> for Value {
+    fn from(value: ArcValue) -> Self {
+        Value::from(&value)
+    }
+}
+
+impl Serialize for ArcValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ArcValue::Null => serializer.serialize_unit(),
+            ArcValue::Bool(bool) => serializer.serialize_bool(*bool),
+            ArcValue::Number(number) => number.serialize(serializer),
+            ArcValue::String(string) => serializer.serialize_str(string),
+            ArcValue::Array(array) => {
+                let mut seq = serializer.serialize_seq(Some(array.len()))?;
+                for value in array.iter() {
+                    seq.serialize_element(value)?;
+                }
+                seq.end()
+            }
+            ArcValue::Object(object) => {
+                let mut map = serializer.serialize_map(Some(object.len()))?;
+                for (key, value) in object.iter() {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
@@ -0,0 +1,137 @@
+/*!
+ * An [axum](https://docs.rs/axum) extractor for merge patch request bodies.
+ */
+
+use core::fmt;
+
+use ::axum::body::Bytes;
+use ::axum::extract::rejection::BytesRejection;
+use ::axum::extract::{FromRequest, Request};
+use ::axum::http::{header, StatusCode};
+use ::axum::response::{IntoResponse, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::media_type::is_merge_patch;
+use crate::typed::MergePatch;
+
+/**
+ * Extracts the `application/merge-patch+json` body of a request as a
+ * [`MergePatch`] of a `T`.
+ *
+ * ```
+ * use axum::http::StatusCode;
+ * use json_merge_patch_gen::axum::JsonMergePatch;
+ * # #[derive(serde::Serialize, serde::Deserialize)]
+ * # struct User { name: String }
+ * # fn load() -> User { User { name: String::new() } }
+ *
+ * async fn patch_user(patch: JsonMergePatch<User>) -> StatusCode {
+ *     let mut user = load();
+ *     match patch.apply_to(&mut user) {
+ *         Ok(()) => StatusCode::NO_CONTENT,
+ *         Err(_) => StatusCode::UNPROCESSABLE_ENTITY,
+ *     }
+ * }
+ * ```
+ */
+pub struct JsonMergePatch<T>(pub MergePatch<T>);
+
+impl<T> JsonMergePatch<T> {
+    /** The patch as it was in the body */
+    pub fn value(&self) -> &Value {
+        self.0.as_value()
+    }
+
+    /** The patch, without the extractor */
+    pub fn into_inner(self) -> MergePatch<T> {
+        self.0
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> JsonMergePatch<T> {
+    /**
+     * Applies the patch to `target`, see [`MergePatch::apply`].
+     */
+    pub fn apply_to(&self, target: &mut T) -> Result<(), serde_json::Error> {
+        self.0.apply(target)
+    }
+}
+
+impl<T> fmt::Debug for JsonMergePatch<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("JsonMergePatch").field(&self.0).finish()
+    }
+}
+
+impl<T, S: Send + Sync> FromRequest<S> for JsonMergePatch<T> {
+    type Rejection = JsonMergePatchRejection;
+
+    async fn from_request(request: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let content_type = request
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok());
+        if !content_type.is_some_and(is_merge_patch) {
+            return Err(JsonMergePatchRejection::UnsupportedMediaType);
+        }
+        let body = Bytes::from_request(request, state)
+            .await
+            .map_err(JsonMergePatchRejection::Body)?;
+        let patch = serde_json::from_slice(&body)
+            .map_err(|error| JsonMergePatchRejection::InvalidJson(error.to_string()))?;
+        Ok(JsonMergePatch(MergePatch::new(patch)))
+    }
+}
+
+/**
+ * Why a request didn't have a merge patch body, responded to with the status
+ * of [`status`](JsonMergePatchRejection::status).
+ */
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum JsonMergePatchRejection {
+    /** The `Content-Type` isn't `application/merge-patch+json` (415) */
+    UnsupportedMediaType,
+    /** The body isn't JSON (400) */
+    InvalidJson(String),
+    /** The body couldn't be read, e.g. as it's too large */
+    Body(BytesRejection),
+}
+
+impl JsonMergePatchRejection {
+    /** The status of the response to the request */
+    pub fn status(&self) -> StatusCode {
+        match self {
+            JsonMergePatchRejection::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            JsonMergePatchRejection::InvalidJson(_) => StatusCode::BAD_REQUEST,
+            JsonMergePatchRejection::Body(rejection) => rejection.status(),
+        }
+    }
+}
+
+impl fmt::Display for JsonMergePatchRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonMergePatchRejection::UnsupportedMediaType => {
+                write!(
+                    f,
+                    "expected a request with `Content-Type: application/merge-patch+json`"
+                )
+            }
+            JsonMergePatchRejection::InvalidJson(message) => {
+                write!(f, "failed to parse the merge patch: {message}")
+            }
+            JsonMergePatchRejection::Body(rejection) => write!(f, "{}", rejection.body_text()),
+        }
+    }
+}
+
+impl std::error::Error for JsonMergePatchRejection {}
+
+impl IntoResponse for JsonMergePatchRejection {
+    fn into_response(self) -> Response {
+        (self.status(), self.to_string()).into_response()
+    }
+}
@@ -0,0 +1,108 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use serde_json::Value;
+
+use crate::Options;
+
+/**
+ * How a document of a keyed collection changed, see
+ * [`generate_batch_keyed`].
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum DocumentChange<'a> {
+    /** The document is new, with its value in `afters` */
+    Added(&'a Value),
+    /** The document is only in `befores` */
+    Removed,
+    /** The document is in both, with the patch between them */
+    Patched(Value),
+}
+
+/**
+ * Generates the JSON Merge Patches (RFC 7386) like
+ * [`generate`](crate::generate) between the documents at the same positions
+ * of `befores` and `afters`, where `None` means that they're equal.
+ *
+ * Panics if the slices have different lengths, see [`generate_batch_keyed`]
+ * for collections that gain and lose documents.
+ */
+pub fn generate_batch(befores: &[Value], afters: &[Value]) -> Vec<Option<Value>> {
+    generate_batch_with(befores, afters, &Options::default())
+}
+
+/**
+ * [`generate_batch`] using the given [`Options`].
+ */
+pub fn generate_batch_with(
+    befores: &[Value],
+    afters: &[Value],
+    options: &Options,
+) -> Vec<Option<Value>> {
+    assert_eq!(befores.len(), afters.len(), "batches of different lengths");
+    befores
+        .iter()
+        .zip(afters)
+        .map(|(before, after)| crate::generate_with(before, after, options))
+        .collect()
+}
+
+/**
+ * Compares the documents with the same keys in `befores` and `afters`,
+ * returning how each document that differs changed, including the ones that
+ * were added or removed.
+ */
+pub fn generate_batch_keyed<'a, K: Ord>(
+    befores: &'a BTreeMap<K, Value>,
+    afters: &'a BTreeMap<K, Value>,
+) -> BTreeMap<&'a K, DocumentChange<'a>> {
+    generate_batch_keyed_with(befores, afters, &Options::default())
+}
+
+/**
+ * [`generate_batch_keyed`] using the given [`Options`].
+ */
+pub fn generate_batch_keyed_with<'a, K: Ord>(
+    befores: &'a BTreeMap<K, Value>,
+    afters: &'a BTreeMap<K, Value>,
+    options: &Options,
+) -> BTreeMap<&'a K, DocumentChange<'a>> {
+    keyed(befores, afters)
+        .filter_map(|(key, before, after)| {
+            document_change(before, after, options).map(|change| (key, change))
+        })
+        .collect()
+}
+
+/**
+ * The keys of either collection, with the documents they have in each.
+ */
+pub(crate) fn keyed<'a, K: Ord>(
+    befores: &'a BTreeMap<K, Value>,
+    afters: &'a BTreeMap<K, Value>,
+) -> impl Iterator<Item = (&'a K, Option<&'a Value>, Option<&'a Value>)> {
+    afters
+        .iter()
+        .map(|(key, after)| (key, befores.get(key), Some(after)))
+        .chain(
+            befores
+                .iter()
+                .filter(|(key, _)| !afters.contains_key(*key))
+                .map(|(key, before)| (key, Some(before), None)),
+        )
+}
+
+pub(crate) fn document_change<'a>(
+    before: Option<&Value>,
+    after: Option<&'a Value>,
+    options: &Options,
+) -> Option<DocumentChange<'a>> {
+    match (before, after) {
+        (Some(before), Some(after)) => {
+            crate::generate_with(before, after, options).map(DocumentChange::Patched)
+        }
+        (None, Some(after)) => Some(DocumentChange::Added(after)),
+        (Some(_), None) => Some(DocumentChange::Removed),
+        (None, None) => None,
+    }
+}
@@ -0,0 +1,71 @@
+/*!
+ * Patch generation for documents encoded as [CBOR](https://www.rfc-editor.org/rfc/rfc8949),
+ * decoding them straight into [`serde_json::Value`]s and encoding the patches
+ * back, without going through JSON text.
+ *
+ * Only documents that JSON can represent are supported: maps need text keys,
+ * and byte strings don't decode.
+ */
+
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::Options;
+
+/**
+ * The bytes aren't a CBOR encoded JSON document.
+ */
+#[derive(Debug)]
+pub struct DecodeError(ciborium::de::Error<std::io::Error>);
+
+/**
+ * Decodes a CBOR encoded document.
+ */
+pub fn from_slice(bytes: &[u8]) -> Result<Value, DecodeError> {
+    ciborium::from_reader(bytes).map_err(DecodeError)
+}
+
+/**
+ * Encodes a document, e.g. a patch, as CBOR.
+ */
+pub fn to_vec(value: &Value) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    // The .unwrap() is safe because writing to a Vec can't fail and every
+    // JSON value can be encoded
+    ciborium::into_writer(value, &mut bytes).unwrap();
+    bytes
+}
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`](crate::generate),
+ * from and to CBOR encoded documents.
+ */
+pub fn generate(before: &[u8], after: &[u8]) -> Result<Option<Vec<u8>>, DecodeError> {
+    generate_with(before, after, &Options::default())
+}
+
+/**
+ * [`generate`] using the given [`Options`].
+ */
+pub fn generate_with(
+    before: &[u8],
+    after: &[u8],
+    options: &Options,
+) -> Result<Option<Vec<u8>>, DecodeError> {
+    let before = from_slice(before)?;
+    let after = from_slice(after)?;
+    Ok(crate::generate_with(&before, &after, options).map(|patch| to_vec(&patch)))
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to decode CBOR: {}", self.0)
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
@@ -0,0 +1,175 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::Value;
+
+use crate::diff::Diff;
+use crate::pointer;
+use crate::Options;
+
+/**
+ * A change that a merge patch makes to a document, see [`changes`](crate::changes).
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change<'a> {
+    /** JSON Pointer (RFC 6901) to the changed value */
+    pub pointer: String,
+    /** The value before the change, `None` if it's added */
+    pub before: Option<&'a Value>,
+    /**
+     * The value after the change, `None` if it's removed. Objects are as they
+     * appear in the patch, i.e. `null` members are dropped when applying it.
+     */
+    pub after: Option<&'a Value>,
+}
+
+/**
+ * Kind of a [`Change`].
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Modified,
+}
+
+impl Change<'_> {
+    pub fn kind(&self) -> ChangeKind {
+        match (self.before, self.after) {
+            (None, _) => ChangeKind::Added,
+            (_, None) => ChangeKind::Removed,
+            _ => ChangeKind::Modified,
+        }
+    }
+}
+
+/**
+ * The changes that a patch makes to a document, see [`preview`](crate::preview).
+ *
+ * Serializes as an array of objects with the `pointer`, `kind` (`"added"`,
+ * `"removed"` or `"modified"`) and the `before` and `after` values of each
+ * change, where absent values are left out.
+ */
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChangeSet<'a> {
+    changes: Vec<Change<'a>>,
+}
+
+impl<'a> ChangeSet<'a> {
+    /** Whether the patch leaves the document as it is */
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn iter(&self) -> core::slice::Iter<'_, Change<'a>> {
+        self.changes.iter()
+    }
+
+    /** The changes of the given kind */
+    pub fn of_kind(&self, kind: ChangeKind) -> impl Iterator<Item = &Change<'a>> {
+        self.changes
+            .iter()
+            .filter(move |change| change.kind() == kind)
+    }
+}
+
+impl<'a> From<Vec<Change<'a>>> for ChangeSet<'a> {
+    fn from(changes: Vec<Change<'a>>) -> Self {
+        Self { changes }
+    }
+}
+
+impl<'a> IntoIterator for ChangeSet<'a> {
+    type Item = Change<'a>;
+    type IntoIter = vec::IntoIter<Change<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.into_iter()
+    }
+}
+
+impl<'s, 'a> IntoIterator for &'s ChangeSet<'a> {
+    type Item = &'s Change<'a>;
+    type IntoIter = core::slice::Iter<'s, Change<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.iter()
+    }
+}
+
+impl Serialize for ChangeSet<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(&self.changes)
+    }
+}
+
+impl Serialize for Change<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let kind = match self.kind() {
+            ChangeKind::Added => "added",
+            ChangeKind::Removed => "removed",
+            ChangeKind::Modified => "modified",
+        };
+        let len = 2 + usize::from(self.before.is_some()) + usize::from(self.after.is_some());
+        let mut map = serializer.serialize_map(Some(len))?;
+        map.serialize_entry("pointer", &self.pointer)?;
+        map.serialize_entry("kind", kind)?;
+        if let Some(before) = self.before {
+            map.serialize_entry("before", before)?;
+        }
+        if let Some(after) = self.after {
+            map.serialize_entry("after", after)?;
+        }
+        map.end()
+    }
+}
+
+pub(crate) fn changes<'a>(document: &'a Value, patch: &'a Value) -> Vec<Change<'a>> {
+    let mut changes = Vec::new();
+    let (Value::Object(document), Value::Object(patch)) = (document, patch) else {
+        if !equal(document, patch) {
+            changes.push(Change {
+                pointer: String::new(),
+                before: Some(document),
+                after: Some(patch),
+            });
+        }
+        return changes;
+    };
+
+    let mut stack = vec![(String::new(), document, patch.iter())];
+    while let Some((path, document, members)) = stack.last_mut() {
+        let document = *document;
+        let Some((key, patch)) = members.next() else {
+            stack.pop();
+            continue;
+        };
+        let pointer = [path.as_str(), "/", &pointer::escape(key)].concat();
+
+        match (document.get(key), patch) {
+            (None, Value::Null) => {}
+            (Some(Value::Object(before)), Value::Object(patch)) => {
+                stack.push((pointer, before, patch.iter()));
+            }
+            (Some(before), _) if equal(before, patch) => {}
+            (before, patch) => changes.push(Change {
+                pointer,
+                before,
+                after: (!patch.is_null()).then_some(patch),
+            }),
+        }
+    }
+    changes
+}
+
+pub(crate) fn equal(a: &Value, b: &Value) -> bool {
+    Diff::new(&Options::default())
+        .member_patch::<Value, Value>(Some(a), Some(b))
+        .is_none()
+}
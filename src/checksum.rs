@@ -0,0 +1,51 @@
+use core::fmt::Write;
+
+use serde_json::Value;
+
+use crate::etag::Fnv1a;
+use crate::Error;
+
+/**
+ * A patch with the [`checksum`](crate::checksum) of the document that it was
+ * generated from, see [`generate_with_checksum`](crate::generate_with_checksum).
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChecksummedPatch {
+    pub patch: Value,
+    /** Checksum of the document before the patch */
+    pub checksum: u64,
+}
+
+pub(crate) fn checksum(document: &Value) -> u64 {
+    // Members are hashed in the order of their keys, which they're only kept
+    // in without serde_json's `preserve_order` feature
+    #[cfg(feature = "preserve_order")]
+    let document = &{
+        let mut document = crate::copy::deep_clone(document);
+        crate::JsonLike::sort_keys(&mut document);
+        document
+    };
+    let mut hasher = Fnv1a::new();
+    // The .unwrap() is safe because writing to the hasher never fails
+    write!(hasher, "{document}").unwrap();
+    hasher.0
+}
+
+pub(crate) fn generate_with_checksum(before: &Value, after: &Value) -> Option<ChecksummedPatch> {
+    crate::generate(before, after).map(|patch| ChecksummedPatch {
+        patch,
+        checksum: checksum(before),
+    })
+}
+
+pub(crate) fn apply_checked(document: &mut Value, patch: &ChecksummedPatch) -> Result<(), Error> {
+    let actual = checksum(document);
+    if actual != patch.checksum {
+        return Err(Error::ChecksumMismatch {
+            expected: patch.checksum,
+            actual,
+        });
+    }
+    crate::apply(document, &patch.patch);
+    Ok(())
+}
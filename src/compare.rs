@@ -0,0 +1,160 @@
+use crate::value::{JsonLike, Kind};
+use crate::Options;
+
+/**
+ * Equality of two values that are not both arrays or objects, according to
+ * `options`.
+ */
+pub(crate) fn scalars_equal<V: JsonLike>(a: &V, b: &V, options: &Options) -> bool {
+    match (a.kind(), b.kind()) {
+        (Kind::Number, Kind::Number) => numbers_equal(a, b, options),
+        (Kind::String(a), Kind::String(b)) => strings_equal(a, b, options),
+        _ => a == b,
+    }
+}
+
+fn strings_equal(a: &str, b: &str, options: &Options) -> bool {
+    if a == b {
+        return true;
+    }
+
+    #[cfg(feature = "unicode")]
+    {
+        use crate::UnicodeNormalization;
+        use unicode_normalization::UnicodeNormalization as _;
+
+        match options.unicode_normalization {
+            Some(UnicodeNormalization::Nfc) => return a.nfc().eq(b.nfc()),
+            Some(UnicodeNormalization::Nfkc) => return a.nfkc().eq(b.nfkc()),
+            None => {}
+        }
+    }
+
+    #[cfg(not(feature = "unicode"))]
+    let _ = options;
+
+    false
+}
+
+fn numbers_equal<V: JsonLike>(a: &V, b: &V, options: &Options) -> bool {
+    if a == b {
+        return true;
+    }
+    if !options.numeric_equality {
+        return false;
+    }
+
+    // Integers are compared exactly below, as they may not fit an f64
+    let integers = as_integer(a).is_some() && as_integer(b).is_some();
+    if let (Some(epsilon), false) = (options.float_epsilon, integers) {
+        if let (Some(a), Some(b)) = (a.as_f64(), b.as_f64()) {
+            return (a - b).abs() <= epsilon;
+        }
+    }
+
+    // Numbers kept as their original decimal text may not fit any primitive
+    // type. Compare the exact decimal values instead.
+    #[cfg(feature = "arbitrary_precision")]
+    if let (Some(a), Some(b)) = (a.number_text(), b.number_text()) {
+        return decimal::equal(&a, &b);
+    }
+
+    // Compare integers against integral floats exactly rather than via a lossy
+    // conversion to f64
+    match (as_integer(a), as_integer(b)) {
+        (Some(a), Some(b)) => a == b,
+        (Some(a), None) => float_equals_integer(b, a),
+        (None, Some(b)) => float_equals_integer(a, b),
+        (None, None) => a.as_f64().is_some() && a.as_f64() == b.as_f64(),
+    }
+}
+
+fn as_integer<V: JsonLike>(n: &V) -> Option<i128> {
+    n.as_i64()
+        .map(i128::from)
+        .or_else(|| n.as_u64().map(i128::from))
+}
+
+fn float_equals_integer<V: JsonLike>(float: &V, integer: i128) -> bool {
+    // The conversion saturates, but integers from i64 or u64 are far from the
+    // bounds of i128. The round trip only succeeds for integral floats.
+    match float.as_f64() {
+        Some(f) => f as i128 == integer && integer as f64 == f,
+        None => false,
+    }
+}
+
+#[cfg(feature = "arbitrary_precision")]
+mod decimal {
+    use alloc::borrow::ToOwned;
+    use alloc::format;
+    use alloc::string::String;
+
+    /**
+     * A JSON number reduced to `(-1)^negative * digits * 10^exponent`, with
+     * leading and trailing zeros stripped from `digits` so that equal values
+     * have equal representations.
+     */
+    #[derive(Debug, PartialEq)]
+    pub(super) struct Decimal {
+        negative: bool,
+        digits: String,
+        exponent: i128,
+    }
+
+    impl Decimal {
+        /**
+         * Parses the textual form of a JSON number. Returns `None` if `text`
+         * is not a valid JSON number.
+         */
+        pub(super) fn parse(text: &str) -> Option<Self> {
+            let (negative, text) = match text.strip_prefix('-') {
+                Some(rest) => (true, rest),
+                None => (false, text),
+            };
+            let (mantissa, exponent) = match text.find(['e', 'E']) {
+                Some(index) => (&text[..index], text[index + 1..].parse::<i128>().ok()?),
+                None => (text, 0),
+            };
+            let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+            if integer.is_empty()
+                || !integer
+                    .chars()
+                    .chain(fraction.chars())
+                    .all(|c| c.is_ascii_digit())
+            {
+                return None;
+            }
+
+            let digits = format!("{integer}{fraction}");
+            let digits = digits.trim_start_matches('0');
+            let trimmed = digits.trim_end_matches('0');
+
+            if trimmed.is_empty() {
+                // All zeros compare equal regardless of sign and exponent
+                return Some(Self {
+                    negative: false,
+                    digits: String::new(),
+                    exponent: 0,
+                });
+            }
+
+            Some(Self {
+                negative,
+                digits: trimmed.to_owned(),
+                exponent: exponent - fraction.len() as i128
+                    + (digits.len() - trimmed.len()) as i128,
+            })
+        }
+    }
+
+    /**
+     * Exact comparison of two JSON numbers given in their textual form.
+     */
+    pub(super) fn equal(a: &str, b: &str) -> bool {
+        match (Decimal::parse(a), Decimal::parse(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::pointer::Segment;
+use crate::value::{JsonLike, Kind};
+
+/**
+ * Deep clone of `value` that doesn't recurse on its nesting depth.
+ */
+pub(crate) fn deep_clone<V: JsonLike>(value: &V) -> V {
+    copy(value, &mut Vec::new(), |_| false)
+}
+
+/**
+ * Deep clone of the value at `path`, leaving out the descendants for which
+ * `skip` returns true given their path. `path` is restored before returning.
+ */
+pub(crate) fn copy<'a, V: JsonLike>(
+    value: &'a V,
+    path: &mut Vec<Segment<'a>>,
+    mut skip: impl FnMut(&[Segment<'a>]) -> bool,
+) -> V {
+    enum Children<'a, V: JsonLike + 'a> {
+        Array(core::iter::Enumerate<core::slice::Iter<'a, V>>),
+        Object(V::Members<'a>),
+    }
+
+    enum Copy<V: JsonLike> {
+        Array(Vec<V>),
+        Object(V::Object),
+    }
+
+    impl<V: JsonLike> Copy<V> {
+        /**
+         * Adds `value` to the copy, under `key` for objects.
+         */
+        fn insert(&mut self, key: Option<&str>, value: V) {
+            match (self, key) {
+                (Copy::Array(array), _) => array.push(value),
+                (Copy::Object(object), Some(key)) => V::insert(object, key, value),
+                (Copy::Object(_), None) => unreachable!("object members have keys"),
+            }
+        }
+
+        fn finish(self) -> V {
+            match self {
+                Copy::Array(array) => V::array(array),
+                Copy::Object(object) => V::object(object),
+            }
+        }
+    }
+
+    /** An array or object being copied */
+    struct Node<'a, V: JsonLike + 'a> {
+        key: Option<&'a str>,
+        depth: usize,
+        children: Children<'a, V>,
+        copy: Copy<V>,
+    }
+
+    fn node<'a, V: JsonLike>(
+        key: Option<&'a str>,
+        depth: usize,
+        value: &'a V,
+    ) -> Option<Node<'a, V>> {
+        let (children, copy) = match value.kind() {
+            Kind::Array(array) => (
+                Children::Array(array.iter().enumerate()),
+                Copy::Array(Vec::with_capacity(array.len())),
+            ),
+            Kind::Object(object) => (
+                Children::Object(V::members(object)),
+                Copy::Object(V::new_object()),
+            ),
+            _ => return None,
+        };
+        Some(Node {
+            key,
+            depth,
+            children,
+            copy,
+        })
+    }
+
+    let base = path.len();
+    let Some(root) = node(None, base, value) else {
+        return value.clone();
+    };
+    let mut stack = vec![root];
+
+    loop {
+        // The .unwrap() calls are safe because the loop returns once the stack is empty
+        let top = stack.last_mut().unwrap();
+        path.truncate(top.depth);
+
+        let child = match &mut top.children {
+            Children::Array(children) => children
+                .next()
+                .map(|(index, child)| (Segment::Index(index), None, child)),
+            Children::Object(children) => children
+                .next()
+                .map(|(key, child)| (Segment::Key(key), Some(key), child)),
+        };
+
+        match child {
+            Some((segment, key, child)) => {
+                path.push(segment);
+                if skip(path) {
+                    continue;
+                }
+                match node(key, top.depth + 1, child) {
+                    Some(node) => stack.push(node),
+                    None => top.copy.insert(key, child.clone()),
+                }
+            }
+            None => {
+                let node = stack.pop().unwrap();
+                match stack.last_mut() {
+                    Some(parent) => parent.copy.insert(node.key, node.copy.finish()),
+                    None => {
+                        path.truncate(base);
+                        return node.copy.finish();
+                    }
+                }
+            }
+        }
+    }
+}
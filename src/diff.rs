@@ -0,0 +1,763 @@
+use alloc::borrow::Cow;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::compare;
+use crate::copy;
+use crate::filter::{self, Visibility};
+use crate::pointer::{self, Segment};
+use crate::value::{JsonLike, Kind};
+use crate::{Options, Visitor};
+
+/**
+ * A patch representation that the generation can build.
+ */
+pub(crate) trait Output<'a, V: JsonLike + 'a>: Sized {
+    type Members;
+
+    /**
+     * Whether the generation stops at the first patch of a member, returning
+     * it as the patch of the whole document, for outputs that only tell
+     * whether there is one.
+     */
+    const SHORT_CIRCUIT: bool = false;
+
+    fn members() -> Self::Members;
+    fn insert(members: &mut Self::Members, key: &'a str, patch: Self);
+    fn is_empty(members: &Self::Members) -> bool;
+    fn merge(members: Self::Members) -> Self;
+    fn replace(value: Cow<'a, V>) -> Self;
+}
+
+impl<'a, V: JsonLike + 'a> Output<'a, V> for V {
+    type Members = V::Object;
+
+    fn members() -> Self::Members {
+        V::new_object()
+    }
+
+    fn insert(members: &mut Self::Members, key: &'a str, patch: Self) {
+        V::insert(members, key, patch);
+    }
+
+    fn is_empty(members: &Self::Members) -> bool {
+        V::len(members) == 0
+    }
+
+    fn merge(members: Self::Members) -> Self {
+        V::object(members)
+    }
+
+    fn replace(value: Cow<'a, V>) -> Self {
+        match value {
+            Cow::Borrowed(value) => value.copy_for_patch(),
+            Cow::Owned(value) => value,
+        }
+    }
+}
+
+/**
+ * State of a single patch generation: the options in effect and the path of
+ * the node currently being compared.
+ *
+ * Nothing in here recurses on the structure of the documents. Nested values
+ * are handled with explicit stacks instead, so that arbitrarily deep inputs
+ * can't overflow the call stack.
+ */
+pub(crate) struct Diff<'o, 'a, W = Unvisited> {
+    options: &'o Options,
+    path: Vec<Segment<'a>>,
+    visitor: W,
+    /** Number of changes the visitor left out so far */
+    vetoes: usize,
+}
+
+/**
+ * The callbacks of a generation, if there's a [`Visitor`].
+ */
+pub(crate) trait Visit<V> {
+    /** Whether there are callbacks, so that their pointers need to be built */
+    const ENABLED: bool;
+
+    fn added(&mut self, pointer: &str, after: &V) -> bool;
+    fn removed(&mut self, pointer: &str, before: &V) -> bool;
+    fn modified(&mut self, pointer: &str, before: &V, after: &V) -> bool;
+}
+
+pub(crate) struct Unvisited;
+
+impl<V> Visit<V> for Unvisited {
+    const ENABLED: bool = false;
+
+    fn added(&mut self, _: &str, _: &V) -> bool {
+        true
+    }
+
+    fn removed(&mut self, _: &str, _: &V) -> bool {
+        true
+    }
+
+    fn modified(&mut self, _: &str, _: &V, _: &V) -> bool {
+        true
+    }
+}
+
+pub(crate) struct Visiting<T>(pub(crate) T);
+
+impl<V: JsonLike, T: Visitor<V>> Visit<V> for Visiting<T> {
+    const ENABLED: bool = true;
+
+    fn added(&mut self, pointer: &str, after: &V) -> bool {
+        self.0.on_added(pointer, after)
+    }
+
+    fn removed(&mut self, pointer: &str, before: &V) -> bool {
+        self.0.on_removed(pointer, before)
+    }
+
+    fn modified(&mut self, pointer: &str, before: &V, after: &V) -> bool {
+        self.0.on_modified(pointer, before, after)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /** Only changed members end up in the patch */
+    Diff,
+    /** Every member of `after` ends up in the patch, see [`Options::max_depth`] */
+    Replace,
+}
+
+/**
+ * Outcome of comparing a pair of values: either the patch for them is known
+ * right away, or they're objects whose members need to be compared.
+ */
+enum Step<'a, V: JsonLike, P> {
+    Done(Option<P>),
+    Descend(Mode, Option<&'a V::Object>, Option<&'a V::Object>),
+}
+
+struct Member<'a, V> {
+    key: &'a str,
+    before: Option<&'a V>,
+    after: Option<&'a V>,
+}
+
+/**
+ * An object whose members are being compared.
+ */
+struct Frame<'a, V: JsonLike + 'a, P: Output<'a, V>> {
+    /** Key of the object in its parent, `None` for the starting object */
+    key: Option<&'a str>,
+    /** Length of the path to the object */
+    depth: usize,
+    mode: Mode,
+    members: vec::IntoIter<Member<'a, V>>,
+    patch: P::Members,
+    /** The objects, if they'd be replaced as a whole once enough members changed */
+    replaceable: Option<(&'a V::Object, &'a V::Object)>,
+    /** Number of members, and of those with a patch so far */
+    total: usize,
+    changed: usize,
+    /** Number of vetoes of the generation when the frame started */
+    vetoes: usize,
+}
+
+impl<'o, 'a> Diff<'o, 'a> {
+    pub(crate) fn new(options: &'o Options) -> Self {
+        Self::at(options, Vec::new())
+    }
+
+    /**
+     * Starts the generation at `path` instead of the document root, so that
+     * path filters still apply to absolute paths.
+     */
+    pub(crate) fn at(options: &'o Options, path: Vec<Segment<'a>>) -> Self {
+        Self {
+            options,
+            path,
+            visitor: Unvisited,
+            vetoes: 0,
+        }
+    }
+}
+
+impl<'o, 'a, T> Diff<'o, 'a, Visiting<T>> {
+    /**
+     * Reports the changes to `visitor`, leaving out the ones it vetoes.
+     */
+    pub(crate) fn visited(options: &'o Options, visitor: T) -> Self {
+        Self {
+            options,
+            path: Vec::new(),
+            visitor: Visiting(visitor),
+            vetoes: 0,
+        }
+    }
+}
+
+impl<'o, 'a, W> Diff<'o, 'a, W> {
+    pub(crate) fn generate<V: JsonLike + 'a, P: Output<'a, V>>(
+        &mut self,
+        before: &'a V,
+        after: &'a V,
+    ) -> Option<P>
+    where
+        W: Visit<V>,
+    {
+        let step = match self.visibility() {
+            Visibility::Excluded => Step::Done(None),
+            Visibility::Ancestor => {
+                Step::Descend(Mode::Diff, before.as_object(), after.as_object())
+            }
+            Visibility::Included => self.value_step(before, after),
+        };
+        self.run(step)
+    }
+
+    /**
+     * Patch for the object member at the current path, where `None` means that
+     * the member is absent.
+     */
+    pub(crate) fn member_patch<V: JsonLike + 'a, P: Output<'a, V>>(
+        &mut self,
+        before: Option<&'a V>,
+        after: Option<&'a V>,
+    ) -> Option<P>
+    where
+        W: Visit<V>,
+    {
+        let step = self.member_step(Mode::Diff, before, after);
+        self.run(step)
+    }
+
+    /**
+     * Compares the members of objects until no more objects are left to
+     * descend into, and returns the patch for the object of `step`.
+     */
+    fn run<V: JsonLike + 'a, P: Output<'a, V>>(&mut self, step: Step<'a, V, P>) -> Option<P>
+    where
+        W: Visit<V>,
+    {
+        let mut stack = match step {
+            Step::Done(patch) => return patch,
+            Step::Descend(mode, before, after) => {
+                vec![self.frame::<V, P>(None, mode, before, after)]
+            }
+        };
+
+        loop {
+            // The .unwrap() calls are safe because the loop returns once the stack is empty
+            let frame = stack.last_mut().unwrap();
+            self.path.truncate(frame.depth);
+
+            if let Some(member) = frame.members.next() {
+                let mode = frame.mode;
+                self.path.push(Segment::Key(member.key));
+                match self.member_step(mode, member.before, member.after) {
+                    Step::Done(Some(patch)) if P::SHORT_CIRCUIT => {
+                        self.path.truncate(stack[0].depth);
+                        return Some(patch);
+                    }
+                    Step::Done(Some(patch)) => {
+                        let frame = stack.last_mut().unwrap();
+                        frame.changed += 1;
+                        P::insert(&mut frame.patch, member.key, patch);
+                    }
+                    Step::Done(None) => {}
+                    Step::Descend(mode, before, after) => {
+                        stack.push(self.frame(Some(member.key), mode, before, after));
+                    }
+                }
+                continue;
+            }
+
+            let frame = stack.pop().unwrap();
+            if let (Some((before, after)), Some(fraction)) =
+                (frame.replaceable, self.options.replace_changed_objects)
+            {
+                // Replacing the object would bring back the vetoed changes
+                if frame.changed as f64 > fraction * frame.total as f64
+                    && frame.vetoes == self.vetoes
+                {
+                    stack.push(self.frame(frame.key, Mode::Replace, Some(before), Some(after)));
+                    continue;
+                }
+            }
+            let patch = match frame.mode {
+                Mode::Diff if P::is_empty(&frame.patch) => None,
+                _ => Some(P::merge(frame.patch)),
+            };
+            match (stack.last_mut(), frame.key) {
+                (Some(_), Some(_)) if P::SHORT_CIRCUIT && patch.is_some() => {
+                    self.path.truncate(stack[0].depth);
+                    return patch;
+                }
+                (Some(parent), Some(key)) => {
+                    if let Some(patch) = patch {
+                        parent.changed += 1;
+                        P::insert(&mut parent.patch, key, patch);
+                    }
+                }
+                _ => {
+                    self.path.truncate(frame.depth);
+                    return patch;
+                }
+            }
+        }
+    }
+
+    fn frame<V: JsonLike + 'a, P: Output<'a, V>>(
+        &self,
+        key: Option<&'a str>,
+        mode: Mode,
+        before: Option<&'a V::Object>,
+        after: Option<&'a V::Object>,
+    ) -> Frame<'a, V, P>
+    where
+        W: Visit<V>,
+    {
+        let removals = !self.options.ignore_removals;
+        let members = match (before, after) {
+            (Some(before), Some(after)) => pair_sorted::<V>(before, after, removals)
+                .unwrap_or_else(|| pair(Some(before), Some(after), removals)),
+            _ => pair(before, after, removals),
+        };
+
+        // Objects below excluded paths are only compared because of their
+        // included descendants, and are left as they are otherwise
+        let replaceable = match (before, after) {
+            (Some(before), Some(after))
+                if mode == Mode::Diff
+                    && self.options.replace_changed_objects.is_some()
+                    && self.visibility() == Visibility::Included =>
+            {
+                Some((before, after))
+            }
+            _ => None,
+        };
+        Frame {
+            key,
+            depth: self.path.len(),
+            mode,
+            total: members.len(),
+            members: members.into_iter(),
+            patch: P::members(),
+            replaceable,
+            changed: 0,
+            vetoes: self.vetoes,
+        }
+    }
+
+    /**
+     * Compares the object member at the current path, where `None` means that
+     * the member is absent.
+     */
+    fn member_step<V: JsonLike + 'a, P: Output<'a, V>>(
+        &mut self,
+        mode: Mode,
+        before: Option<&'a V>,
+        after: Option<&'a V>,
+    ) -> Step<'a, V, P>
+    where
+        W: Visit<V>,
+    {
+        if before.is_some() && after.is_none() && self.options.ignore_removals {
+            return Step::Done(None);
+        }
+        if mode == Mode::Replace {
+            if self.ignored() {
+                return Step::Done(None);
+            }
+            return match (before, after) {
+                (_, None) => Step::Done(before.map(|_| P::replace(Cow::Owned(V::null())))),
+                (Some(before), Some(after)) if before.is_object() && after.is_object() => {
+                    Step::Descend(Mode::Replace, before.as_object(), after.as_object())
+                }
+                (_, Some(after)) => Step::Done(Some(P::replace(self.emit(after)))),
+            };
+        }
+
+        match self.visibility() {
+            Visibility::Excluded => Step::Done(None),
+            Visibility::Ancestor => Step::Descend(
+                Mode::Diff,
+                before.and_then(V::as_object),
+                after.and_then(V::as_object),
+            ),
+            Visibility::Included => match (before, after) {
+                (None, None) => Step::Done(None),
+                (Some(before), None) => {
+                    if !self.visit("removed", |visitor, pointer| {
+                        visitor.removed(pointer, before)
+                    }) {
+                        return Step::Done(None);
+                    }
+                    Step::Done(Some(P::replace(Cow::Owned(V::null()))))
+                }
+                (None, Some(after)) => {
+                    if !self.visit("added", |visitor, pointer| visitor.added(pointer, after)) {
+                        return Step::Done(None);
+                    }
+                    Step::Done(Some(P::replace(self.emit(after))))
+                }
+                (Some(before), Some(after)) if identical(before, after) => Step::Done(None),
+                (Some(before), Some(after)) if before.is_object() => self.value_step(before, after),
+                (Some(before), Some(after)) => {
+                    if self.equal(before, after) {
+                        return Step::Done(None);
+                    }
+                    self.modified(before, after)
+                }
+            },
+        }
+    }
+
+    /**
+     * Compares two values at an included path.
+     */
+    fn value_step<V: JsonLike + 'a, P: Output<'a, V>>(
+        &mut self,
+        before: &'a V,
+        after: &'a V,
+    ) -> Step<'a, V, P>
+    where
+        W: Visit<V>,
+    {
+        match (before.kind(), after.kind()) {
+            // Documents that are polled for changes are usually unchanged,
+            // which is cheap to tell if they're the same value
+            (Kind::Array(_), Kind::Array(_)) | (Kind::Object(_), Kind::Object(_))
+                if identical(before, after) =>
+            {
+                return Step::Done(None);
+            }
+            (Kind::Array(_), Kind::Array(_)) => {
+                if !self.equal(before, after) {
+                    return self.modified(before, after);
+                }
+                return Step::Done(None);
+            }
+            (Kind::Object(_), Kind::Object(_)) => {}
+            _ => return self.modified(before, after),
+        }
+
+        if self
+            .options
+            .max_depth
+            .is_some_and(|max_depth| self.path.len() >= max_depth)
+        {
+            if self.equal(before, after)
+                || !self.visit("modified", |visitor, pointer| {
+                    visitor.modified(pointer, before, after)
+                })
+            {
+                return Step::Done(None);
+            }
+            return Step::Descend(Mode::Replace, before.as_object(), after.as_object());
+        }
+
+        Step::Descend(Mode::Diff, before.as_object(), after.as_object())
+    }
+
+    /**
+     * Patch replacing `before` with `after`, unless the visitor vetoes it.
+     */
+    fn modified<V: JsonLike + 'a, P: Output<'a, V>>(
+        &mut self,
+        before: &'a V,
+        after: &'a V,
+    ) -> Step<'a, V, P>
+    where
+        W: Visit<V>,
+    {
+        if !self.visit("modified", |visitor, pointer| {
+            visitor.modified(pointer, before, after)
+        }) {
+            return Step::Done(None);
+        }
+        Step::Done(Some(P::replace(self.emit(after))))
+    }
+
+    /**
+     * Calls the visitor with the pointer of the current path, returning
+     * whether to keep the change, which is `"added"`, `"removed"` or
+     * `"modified"`.
+     */
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    fn visit<V>(&mut self, change: &'static str, call: impl FnOnce(&mut W, &str) -> bool) -> bool
+    where
+        W: Visit<V>,
+    {
+        let keep = !W::ENABLED || call(&mut self.visitor, &pointer::format(&self.path));
+        self.vetoes += usize::from(!keep);
+        #[cfg(feature = "tracing")]
+        if keep {
+            crate::trace::change(change, &self.path);
+        }
+        keep
+    }
+
+    fn visibility(&self) -> Visibility {
+        filter::visibility(&self.options.ignore, &self.options.include, &self.path)
+    }
+
+    /**
+     * Whether the current node is excluded by one of the ignore patterns.
+     * Descendants of included nodes are always included, so within them only
+     * the ignore patterns need to be checked.
+     */
+    fn ignored(&self) -> bool {
+        self.options
+            .ignore
+            .iter()
+            .any(|pattern| pattern.covers(&self.path))
+    }
+
+    /**
+     * Deep equality of the values at the current path, skipping ignored
+     * descendants.
+     */
+    fn equal<V: JsonLike + 'a>(&mut self, a: &'a V, b: &'a V) -> bool {
+        // Most values compared are scalars, which don't need the stack below
+        match (a.kind(), b.kind()) {
+            (Kind::Array(_), Kind::Array(_)) | (Kind::Object(_), Kind::Object(_)) => {}
+            _ => return identical(a, b) || compare::scalars_equal(a, b, self.options),
+        }
+
+        let filtered = !self.options.ignore.is_empty() || self.options.ignore_removals;
+        let base = self.path.len();
+        // Pairs of values left to compare, with the path length of their parent
+        // and their segment in it. A missing value is only equal if ignored.
+        let mut stack = vec![(Some(a), Some(b), base, None)];
+
+        let equal = loop {
+            let Some((a, b, depth, segment)) = stack.pop() else {
+                break true;
+            };
+            self.path.truncate(depth);
+            if let Some(segment) = segment {
+                self.path.push(segment);
+                if filtered && self.ignored() {
+                    continue;
+                }
+            }
+            let depth = self.path.len();
+
+            let (Some(a), Some(b)) = (a, b) else {
+                // Only members of `b` can be missing then, not array elements
+                if b.is_none() && self.options.ignore_removals {
+                    if let Some(Segment::Key(_)) = segment {
+                        continue;
+                    }
+                }
+                break false;
+            };
+            if identical(a, b) {
+                continue;
+            }
+            match (a.kind(), b.kind()) {
+                (Kind::Array(a), Kind::Array(b)) => {
+                    if !filtered && a.len() != b.len() {
+                        break false;
+                    }
+                    for index in 0..a.len().max(b.len()) {
+                        stack.push((
+                            a.get(index),
+                            b.get(index),
+                            depth,
+                            Some(Segment::Index(index)),
+                        ));
+                    }
+                }
+                (Kind::Object(a), Kind::Object(b)) => {
+                    if !filtered && V::len(a) != V::len(b) {
+                        break false;
+                    }
+                    for (key, a) in V::members(a) {
+                        stack.push((Some(a), V::get(b, key), depth, Some(Segment::Key(key))));
+                    }
+                    if filtered {
+                        for (key, b) in V::members(b) {
+                            if V::get(a, key).is_none() {
+                                stack.push((None, Some(b), depth, Some(Segment::Key(key))));
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    if !compare::scalars_equal(a, b, self.options) {
+                        break false;
+                    }
+                }
+            }
+        };
+
+        self.path.truncate(base);
+        equal
+    }
+
+    /**
+     * A value from the `after` document for the patch, leaving out ignored
+     * descendants.
+     */
+    fn emit<V: JsonLike + 'a>(&mut self, value: &'a V) -> Cow<'a, V> {
+        if self.options.ignore.is_empty() {
+            return Cow::Borrowed(value);
+        }
+        let ignore = &self.options.ignore;
+        Cow::Owned(copy::copy(value, &mut self.path, |path| {
+            ignore.iter().any(|pattern| pattern.covers(path))
+        }))
+    }
+}
+
+/**
+ * Whether two values are known to be equal without comparing them: they're
+ * the same value, or share their contents.
+ */
+fn identical<V: JsonLike>(a: &V, b: &V) -> bool {
+    core::ptr::eq(a, b) || a.shares_with(b)
+}
+
+/**
+ * The members to compare of two objects: those of `after`, then those only in
+ * `before` if `removals` are included.
+ */
+fn pair<'a, V: JsonLike + 'a>(
+    before: Option<&'a V::Object>,
+    after: Option<&'a V::Object>,
+    removals: bool,
+) -> Vec<Member<'a, V>> {
+    let before_len = before.map_or(0, V::len);
+    let mut members = Vec::with_capacity(after.map_or(0, V::len).max(before_len));
+
+    // New & modified elements
+    let mut kept = 0;
+    for (key, after_value) in after.into_iter().flat_map(V::members) {
+        let before_value = before.and_then(|before| V::get(before, key));
+        kept += usize::from(before_value.is_some());
+        members.push(Member {
+            key,
+            before: before_value,
+            after: Some(after_value),
+        });
+    }
+
+    // Removed elements, unless every member of `before` was found above
+    if removals && kept < before_len {
+        members.extend(
+            before
+                .into_iter()
+                .flat_map(V::members)
+                .filter(|(key, _)| after.is_none_or(|after| V::get(after, key).is_none()))
+                .map(|(key, before_value)| Member {
+                    key,
+                    before: Some(before_value),
+                    after: None,
+                }),
+        );
+    }
+    members
+}
+
+/**
+ * [`pair`] in a single pass over both objects, rather than a lookup per
+ * member, for objects whose keys iterate in ascending order, like those of
+ * serde_json's default `BTreeMap`. Returns `None` as soon as either turns
+ * out not to be sorted.
+ */
+fn pair_sorted<'a, V: JsonLike + 'a>(
+    before: &'a V::Object,
+    after: &'a V::Object,
+    removals: bool,
+) -> Option<Vec<Member<'a, V>>> {
+    let mut members = Vec::with_capacity(V::len(after).max(V::len(before)));
+    let mut removed = Vec::new();
+    let mut before_members = V::members(before).peekable();
+    let (mut last_before, mut last_after) = (None, None);
+
+    for (key, after_value) in V::members(after) {
+        if last_after.is_some_and(|last| last >= key) {
+            return None;
+        }
+        last_after = Some(key);
+
+        let mut before_value = None;
+        while let Some(&(before_key, value)) = before_members.peek() {
+            if last_before.is_some_and(|last| last >= before_key) {
+                return None;
+            }
+            if before_key > key {
+                break;
+            }
+            last_before = Some(before_key);
+            before_members.next();
+            if before_key == key {
+                before_value = Some(value);
+                break;
+            }
+            if removals {
+                removed.push(Member {
+                    key: before_key,
+                    before: Some(value),
+                    after: None,
+                });
+            }
+        }
+        members.push(Member {
+            key,
+            before: before_value,
+            after: Some(after_value),
+        });
+    }
+
+    for (before_key, value) in before_members {
+        if last_before.is_some_and(|last| last >= before_key) {
+            return None;
+        }
+        last_before = Some(before_key);
+        if removals {
+            removed.push(Member {
+                key: before_key,
+                before: Some(value),
+                after: None,
+            });
+        }
+    }
+
+    members.append(&mut removed);
+    Some(members)
+}
+
+/**
+ * An output that only tells whether there's a patch, see
+ * [`has_changes`](crate::has_changes).
+ */
+pub(crate) struct Changed;
+
+impl<'a, V: JsonLike + 'a> Output<'a, V> for Changed {
+    type Members = bool;
+
+    const SHORT_CIRCUIT: bool = true;
+
+    fn members() -> bool {
+        false
+    }
+
+    fn insert(members: &mut bool, _: &'a str, _: Self) {
+        *members = true;
+    }
+
+    fn is_empty(members: &bool) -> bool {
+        !members
+    }
+
+    fn merge(_: bool) -> Self {
+        Changed
+    }
+
+    fn replace(_: Cow<'a, V>) -> Self {
+        Changed
+    }
+}
@@ -0,0 +1,303 @@
+use alloc::borrow::ToOwned;
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde_json::{Map, Value};
+
+use crate::changes::{changes, equal};
+use crate::{pointer, Error};
+
+type Observer = Box<dyn FnMut(&Value, &Value) + Send>;
+
+/**
+ * A JSON document that's changed through merge patches: every mutation
+ * returns the patch that it made, or `None` if it didn't change anything, and
+ * passes it to the subscribed observers.
+ *
+ * The patches only contain what actually changed, e.g. setting a member to
+ * its current value produces no patch.
+ *
+ * Documents created with [`with_history`](Self::with_history) can also undo
+ * and redo their changes. As patches can't set members to `null`, undoing
+ * the removal of an object with `null` members restores it without them.
+ */
+pub struct Document {
+    value: Value,
+    observers: Vec<(Subscription, Observer)>,
+    next_subscription: u64,
+    history: Option<History>,
+}
+
+/**
+ * The (patch, inverse patch) pairs of the changes that can be undone and
+ * redone, the most recent last.
+ */
+struct History {
+    limit: usize,
+    undo: VecDeque<(Value, Value)>,
+    redo: Vec<(Value, Value)>,
+}
+
+/**
+ * Identifies an observer of a [`Document`], to [unsubscribe](Document::unsubscribe) it.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Subscription(u64);
+
+impl Document {
+    pub fn new(value: Value) -> Self {
+        Document {
+            value,
+            observers: Vec::new(),
+            next_subscription: 0,
+            history: None,
+        }
+    }
+
+    /**
+     * A document that keeps the last `limit` changes to [undo](Self::undo).
+     */
+    pub fn with_history(value: Value, limit: usize) -> Self {
+        Document {
+            history: Some(History {
+                limit,
+                undo: VecDeque::new(),
+                redo: Vec::new(),
+            }),
+            ..Document::new(value)
+        }
+    }
+
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+
+    pub fn into_value(self) -> Value {
+        self.value
+    }
+
+    /**
+     * Sets the value at the JSON Pointer `pointer`, creating the objects
+     * leading to it as needed, like applying a patch of just that value. A
+     * `null` value removes the member.
+     *
+     * Fails for pointers through arrays, whose elements patches can't address.
+     */
+    pub fn set(&mut self, pointer: &str, value: Value) -> Result<Option<Value>, Error> {
+        let mut target = &self.value;
+        let mut tokens = Vec::new();
+        for token in pointer::tokens(pointer) {
+            match target {
+                Value::Array(_) => {
+                    return Err(Error::PointerThroughArray {
+                        pointer: pointer.to_owned(),
+                    })
+                }
+                _ => target = target.get(token.as_ref()).unwrap_or(&Value::Null),
+            }
+            tokens.push(token);
+        }
+        let patch = tokens.into_iter().rev().fold(value, |patch, token| {
+            let mut object = Map::new();
+            object.insert(token.into_owned(), patch);
+            Value::Object(object)
+        });
+        Ok(self.apply(&patch))
+    }
+
+    /**
+     * Removes the member at the JSON Pointer `pointer`, see [`set`](Self::set).
+     */
+    pub fn remove(&mut self, pointer: &str) -> Result<Option<Value>, Error> {
+        self.set(pointer, Value::Null)
+    }
+
+    /**
+     * Replaces the whole document with `after`.
+     */
+    pub fn replace(&mut self, after: Value) -> Option<Value> {
+        if equal(&self.value, &after) {
+            return None;
+        }
+        let patch = crate::generate(&self.value, &after)?;
+        let inverse = self.history.is_some().then(|| inverse(&self.value, &patch));
+        self.value = after;
+        self.changed(&patch, inverse);
+        Some(patch)
+    }
+
+    /**
+     * Applies `patch`, returning just the part of it that changed the document.
+     */
+    pub fn apply(&mut self, patch: &Value) -> Option<Value> {
+        let changed = effective(&self.value, patch)?;
+        let inverse = self
+            .history
+            .is_some()
+            .then(|| inverse(&self.value, &changed));
+        crate::apply(&mut self.value, &changed);
+        self.changed(&changed, inverse);
+        Some(changed)
+    }
+
+    /**
+     * Reverts the most recent change that isn't undone yet, returning the
+     * patch that reverted it, or `None` if there's nothing to undo.
+     */
+    pub fn undo(&mut self) -> Option<Value> {
+        let history = self.history.as_mut()?;
+        let (patch, inverse) = history.undo.pop_back()?;
+        crate::apply(&mut self.value, &inverse);
+        self.notify(&inverse);
+        // The .unwrap() is safe because the history was there above
+        self.history
+            .as_mut()
+            .unwrap()
+            .redo
+            .push((patch, inverse.clone()));
+        Some(inverse)
+    }
+
+    /**
+     * Makes the most recently undone change again, returning its patch, or
+     * `None` if there's nothing to redo. Changes other than undoing clear
+     * what can be redone.
+     */
+    pub fn redo(&mut self) -> Option<Value> {
+        let history = self.history.as_mut()?;
+        let (patch, inverse) = history.redo.pop()?;
+        crate::apply(&mut self.value, &patch);
+        self.notify(&patch);
+        // The .unwrap() is safe because the history was there above
+        self.history
+            .as_mut()
+            .unwrap()
+            .undo
+            .push_back((patch.clone(), inverse));
+        Some(patch)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.history
+            .as_ref()
+            .is_some_and(|history| !history.undo.is_empty())
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.history
+            .as_ref()
+            .is_some_and(|history| !history.redo.is_empty())
+    }
+
+    /**
+     * Calls `observer` with the patch and the patched document after every
+     * change of the document, until it's unsubscribed.
+     */
+    pub fn subscribe(
+        &mut self,
+        observer: impl FnMut(&Value, &Value) + Send + 'static,
+    ) -> Subscription {
+        let subscription = Subscription(self.next_subscription);
+        self.next_subscription += 1;
+        self.observers.push((subscription, Box::new(observer)));
+        subscription
+    }
+
+    /**
+     * Stops calling the observer of `subscription`, returning whether it was
+     * still subscribed.
+     */
+    pub fn unsubscribe(&mut self, subscription: Subscription) -> bool {
+        let len = self.observers.len();
+        self.observers.retain(|(other, _)| *other != subscription);
+        self.observers.len() != len
+    }
+
+    /**
+     * Records and announces a change, given its inverse if there's a history.
+     */
+    fn changed(&mut self, patch: &Value, inverse: Option<Value>) {
+        if let (Some(history), Some(inverse)) = (&mut self.history, inverse) {
+            history.redo.clear();
+            if history.limit > 0 {
+                if history.undo.len() == history.limit {
+                    history.undo.pop_front();
+                }
+                history.undo.push_back((patch.clone(), inverse));
+            }
+        }
+        self.notify(patch);
+    }
+
+    fn notify(&mut self, patch: &Value) {
+        for (_, observer) in &mut self.observers {
+            observer(patch, &self.value);
+        }
+    }
+}
+
+/**
+ * The part of `patch` that changes `document`.
+ */
+fn effective(document: &Value, patch: &Value) -> Option<Value> {
+    let mut changed = None;
+    for change in changes(document, patch) {
+        let value = change.after.cloned().unwrap_or(Value::Null);
+        insert(&mut changed, &change.pointer, value);
+    }
+    changed
+}
+
+/**
+ * The patch that reverts applying `patch` to `document`.
+ */
+fn inverse(document: &Value, patch: &Value) -> Value {
+    let mut inverse = None;
+    for change in changes(document, patch) {
+        let value = change.before.cloned().unwrap_or(Value::Null);
+        insert(&mut inverse, &change.pointer, value);
+    }
+    inverse.unwrap_or_else(|| Value::Object(Map::new()))
+}
+
+/**
+ * Adds `value` to `patch` at `pointer`, which the patch only has objects
+ * along so far.
+ */
+fn insert(patch: &mut Option<Value>, pointer: &str, value: Value) {
+    let mut tokens = pointer::tokens(pointer).peekable();
+    if tokens.peek().is_none() {
+        *patch = Some(value);
+        return;
+    }
+    let mut object = patch.get_or_insert_with(|| Value::Object(Map::new()));
+    while let Some(token) = tokens.next() {
+        // The .unwrap() is safe because changes are at different members,
+        // so the patch only has objects along their pointers
+        let members = object.as_object_mut().unwrap();
+        if tokens.peek().is_none() {
+            members.insert(token.into_owned(), value);
+            return;
+        }
+        object = members
+            .entry(token.into_owned())
+            .or_insert_with(|| Value::Object(Map::new()));
+    }
+}
+
+impl From<Value> for Document {
+    fn from(value: Value) -> Self {
+        Document::new(value)
+    }
+}
+
+impl fmt::Debug for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Document")
+            .field("value", &self.value)
+            .field("observers", &self.observers.len())
+            .finish()
+    }
+}
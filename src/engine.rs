@@ -0,0 +1,58 @@
+use serde_json::Value;
+
+use crate::Options;
+
+/**
+ * Generates the patches between consecutive states of a document, e.g. ones
+ * polled from an API, keeping the last one to diff the next against.
+ *
+ * [`push`](Self::push) takes ownership of the new state, so the states aren't
+ * copied.
+ */
+#[derive(Debug, Clone)]
+pub struct DiffEngine {
+    state: Value,
+    options: Options,
+}
+
+impl DiffEngine {
+    pub fn new(state: Value) -> Self {
+        Self::with_options(state, Options::default())
+    }
+
+    /**
+     * An engine generating the patches with the given [`Options`].
+     */
+    pub fn with_options(state: Value, options: Options) -> Self {
+        DiffEngine { state, options }
+    }
+
+    /**
+     * Returns the patch from the last state to `state`, or `None` if they're
+     * equal, and makes `state` the last state.
+     */
+    pub fn push(&mut self, state: Value) -> Option<Value> {
+        let patch = crate::generate_with(&self.state, &state, &self.options);
+        self.state = state;
+        patch
+    }
+
+    /**
+     * Makes `state` the last state without generating a patch, e.g. after
+     * reconnecting to the source of the states.
+     */
+    pub fn reset(&mut self, state: Value) {
+        self.state = state;
+    }
+
+    /**
+     * The last state, which the next patch applies to.
+     */
+    pub fn state(&self) -> &Value {
+        &self.state
+    }
+
+    pub fn into_state(self) -> Value {
+        self.state
+    }
+}
@@ -0,0 +1,149 @@
+use alloc::string::String;
+
+use serde::de::{Deserialize, Deserializer, Error as _};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::{Map, Value};
+
+use crate::Error;
+
+const AUTHOR: &str = "author";
+const TIMESTAMP: &str = "timestamp";
+const BASE_REVISION: &str = "base_revision";
+
+/**
+ * Where a patch comes from, see [`PatchEnvelope`].
+ *
+ * Serializes as an object with the `author`, `timestamp` and `base_revision`
+ * that are known, followed by the `extra` members.
+ */
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PatchMeta {
+    /** Who made the change, e.g. a user or service name */
+    pub author: Option<String>,
+    /** When the change was made, e.g. as an RFC 3339 date-time */
+    pub timestamp: Option<String>,
+    /** The revision of the document that the patch was generated from */
+    pub base_revision: Option<String>,
+    /**
+     * Any other members, e.g. request IDs. Ones named like the fields above
+     * are left out when serializing.
+     */
+    pub extra: Map<String, Value>,
+}
+
+/**
+ * A JSON Merge Patch (RFC 7386) with metadata about where it comes from, for
+ * audit trails and replication streams.
+ *
+ * Serializes as an object with the `meta` and the `patch`.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchEnvelope {
+    pub meta: PatchMeta,
+    pub patch: Value,
+}
+
+impl PatchEnvelope {
+    pub fn new(patch: Value, meta: PatchMeta) -> Self {
+        PatchEnvelope { meta, patch }
+    }
+
+    /**
+     * Wraps the patch from `before` to `after` like [`generate`](crate::generate),
+     * or returns `None` if they're equal.
+     */
+    pub fn generate(before: &Value, after: &Value, meta: PatchMeta) -> Option<Self> {
+        crate::generate(before, after).map(|patch| Self::new(patch, meta))
+    }
+
+    /**
+     * Applies the patch to `document` like [`apply`](crate::apply).
+     */
+    pub fn apply(&self, document: &mut Value) {
+        crate::apply(document, &self.patch)
+    }
+
+    /**
+     * Applies the patch to `document`, which is at `revision`, if the patch
+     * was generated from that revision or doesn't tell which. Otherwise fails
+     * with [`Error::RevisionMismatch`] leaving the document as it is.
+     */
+    pub fn apply_at_revision(&self, document: &mut Value, revision: &str) -> Result<(), Error> {
+        match &self.meta.base_revision {
+            Some(base) if base != revision => Err(Error::RevisionMismatch {
+                expected: base.clone(),
+                actual: String::from(revision),
+            }),
+            _ => {
+                self.apply(document);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Serialize for PatchMeta {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let known = [
+            (AUTHOR, &self.author),
+            (TIMESTAMP, &self.timestamp),
+            (BASE_REVISION, &self.base_revision),
+        ];
+        let mut map = serializer.serialize_map(None)?;
+        for (key, value) in known {
+            if let Some(value) = value {
+                map.serialize_entry(key, value)?;
+            }
+        }
+        for (key, value) in &self.extra {
+            if !known.iter().any(|(known, _)| known == key) {
+                map.serialize_entry(key, value)?;
+            }
+        }
+        map.end()
+    }
+}
+
+impl Serialize for PatchEnvelope {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry("meta", &self.meta)?;
+        map.serialize_entry("patch", &self.patch)?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for PatchMeta {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let Value::Object(mut extra) = Value::deserialize(deserializer)? else {
+            return Err(D::Error::custom("patch metadata must be an object"));
+        };
+        let mut take = |key| match extra.remove(key) {
+            None | Some(Value::Null) => Ok(None),
+            Some(Value::String(value)) => Ok(Some(value)),
+            Some(_) => Err(D::Error::custom(format_args!("`{key}` must be a string"))),
+        };
+        Ok(PatchMeta {
+            author: take(AUTHOR)?,
+            timestamp: take(TIMESTAMP)?,
+            base_revision: take(BASE_REVISION)?,
+            extra,
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for PatchEnvelope {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let Value::Object(mut envelope) = Value::deserialize(deserializer)? else {
+            return Err(D::Error::custom("a patch envelope must be an object"));
+        };
+        let patch = envelope
+            .remove("patch")
+            .ok_or_else(|| D::Error::missing_field("patch"))?;
+        let meta = match envelope.remove("meta") {
+            Some(meta) => PatchMeta::deserialize(meta).map_err(D::Error::custom)?,
+            None => PatchMeta::default(),
+        };
+        Ok(PatchEnvelope { meta, patch })
+    }
+}
@@ -0,0 +1,241 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+/**
+ * Errors returned by the fallible functions of this crate.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /** The JSON Pointer doesn't refer to a value in the document */
+    PointerNotFound { pointer: String },
+    /** The JSON Pointer goes through an array, which a merge patch can't address */
+    PointerThroughArray { pointer: String },
+    /** Both sides of a three-way merge change the values at the JSON Pointers differently */
+    MergeConflict { pointers: Vec<String> },
+    /** The document's ETag doesn't match the `If-Match` precondition of the patch */
+    PreconditionFailed { etag: String },
+    /** The patch was generated from a document with the `expected` checksum, not the `actual` one */
+    ChecksumMismatch { expected: u64, actual: u64 },
+    /** The patch was generated from the `expected` revision of the document, which is at `actual` */
+    RevisionMismatch { expected: String, actual: String },
+    /** The `null` members of `after` at the JSON Pointers can't be set by a merge patch */
+    NotRepresentable { pointers: Vec<String> },
+    /** The patch isn't valid JSON, with the line and column of the error if known */
+    InvalidPatch {
+        location: Option<(usize, usize)>,
+        message: String,
+    },
+    /**
+     * A JSON document, `"before"` or `"after"`, isn't valid, with the line and
+     * column of the error and its offset in bytes from the start
+     */
+    InvalidJson {
+        document: &'static str,
+        line: usize,
+        column: usize,
+        offset: usize,
+        message: String,
+    },
+    /** The patched document can't be converted to the type of the target */
+    TypeMismatch { message: String },
+    /** The patch nests more arrays and objects than the limit */
+    DepthLimitExceeded { limit: usize },
+    /** The member of the patch at the JSON Pointer can't be translated to a MongoDB update */
+    MongoUpdate {
+        pointer: String,
+        reason: &'static str,
+    },
+    /** The patched document doesn't match the JSON Schema */
+    #[cfg(feature = "jsonschema")]
+    SchemaViolation {
+        violations: Vec<crate::SchemaViolation>,
+    },
+    /** A file couldn't be read */
+    #[cfg(feature = "mmap")]
+    Io {
+        path: std::path::PathBuf,
+        kind: std::io::ErrorKind,
+        message: String,
+    },
+    /** A file isn't valid JSON */
+    #[cfg(feature = "mmap")]
+    Parse {
+        path: std::path::PathBuf,
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /** A JSON5 document, `"before"`, `"after"` or a parsed `"document"`, isn't valid */
+    #[cfg(feature = "json5")]
+    Json5 {
+        document: &'static str,
+        location: Option<(usize, usize)>,
+        message: String,
+    },
+    /** A TOML document, `"before"` or `"after"`, isn't valid, or a `"patch"` can't be rendered */
+    #[cfg(feature = "toml")]
+    Toml {
+        document: &'static str,
+        location: Option<(usize, usize)>,
+        message: String,
+    },
+    /** The patch removes the member at the JSON Pointer, which TOML can't express */
+    #[cfg(feature = "toml")]
+    TomlRemoval { pointer: String },
+    /** A YAML document, `"before"` or `"after"`, isn't valid or can't be represented as JSON */
+    #[cfg(feature = "yaml")]
+    Yaml {
+        document: &'static str,
+        location: Option<(usize, usize)>,
+        message: String,
+    },
+}
+
+impl Error {
+    /**
+     * The HTTP status code for responding to a request that failed with this
+     * error, e.g. 400 for patches that aren't JSON, 412 for failed
+     * preconditions and 422 for patches that can't be applied.
+     */
+    pub fn status_code(&self) -> u16 {
+        match self {
+            Error::PointerNotFound { .. } => 404,
+            Error::MergeConflict { .. } => 409,
+            Error::RevisionMismatch { .. } => 409,
+            Error::PreconditionFailed { .. } => 412,
+            Error::ChecksumMismatch { .. } => 412,
+            Error::DepthLimitExceeded { .. } => 413,
+            Error::InvalidPatch { .. } => 400,
+            Error::InvalidJson { .. } => 400,
+            #[cfg(feature = "mmap")]
+            Error::Io { .. } => 500,
+            #[cfg(feature = "mmap")]
+            Error::Parse { .. } => 400,
+            #[cfg(feature = "json5")]
+            Error::Json5 { .. } => 400,
+            #[cfg(feature = "toml")]
+            Error::Toml { .. } => 400,
+            #[cfg(feature = "yaml")]
+            Error::Yaml { .. } => 400,
+            _ => 422,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::PointerNotFound { pointer } => write!(f, "no value at JSON Pointer {pointer:?}"),
+            Error::PointerThroughArray { pointer } => {
+                write!(f, "JSON Pointer {pointer:?} goes through an array")
+            }
+            Error::MergeConflict { pointers } => {
+                write!(f, "conflicting changes at {pointers:?}")
+            }
+            Error::PreconditionFailed { etag } => {
+                write!(f, "the document's ETag {etag} doesn't match If-Match")
+            }
+            Error::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "the patch is for a document with checksum {expected:016x}, not {actual:016x}"
+            ),
+            Error::RevisionMismatch { expected, actual } => {
+                write!(
+                    f,
+                    "the patch is based on revision {expected:?}, not {actual:?}"
+                )
+            }
+            Error::NotRepresentable { pointers } => {
+                write!(
+                    f,
+                    "a merge patch can't set the null members at {pointers:?}"
+                )
+            }
+            Error::InvalidPatch { location, message } => match location {
+                Some((line, column)) => write!(f, "invalid patch at {line}:{column}: {message}"),
+                None => write!(f, "invalid patch: {message}"),
+            },
+            Error::InvalidJson {
+                document,
+                line,
+                column,
+                offset,
+                message,
+            } => write!(f, "{document}:{line}:{column} (byte {offset}): {message}"),
+            Error::TypeMismatch { message } => {
+                write!(f, "the patched document doesn't match the type: {message}")
+            }
+            Error::DepthLimitExceeded { limit } => {
+                write!(f, "the patch nests more than {limit} arrays and objects")
+            }
+            Error::MongoUpdate { pointer, reason } => {
+                write!(
+                    f,
+                    "cannot translate {pointer:?} to a MongoDB update: {reason}"
+                )
+            }
+            #[cfg(feature = "jsonschema")]
+            Error::SchemaViolation { violations } => {
+                write!(f, "the patched document doesn't match the schema")?;
+                for (index, violation) in violations.iter().enumerate() {
+                    let separator = if index == 0 { ": " } else { "; " };
+                    write!(
+                        f,
+                        "{separator}{:?}: {}",
+                        violation.pointer, violation.message
+                    )?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "mmap")]
+            Error::Io { path, message, .. } => {
+                write!(f, "failed to read {}: {message}", path.display())
+            }
+            #[cfg(feature = "mmap")]
+            Error::Parse {
+                path,
+                line,
+                column,
+                message,
+            } => write!(f, "{}:{line}:{column}: {message}", path.display()),
+            #[cfg(feature = "json5")]
+            Error::Json5 {
+                document,
+                location,
+                message,
+            } => match location {
+                Some((line, column)) => write!(f, "{document}:{line}:{column}: {message}"),
+                None => write!(f, "{document}: {message}"),
+            },
+            #[cfg(feature = "toml")]
+            Error::Toml {
+                document,
+                location,
+                message,
+            } => match location {
+                Some((line, column)) => write!(f, "{document}:{line}:{column}: {message}"),
+                None => write!(f, "{document}: {message}"),
+            },
+            #[cfg(feature = "toml")]
+            Error::TomlRemoval { pointer } => {
+                write!(
+                    f,
+                    "cannot express removal in TOML: the patch removes {pointer:?}"
+                )
+            }
+            #[cfg(feature = "yaml")]
+            Error::Yaml {
+                document,
+                location,
+                message,
+            } => match location {
+                Some((line, column)) => write!(f, "{document}:{line}:{column}: {message}"),
+                None => write!(f, "{document}: {message}"),
+            },
+        }
+    }
+}
+
+impl core::error::Error for Error {}
@@ -0,0 +1,111 @@
+/*!
+ * Optimistic concurrency for merge patches over HTTP: clients send a patch
+ * with the ETag of the document it was generated from in `If-Match`, and
+ * servers only apply it if the document still has that ETag.
+ */
+
+use alloc::format;
+use alloc::string::String;
+use core::fmt::{self, Write};
+
+use serde_json::Value;
+
+use crate::Error;
+
+/**
+ * A patch with the ETag of the document that it was generated from, to be
+ * sent with the ETag in an `If-Match` header.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaggedPatch {
+    pub patch: Value,
+    /** ETag of the document before the patch, including its quotes */
+    pub etag: String,
+}
+
+impl TaggedPatch {
+    /**
+     * The patch from `before`, whose ETag is `etag`, to `after`, or `None` if
+     * they're equal.
+     */
+    pub fn generate(before: &Value, after: &Value, etag: impl Into<String>) -> Option<Self> {
+        crate::generate(before, after).map(|patch| TaggedPatch {
+            patch,
+            etag: etag.into(),
+        })
+    }
+
+    /**
+     * The value of the `If-Match` header of the request.
+     */
+    pub fn if_match(&self) -> &str {
+        &self.etag
+    }
+}
+
+/**
+ * Applies `patch` to `document`, whose current ETag is `etag`, if `if_match`
+ * (the value of an `If-Match` header) matches it, or fails with
+ * [`Error::PreconditionFailed`] leaving the document as it is.
+ */
+pub fn apply_if_match(
+    document: &mut Value,
+    etag: &str,
+    if_match: &str,
+    patch: &Value,
+) -> Result<(), Error> {
+    if !matches(if_match, etag) {
+        return Err(Error::PreconditionFailed {
+            etag: String::from(etag),
+        });
+    }
+    crate::apply(document, patch);
+    Ok(())
+}
+
+/**
+ * Whether the value of an `If-Match` header matches `etag`: if it's `*` or
+ * lists it, compared strongly as RFC 9110 requires, so weak ETags never match.
+ */
+pub fn matches(if_match: &str, etag: &str) -> bool {
+    let if_match = if_match.trim();
+    if if_match == "*" {
+        return true;
+    }
+    !etag.starts_with("W/")
+        && if_match
+            .split(',')
+            .any(|candidate| candidate.trim() == etag)
+}
+
+/**
+ * A strong ETag for `document`, for servers that don't version documents
+ * otherwise: a hash of its compact JSON, which is the same for equal
+ * documents as long as serde_json's `preserve_order` feature is disabled.
+ */
+pub fn etag(document: &Value) -> String {
+    let mut hasher = Fnv1a::new();
+    // The .unwrap() is safe because writing to the hasher never fails
+    write!(hasher, "{document}").unwrap();
+    format!("\"{:016x}\"", hasher.0)
+}
+
+/**
+ * 64-bit FNV-1a, which is enough to tell versions of a document apart.
+ */
+pub(crate) struct Fnv1a(pub(crate) u64);
+
+impl Fnv1a {
+    pub(crate) fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl fmt::Write for Fnv1a {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        for byte in text.bytes() {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+/*!
+ * A C ABI for generating and applying patches, with documents and patches as
+ * NUL-terminated UTF-8 JSON strings. The `ffi` crate in the repository builds
+ * it into a shared and a static library, with a header.
+ *
+ * Strings returned, including errors, are owned by the caller and must be
+ * freed with [`json_merge_patch_free`]. Functions that fail return NULL and,
+ * if `error` isn't NULL, set `*error` to a message, or to NULL on success.
+ */
+
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+
+use serde_json::Value;
+
+/**
+ * Generates the merge patch that turns `before` into `after`, like
+ * [`generate`](crate::generate), or `{}` if they're equal.
+ *
+ * # Safety
+ *
+ * `before` and `after` must be NULL or NUL-terminated strings, and `error`
+ * NULL or valid for writes.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn json_merge_patch_generate(
+    before: *const c_char,
+    after: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut c_char {
+    let result = parse(before, "before").and_then(|before| {
+        let after = parse(after, "after")?;
+        let patch =
+            crate::generate(&before, &after).unwrap_or_else(|| Value::Object(Default::default()));
+        Ok(patch.to_string())
+    });
+    finish(result, error)
+}
+
+/**
+ * Applies `patch` to `document`, like [`apply`](crate::apply), returning the
+ * patched document.
+ *
+ * # Safety
+ *
+ * See [`json_merge_patch_generate`].
+ */
+#[no_mangle]
+pub unsafe extern "C" fn json_merge_patch_apply(
+    document: *const c_char,
+    patch: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut c_char {
+    let result = parse(document, "document").and_then(|mut document| {
+        let patch = parse(patch, "patch")?;
+        crate::apply(&mut document, &patch);
+        Ok(document.to_string())
+    });
+    finish(result, error)
+}
+
+/**
+ * Frees a string returned by this library. Does nothing for NULL.
+ *
+ * # Safety
+ *
+ * `string` must be NULL or returned by this library, and not freed before.
+ */
+#[no_mangle]
+pub unsafe extern "C" fn json_merge_patch_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+unsafe fn parse(json: *const c_char, name: &str) -> Result<Value, String> {
+    if json.is_null() {
+        return Err(format!("{name} is NULL"));
+    }
+    let json = CStr::from_ptr(json)
+        .to_str()
+        .map_err(|error| format!("{name} isn't UTF-8: {error}"))?;
+    serde_json::from_str(json).map_err(|error| format!("{name}: {error}"))
+}
+
+unsafe fn finish(result: Result<String, String>, error: *mut *mut c_char) -> *mut c_char {
+    // The .unwrap()s are safe because JSON text escapes NUL, and messages
+    // only quote the inputs, which are NUL-terminated
+    let (output, message) = match result {
+        Ok(output) => (CString::new(output).unwrap().into_raw(), ptr::null_mut()),
+        Err(message) => (ptr::null_mut(), CString::new(message).unwrap().into_raw()),
+    };
+    match error.is_null() {
+        true => json_merge_patch_free(message),
+        false => *error = message,
+    }
+    output
+}
@@ -0,0 +1,57 @@
+use std::fs::File;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::{Error, Options};
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`](crate::generate),
+ * between the JSON documents in two files. The files are memory-mapped rather
+ * than read into buffers.
+ *
+ * The files must not be modified while the patch is generated.
+ */
+pub fn generate_from_files(
+    before: impl AsRef<Path>,
+    after: impl AsRef<Path>,
+) -> Result<Option<Value>, Error> {
+    generate_from_files_with(before, after, &Options::default())
+}
+
+/**
+ * [`generate_from_files`] using the given [`Options`].
+ */
+pub fn generate_from_files_with(
+    before: impl AsRef<Path>,
+    after: impl AsRef<Path>,
+    options: &Options,
+) -> Result<Option<Value>, Error> {
+    let before = parse(before.as_ref())?;
+    let after = parse(after.as_ref())?;
+    Ok(crate::generate_with(&before, &after, options))
+}
+
+fn parse(path: &Path) -> Result<Value, Error> {
+    let io_error = |error: std::io::Error| Error::Io {
+        path: path.to_owned(),
+        kind: error.kind(),
+        message: error.to_string(),
+    };
+
+    let file = File::open(path).map_err(io_error)?;
+    // Safety: the caller guarantees that the file isn't modified while mapped
+    let map = unsafe { memmap2::Mmap::map(&file) }.map_err(io_error)?;
+
+    serde_json::from_slice(&map).map_err(|error| {
+        let (line, column) = (error.line(), error.column());
+        let message = error.to_string();
+        let suffix = format!(" at line {line} column {column}");
+        Error::Parse {
+            path: path.to_owned(),
+            line,
+            column,
+            message: message.strip_suffix(&suffix).unwrap_or(&message).to_owned(),
+        }
+    })
+}
@@ -0,0 +1,166 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::pointer::{self, Segment};
+
+/**
+ * A JSON Pointer where the reference tokens `*` and `**` match any single
+ * segment and any number of segments respectively.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PathPattern {
+    tokens: Vec<Token>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Literal(String),
+    Any,
+    AnyDepth,
+    /** An object key where `*` matches any number of characters */
+    KeyGlob(String),
+}
+
+/**
+ * How a node relates to the configured path filters.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Visibility {
+    /** The node is diffed */
+    Included,
+    /** Only some descendants of the node are diffed */
+    Ancestor,
+    /** The node is treated as unchanged */
+    Excluded,
+}
+
+impl PathPattern {
+    pub(crate) fn parse(pattern: &str) -> Self {
+        let tokens = pointer::tokens(pattern)
+            .map(|token| match token.as_ref() {
+                "*" => Token::Any,
+                "**" => Token::AnyDepth,
+                _ => Token::Literal(token.into_owned()),
+            })
+            .collect();
+        Self { tokens }
+    }
+
+    /**
+     * Matches the object members at any depth whose key matches `glob`, where
+     * `*` matches any number of characters.
+     */
+    pub(crate) fn key_glob(glob: &str) -> Self {
+        Self {
+            tokens: Vec::from([Token::AnyDepth, Token::KeyGlob(glob.into())]),
+        }
+    }
+
+    /**
+     * Whether the pattern matches `path` or one of its ancestors.
+     */
+    pub(crate) fn covers(&self, path: &[Segment<'_>]) -> bool {
+        covers(&self.tokens, path)
+    }
+
+    /**
+     * Whether the pattern matches `path` itself.
+     */
+    pub(crate) fn matches(&self, path: &[Segment<'_>]) -> bool {
+        matches(&self.tokens, path)
+    }
+
+    /**
+     * Whether the pattern could match a strict descendant of `path`.
+     */
+    pub(crate) fn below(&self, path: &[Segment<'_>]) -> bool {
+        below(&self.tokens, path)
+    }
+}
+
+/**
+ * Visibility of the node at `path` given the ignore and include patterns.
+ * Ignore patterns take precedence, and an empty include list includes
+ * everything.
+ */
+pub(crate) fn visibility(
+    ignore: &[PathPattern],
+    include: &[PathPattern],
+    path: &[Segment<'_>],
+) -> Visibility {
+    if ignore.iter().any(|pattern| pattern.covers(path)) {
+        Visibility::Excluded
+    } else if include.is_empty() || include.iter().any(|pattern| pattern.covers(path)) {
+        Visibility::Included
+    } else if include.iter().any(|pattern| pattern.below(path)) {
+        Visibility::Ancestor
+    } else {
+        Visibility::Excluded
+    }
+}
+
+fn covers(tokens: &[Token], path: &[Segment<'_>]) -> bool {
+    match tokens.split_first() {
+        None => true,
+        Some((Token::AnyDepth, rest)) => (0..=path.len()).any(|skip| covers(rest, &path[skip..])),
+        Some((token, rest)) => match path.split_first() {
+            None => false,
+            Some((segment, path)) => token_matches(token, segment) && covers(rest, path),
+        },
+    }
+}
+
+fn matches(tokens: &[Token], path: &[Segment<'_>]) -> bool {
+    match tokens.split_first() {
+        None => path.is_empty(),
+        Some((Token::AnyDepth, rest)) => (0..=path.len()).any(|skip| matches(rest, &path[skip..])),
+        Some((token, rest)) => match path.split_first() {
+            None => false,
+            Some((segment, path)) => token_matches(token, segment) && matches(rest, path),
+        },
+    }
+}
+
+fn below(tokens: &[Token], path: &[Segment<'_>]) -> bool {
+    match tokens.split_first() {
+        None => false,
+        Some((Token::AnyDepth, _)) => true,
+        Some((token, rest)) => match path.split_first() {
+            None => true,
+            Some((segment, path)) => token_matches(token, segment) && below(rest, path),
+        },
+    }
+}
+
+fn token_matches(token: &Token, segment: &Segment<'_>) -> bool {
+    match token {
+        Token::Literal(literal) => segment.matches(literal),
+        Token::Any | Token::AnyDepth => true,
+        Token::KeyGlob(glob) => matches!(segment, Segment::Key(key) if glob_matches(glob, key)),
+    }
+}
+
+/**
+ * Whether `text` matches `glob`, where `*` matches any number of characters.
+ */
+fn glob_matches(glob: &str, text: &str) -> bool {
+    let Some((prefix, rest)) = glob.split_once('*') else {
+        return glob == text;
+    };
+    let Some(mut text) = text.strip_prefix(prefix) else {
+        return false;
+    };
+    let (middle, suffix) = rest.rsplit_once('*').unwrap_or(("", rest));
+    if text.len() < suffix.len() || !text.ends_with(suffix) {
+        return false;
+    }
+    text = &text[..text.len() - suffix.len()];
+    // Each part between stars matches at its first occurrence after the previous one
+    for part in middle.split('*') {
+        match text.find(part) {
+            Some(index) => text = &text[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
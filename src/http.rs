@@ -0,0 +1,119 @@
+/*!
+ * Merge patch bodies of [http](https://docs.rs/http) requests and responses,
+ * for services that don't use a framework integration.
+ */
+
+use core::fmt;
+
+use ::http::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use ::http::{request, response, Request, Response, StatusCode};
+use serde_json::Value;
+
+use crate::media_type::is_merge_patch;
+use crate::MERGE_PATCH_MEDIA_TYPE;
+
+/**
+ * Builds a request with `patch` as its body and the merge patch
+ * `Content-Type`.
+ */
+pub fn encode_request(
+    builder: request::Builder,
+    patch: &Value,
+) -> ::http::Result<Request<Vec<u8>>> {
+    builder
+        .header(
+            CONTENT_TYPE,
+            HeaderValue::from_static(MERGE_PATCH_MEDIA_TYPE),
+        )
+        .body(body(patch))
+}
+
+/**
+ * Builds a response with `patch` as its body and the merge patch
+ * `Content-Type`.
+ */
+pub fn encode_response(
+    builder: response::Builder,
+    patch: &Value,
+) -> ::http::Result<Response<Vec<u8>>> {
+    builder
+        .header(
+            CONTENT_TYPE,
+            HeaderValue::from_static(MERGE_PATCH_MEDIA_TYPE),
+        )
+        .body(body(patch))
+}
+
+/**
+ * The merge patch in the body of `request`, failing if its `Content-Type`
+ * isn't the merge patch media type or the body isn't JSON.
+ */
+pub fn decode_request<B: AsRef<[u8]>>(request: &Request<B>) -> Result<Value, DecodeError> {
+    decode(request.headers(), request.body().as_ref())
+}
+
+/**
+ * [`decode_request`] for the body of `response`.
+ */
+pub fn decode_response<B: AsRef<[u8]>>(response: &Response<B>) -> Result<Value, DecodeError> {
+    decode(response.headers(), response.body().as_ref())
+}
+
+fn body(patch: &Value) -> Vec<u8> {
+    // The .unwrap() is safe because values with string keys always serialize
+    serde_json::to_vec(patch).unwrap()
+}
+
+fn decode(headers: &HeaderMap, body: &[u8]) -> Result<Value, DecodeError> {
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+    if !content_type.is_some_and(is_merge_patch) {
+        return Err(DecodeError::UnsupportedMediaType);
+    }
+    serde_json::from_slice(body).map_err(DecodeError::InvalidJson)
+}
+
+/**
+ * Why a body isn't a merge patch.
+ */
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DecodeError {
+    /** The `Content-Type` isn't `application/merge-patch+json` */
+    UnsupportedMediaType,
+    /** The body isn't JSON */
+    InvalidJson(serde_json::Error),
+}
+
+impl DecodeError {
+    /** The status of the response to a request with the body */
+    pub fn status(&self) -> StatusCode {
+        match self {
+            DecodeError::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            DecodeError::InvalidJson(_) => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnsupportedMediaType => {
+                write!(f, "expected a `Content-Type` of {MERGE_PATCH_MEDIA_TYPE}")
+            }
+            DecodeError::InvalidJson(error) => {
+                write!(f, "failed to parse the merge patch: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DecodeError::UnsupportedMediaType => None,
+            DecodeError::InvalidJson(error) => Some(error),
+        }
+    }
+}
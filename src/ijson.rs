@@ -0,0 +1,90 @@
+/*!
+ * Patch generation for [ijson](https://docs.rs/ijson) values, without
+ * converting them to [`serde_json::Value`] first.
+ *
+ * Note that ijson considers numbers like `1` and `1.0` equal, so changes
+ * between them don't end up in patches even without
+ * [`Options::numeric_equality`].
+ */
+
+use ::ijson::{object, DestructuredRef, IArray, INumber, IObject, IString, IValue};
+
+use crate::diff::Diff;
+use crate::value::{JsonLike, Kind};
+use crate::Options;
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`](crate::generate),
+ * for [`IValue`]s.
+ */
+pub fn generate(before: &IValue, after: &IValue) -> Option<IValue> {
+    generate_with(before, after, &Options::default())
+}
+
+/**
+ * [`generate`] using the given [`Options`].
+ */
+pub fn generate_with(before: &IValue, after: &IValue, options: &Options) -> Option<IValue> {
+    options.finish(Diff::new(options).generate(before, after))
+}
+
+impl JsonLike for IValue {
+    type Object = IObject;
+    type Members<'a> =
+        std::iter::Map<object::Iter<'a>, fn((&'a IString, &'a IValue)) -> (&'a str, &'a IValue)>;
+
+    fn kind(&self) -> Kind<'_, Self> {
+        match self.destructure_ref() {
+            DestructuredRef::Null => Kind::Null,
+            DestructuredRef::Bool(_) => Kind::Bool,
+            DestructuredRef::Number(_) => Kind::Number,
+            DestructuredRef::String(string) => Kind::String(string.as_str()),
+            DestructuredRef::Array(array) => Kind::Array(array.as_slice()),
+            DestructuredRef::Object(object) => Kind::Object(object),
+        }
+    }
+
+    fn members(object: &IObject) -> Self::Members<'_> {
+        object.iter().map(|(key, value)| (key.as_str(), value))
+    }
+
+    fn get<'a>(object: &'a IObject, key: &str) -> Option<&'a Self> {
+        object.get(key)
+    }
+
+    fn len(object: &IObject) -> usize {
+        object.len()
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        self.to_i64()
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        self.to_u64()
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        self.as_number().map(INumber::to_f64_lossy)
+    }
+
+    fn null() -> Self {
+        IValue::NULL
+    }
+
+    fn array(values: Vec<Self>) -> Self {
+        IArray::from(values).into()
+    }
+
+    fn new_object() -> IObject {
+        IObject::new()
+    }
+
+    fn insert(object: &mut IObject, key: &str, value: Self) {
+        object.insert(key, value);
+    }
+
+    fn object(object: IObject) -> Self {
+        object.into()
+    }
+}
@@ -0,0 +1,121 @@
+use core::fmt;
+
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Number, Value};
+
+use crate::{Error, Options};
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`](crate::generate),
+ * between two JSON5 documents, e.g. human-edited configuration files with
+ * comments and trailing commas (JSONC). Plain JSON is valid JSON5 too.
+ *
+ * `NaN` and `Infinity`, which JSON can't represent, fail to parse.
+ */
+pub fn generate_json5(before: &str, after: &str) -> Result<Option<Value>, Error> {
+    generate_json5_with(before, after, &Options::default())
+}
+
+/**
+ * [`generate_json5`] using the given [`Options`].
+ */
+pub fn generate_json5_with(
+    before: &str,
+    after: &str,
+    options: &Options,
+) -> Result<Option<Value>, Error> {
+    let before = parse("before", before)?;
+    let after = parse("after", after)?;
+    Ok(crate::generate_with(&before, &after, options))
+}
+
+/**
+ * Parses a JSON5 document, see [`generate_json5`].
+ */
+pub fn parse_json5(text: &str) -> Result<Value, Error> {
+    parse("document", text)
+}
+
+fn parse(document: &'static str, text: &str) -> Result<Value, Error> {
+    match ::json5::from_str::<Json5>(text) {
+        Ok(Json5(value)) => Ok(value),
+        Err(::json5::Error::Message { msg, location }) => Err(Error::Json5 {
+            document,
+            location: location.map(|location| (location.line, location.column)),
+            // Syntax errors end with what was expected, after a snippet of
+            // the document
+            message: match msg.rsplit_once("\n  = ") {
+                Some((_, expected)) => expected.to_owned(),
+                None => msg,
+            },
+        }),
+    }
+}
+
+/**
+ * A [`Value`] that fails to deserialize from non-finite numbers, rather than
+ * becoming `null`.
+ */
+struct Json5(Value);
+
+impl<'de> Deserialize<'de> for Json5 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(Json5Visitor).map(Json5)
+    }
+}
+
+struct Json5Visitor;
+
+impl<'de> Visitor<'de> for Json5Visitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a JSON5 value")
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_bool<E>(self, value: bool) -> Result<Value, E> {
+        Ok(Value::Bool(value))
+    }
+
+    fn visit_i64<E>(self, value: i64) -> Result<Value, E> {
+        Ok(Value::Number(value.into()))
+    }
+
+    fn visit_u64<E>(self, value: u64) -> Result<Value, E> {
+        Ok(Value::Number(value.into()))
+    }
+
+    fn visit_f64<E: serde::de::Error>(self, value: f64) -> Result<Value, E> {
+        Number::from_f64(value)
+            .map(Value::Number)
+            .ok_or_else(|| E::custom(format!("{value} can't be represented as JSON")))
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Value, E> {
+        Ok(Value::String(value.to_owned()))
+    }
+
+    fn visit_string<E>(self, value: String) -> Result<Value, E> {
+        Ok(Value::String(value))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut values = Vec::new();
+        while let Some(Json5(value)) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(Value::Array(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut members = Map::new();
+        while let Some((key, Json5(value))) = map.next_entry::<String, Json5>()? {
+            members.insert(key, value);
+        }
+        Ok(Value::Object(members))
+    }
+}
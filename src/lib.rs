@@ -1,222 +1,4823 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+// For the paths of the code that `#[derive(JsonMergePatch)]` generates in tests
+#[cfg(all(test, feature = "derive"))]
+extern crate self as json_merge_patch_gen;
+
+#[cfg(feature = "actix-web")]
+pub mod actix_web;
+mod apply;
+mod arc_value;
+#[cfg(feature = "axum")]
+pub mod axum;
+mod batch;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+mod changes;
+mod checksum;
+mod compare;
+mod copy;
+mod diff;
+mod document;
+mod engine;
+mod envelope;
+mod error;
+pub mod etag;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "mmap")]
+mod files;
+mod filter;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "ijson")]
+pub mod ijson;
+#[cfg(feature = "json5")]
+mod json5;
+mod maps;
+mod media_type;
+mod merge;
+mod merge_patch;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+mod mongo;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+#[cfg(feature = "std")]
+mod ndjson;
+mod normalize;
+mod options;
+mod owned;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod patch_diff;
+mod patch_log;
+mod patch_ref;
+#[cfg(any(feature = "schemars", feature = "utoipa"))]
+mod patch_schema;
+mod plain;
+mod pointer;
+#[cfg(feature = "proptest")]
+pub mod proptest;
+#[cfg(feature = "raw_value")]
+mod raw;
+mod rebase;
+mod redact;
+mod redis;
+pub mod render;
+#[cfg(feature = "reqwest")]
+pub mod reqwest;
+#[cfg(feature = "jsonschema")]
+mod schema;
+#[cfg(feature = "simd-json")]
+pub mod simd_json;
+mod size;
+mod squash;
+pub mod strategic;
+#[cfg(feature = "std")]
+mod stream;
+pub mod sync;
+#[cfg(feature = "testutil")]
+pub mod testutil;
+mod text;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+#[cfg(feature = "toml")]
+mod toml;
+#[cfg(feature = "tracing")]
+mod trace;
+pub mod typed;
+mod untrusted;
+mod update;
+#[cfg(feature = "utoipa")]
+pub mod utoipa;
+mod validate;
+mod value;
+mod visitor;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "yaml")]
+mod yaml;
+
+pub use arc_value::ArcValue;
+pub use batch::{
+    generate_batch, generate_batch_keyed, generate_batch_keyed_with, generate_batch_with,
+    DocumentChange,
+};
+pub use changes::{Change, ChangeKind, ChangeSet};
+pub use checksum::ChecksummedPatch;
+pub use document::{Document, Subscription};
+pub use engine::DiffEngine;
+pub use envelope::{PatchEnvelope, PatchMeta};
+pub use error::Error;
+#[cfg(feature = "mmap")]
+pub use files::{generate_from_files, generate_from_files_with};
+#[cfg(feature = "json5")]
+pub use json5::{generate_json5, generate_json5_with, parse_json5};
+pub use media_type::MERGE_PATCH_MEDIA_TYPE;
+pub use merge_patch::MergePatch;
+#[cfg(feature = "std")]
+pub use ndjson::{generate_ndjson, generate_ndjson_with, Unpaired};
+#[cfg(feature = "unicode")]
+pub use options::UnicodeNormalization;
+pub use options::{EmptyPatchBehavior, Options};
+#[cfg(feature = "rayon")]
+pub use parallel::{
+    par_generate, par_generate_batch, par_generate_batch_keyed, par_generate_batch_keyed_with,
+    par_generate_batch_with, par_generate_with,
+};
+pub use patch_diff::{ConflictPath, PatchDifference};
+pub use patch_log::PatchLog;
+pub use patch_ref::PatchRef;
+pub use plain::PlainNumbers;
+#[cfg(feature = "raw_value")]
+pub use raw::{generate_raw, generate_raw_with};
+pub use rebase::Rebased;
+pub use redact::Redaction;
+pub use redis::RedisJsonCommand;
+#[cfg(feature = "jsonschema")]
+pub use schema::SchemaViolation;
+#[cfg(feature = "std")]
+pub use stream::{apply_stream, generate_from_readers, generate_stream, generate_stream_with};
+pub use text::{generate_str, generate_str_with};
+#[cfg(feature = "toml")]
+pub use toml::{generate_toml, generate_toml_with, to_toml};
+pub use typed::JsonMergePatch;
+pub use untrusted::{InvalidPatches, PatchError};
+pub use update::Update;
+pub use validate::ValidationError;
+pub use value::{JsonLike, Kind};
+pub use visitor::Visitor;
+#[cfg(feature = "yaml")]
+pub use yaml::{generate_yaml, generate_yaml_with, to_yaml};
+
+/**
+ * Derives [`trait@JsonMergePatch`] for a struct with named fields, generating
+ * a `{Name}Patch` struct with an `Option` of each field, or of the patch of
+ * the field's type for fields marked `#[json_merge_patch(nested)]`. Fields of
+ * type `Option` can also be removed, as `Some(None)`. Field names follow
+ * `#[serde(rename = "...")]`.
+ */
+#[cfg(feature = "derive")]
+pub use json_merge_patch_gen_derive::JsonMergePatch;
+
+/** Used by the code that `#[derive(JsonMergePatch)]` generates */
+#[cfg(feature = "derive")]
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::string::String;
+    pub use serde;
+    pub use serde_json;
+}
+
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec::Vec;
+
 /**
  * Generates a JSON Merge Patch (RFC 7386)
  * <https://datatracker.ietf.org/doc/html/rfc7386>
  *
  * Ported from <https://github.com/pierreinglebert/json-merge-patch/blob/master/lib/generate.js>
+ *
+ * Works with any [`JsonLike`] value, usually [`serde_json::Value`]. See
+ * [`diff`] for a structured representation of the patch.
+ *
+ * The members of objects in the patch come in the order of `after`, followed
+ * by those removed from `before`, where the object type keeps them in order:
+ * [`serde_json::Value`] sorts them by key unless serde_json's `preserve_order`
+ * feature is enabled, e.g. by this crate's feature of the same name. See
+ * [`Options::canonical`] to sort them either way.
+ */
+pub fn generate<V: JsonLike>(before: &V, after: &V) -> Option<V> {
+    generate_with(before, after, &Options::default())
+}
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) using the given [`Options`].
+ */
+pub fn generate_with<V: JsonLike>(before: &V, after: &V, options: &Options) -> Option<V> {
+    #[cfg(feature = "tracing")]
+    let span = trace::span(before, after);
+    #[cfg(feature = "tracing")]
+    let _entered = span.enter();
+    #[cfg(feature = "metrics")]
+    let start = std::time::Instant::now();
+    let patch = options.finish(diff::Diff::new(options).generate(before, after));
+    #[cfg(feature = "metrics")]
+    metrics::record(start, patch.as_ref());
+    #[cfg(feature = "tracing")]
+    trace::generated(&span, patch.as_ref());
+    patch
+}
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`], calling
+ * `visitor` for each change as it's found, which can leave it out of the
+ * patch.
+ */
+pub fn generate_visited<V: JsonLike>(before: &V, after: &V, visitor: impl Visitor<V>) -> Option<V> {
+    generate_visited_with(before, after, &Options::default(), visitor)
+}
+
+/**
+ * [`generate_visited`] using the given [`Options`]. Ignored paths aren't
+ * visited.
+ */
+pub fn generate_visited_with<V: JsonLike>(
+    before: &V,
+    after: &V,
+    options: &Options,
+    visitor: impl Visitor<V>,
+) -> Option<V> {
+    options.finish(diff::Diff::visited(options, visitor).generate(before, after))
+}
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`] between copies of
+ * the documents that `normalize` was called on with the JSON Pointer of each
+ * of their values, e.g. to trim strings or remove members set to their
+ * defaults, so that documents that only differ in ways that don't matter have
+ * no patch.
+ *
+ * Values are normalized from the root down, so members that `normalize`
+ * removes from an object aren't visited. The patch sets the normalized values.
+ */
+pub fn generate_normalized(
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+    normalize: impl FnMut(&str, &mut serde_json::Value),
+) -> Option<serde_json::Value> {
+    generate_normalized_with(before, after, &Options::default(), normalize)
+}
+
+/**
+ * [`generate_normalized`] using the given [`Options`].
+ */
+pub fn generate_normalized_with(
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+    options: &Options,
+    mut normalize: impl FnMut(&str, &mut serde_json::Value),
+) -> Option<serde_json::Value> {
+    let before = normalize::normalized(before, &mut normalize);
+    let after = normalize::normalized(after, &mut normalize);
+    generate_with(&before, &after, options)
+}
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`], passing each
+ * value of the patch to `redact` with its JSON Pointer, so that e.g. secrets
+ * can be masked before the patch is logged or sent elsewhere.
+ *
+ * Values are visited from the root down, including the members of the
+ * objects that the patch adds, but not the `null`s of removed members.
+ * Dropping a value leaves the target's value as it is when the patch is
+ * applied, and dropping the root leaves no patch.
+ */
+pub fn generate_redacted(
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+    redact: impl FnMut(&str, &serde_json::Value) -> Redaction,
+) -> Option<serde_json::Value> {
+    generate_redacted_with(before, after, &Options::default(), redact)
+}
+
+/**
+ * [`generate_redacted`] using the given [`Options`].
+ */
+pub fn generate_redacted_with(
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+    options: &Options,
+    redact: impl FnMut(&str, &serde_json::Value) -> Redaction,
+) -> Option<serde_json::Value> {
+    redact::redact(generate_with(before, after, options), redact)
+}
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) without cloning values from the
+ * documents: the patch borrows from `after` (and `before`, for the keys of
+ * removed members).
+ *
+ * The patch can be serialized as-is, or converted to the result of
+ * [`generate`] with [`PatchRef::into_owned`].
+ */
+pub fn generate_ref<'a>(
+    before: &'a serde_json::Value,
+    after: &'a serde_json::Value,
+) -> Option<PatchRef<'a>> {
+    generate_ref_with(before, after, &Options::default())
+}
+
+/**
+ * [`generate_ref`] using the given [`Options`].
+ */
+pub fn generate_ref_with<'a>(
+    before: &'a serde_json::Value,
+    after: &'a serde_json::Value,
+    options: &Options,
+) -> Option<PatchRef<'a>> {
+    let patch = diff::Diff::new(options).generate(before, after);
+    match options.empty_patch {
+        EmptyPatchBehavior::EmptyObject if patch.is_none() => {
+            Some(PatchRef::Merge(Default::default()))
+        }
+        _ => patch,
+    }
+}
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`], or no patch
+ * without comparing the documents if their hashes are equal, e.g. their ETags
+ * or hashes of the bytes they were parsed from. Both must be computed the same
+ * way, and a hash collision hides the changes.
+ */
+pub fn generate_hashed<V: JsonLike, H: PartialEq + ?Sized>(
+    before: &V,
+    before_hash: &H,
+    after: &V,
+    after_hash: &H,
+) -> Option<V> {
+    generate_hashed_with(before, before_hash, after, after_hash, &Options::default())
+}
+
+/**
+ * [`generate_hashed`] using the given [`Options`].
+ */
+pub fn generate_hashed_with<V: JsonLike, H: PartialEq + ?Sized>(
+    before: &V,
+    before_hash: &H,
+    after: &V,
+    after_hash: &H,
+    options: &Options,
+) -> Option<V> {
+    if before_hash == after_hash {
+        return options.finish(None);
+    }
+    generate_with(before, after, options)
+}
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`], along with the
+ * JSON Pointers of the `null` members of `after` that it can't represent: a
+ * `null` in a merge patch removes the member instead. If there are any, the
+ * patch doesn't turn `before` into `after`, and e.g. a JSON Patch (RFC 6902)
+ * is needed instead.
+ */
+pub fn generate_checked(
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+) -> (Option<serde_json::Value>, Vec<String>) {
+    generate_checked_with(before, after, &Options::default())
+}
+
+/**
+ * [`generate_checked`] using the given [`Options`].
+ */
+pub fn generate_checked_with(
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+    options: &Options,
+) -> (Option<serde_json::Value>, Vec<String>) {
+    let patch = generate_with(before, after, options);
+    let lossy = patch
+        .as_ref()
+        .map_or_else(Vec::new, |patch| validate::lossy_nulls(after, patch));
+    (patch, lossy)
+}
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`] that is
+ * guaranteed to turn `before` into `after` when applied, or fails with
+ * [`Error::NotRepresentable`] if there's none, see [`generate_checked`].
  */
-pub fn generate(
+pub fn generate_strict(
     before: &serde_json::Value,
     after: &serde_json::Value,
+) -> Result<Option<serde_json::Value>, Error> {
+    match generate_checked(before, after) {
+        (patch, pointers) if pointers.is_empty() => Ok(patch),
+        (_, pointers) => Err(Error::NotRepresentable { pointers }),
+    }
+}
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) by moving values out of `after`
+ * instead of cloning them, for when the documents are no longer needed.
+ */
+pub fn generate_owned(
+    before: serde_json::Value,
+    after: serde_json::Value,
+) -> Option<serde_json::Value> {
+    generate_owned_with(before, after, &Options::default())
+}
+
+/**
+ * [`generate_owned`] using the given [`Options`].
+ */
+pub fn generate_owned_with(
+    before: serde_json::Value,
+    after: serde_json::Value,
+    options: &Options,
 ) -> Option<serde_json::Value> {
-    if before.is_null()
-        || after.is_null()
-        || (!before.is_object() && !before.is_array())
-        || (!after.is_object() && !after.is_array())
-        || before.is_array() != after.is_array()
-    {
-        return Some(after.clone());
+    let Some(plan) = diff::Diff::new(options).generate::<_, owned::Plan>(&before, &after) else {
+        return options.finish(None);
+    };
+    drop(before);
+    options.finish(Some(plan.execute(after)))
+}
+
+/**
+ * Whether [`generate`] returns a patch, without building it: the documents
+ * are only compared up to the first difference.
+ */
+pub fn has_changes<V: JsonLike>(before: &V, after: &V) -> bool {
+    has_changes_with(before, after, &Options::default())
+}
+
+/**
+ * Whether [`generate_with`] returns a patch other than the one of
+ * [`Options::empty_patch`], see [`has_changes`].
+ */
+pub fn has_changes_with<V: JsonLike>(before: &V, after: &V, options: &Options) -> bool {
+    diff::Diff::new(options)
+        .generate::<V, diff::Changed>(before, after)
+        .is_some()
+}
+
+/**
+ * The length of the compact JSON of the patch that [`generate`] returns, or
+ * 0 if there's none, computed without building the patch, e.g. to decide
+ * whether to send a patch or the whole document.
+ */
+pub fn estimate_patch_size(before: &serde_json::Value, after: &serde_json::Value) -> usize {
+    estimate_patch_size_with(before, after, &Options::default())
+}
+
+/**
+ * [`estimate_patch_size`] of the patch that [`generate_with`] returns.
+ */
+pub fn estimate_patch_size_with(
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+    options: &Options,
+) -> usize {
+    size::estimate_patch_size(before, after, options)
+}
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`], or
+ * [`Update::Replace`] if the patch would be larger when serialized than
+ * `after`, for sending whichever is smaller.
+ */
+pub fn generate_update<'a>(
+    before: &serde_json::Value,
+    after: &'a serde_json::Value,
+) -> Option<Update<'a>> {
+    generate_update_with(before, after, &Options::new().max_patch_ratio(1.0))
+}
+
+/**
+ * [`generate_update`] using the given [`Options`], where
+ * [`Options::max_patch_ratio`] and [`Options::max_patch_size`] set when to
+ * replace the document instead, and a patch is always returned if neither is.
+ */
+pub fn generate_update_with<'a>(
+    before: &serde_json::Value,
+    after: &'a serde_json::Value,
+    options: &Options,
+) -> Option<Update<'a>> {
+    update::generate_update(before, after, options)
+}
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) between two maps, as if they were
+ * serialized to JSON objects, without serializing the entries whose values are
+ * equal (`==`) in both.
+ *
+ * Fails if serializing an entry fails.
+ */
+pub fn generate_map<K: Ord + AsRef<str> + serde::Serialize, V: serde::Serialize + PartialEq>(
+    before: &alloc::collections::BTreeMap<K, V>,
+    after: &alloc::collections::BTreeMap<K, V>,
+) -> Result<Option<serde_json::Value>, serde_json::Error> {
+    generate_map_with(before, after, &Options::default())
+}
+
+/**
+ * [`generate_map`] using the given [`Options`]. The whole maps are serialized
+ * for options that look at the root object as a whole, like
+ * [`Options::max_depth`] of 0.
+ */
+pub fn generate_map_with<
+    K: Ord + AsRef<str> + serde::Serialize,
+    V: serde::Serialize + PartialEq,
+>(
+    before: &alloc::collections::BTreeMap<K, V>,
+    after: &alloc::collections::BTreeMap<K, V>,
+    options: &Options,
+) -> Result<Option<serde_json::Value>, serde_json::Error> {
+    maps::generate_map(before, after, options)
+}
+
+/**
+ * [`generate_map`] for `HashMap`s.
+ */
+#[cfg(feature = "std")]
+pub fn generate_hash_map<K, V, S>(
+    before: &std::collections::HashMap<K, V, S>,
+    after: &std::collections::HashMap<K, V, S>,
+) -> Result<Option<serde_json::Value>, serde_json::Error>
+where
+    K: Eq + core::hash::Hash + AsRef<str> + serde::Serialize,
+    V: serde::Serialize + PartialEq,
+    S: core::hash::BuildHasher,
+{
+    generate_hash_map_with(before, after, &Options::default())
+}
+
+/**
+ * [`generate_hash_map`] using the given [`Options`], see
+ * [`generate_map_with`].
+ */
+#[cfg(feature = "std")]
+pub fn generate_hash_map_with<K, V, S>(
+    before: &std::collections::HashMap<K, V, S>,
+    after: &std::collections::HashMap<K, V, S>,
+    options: &Options,
+) -> Result<Option<serde_json::Value>, serde_json::Error>
+where
+    K: Eq + core::hash::Hash + AsRef<str> + serde::Serialize,
+    V: serde::Serialize + PartialEq,
+    S: core::hash::BuildHasher,
+{
+    maps::generate_map(before, after, options)
+}
+
+/**
+ * Generates a structured JSON Merge Patch (RFC 7386).
+ *
+ * Returns [`MergePatch::Unchanged`] where [`generate`] returns `None`.
+ */
+pub fn diff(before: &serde_json::Value, after: &serde_json::Value) -> MergePatch {
+    diff_with(before, after, &Options::default())
+}
+
+/**
+ * Generates a structured JSON Merge Patch (RFC 7386) using the given [`Options`].
+ */
+pub fn diff_with(
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+    options: &Options,
+) -> MergePatch {
+    MergePatch::from(generate_with(before, after, options))
+}
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) between the values at the JSON
+ * Pointer (RFC 6901) `pointer` of the two documents. The patch applies to the
+ * whole document, i.e. it's nested under the path of `pointer`.
+ *
+ * Fails if `pointer` doesn't refer to a value in both documents, or if it goes
+ * through an array (as array elements can't be patched individually).
+ */
+pub fn generate_at(
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+    pointer: &str,
+) -> Result<Option<serde_json::Value>, Error> {
+    generate_at_with(before, after, pointer, &Options::default())
+}
+
+/**
+ * [`generate_at`] using the given [`Options`]. Path filters are matched
+ * against paths from the document root.
+ */
+pub fn generate_at_with(
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+    pointer: &str,
+    options: &Options,
+) -> Result<Option<serde_json::Value>, Error> {
+    let (before, path) = resolve(before, pointer)?;
+    let (after, _) = resolve(after, pointer)?;
+
+    // Below the root, the values are compared like object members
+    let patch = if path.is_empty() {
+        diff::Diff::new(options).generate(before, after)
+    } else {
+        diff::Diff::at(options, path).member_patch(Some(before), Some(after))
+    };
+
+    Ok(options.finish(patch.map(|patch| {
+        let tokens: Vec<_> = pointer::tokens(pointer).collect();
+        tokens.into_iter().rev().fold(patch, |patch, token| {
+            let mut object = serde_json::Map::new();
+            object.insert(token.into_owned(), patch);
+            serde_json::Value::Object(object)
+        })
+    })))
+}
+
+fn resolve<'a>(
+    document: &'a serde_json::Value,
+    pointer: &str,
+) -> Result<(&'a serde_json::Value, Vec<pointer::Segment<'a>>), Error> {
+    let mut value = document;
+    let mut path = Vec::new();
+    for token in pointer::tokens(pointer) {
+        match value {
+            serde_json::Value::Object(object) => match object.get_key_value(token.as_ref()) {
+                Some((key, child)) => {
+                    path.push(pointer::Segment::Key(key));
+                    value = child;
+                }
+                None => {
+                    return Err(Error::PointerNotFound {
+                        pointer: pointer.to_owned(),
+                    })
+                }
+            },
+            serde_json::Value::Array(_) => {
+                return Err(Error::PointerThroughArray {
+                    pointer: pointer.to_owned(),
+                })
+            }
+            _ => {
+                return Err(Error::PointerNotFound {
+                    pointer: pointer.to_owned(),
+                })
+            }
+        }
+    }
+    Ok((value, path))
+}
+
+/**
+ * Applies a JSON Merge Patch (RFC 7386) to `document`, so that applying the
+ * result of [`generate`] to `before` turns it into `after`.
+ */
+pub fn apply(document: &mut serde_json::Value, patch: &serde_json::Value) {
+    apply::apply(document, patch)
+}
+
+/**
+ * A hash of `document` that's the same for equal documents, regardless of the
+ * order of their members, for telling whether a patch is applied to the
+ * document it was generated from. It's not cryptographic, so it doesn't
+ * prevent documents from being forged to match.
+ */
+pub fn checksum(document: &serde_json::Value) -> u64 {
+    checksum::checksum(document)
+}
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`], with the
+ * [`checksum`] of `before` for [`apply_checked`], or returns `None` if the
+ * documents are equal.
+ */
+pub fn generate_with_checksum(
+    before: &serde_json::Value,
+    after: &serde_json::Value,
+) -> Option<ChecksummedPatch> {
+    checksum::generate_with_checksum(before, after)
+}
+
+/**
+ * Applies `patch` to `document` like [`apply`] if the document's [`checksum`]
+ * is the one that the patch was generated from. Otherwise fails with
+ * [`Error::ChecksumMismatch`] leaving the document as it is, e.g. if it's the
+ * wrong document or it changed since.
+ */
+pub fn apply_checked(
+    document: &mut serde_json::Value,
+    patch: &ChecksummedPatch,
+) -> Result<(), Error> {
+    checksum::apply_checked(document, patch)
+}
+
+/**
+ * Applies the parts of `patch` that fit the shape of `document`, e.g. for
+ * patches that were generated from an older version of it. Objects in the
+ * patch that would replace a value other than an object, rather than merge
+ * into one, are skipped instead, and the JSON Pointers of the values they'd
+ * replace are returned.
+ */
+pub fn apply_partial(document: &mut serde_json::Value, patch: &serde_json::Value) -> Vec<String> {
+    apply::apply_partial(document, patch)
+}
+
+/**
+ * Lists the changes that applying `patch` makes to `document`, in the order
+ * of the members of the patch. Members that the patch leaves as they are,
+ * like values replaced with an equal one, aren't listed.
+ */
+pub fn changes<'a>(
+    document: &'a serde_json::Value,
+    patch: &'a serde_json::Value,
+) -> Vec<Change<'a>> {
+    changes::changes(document, patch)
+}
+
+/**
+ * Merges the changes from `base` to `ours` and from `base` to `theirs`, like
+ * applying the patches of both to `base`.
+ *
+ * Fails with [`Error::MergeConflict`] if both change a value differently.
+ * As with any merge patch, `null` values that either side adds are removed
+ * instead.
+ */
+pub fn merge(
+    base: &serde_json::Value,
+    ours: &serde_json::Value,
+    theirs: &serde_json::Value,
+) -> Result<serde_json::Value, Error> {
+    merge::merge(base, ours, theirs)
+}
+
+/**
+ * Applies `patch` to `document` like [`apply`], if the result matches the
+ * JSON Schema of `schema`. Otherwise fails with [`Error::SchemaViolation`] and
+ * leaves `document` as it is.
+ */
+#[cfg(feature = "jsonschema")]
+pub fn apply_validated(
+    document: &mut serde_json::Value,
+    patch: &serde_json::Value,
+    schema: &jsonschema::Validator,
+) -> Result<(), Error> {
+    schema::apply_validated(document, patch, schema)
+}
+
+/**
+ * JSON Schema for the merge patches of values of `T`: objects have no
+ * required members, and members that aren't required may also be `null` to
+ * remove them. Values other than objects, like array items, are replaced by
+ * patches, so their schemas stay as they are.
+ */
+#[cfg(feature = "schemars")]
+pub fn merge_patch_schema<T: schemars::JsonSchema + ?Sized>() -> schemars::Schema {
+    patch_schema::merge_patch_schema::<T>()
+}
+
+/**
+ * [`merge_patch_schema`] for a root schema generated with custom settings.
+ * The schemas for patches of definitions are added as definitions with a
+ * `MergePatch` suffix.
+ */
+#[cfg(feature = "schemars")]
+pub fn to_merge_patch_schema(schema: &schemars::Schema) -> schemars::Schema {
+    patch_schema::to_merge_patch_schema(schema)
+}
+
+/**
+ * Translates a merge patch into a MongoDB update document, which `$set`s the
+ * values that the patch sets and `$unset`s the members it removes, addressed
+ * by dotted paths, e.g. `{"a": {"b": 1, "c": null}}` becomes
+ * `{"$set": {"a.b": 1}, "$unset": {"a.c": ""}}`. Patches that change nothing
+ * become `{}`.
+ *
+ * Like merge patches, `$set` creates the objects that the paths go through,
+ * but it fails on values that aren't objects, which merge patches replace.
+ * Empty objects in the patch are `$set`, as in the patches of [`generate`],
+ * where they only replace values that aren't objects.
+ *
+ * Fails with [`Error::MongoUpdate`] for patches that aren't objects and keys
+ * that can't be part of dotted paths: empty ones, ones with dots and ones
+ * starting with `$`.
+ */
+pub fn to_mongo_update(patch: &serde_json::Value) -> Result<serde_json::Value, Error> {
+    mongo::to_mongo_update(patch)
+}
+
+/**
+ * Translates a merge patch into the RedisJSON commands that apply it to a
+ * document stored in Redis: `JSON.SET` for the values it sets, and `JSON.DEL`
+ * for the members it removes, at JSONPaths like `$.a["b c"]`.
+ *
+ * As `JSON.SET` only creates the last member of a path, the objects that the
+ * patch merges into are first created with `NX` if they're missing, like the
+ * document itself. Values there that aren't objects aren't replaced, unlike
+ * with merge patches. Empty objects in the patch are set, as in the patches of
+ * [`generate`], where they only replace values that aren't objects.
+ */
+pub fn to_redis_json_commands(patch: &serde_json::Value) -> Vec<RedisJsonCommand> {
+    redis::to_redis_json_commands(patch)
+}
+
+/**
+ * Previews applying `patch` to `document` without modifying it: returns the
+ * patched document along with the changes that the patch makes to it.
+ */
+pub fn preview<'a>(
+    document: &'a serde_json::Value,
+    patch: &'a serde_json::Value,
+) -> (serde_json::Value, ChangeSet<'a>) {
+    let mut result = copy::deep_clone(document);
+    apply(&mut result, patch);
+    (result, ChangeSet::from(changes(document, patch)))
+}
+
+/**
+ * Checks that `patch` is a valid JSON value and thus merge patch: that its
+ * object keys are strings and unique, and its numbers finite. Any
+ * [`serde_json::Value`] is one, but values from other formats, e.g. YAML, may
+ * not be.
+ */
+pub fn validate_patch<T: serde::Serialize + ?Sized>(patch: &T) -> Result<(), ValidationError> {
+    validate::validate_patch(patch)
+}
+
+/**
+ * Lists the JSON Pointers of the parts of `patch` that don't change
+ * `document`: removals of absent members and values equal to the current ones.
+ * The whole patch is a no-op if the result is empty and [`changes`] is too.
+ */
+pub fn validate_applicability(
+    document: &serde_json::Value,
+    patch: &serde_json::Value,
+) -> Vec<String> {
+    validate::validate_applicability(document, patch)
+}
+
+/**
+ * Parses a merge patch from an untrusted source, failing with
+ * [`Error::InvalidPatch`] if it isn't JSON and [`Error::DepthLimitExceeded`]
+ * if it nests more than `max_depth` arrays and objects. serde_json rejects
+ * more than 128 of them regardless.
+ */
+pub fn parse_patch(json: &[u8], max_depth: usize) -> Result<serde_json::Value, Error> {
+    untrusted::parse_patch(json, max_depth)
+}
+
+/**
+ * Parses a merge patch from an untrusted source like [`parse_patch`] and
+ * applies it to `document`, which is left as it is on failure.
+ */
+pub fn apply_untrusted(
+    document: &mut serde_json::Value,
+    patch: &[u8],
+    max_depth: usize,
+) -> Result<(), Error> {
+    let patch = untrusted::parse_patch(patch, max_depth)?;
+    apply(document, &patch);
+    Ok(())
+}
+
+/**
+ * [`apply_untrusted`] for a `target` of any type, through its JSON
+ * representation. Fails with [`Error::TypeMismatch`], leaving `target` as it
+ * is, if the patched JSON isn't a `T`.
+ */
+pub fn apply_untrusted_to<T: serde::Serialize + serde::de::DeserializeOwned>(
+    target: &mut T,
+    patch: &[u8],
+    max_depth: usize,
+) -> Result<(), Error> {
+    let patch = untrusted::parse_patch(patch, max_depth)?;
+    untrusted::apply_to(target, &patch)
+}
+
+/**
+ * Applies a sequence of patches to `document` in order, e.g. to replay a log of
+ * them, parsing each like [`parse_patch`]. Patches that fail to parse are
+ * handled as `invalid` says: they either stop the replay with the index of
+ * the patch, or are skipped and returned.
+ */
+pub fn apply_all<P: AsRef<[u8]>>(
+    document: &mut serde_json::Value,
+    patches: impl IntoIterator<Item = P>,
+    max_depth: usize,
+    invalid: InvalidPatches,
+) -> Result<Vec<PatchError>, PatchError> {
+    untrusted::apply_all(document, patches, max_depth, invalid)
+}
+
+/**
+ * Combines patches that are applied one after the other into one with the
+ * same effect, e.g. to send the patches of a batch as one. Returns `None` if
+ * there are none, or if a patch merges an object into a member (or the
+ * document) that an earlier one removes or sets to something else: combined,
+ * it would be merged into the original value instead.
+ * [`PatchLog::squash`] generates the patch from the documents instead.
+ */
+pub fn squash(patches: &[serde_json::Value]) -> Option<serde_json::Value> {
+    squash::squash(patches)
+}
+
+/**
+ * Lists the paths that two patches touch differently, e.g. to check that a
+ * regenerated patch is equivalent to a stored one: where one sets or removes
+ * a value and the other doesn't, or sets a different one. Objects are
+ * compared member by member, as they're merged rather than set.
+ */
+pub fn diff_patches<'a>(
+    a: &'a serde_json::Value,
+    b: &'a serde_json::Value,
+) -> Vec<PatchDifference<'a>> {
+    patch_diff::diff_patches(a, b)
+}
+
+/**
+ * Lists the paths that two patches generated from the same document, e.g. by
+ * concurrent edits, both change but differently: where they set different
+ * values, or one removes a value that the other sets. Applying them in either
+ * order gives the same result if there are none.
+ */
+pub fn conflicts<'a>(a: &'a serde_json::Value, b: &'a serde_json::Value) -> Vec<ConflictPath<'a>> {
+    patch_diff::conflicts(a, b)
+}
+
+/**
+ * Recomputes `patch`, generated from `old_base`, to apply to `new_base`, e.g.
+ * for a client that edited `old_base` offline while the document changed to
+ * `new_base`. The rebased patch makes the changes of `patch` that
+ * `new_base` doesn't have yet: values that changed concurrently are still
+ * overwritten but flagged as conflicts, and changes inside objects that are
+ * gone are dropped.
+ */
+pub fn rebase(
+    patch: &serde_json::Value,
+    old_base: &serde_json::Value,
+    new_base: &serde_json::Value,
+) -> Rebased {
+    rebase::rebase(patch, old_base, new_base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_replace_attr() {
+        let before = json!({ "a": "b" });
+        let after = json!({ "a": "c" });
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(patch, json!({ "a": "c" }));
+    }
+
+    #[test]
+    fn test_add_attr() {
+        let before = json!({ "a": "b" });
+        let after = json!({ "a": "b", "b": "c" });
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(patch, json!({ "b": "c" }));
+    }
+
+    #[test]
+    fn test_del_attr() {
+        let before = json!({ "a": "b" });
+        let after = json!({});
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(patch, json!({ "a": null }));
+    }
+
+    #[test]
+    fn test_del_attr_no_affect_others() {
+        let before = json!({ "a": "b", "b": "c" });
+        let after = json!({ "b": "c" });
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(patch, json!({ "a": null }));
+    }
+
+    #[test]
+    fn test_replace_array_with_attr() {
+        let before = json!({ "a": ["b"] });
+        let after = json!({ "a": "c" });
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(patch, json!({ "a": "c" }));
+    }
+
+    #[test]
+    fn test_replace_attr_with_array() {
+        let before = json!({ "a": "c" });
+        let after = json!({ "a": ["b"] });
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(patch, json!({ "a": ["b"] }));
+    }
+
+    #[test]
+    fn test_replace_obj_array_with_num_array() {
+        let before = json!({ "a": [{ "b": "c" }] });
+        let after = json!({ "a": [1] });
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(patch, json!({ "a": [1] }));
+    }
+
+    #[test]
+    fn test_replace_arr_if_changed() {
+        let before = json!(["a", "b"]);
+        let after = json!(["c", "d"]);
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(patch, json!(["c", "d"]));
+    }
+
+    #[test]
+    fn test_replace_arr_if_elem_deleted() {
+        let before = json!(["a", "b"]);
+        let after = json!(["a"]);
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(patch, json!(["a"]));
+    }
+
+    #[test]
+    fn test_replace_obj_with_array() {
+        let before = json!({ "a": "b" });
+        let after = json!(["c"]);
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(patch, json!(["c"]));
+    }
+
+    #[test]
+    fn test_replace_with_null() {
+        let before = json!({ "a": "b" });
+        let after = json!(null);
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(patch, json!(null));
+    }
+
+    #[test]
+    fn test_replace_with_string() {
+        let before = json!({ "a": "foo" });
+        let after = json!("bar");
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(patch, json!("bar"));
+    }
+
+    #[test]
+    fn test_keep_null_attrs() {
+        let before = json!({ "e": null });
+        let after = json!({ "e": null, "a": 1 });
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(patch, json!({ "a": 1 }));
+    }
+
+    #[test]
+    fn test_recursive() {
+        let before = json!({});
+        let after = json!({ "a": { "bb": {} } });
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(patch, json!({ "a": { "bb": {} } }));
+    }
+
+    #[test]
+    fn test_recursive_replace() {
+        let before = json!({ "a": { "b": "c" } });
+        let after = json!({ "a": { "b": "d" } });
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(patch, json!({ "a": { "b": "d" } }));
+    }
+
+    #[test]
+    fn test_recursive_add() {
+        let before = json!({ "a": { "b": "c" } });
+        let after = json!({ "a": { "b": "c", "d": "e" } });
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(patch, json!({ "a": { "d": "e" } }));
+    }
+
+    #[test]
+    fn test_unchanged() {
+        let before = json!({ "a": "a" });
+        let after = json!({ "a": "a" });
+        let patch = generate(&before, &after);
+        assert_eq!(patch, None);
+    }
+
+    #[test]
+    fn test_recursive_unchanged() {
+        let before = json!({ "a": { "b": "c" } });
+        let after = json!({ "a": { "b": "c" } });
+        let patch = generate(&before, &after);
+        assert_eq!(patch, None);
+    }
+
+    #[test]
+    fn test_unchanged_array() {
+        let before = json!([1, 2, 3]);
+        let after = json!([1, 2, 3]);
+        let patch = generate(&before, &after);
+        assert_eq!(patch, None);
+    }
+
+    #[test]
+    fn test_int_float_differ_by_default() {
+        let before = json!({ "a": 1 });
+        let after = json!({ "a": 1.0 });
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(patch, json!({ "a": 1.0 }));
+    }
+
+    #[test]
+    fn test_numeric_equality() {
+        let options = Options::new().numeric_equality(true);
+        let before = json!({ "a": 1, "b": [2, { "c": -3 }] });
+        let after = json!({ "a": 1.0, "b": [2.0, { "c": -3.0 }] });
+        let patch = generate_with(&before, &after, &options);
+        assert_eq!(patch, None);
+    }
+
+    #[test]
+    fn test_numeric_equality_detects_changes() {
+        let options = Options::new().numeric_equality(true);
+        let before = json!({ "a": 1, "b": 9007199254740993u64 });
+        let after = json!({ "a": 1.5, "b": 9007199254740992.0 });
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(patch, json!({ "a": 1.5, "b": 9007199254740992.0 }));
+    }
+
+    #[test]
+    fn test_float_epsilon() {
+        let options = Options::new().float_epsilon(0.01);
+        let before = json!({ "a": 0.1, "b": 1 });
+        let after = json!({ "a": 0.105, "b": 1.5 });
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(patch, json!({ "b": 1.5 }));
+
+        // Integers are compared exactly, even where they don't fit an f64
+        let options = Options::new().float_epsilon(0.0);
+        let before = json!({ "a": 9007199254740992_u64, "b": 1.0 });
+        let after = json!({ "a": 9007199254740993_u64, "b": 1 });
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(patch, json!({ "a": 9007199254740993_u64 }));
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_arbitrary_precision_diff() {
+        let before: serde_json::Value =
+            serde_json::from_str(r#"{ "a": 3.14159265358979323846264338327950288, "b": 1 }"#)
+                .unwrap();
+        let after: serde_json::Value =
+            serde_json::from_str(r#"{ "a": 3.14159265358979323846264338327950289, "b": 1 }"#)
+                .unwrap();
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(
+            serde_json::to_string(&patch).unwrap(),
+            r#"{"a":3.14159265358979323846264338327950289}"#
+        );
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_arbitrary_precision_big_integers() {
+        let before: serde_json::Value =
+            serde_json::from_str(r#"{ "a": 123456789012345678901234567890 }"#).unwrap();
+        let after: serde_json::Value =
+            serde_json::from_str(r#"{ "a": 123456789012345678901234567891 }"#).unwrap();
+        let options = Options::new().numeric_equality(true);
+        let patch = generate_with(&before, &after, &options).unwrap();
+        let text = serde_json::to_string(&patch).unwrap();
+        assert_eq!(text, r#"{"a":123456789012345678901234567891}"#);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&text).unwrap(),
+            patch
+        );
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn test_arbitrary_precision_numeric_equality() {
+        let before: serde_json::Value = serde_json::from_str(
+            r#"{ "a": 1.10, "b": 100, "c": -0.0, "d": 12345678901234567890.5 }"#,
+        )
+        .unwrap();
+        let after: serde_json::Value = serde_json::from_str(
+            r#"{ "a": 1.1, "b": 1e2, "c": 0, "d": 1234567890123456789.05e1 }"#,
+        )
+        .unwrap();
+        assert!(generate(&before, &after).is_some());
+        let options = Options::new().numeric_equality(true);
+        assert_eq!(generate_with(&before, &after, &options), None);
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn test_unicode_nfc() {
+        let before = json!({ "file": "Cafe\u{301}.txt", "ligature": "\u{fb01}le" });
+        let after = json!({ "file": "Caf\u{e9}.txt", "ligature": "file" });
+        assert_eq!(generate(&before, &after), Some(after.clone()));
+
+        let options = Options::new().unicode_normalization(UnicodeNormalization::Nfc);
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(patch, json!({ "ligature": "file" }));
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn test_unicode_nfkc() {
+        let before = json!({ "file": ["Cafe\u{301}.txt"], "ligature": "\u{fb01}le" });
+        let after = json!({ "file": ["Caf\u{e9}.txt"], "ligature": "file" });
+        let options = Options::new().unicode_normalization(UnicodeNormalization::Nfkc);
+        assert_eq!(generate_with(&before, &after, &options), None);
+    }
+
+    #[test]
+    fn test_ignore_path() {
+        let options = Options::new().ignore_path("/metadata/updatedAt");
+        let before = json!({ "metadata": { "updatedAt": 1, "name": "a" } });
+        let after = json!({ "metadata": { "updatedAt": 2, "name": "a" } });
+        assert_eq!(generate_with(&before, &after, &options), None);
+
+        let after = json!({ "metadata": { "updatedAt": 2, "name": "b" } });
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(patch, json!({ "metadata": { "name": "b" } }));
+    }
+
+    #[test]
+    fn test_ignore_path_add_remove() {
+        let options = Options::new().ignore_path("/metadata/updatedAt");
+        let before = json!({ "metadata": { "updatedAt": 1 } });
+        let after = json!({ "metadata": {} });
+        assert_eq!(generate_with(&before, &after, &options), None);
+
+        let before = json!({});
+        let after = json!({ "metadata": { "updatedAt": 2, "name": "b" } });
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(patch, json!({ "metadata": { "name": "b" } }));
+    }
+
+    #[test]
+    fn test_ignore_glob_in_array() {
+        let options = Options::new().ignore_path("/items/*/etag");
+        let before = json!({ "items": [{ "id": 1, "etag": "x" }, { "id": 2, "etag": "y" }] });
+        let after = json!({ "items": [{ "id": 1, "etag": "z" }, { "id": 2 }] });
+        assert_eq!(generate_with(&before, &after, &options), None);
+
+        let after = json!({ "items": [{ "id": 1, "etag": "z" }] });
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(patch, json!({ "items": [{ "id": 1 }] }));
+    }
+
+    #[test]
+    fn test_ignore_glob_in_object() {
+        let options = Options::new().ignore_path("/items/*/etag");
+        let before = json!({ "items": { "a": { "v": 1, "etag": "x" } } });
+        let after = json!({ "items": { "a": { "v": 2, "etag": "y" }, "b": { "etag": "z" } } });
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(patch, json!({ "items": { "a": { "v": 2 }, "b": {} } }));
+    }
+
+    #[test]
+    fn test_ignore_any_depth() {
+        let options = Options::new().ignore_path("/**/etag");
+        let before = json!({ "etag": 1, "a": { "b": { "etag": 2 } } });
+        let after = json!({ "etag": 3, "a": { "b": { "etag": 4 } } });
+        assert_eq!(generate_with(&before, &after, &options), None);
+    }
+
+    #[test]
+    fn test_ignore_escaped_pointer() {
+        let options = Options::new().ignore_path("/a~1b/c~0d");
+        let before = json!({ "a/b": { "c~d": 1, "e": 1 } });
+        let after = json!({ "a/b": { "c~d": 2, "e": 2 } });
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(patch, json!({ "a/b": { "e": 2 } }));
+    }
+
+    #[test]
+    fn test_include_path() {
+        let options = Options::new().include_path("/spec");
+        let before = json!({ "spec": { "a": 1, "b": 1 }, "status": { "ready": false } });
+        let after = json!({ "spec": { "a": 2, "b": 1 }, "status": { "ready": true }, "new": 1 });
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(patch, json!({ "spec": { "a": 2 } }));
+
+        let after = json!({ "spec": { "a": 1, "b": 1 } });
+        assert_eq!(generate_with(&before, &after, &options), None);
+    }
+
+    #[test]
+    fn test_include_nested_path() {
+        let options = Options::new()
+            .include_path("/spec/template")
+            .include_path("/metadata/labels");
+        let before = json!({ "spec": { "template": { "a": 1 }, "replicas": 1 } });
+        let after = json!({
+            "spec": { "template": { "a": 2 }, "replicas": 2 },
+            "metadata": { "labels": { "app": "x" }, "name": "y" },
+        });
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(
+            patch,
+            json!({ "spec": { "template": { "a": 2 } }, "metadata": { "labels": { "app": "x" } } })
+        );
+    }
+
+    #[test]
+    fn test_include_path_removed_ancestor() {
+        let options = Options::new().include_path("/spec/template");
+        let before = json!({ "spec": { "template": { "a": 1 }, "replicas": 1 } });
+        let after = json!({ "spec": "none" });
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(patch, json!({ "spec": { "template": null } }));
+
+        let after = json!({});
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(patch, json!({ "spec": { "template": null } }));
+    }
+
+    #[test]
+    fn test_include_with_ignore() {
+        let options = Options::new()
+            .include_path("/spec")
+            .ignore_path("/spec/generation");
+        let before = json!({ "spec": { "a": 1, "generation": 1 }, "other": 1 });
+        let after = json!({ "spec": { "a": 2, "generation": 2 }, "other": 2 });
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(patch, json!({ "spec": { "a": 2 } }));
+    }
+
+    #[test]
+    fn test_generate_at() {
+        let before = json!({ "spec": { "template": { "a": 1, "b": 1 } }, "status": 1 });
+        let after = json!({ "spec": { "template": { "a": 2, "b": 1 } }, "status": 2 });
+        let patch = generate_at(&before, &after, "/spec/template").unwrap();
+        assert_eq!(patch, Some(json!({ "spec": { "template": { "a": 2 } } })));
+        let patch = generate_at(&before, &after, "/status").unwrap();
+        assert_eq!(patch, Some(json!({ "status": 2 })));
+        let patch = generate_at(&before, &after, "").unwrap();
+        assert_eq!(patch, generate(&before, &after));
+    }
+
+    #[test]
+    fn test_generate_at_unchanged() {
+        let before = json!({ "spec": { "a": 1 }, "status": "x" });
+        let after = json!({ "spec": { "a": 1 }, "status": "y" });
+        assert_eq!(generate_at(&before, &after, "/spec").unwrap(), None);
+        assert_eq!(generate_at(&before, &after, "/spec/a").unwrap(), None);
+    }
+
+    #[test]
+    fn test_generate_at_escaped() {
+        let before = json!({ "a/b": { "c": 1 } });
+        let after = json!({ "a/b": { "c": 2 } });
+        let patch = generate_at(&before, &after, "/a~1b").unwrap();
+        assert_eq!(patch, Some(json!({ "a/b": { "c": 2 } })));
+    }
+
+    #[test]
+    fn test_generate_at_missing() {
+        let before = json!({ "spec": { "a": 1 } });
+        let after = json!({ "status": 1 });
+        assert_eq!(
+            generate_at(&before, &after, "/spec"),
+            Err(Error::PointerNotFound {
+                pointer: "/spec".to_owned()
+            })
+        );
+        assert_eq!(
+            generate_at(&before, &after, "/status"),
+            Err(Error::PointerNotFound {
+                pointer: "/status".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn test_generate_at_array() {
+        let before = json!({ "items": [{ "a": 1 }] });
+        assert_eq!(
+            generate_at(&before, &before, "/items/0"),
+            Err(Error::PointerThroughArray {
+                pointer: "/items/0".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn test_generate_at_filters() {
+        let options = Options::new().ignore_path("/spec/generation");
+        let before = json!({ "spec": { "a": 1, "generation": 1 } });
+        let after = json!({ "spec": { "a": 1, "generation": 2 } });
+        assert_eq!(
+            generate_at_with(&before, &after, "/spec", &options).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let options = Options::new().max_depth(1);
+        let before = json!({ "a": { "b": { "c": 1, "d": 1 }, "e": 1, "f": 1 }, "g": 1 });
+        let after = json!({ "a": { "b": { "c": 2, "d": 1 }, "e": 1 }, "g": 2 });
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(
+            patch,
+            json!({ "a": { "b": { "c": 2, "d": 1 }, "e": 1, "f": null }, "g": 2 })
+        );
+    }
+
+    #[test]
+    fn test_max_depth_nested_removal() {
+        let options = Options::new().max_depth(0);
+        let before = json!({ "a": { "b": { "c": 1, "d": 1 } }, "e": "x" });
+        let after = json!({ "a": { "b": { "c": 1 } }, "e": "x" });
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(
+            patch,
+            json!({ "a": { "b": { "c": 1, "d": null } }, "e": "x" })
+        );
+    }
+
+    #[test]
+    fn test_max_depth_unchanged() {
+        let options = Options::new().max_depth(0);
+        let before = json!({ "a": { "b": 1 } });
+        assert_eq!(generate_with(&before, &before, &options), None);
+    }
+
+    fn nested(depth: usize, leaf: serde_json::Value) -> serde_json::Value {
+        (0..depth).fold(leaf, |value, _| {
+            let mut object = serde_json::Map::new();
+            object.insert("a".to_owned(), value);
+            serde_json::Value::Object(object)
+        })
+    }
+
+    /**
+     * Returns the depth and leaf of a value built by `nested`, dismantling it
+     * without recursion (dropping it as-is would overflow the stack).
+     */
+    fn unnest(mut value: serde_json::Value) -> (usize, serde_json::Value) {
+        let mut depth = 0;
+        loop {
+            match value {
+                serde_json::Value::Object(mut object) if object.len() == 1 => {
+                    value = object.remove("a").unwrap();
+                    depth += 1;
+                }
+                serde_json::Value::Array(mut array) if array.len() == 1 => {
+                    value = array.pop().unwrap();
+                    depth += 1;
+                }
+                leaf => return (depth, leaf),
+            }
+        }
+    }
+
+    #[test]
+    fn test_deeply_nested_change() {
+        let before = nested(100_000, json!(1));
+        let after = nested(100_000, json!(2));
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(unnest(patch), (100_000, json!(2)));
+        assert_eq!(generate(&before, &before), None);
+        unnest(before);
+        unnest(after);
+    }
+
+    #[test]
+    fn test_deeply_nested_add() {
+        let before = json!({});
+        let after = nested(100_000, json!(1));
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(unnest(patch), (100_000, json!(1)));
+        unnest(after);
+    }
+
+    #[test]
+    fn test_deeply_nested_arrays() {
+        let options = Options::new().ignore_path("/x");
+        let deep_array = |leaf| {
+            let array = (0..100_000).fold(leaf, |value, _| serde_json::Value::Array(vec![value]));
+            nested(1, array)
+        };
+        let before = deep_array(json!(1));
+        let after = deep_array(json!(2));
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(unnest(patch), (100_001, json!(2)));
+        assert_eq!(generate_with(&before, &before, &options), None);
+        unnest(before);
+        unnest(after);
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = json!({ "a": { "b": 1, "c": 1 }, "d": 1 });
+        let after = json!({ "a": { "b": 2 }, "d": null });
+        let patch = diff(&before, &after);
+        let MergePatch::Merge(members) = &patch else {
+            panic!("expected a merge patch, got {patch:?}");
+        };
+        assert_eq!(members["d"], MergePatch::Replace(json!(null)));
+        assert_eq!(
+            members["a"],
+            MergePatch::Merge(
+                [
+                    ("b".to_owned(), MergePatch::Replace(json!(2))),
+                    ("c".to_owned(), MergePatch::Replace(json!(null))),
+                ]
+                .into()
+            )
+        );
+        assert_eq!(patch.into_value(), generate(&before, &after));
+    }
+
+    #[test]
+    fn test_diff_unchanged() {
+        let before = json!({ "a": 1 });
+        let patch = diff(&before, &before);
+        assert_eq!(patch, MergePatch::Unchanged);
+        assert!(patch.is_unchanged());
+        assert_eq!(serde_json::Value::from(patch), json!({}));
+        assert_eq!(
+            diff(&before, &json!(null)),
+            MergePatch::Replace(json!(null))
+        );
+    }
+
+    #[test]
+    fn test_merge_patch_conversions() {
+        let value = json!({ "a": { "b": null }, "c": [1] });
+        let patch = MergePatch::from(value.clone());
+        assert_eq!(serde_json::Value::from(patch), value);
+
+        let patch = MergePatch::Merge(
+            [
+                ("a".to_owned(), MergePatch::Unchanged),
+                ("b".to_owned(), MergePatch::Merge(Default::default())),
+            ]
+            .into(),
+        );
+        assert!(patch.is_unchanged());
+        assert_eq!(patch.into_value(), None);
+    }
+
+    #[test]
+    fn test_generate_ref() {
+        let before = json!({ "a": { "b": 1, "c": 1 }, "d": [1], "h": 1 });
+        let after = json!({ "a": { "b": { "x": 2 } }, "d": [2], "f": "g" });
+        let patch = generate_ref(&before, &after).unwrap();
+
+        let PatchRef::Merge(members) = &patch else {
+            panic!("expected a merge patch, got {patch:?}");
+        };
+        assert!(
+            matches!(&members["d"], PatchRef::Replace(std::borrow::Cow::Borrowed(d)) if std::ptr::eq(*d, &after["d"]))
+        );
+
+        let expected = generate(&before, &after).unwrap();
+        assert_eq!(serde_json::to_value(&patch).unwrap(), expected);
+        assert_eq!(
+            serde_json::to_string(&patch).unwrap(),
+            serde_json::to_string(&expected).unwrap()
+        );
+        assert_eq!(patch.into_owned(), expected);
+    }
+
+    #[test]
+    fn test_generate_ref_options() {
+        let options = Options::new().ignore_path("/a/etag");
+        let before = json!({});
+        let after = json!({ "a": { "etag": 1, "b": 2 } });
+        let patch = generate_ref_with(&before, &after, &options).unwrap();
+        assert_eq!(patch.into_owned(), json!({ "a": { "b": 2 } }));
+        assert!(generate_ref(&after, &after).is_none());
+    }
+
+    #[test]
+    fn test_generate_owned() {
+        let cases = [
+            (
+                json!({ "a": { "b": 1, "c": 1 }, "d": [1] }),
+                json!({ "a": { "b": { "x": 2 } }, "d": [2], "e": "f" }),
+            ),
+            (json!({ "a": 1 }), json!(["a"])),
+            (json!({ "a": 1 }), json!({ "a": 1 })),
+            (json!([1]), json!(null)),
+        ];
+        for (before, after) in cases {
+            let expected = generate(&before, &after);
+            assert_eq!(generate_owned(before, after), expected);
+        }
+    }
+
+    #[test]
+    fn test_generate_owned_options() {
+        let options = Options::new()
+            .include_path("/spec/template")
+            .ignore_path("/spec/template/etag");
+        let before = json!({ "spec": { "template": { "a": 1, "etag": 1 } } });
+        let after = json!({ "spec": { "template": { "a": { "b": 2 }, "etag": 2 }, "x": 1 } });
+        let patch = generate_owned_with(before, after, &options).unwrap();
+        assert_eq!(
+            patch,
+            json!({ "spec": { "template": { "a": { "b": 2 } } } })
+        );
+
+        let before = json!({ "spec": { "template": { "a": 1 } } });
+        let after = json!({ "spec": 1 });
+        let patch = generate_owned_with(before, after, &options).unwrap();
+        assert_eq!(patch, json!({ "spec": { "template": null } }));
+    }
+
+    #[test]
+    fn test_custom_json_like() {
+        use std::collections::BTreeMap;
+
+        #[derive(Debug, Clone, PartialEq)]
+        enum Doc {
+            Null,
+            Int(i64),
+            Text(String),
+            List(Vec<Doc>),
+            Map(BTreeMap<String, Doc>),
+        }
+
+        impl JsonLike for Doc {
+            type Object = BTreeMap<String, Doc>;
+            type Members<'a> = std::iter::Map<
+                std::collections::btree_map::Iter<'a, String, Doc>,
+                fn((&'a String, &'a Doc)) -> (&'a str, &'a Doc),
+            >;
+
+            fn kind(&self) -> Kind<'_, Self> {
+                match self {
+                    Doc::Null => Kind::Null,
+                    Doc::Int(_) => Kind::Number,
+                    Doc::Text(text) => Kind::String(text),
+                    Doc::List(list) => Kind::Array(list),
+                    Doc::Map(map) => Kind::Object(map),
+                }
+            }
+
+            fn members(object: &Self::Object) -> Self::Members<'_> {
+                object.iter().map(|(key, value)| (key.as_str(), value))
+            }
+
+            fn get<'a>(object: &'a Self::Object, key: &str) -> Option<&'a Self> {
+                object.get(key)
+            }
+
+            fn len(object: &Self::Object) -> usize {
+                object.len()
+            }
+
+            fn as_i64(&self) -> Option<i64> {
+                match self {
+                    Doc::Int(n) => Some(*n),
+                    _ => None,
+                }
+            }
+
+            fn as_u64(&self) -> Option<u64> {
+                self.as_i64().and_then(|n| n.try_into().ok())
+            }
+
+            fn as_f64(&self) -> Option<f64> {
+                self.as_i64().map(|n| n as f64)
+            }
+
+            fn null() -> Self {
+                Doc::Null
+            }
+
+            fn array(values: Vec<Self>) -> Self {
+                Doc::List(values)
+            }
+
+            fn new_object() -> Self::Object {
+                BTreeMap::new()
+            }
+
+            fn insert(object: &mut Self::Object, key: &str, value: Self) {
+                object.insert(key.to_owned(), value);
+            }
+
+            fn object(object: Self::Object) -> Self {
+                Doc::Map(object)
+            }
+        }
+
+        let map = |members: Vec<(&str, Doc)>| {
+            Doc::Map(
+                members
+                    .into_iter()
+                    .map(|(key, value)| (key.to_owned(), value))
+                    .collect(),
+            )
+        };
+        let before = map(vec![
+            ("a", Doc::Int(1)),
+            ("b", map(vec![("c", Doc::List(vec![Doc::Int(1)]))])),
+            ("d", Doc::Text("x".to_owned())),
+        ]);
+        let after = map(vec![
+            ("a", Doc::Int(1)),
+            ("b", map(vec![("c", Doc::List(vec![Doc::Int(2)]))])),
+        ]);
+
+        assert_eq!(
+            generate(&before, &after),
+            Some(map(vec![
+                ("b", map(vec![("c", Doc::List(vec![Doc::Int(2)]))])),
+                ("d", Doc::Null),
+            ]))
+        );
+        assert_eq!(generate(&after, &after), None);
+        assert_eq!(
+            generate_with(&before, &after, &Options::new().ignore_path("/b")),
+            Some(map(vec![("d", Doc::Null)]))
+        );
+    }
+
+    #[test]
+    fn test_arc_value() {
+        let before = ArcValue::from(json!({ "a": { "b": [1, 2] }, "c": "x", "d": 1 }));
+        let ArcValue::Object(members) = &before else {
+            unreachable!()
+        };
+        let mut members = (**members).clone();
+        members.insert("d".to_owned(), ArcValue::from(json!({ "e": [3] })));
+        let after = ArcValue::Object(members.into());
+
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(
+            serde_json::to_value(&patch).unwrap(),
+            json!({ "d": { "e": [3] } })
+        );
+        assert_eq!(Value::from(&patch), json!({ "d": { "e": [3] } }));
+
+        // The patch shares the value of "d" with `after`
+        let (ArcValue::Object(patch), ArcValue::Object(after)) = (&patch, &after) else {
+            unreachable!()
+        };
+        match (&patch["d"], &after["d"]) {
+            (ArcValue::Object(a), ArcValue::Object(b)) => assert!(Arc::ptr_eq(a, b)),
+            _ => unreachable!(),
+        }
+
+        let options = Options::new().numeric_equality(true);
+        assert_eq!(
+            generate_with(
+                &ArcValue::from(json!([1])),
+                &ArcValue::from(json!([1.0])),
+                &options
+            ),
+            None
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_generate() {
+        let before: serde_json::Value = (0..10_000)
+            .map(|i| (i.to_string(), json!({ "v": i, "w": [i] })))
+            .collect::<serde_json::Map<_, _>>()
+            .into();
+        let after: serde_json::Value = (0..10_000)
+            .filter(|i| i % 7 != 0)
+            .map(|i| (i.to_string(), json!({ "v": i % 3, "w": [i] })))
+            .chain([("new".to_owned(), json!(1))])
+            .collect::<serde_json::Map<_, _>>()
+            .into();
+
+        let patch = par_generate(&before, &after);
+        assert!(patch.is_some());
+        assert_eq!(patch, generate(&before, &after));
+        assert_eq!(
+            serde_json::to_string(&par_generate(&before, &after)).unwrap(),
+            serde_json::to_string(&par_generate(&before, &after)).unwrap()
+        );
+        assert_eq!(par_generate(&before, &before), None);
+
+        let options = Options::new().ignore_path("/*/v").max_depth(1);
+        assert_eq!(
+            par_generate_with(&before, &after, &options),
+            generate_with(&before, &after, &options)
+        );
+        assert_eq!(par_generate(&json!([1]), &json!([2])), Some(json!([2])));
+    }
+
+    #[cfg(feature = "simd-json")]
+    #[test]
+    fn test_simd_json() {
+        use ::simd_json::json as simd;
+
+        let before = simd!({ "a": 1, "b": { "c": "x", "d": [1, 2] }, "e": null });
+        let after = simd!({ "a": 1, "b": { "c": "y", "d": [1, 2] }, "f": true });
+        assert_eq!(
+            simd_json::generate(&before, &after),
+            Some(simd!({ "b": { "c": "y" }, "e": null, "f": true }))
+        );
+        assert_eq!(simd_json::generate(&before, &before), None);
+
+        let options = Options::new().numeric_equality(true);
+        assert_eq!(
+            simd_json::generate_with(&simd!({ "a": 1 }), &simd!({ "a": 1.0 }), &options),
+            None
+        );
+        assert_eq!(
+            simd_json::generate_with(&simd!({ "a": -1 }), &simd!({ "a": 1 }), &options),
+            Some(simd!({ "a": 1 }))
+        );
+        let options = Options::new().float_epsilon(0.1);
+        assert_eq!(
+            simd_json::generate_with(&simd!({ "a": 1 }), &simd!({ "a": 1.05 }), &options),
+            None
+        );
+
+        let mut before = br#"{ "a": "x", "b": { "c": [1], "d": 2 } }"#.to_vec();
+        let mut after = br#"{ "a": "x", "b": { "c": [2] } }"#.to_vec();
+        let before = ::simd_json::to_borrowed_value(&mut before).unwrap();
+        let after = ::simd_json::to_borrowed_value(&mut after).unwrap();
+        let patch = simd_json::generate_borrowed(&before, &after).unwrap();
+        assert_eq!(
+            serde_json::to_value(&patch).unwrap(),
+            json!({ "b": { "c": [2], "d": null } })
+        );
+    }
+
+    #[cfg(feature = "ijson")]
+    #[test]
+    fn test_ijson() {
+        use ::ijson::ijson;
+
+        let before = ijson!({ "a": 1, "b": { "c": "x", "d": [1, 2] }, "e": null });
+        let after = ijson!({ "a": 1.0, "b": { "c": "y", "d": [1, 2] }, "f": true });
+        assert_eq!(
+            ijson::generate(&before, &after),
+            Some(ijson!({ "b": { "c": "y" }, "e": null, "f": true }))
+        );
+        assert_eq!(ijson::generate(&before, &before), None);
+
+        let options = Options::new().ignore_path("/b/c");
+        assert_eq!(
+            ijson::generate_with(&before, &after, &options),
+            Some(ijson!({ "e": null, "f": true }))
+        );
+    }
+
+    #[cfg(feature = "raw_value")]
+    #[test]
+    fn test_generate_raw() {
+        use serde_json::value::RawValue;
+
+        let raw = |value: &serde_json::Value| RawValue::from_string(value.to_string()).unwrap();
+        let check = |before: serde_json::Value, after: serde_json::Value, options: &Options| {
+            let patch = generate_raw_with(&raw(&before), &raw(&after), options).unwrap();
+            let patch = patch.map(|patch| serde_json::from_str(patch.get()).unwrap());
+            assert_eq!(patch, generate_with(&before, &after, options));
+        };
+
+        let before = json!({
+            "a": { "b": { "c": 1, "d": [1, 2] }, "e": "x" },
+            "f": { "g": 1 },
+            "h": 1,
+            "n": { "m": 1 }
+        });
+        let after = json!({
+            "a": { "b": { "c": 2, "d": [1, 2] }, "e": "x" },
+            "f": 1,
+            "h": 1.0,
+            "i": { "j": [{}] }
+        });
+        check(before.clone(), after.clone(), &Options::default());
+        check(
+            before.clone(),
+            after.clone(),
+            &Options::new().numeric_equality(true),
+        );
+        check(
+            before.clone(),
+            after.clone(),
+            &Options::new().ignore_path("/**/c"),
+        );
+        check(
+            before.clone(),
+            after.clone(),
+            &Options::new().include_path("/a/b"),
+        );
+        check(
+            before.clone(),
+            after.clone(),
+            &Options::new().include_path("/n/m"),
+        );
+        check(before.clone(), after.clone(), &Options::new().max_depth(1));
+        check(before.clone(), before.clone(), &Options::default());
+        check(json!(1), json!(1), &Options::default());
+        check(json!([1]), json!({ "a": 1 }), &Options::default());
+        let options = Options::new().empty_patch(EmptyPatchBehavior::EmptyObject);
+        check(before.clone(), before.clone(), &options);
+        check(before.clone(), before.clone(), &options.include_path("/x"));
+
+        // Unchanged members are compared by their text, added ones are copied
+        let before = RawValue::from_string(r#"{"a": {"b": [1,2]}, "c": 1}"#.to_owned()).unwrap();
+        let after = RawValue::from_string(r#"{"a": {"b": [1,2]}, "d": [ 1 ]}"#.to_owned()).unwrap();
+        assert_eq!(
+            generate_raw(&before, &after).unwrap().unwrap().get(),
+            r#"{"c":null,"d":[ 1 ]}"#
+        );
+        assert!(generate_raw(&before, &before).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_generate_stream() {
+        let check = |before: &str, after: &str, options: &Options| {
+            let mut patch = Vec::new();
+            let written =
+                generate_stream_with(before.as_bytes(), after.as_bytes(), &mut patch, options)
+                    .unwrap();
+            assert_eq!(written, !patch.is_empty());
+            let patch = written.then(|| serde_json::from_slice(&patch).unwrap());
+            let (before, after) = (
+                serde_json::from_str(before).unwrap(),
+                serde_json::from_str(after).unwrap(),
+            );
+            assert_eq!(patch, generate_with::<Value>(&before, &after, options));
+        };
+
+        let before = r#"{
+            "a": { "b": { "c": 1, "d": [1, 2] }, "e": "x" },
+            "f": { "g": 1 },
+            "h": 1,
+            "n": { "m": { "o": 1 } },
+            "p": "\"}"
+        }"#;
+        let after = r#"{
+            "a": { "b": { "c": 2, "d": [1,2] }, "e": "x" },
+            "i": { "j": [{}] },
+            "h": 1.0,
+            "f": 1,
+            "p": "\"}"
+        }"#;
+        let options = [
+            Options::default(),
+            Options::new().numeric_equality(true),
+            Options::new().ignore_path("/**/c"),
+            Options::new().include_path("/a/b"),
+            Options::new().include_path("/n/m/o"),
+            Options::new().max_depth(1),
+        ];
+        for options in &options {
+            check(before, after, options);
+            check(after, before, options);
+            check(before, before, options);
+        }
+        check("1", "1", &Options::default());
+        check("[1]", r#"{ "a": 1 }"#, &Options::default());
+        check("{}", r#"{ "a": { "b": null } }"#, &Options::default());
+
+        let mut patch = Vec::new();
+        let before = r#"{ "a": { "b": 1, "c": 2 } }"#;
+        let after = r#"{ "a": { "b": 1, "c": 3 }, "d": [ 1 ] }"#;
+        assert!(generate_stream(before.as_bytes(), after.as_bytes(), &mut patch).unwrap());
+        assert_eq!(patch, br#"{"a":{"c":3},"d":[ 1 ]}"#);
+
+        for invalid in [
+            r#"{ "a": 1 "#,
+            r#"{ "a" 1 }"#,
+            r#"{ "a": 1 } 2"#,
+            r#"{ "a": }"#,
+        ] {
+            assert!(generate_stream(invalid.as_bytes(), "{}".as_bytes(), Vec::new()).is_err());
+        }
+        let error = generate_stream(
+            r#"{ "a": 1 }"#.as_bytes(),
+            "{\n\"a\": tru }".as_bytes(),
+            Vec::new(),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("line 2 column 6"), "{error}");
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_generate_from_files() {
+        let dir = std::env::temp_dir().join(format!("json-merge-patch-gen-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (before, after, invalid) = (
+            dir.join("before.json"),
+            dir.join("after.json"),
+            dir.join("invalid.json"),
+        );
+        std::fs::write(&before, r#"{ "a": 1, "b": [1] }"#).unwrap();
+        std::fs::write(&after, r#"{ "a": 1, "b": [2] }"#).unwrap();
+        std::fs::write(&invalid, "{\n  \"a\": 1,\n}").unwrap();
+
+        assert_eq!(
+            generate_from_files(&before, &after),
+            Ok(Some(json!({ "b": [2] })))
+        );
+        assert_eq!(generate_from_files(&before, &before), Ok(None));
+
+        let error = generate_from_files(&before, &invalid).unwrap_err();
+        assert!(
+            matches!(
+                error,
+                Error::Parse {
+                    line: 3,
+                    column: 1,
+                    ..
+                }
+            ),
+            "{error:?}"
+        );
+        assert_eq!(
+            error.to_string(),
+            format!("{}:3:1: trailing comma", invalid.display())
+        );
+
+        let empty = dir.join("empty.json");
+        std::fs::write(&empty, "").unwrap();
+        assert!(matches!(
+            generate_from_files(&empty, &after),
+            Err(Error::Parse {
+                line: 1,
+                column: 0,
+                ..
+            })
+        ));
+
+        let missing = dir.join("missing.json");
+        assert!(matches!(
+            generate_from_files(&missing, &after),
+            Err(Error::Io {
+                kind: std::io::ErrorKind::NotFound,
+                ..
+            })
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_generate_ndjson() {
+        let ndjson = |before: &str, after: &str, unpaired| {
+            let mut output = Vec::new();
+            generate_ndjson(before.as_bytes(), after.as_bytes(), &mut output, unpaired)
+                .map(|changed| (changed, String::from_utf8(output).unwrap()))
+        };
+
+        let before = "{\"a\":1}\n\n{\"b\":1}\n{\"c\":1}\n";
+        let after = "{\"a\":1}\n{\"b\":2}\n";
+        assert_eq!(
+            ndjson(before, after, Unpaired::Skip).unwrap(),
+            (1, "null\n{\"b\":2}\n".to_string())
+        );
+        assert_eq!(
+            ndjson(before, after, Unpaired::Empty).unwrap(),
+            (2, "null\n{\"b\":2}\n{\"c\":null}\n".to_string())
+        );
+        assert_eq!(
+            ndjson(after, before, Unpaired::Empty).unwrap(),
+            (2, "null\n{\"b\":1}\n{\"c\":1}\n".to_string())
+        );
+        assert_eq!(
+            ndjson(before, after, Unpaired::Error)
+                .unwrap_err()
+                .to_string(),
+            "record at line 4 of before has no pair"
+        );
+        assert_eq!(
+            ndjson("{}\n{\"a\":}\n", "{}\n{}\n", Unpaired::Error)
+                .unwrap_err()
+                .to_string(),
+            "expected value at line 2 column 6 of before"
+        );
+        assert_eq!(ndjson("", "", Unpaired::Error).unwrap(), (0, String::new()));
+    }
+
+    #[test]
+    fn test_apply() {
+        // Examples from RFC 7386
+        let examples = [
+            (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+            (
+                json!({"a": "b"}),
+                json!({"b": "c"}),
+                json!({"a": "b", "b": "c"}),
+            ),
+            (json!({"a": "b"}), json!({"a": null}), json!({})),
+            (
+                json!({"a": "b", "b": "c"}),
+                json!({"a": null}),
+                json!({"b": "c"}),
+            ),
+            (json!({"a": ["b"]}), json!({"a": "c"}), json!({"a": "c"})),
+            (json!({"a": "c"}), json!({"a": ["b"]}), json!({"a": ["b"]})),
+            (
+                json!({"a": {"b": "c"}}),
+                json!({"a": {"b": "d", "c": null}}),
+                json!({"a": {"b": "d"}}),
+            ),
+            (
+                json!({"a": [{"b": "c"}]}),
+                json!({"a": [1]}),
+                json!({"a": [1]}),
+            ),
+            (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+            (json!({"a": "b"}), json!(["c"]), json!(["c"])),
+            (json!({"a": "foo"}), json!(null), json!(null)),
+            (json!({"a": "foo"}), json!("bar"), json!("bar")),
+            (
+                json!({"e": null}),
+                json!({"a": 1}),
+                json!({"e": null, "a": 1}),
+            ),
+            (
+                json!([1, 2]),
+                json!({"a": "b", "c": null}),
+                json!({"a": "b"}),
+            ),
+            (
+                json!({}),
+                json!({"a": {"bb": {"ccc": null}}}),
+                json!({"a": {"bb": {}}}),
+            ),
+        ];
+        for (mut document, patch, result) in examples {
+            apply(&mut document, &patch);
+            assert_eq!(document, result);
+        }
+
+        let before = json!({"a": {"b": 1, "c": [1]}, "d": "e"});
+        let after = json!({"a": {"b": 2, "f": {"g": [null]}}, "h": 1});
+        let mut document = before.clone();
+        apply(&mut document, &generate(&before, &after).unwrap());
+        assert_eq!(document, after);
+    }
+
+    #[test]
+    fn test_changes() {
+        let before = json!({"a": {"b": 1, "c": [1]}, "d": "e", "f/g": 1, "h": 1});
+        let patch =
+            json!({"a": {"b": 2, "c": null, "x": null}, "d": "e", "f/g": {"i": 1}, "j": [2]});
+        let list: Vec<_> = changes(&before, &patch)
+            .into_iter()
+            .map(|change| (change.kind(), change.pointer, change.before, change.after))
+            .collect();
+        assert_eq!(
+            list,
+            [
+                (
+                    ChangeKind::Modified,
+                    "/a/b".into(),
+                    Some(&json!(1)),
+                    Some(&json!(2))
+                ),
+                (ChangeKind::Removed, "/a/c".into(), Some(&json!([1])), None),
+                (
+                    ChangeKind::Modified,
+                    "/f~1g".into(),
+                    Some(&json!(1)),
+                    Some(&json!({"i": 1}))
+                ),
+                (ChangeKind::Added, "/j".into(), None, Some(&json!([2]))),
+            ]
+        );
+
+        assert!(changes(&json!(1), &json!(1)).is_empty());
+        assert_eq!(changes(&json!(1), &json!({}))[0].pointer, "");
+        assert_eq!(
+            changes(&json!({}), &json!(null))[0].kind(),
+            ChangeKind::Modified
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let base = json!({"a": {"b": 1, "c": 2}, "d": [1], "e": "x", "f": 1});
+        let ours = json!({"a": {"b": 2, "c": 2}, "d": [1, 2], "e": "x", "f": 1, "g": true});
+        let theirs = json!({"a": {"b": 1, "x": 3}, "d": [1, 2], "f": 2});
+        assert_eq!(
+            merge(&base, &ours, &theirs),
+            Ok(json!({"a": {"b": 2, "x": 3}, "d": [1, 2], "f": 2, "g": true}))
+        );
+        assert_eq!(merge(&base, &base, &ours), Ok(ours.clone()));
+        assert_eq!(merge(&base, &ours, &ours), Ok(ours.clone()));
+
+        let theirs = json!({"a": {"b": 3, "c": 2}, "d": {}, "e": "x", "f": 1, "g": false});
+        assert_eq!(
+            merge(&base, &ours, &theirs),
+            Err(Error::MergeConflict {
+                pointers: vec!["/a/b".into(), "/d".into(), "/g".into()]
+            })
+        );
+        assert_eq!(
+            merge(&json!(1), &json!(2), &json!(3)),
+            Err(Error::MergeConflict {
+                pointers: vec!["".into()]
+            })
+        );
+    }
+
+    #[test]
+    fn test_render_unified() {
+        let before = json!({"features": {"beta": true}, "settings": {"name": "x", "timeout": 30}});
+        let after =
+            json!({"features": {}, "settings": {"name": "x", "tags": ["a"], "timeout": 60}});
+        assert_eq!(
+            render::unified_diff(&before, &after),
+            "@@ /features/beta @@\n\
+             -true\n\
+             @@ /settings/tags @@\n\
+             +[\n\
+             +  \"a\"\n\
+             +]\n\
+             @@ /settings/timeout @@\n\
+             -30\n\
+             +60\n"
+        );
+        assert_eq!(
+            render::unified(&json!(1), &json!(2)),
+            "@@ (root) @@\n-1\n+2\n"
+        );
+        assert_eq!(render::unified_diff(&before, &before), "");
+    }
+
+    #[test]
+    #[cfg(feature = "html")]
+    fn test_render_html() {
+        let before = json!({"a": {"b": 1, "c": "<x>"}, "e": true});
+        let after = json!({"a": {"b": 2}, "d": [1]});
+        let html = render::html_diff(&before, &after);
+        let body = html.split("</style>").nth(1).unwrap();
+        assert_eq!(
+            body,
+            "<ul>\
+             <li class=\"jmp-modified\"><details open><summary>a</summary><ul>\
+             <li class=\"jmp-modified\">b: <del>1</del> <ins>2</ins></li>\
+             <li class=\"jmp-removed\">c: <del>&quot;&lt;x&gt;&quot;</del></li>\
+             </ul></details></li>\
+             <li class=\"jmp-added\">d: <ins>[1]</ins></li>\
+             <li class=\"jmp-removed\">e: <del>true</del></li>\
+             </ul></div>"
+        );
+        assert!(html.starts_with("<div class=\"jmp-diff\"><style>"));
+
+        let html = render::html(&json!(1), &json!(2));
+        assert!(html.ends_with(
+            "</style><ul><li class=\"jmp-modified\"><del>1</del> <ins>2</ins></li></ul></div>"
+        ));
+
+        let patch = json!({"a": {"b": 1, "c": null}, "d": {"e": null}});
+        let html = render::html(&json!({"a": 1}), &patch);
+        assert!(html.ends_with(
+            "<ul>\
+             <li class=\"jmp-modified\">a: <del>1</del> <ins>{&quot;b&quot;:1}</ins></li>\
+             <li class=\"jmp-added\">d: <ins>{}</ins></li>\
+             </ul></div>"
+        ));
+        let html = render::html(&json!(1), &json!({"b": null}));
+        assert!(html.ends_with("<del>1</del> <ins>{}</ins></li></ul></div>"));
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        let before = json!({"features": {"beta": true}, "settings": {"timeout": 30}});
+        let after = json!({"features": {"alpha": "x"}, "settings": {"timeout": 60}});
+        assert_eq!(
+            render::markdown_diff(&before, &after),
+            "- added `/features/alpha`: \"x\"\n\
+             - removed `/features/beta`\n\
+             - `/settings/timeout`: 30 → 60\n"
+        );
+        assert_eq!(
+            render::markdown(&json!({"a.b": 1}), &json!({"a.b": 2})),
+            "- `/a.b`: 1 → 2\n"
+        );
+        assert_eq!(
+            render::markdown(&json!({}), &json!({"`x``": 4})),
+            "- added ``` /`x`` ```: 4\n"
+        );
+        assert_eq!(
+            render::markdown(&json!(1), &json!(2)),
+            "- `(root)`: 1 → 2\n"
+        );
+        assert_eq!(render::markdown_diff(&before, &before), "");
+    }
+
+    #[test]
+    fn test_validate_patch() {
+        use std::collections::BTreeMap;
+
+        assert_eq!(validate_patch(&json!({"a": [1, {"b": null}]})), Ok(()));
+        assert_eq!(
+            validate_patch(&BTreeMap::from([("a", vec![BTreeMap::from([(1, 1.0)])])])),
+            Err(ValidationError::NonStringKey {
+                pointer: "/a/0".into()
+            })
+        );
+        assert_eq!(
+            validate_patch(&BTreeMap::from([("a/b", [0.0, f64::NAN])])),
+            Err(ValidationError::NonFiniteNumber {
+                pointer: "/a~1b/1".into()
+            })
+        );
+        assert_eq!(
+            validate_patch(&[("a", 1), ("a", 2)].into_iter().collect::<Pairs>()),
+            Err(ValidationError::DuplicateKey {
+                pointer: "".into(),
+                key: "a".into()
+            })
+        );
+
+        /** Serializes as an object with the keys of the pairs in order */
+        struct Pairs(Vec<(&'static str, i32)>);
+
+        impl FromIterator<(&'static str, i32)> for Pairs {
+            fn from_iter<I: IntoIterator<Item = (&'static str, i32)>>(iter: I) -> Self {
+                Pairs(iter.into_iter().collect())
+            }
+        }
+
+        impl serde::Serialize for Pairs {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_map(self.0.iter().copied())
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_applicability() {
+        let document = json!({"a": {"b": 1, "c": 2}, "d": [1]});
+        let patch = json!({"a": {"b": 1, "c": 3, "x": null}, "d": [1], "e": null, "f": 1});
+        assert_eq!(
+            validate_applicability(&document, &patch),
+            ["/a/b", "/a/x", "/d", "/e"]
+        );
+        assert_eq!(validate_applicability(&json!(1), &json!(1)), [""]);
+        assert!(validate_applicability(&json!(1), &json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_preview() {
+        let document = json!({"a": {"b": 1}, "c": [1], "d": "x"});
+        let patch = json!({"a": {"b": 2}, "c": null, "d": "x", "e": {"f": null}});
+        let (result, changes) = preview(&document, &patch);
+        assert_eq!(result, json!({"a": {"b": 2}, "d": "x", "e": {}}));
+        assert_eq!(changes.len(), 3);
+        assert_eq!(changes.of_kind(ChangeKind::Added).count(), 1);
+        assert_eq!(
+            serde_json::to_value(&changes).unwrap(),
+            json!([
+                {"pointer": "/a/b", "kind": "modified", "before": 1, "after": 2},
+                {"pointer": "/c", "kind": "removed", "before": [1]},
+                {"pointer": "/e", "kind": "added", "after": {"f": null}},
+            ])
+        );
+
+        let patch = json!({"d": "x"});
+        let (result, changes) = preview(&document, &patch);
+        assert_eq!(result, document);
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "jsonschema")]
+    fn test_apply_validated() {
+        let schema = jsonschema::validator_for(&json!({
+            "type": "object",
+            "properties": {"a": {"type": "integer"}, "b": {"type": "string"}},
+            "required": ["a"],
+        }))
+        .unwrap();
+
+        let mut document = json!({"a": 1, "b": "x"});
+        apply_validated(&mut document, &json!({"a": 2}), &schema).unwrap();
+        assert_eq!(document, json!({"a": 2, "b": "x"}));
+
+        let error = apply_validated(&mut document, &json!({"a": null, "b": 1}), &schema);
+        let Err(Error::SchemaViolation { violations }) = error else {
+            panic!("expected a schema violation, got {error:?}");
+        };
+        let pointers: Vec<_> = violations
+            .iter()
+            .map(|violation| &violation.pointer)
+            .collect();
+        assert_eq!(pointers, ["", "/b"]);
+        assert_eq!(document, json!({"a": 2, "b": "x"}));
+    }
+
+    #[test]
+    #[cfg(feature = "schemars")]
+    fn test_merge_patch_schema() {
+        #[derive(schemars::JsonSchema)]
+        #[allow(dead_code)]
+        struct Settings {
+            timeout: u32,
+            name: Option<String>,
+            inner: Inner,
+            items: Vec<Inner>,
+        }
+
+        #[derive(schemars::JsonSchema)]
+        #[allow(dead_code)]
+        struct Inner {
+            enabled: bool,
+        }
+
+        let schema = merge_patch_schema::<Settings>();
+        let schema = schema.as_value();
+        assert_eq!(schema["title"], "Settings");
+        assert_eq!(schema.get("required"), None);
+        assert_eq!(
+            schema["properties"],
+            json!({
+                "timeout": {"type": "integer", "format": "uint32", "minimum": 0},
+                "name": {"type": ["string", "null"]},
+                "inner": {"$ref": "#/$defs/InnerMergePatch"},
+                "items": {"type": "array", "items": {"$ref": "#/$defs/Inner"}},
+            })
+        );
+        assert_eq!(schema["$defs"]["Inner"]["required"], json!(["enabled"]),);
+        assert_eq!(
+            schema["$defs"]["InnerMergePatch"],
+            json!({"type": "object", "properties": {"enabled": {"type": "boolean"}}})
+        );
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    fn test_derive_json_merge_patch() {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, PartialEq, JsonMergePatch)]
+        struct Settings {
+            timeout: u32,
+            #[serde(rename = "displayName")]
+            name: Option<String>,
+            #[json_merge_patch(nested)]
+            inner: Inner,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize, JsonMergePatch)]
+        struct Inner {
+            enabled: bool,
+            tags: Vec<String>,
+        }
+
+        let mut settings = Settings {
+            timeout: 30,
+            name: Some("main".into()),
+            inner: Inner {
+                enabled: false,
+                tags: vec!["a".into()],
+            },
+        };
+        let patch = SettingsPatch {
+            name: Some(None),
+            inner: Some(InnerPatch {
+                enabled: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let json = json!({"displayName": null, "inner": {"enabled": true}});
+        assert_eq!(serde_json::Value::from(patch), json);
+
+        settings.apply_patch(SettingsPatch::try_from(json).unwrap());
+        assert_eq!(
+            settings,
+            Settings {
+                timeout: 30,
+                name: None,
+                inner: Inner {
+                    enabled: true,
+                    tags: vec!["a".into()],
+                },
+            }
+        );
+
+        let patch = SettingsPatch::try_from(json!({"timeout": 60, "displayName": "x"})).unwrap();
+        assert_eq!(patch.timeout, Some(60));
+        assert_eq!(patch.name, Some(Some("x".into())));
+        assert!(SettingsPatch::try_from(json!({"timeout": null})).is_err());
+        assert!(SettingsPatch::try_from(json!([])).is_err());
+    }
+
+    #[test]
+    fn test_typed_merge_patch() {
+        use serde::{Deserialize, Serialize};
+        use typed::MergePatch;
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct User {
+            name: String,
+            email: Option<String>,
+            age: u32,
+        }
+
+        let mut user = User {
+            name: "ann".into(),
+            email: Some("ann@example.com".into()),
+            age: 30,
+        };
+        let patch: MergePatch<User> =
+            serde_json::from_value(json!({"email": null, "age": 31})).unwrap();
+        assert!(patch.removes("/email"));
+        assert!(patch.contains("/age") && !patch.removes("/age"));
+        assert!(!patch.contains("/name"));
+
+        patch.apply(&mut user).unwrap();
+        assert_eq!(
+            user,
+            User {
+                name: "ann".into(),
+                email: None,
+                age: 31,
+            }
+        );
+
+        // Patches that don't result in a `User` leave it as it is
+        let invalid = MergePatch::<User>::new(json!({"name": null}));
+        assert!(invalid.apply(&mut user).is_err());
+        assert_eq!(user.name, "ann");
+
+        let after = User {
+            name: "bob".into(),
+            ..user
+        };
+        let before = User {
+            name: "ann".into(),
+            email: None,
+            age: 31,
+        };
+        let diff = MergePatch::diff(&before, &after).unwrap();
+        assert_eq!(diff.as_value(), &json!({"name": "bob"}));
+        assert_eq!(
+            MergePatch::diff(&before, &before).unwrap(),
+            MergePatch::default()
+        );
+    }
+
+    #[cfg(feature = "axum")]
+    #[test]
+    fn test_axum_extractor() {
+        use crate::axum::{JsonMergePatch, JsonMergePatchRejection};
+        use ::axum::body::Body;
+        use ::axum::extract::FromRequest;
+        use ::axum::http::{Request, StatusCode};
+        use core::future::Future;
+        use core::pin::pin;
+        use core::task::{Context, Poll, Waker};
+
+        fn extract(
+            content_type: &str,
+            body: &str,
+        ) -> Result<JsonMergePatch<Value>, JsonMergePatchRejection> {
+            let request = Request::builder()
+                .header("content-type", content_type)
+                .body(Body::from(body.to_owned()))
+                .unwrap();
+            // The body is in memory, so the extractor completes without waiting
+            let mut future = pin!(JsonMergePatch::from_request(request, &()));
+            match future
+                .as_mut()
+                .poll(&mut Context::from_waker(Waker::noop()))
+            {
+                Poll::Ready(result) => result,
+                Poll::Pending => panic!("the extractor should be ready"),
+            }
+        }
+
+        let patch = extract(
+            "application/merge-patch+json; charset=utf-8",
+            r#"{"a":null}"#,
+        );
+        let patch = patch.unwrap();
+        assert_eq!(patch.value(), &json!({"a": null}));
+        let mut document = json!({"a": 1, "b": 2});
+        patch.apply_to(&mut document).unwrap();
+        assert_eq!(document, json!({"b": 2}));
+
+        let rejection = extract("application/json", "{}").unwrap_err();
+        assert_eq!(rejection.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let rejection = extract("application/merge-patch+json", "{").unwrap_err();
+        assert_eq!(rejection.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[cfg(feature = "actix-web")]
+    #[test]
+    fn test_actix_web_extractor() {
+        use crate::actix_web::{JsonMergePatch, JsonMergePatchConfig, JsonMergePatchError};
+        use ::actix_web::http::StatusCode;
+        use ::actix_web::test::TestRequest;
+        use ::actix_web::{FromRequest, ResponseError};
+        use core::task::{Context, Poll, Waker};
+
+        fn extract(request: TestRequest) -> Result<JsonMergePatch<Value>, JsonMergePatchError> {
+            let (request, mut payload) = request.to_http_parts();
+            // The body is in memory, so the extractor completes without waiting
+            let mut future = JsonMergePatch::from_request(&request, &mut payload);
+            match future
+                .as_mut()
+                .poll(&mut Context::from_waker(Waker::noop()))
+            {
+                Poll::Ready(result) => result,
+                Poll::Pending => panic!("the extractor should be ready"),
+            }
+        }
+        let request = |content_type: &str, body: &'static str| {
+            TestRequest::default()
+                .insert_header(("content-type", content_type))
+                .set_payload(body)
+        };
+
+        let patch = extract(request("application/merge-patch+json", r#"{"a":null}"#)).unwrap();
+        let mut document = json!({"a": 1, "b": 2});
+        patch.apply_to(&mut document).unwrap();
+        assert_eq!(document, json!({"b": 2}));
+
+        let error = extract(request("application/json", "{}")).unwrap_err();
+        assert_eq!(error.status_code(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let error = extract(request("application/merge-patch+json", "{")).unwrap_err();
+        assert_eq!(error.status_code(), StatusCode::BAD_REQUEST);
+        let limited = request("application/merge-patch+json", r#"{"a":1}"#)
+            .app_data(JsonMergePatchConfig::default().limit(4));
+        let error = extract(limited).unwrap_err();
+        assert_eq!(error.status_code(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn test_reqwest_patch_and_refresh() {
+        use std::io::{BufRead, BufReader, Read, Write};
+        use std::net::TcpListener;
+
+        // A server that answers one request with the patched document
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/settings", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = Vec::new();
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some((name, value)) = line.trim_end().split_once(": ") {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.parse().unwrap();
+                    }
+                }
+                if line == "\r\n" {
+                    break;
+                }
+                head.push(line.trim_end().to_ascii_lowercase());
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            let response = r#"{"a":2,"b":1,"version":2}"#;
+            write!(
+                reader.get_mut(),
+                "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{response}",
+                response.len()
+            )
+            .unwrap();
+            (head, body)
+        });
+
+        let runtime = ::tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let client = ::reqwest::Client::new();
+        let mut before = json!({"a": 1, "b": 1});
+        let after = json!({"a": 2, "b": 1});
+
+        let sent = runtime.block_on(reqwest::patch_and_refresh(
+            &client,
+            &url,
+            &mut before.clone(),
+            &before.clone(),
+        ));
+        assert!(!sent.unwrap());
+
+        let sent = runtime.block_on(reqwest::patch_and_refresh(
+            &client,
+            &url,
+            &mut before,
+            &after,
+        ));
+        assert!(sent.unwrap());
+        assert_eq!(before, json!({"a": 2, "b": 1, "version": 2}));
+
+        let (head, body) = server.join().unwrap();
+        assert!(head[0].starts_with("patch /settings "));
+        assert!(head.contains(&"content-type: application/merge-patch+json".to_owned()));
+        assert_eq!(
+            serde_json::from_slice::<Value>(&body).unwrap(),
+            json!({"a": 2})
+        );
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_http_encode_decode() {
+        use ::http::{Request, Response, StatusCode};
+
+        let patch = json!({"a": null, "b": {"c": 1}});
+        let request = http::encode_request(Request::patch("/settings"), &patch).unwrap();
+        assert_eq!(
+            request.headers()["content-type"],
+            "application/merge-patch+json"
+        );
+        assert_eq!(http::decode_request(&request).unwrap(), patch);
+
+        let response = http::encode_response(Response::builder(), &patch).unwrap();
+        assert_eq!(http::decode_response(&response).unwrap(), patch);
+
+        let request = Request::patch("/settings")
+            .header("content-type", "application/json")
+            .body("{}")
+            .unwrap();
+        let error = http::decode_request(&request).unwrap_err();
+        assert_eq!(error.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let request = Request::patch("/settings")
+            .header(
+                "content-type",
+                "Application/Merge-Patch+JSON; charset=utf-8",
+            )
+            .body("{")
+            .unwrap();
+        let error = http::decode_request(&request).unwrap_err();
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_etag_apply_if_match() {
+        use etag::{apply_if_match, TaggedPatch};
+
+        let before = json!({"a": 1, "b": 1});
+        let tag = etag::etag(&before);
+        assert_eq!(tag, etag::etag(&before.clone()));
+        assert_ne!(tag, etag::etag(&json!({"a": 2, "b": 1})));
+
+        let after = json!({"a": 2, "b": 1});
+        let tagged = TaggedPatch::generate(&before, &after, tag.clone()).unwrap();
+        assert_eq!(tagged.patch, json!({"a": 2}));
+        assert!(TaggedPatch::generate(&before, &before, tag.clone()).is_none());
+
+        let mut document = before.clone();
+        let if_match = format!("\"other\", {}", tagged.if_match());
+        apply_if_match(&mut document, &tag, &if_match, &tagged.patch).unwrap();
+        assert_eq!(document, after);
+
+        // The document has changed since, so the patch isn't applied again
+        let current = etag::etag(&document);
+        assert_eq!(
+            apply_if_match(&mut document, &current, &tag, &json!({"a": 3})),
+            Err(Error::PreconditionFailed { etag: current })
+        );
+        assert_eq!(document, after);
+
+        assert!(etag::matches("*", "W/\"1\""));
+        assert!(!etag::matches("W/\"1\"", "W/\"1\""));
+    }
+
+    #[test]
+    fn test_strategic_merge_patch() {
+        use strategic::StrategicOptions;
+
+        let before = json!({
+            "metadata": {"labels": {"app": "web"}},
+            "spec": {"template": {"spec": {"containers": [
+                {"name": "web", "image": "web:1", "env": [{"name": "A", "value": "1"}]},
+                {"name": "sidecar", "image": "proxy:1"},
+                {"name": "old", "image": "old:1"},
+            ]}}}
+        });
+        let after = json!({
+            "metadata": {"labels": {"app": "web", "tier": "frontend"}},
+            "spec": {"template": {"spec": {"containers": [
+                {"name": "web", "image": "web:2", "env": [
+                    {"name": "A", "value": "1"},
+                    {"name": "B", "value": "2"},
+                ]},
+                {"name": "sidecar", "image": "proxy:1"},
+                {"name": "new", "image": "new:1"},
+            ]}}}
+        });
+        let options = StrategicOptions::kubernetes();
+        assert_eq!(
+            strategic::generate(&before, &after, &options),
+            Some(json!({
+                "metadata": {"labels": {"tier": "frontend"}},
+                "spec": {"template": {"spec": {"containers": [
+                    {"name": "web", "image": "web:2", "env": [{"name": "B", "value": "2"}]},
+                    {"name": "new", "image": "new:1"},
+                    {"name": "old", "$patch": "delete"},
+                ]}}}
+            }))
+        );
+        assert_eq!(strategic::generate(&before, &before, &options), None);
+
+        // Reordering only needs the order directive
+        let before = json!({"spec": {"ports": [{"port": 80}, {"port": 443}]}});
+        let after = json!({"spec": {"ports": [{"port": 443}, {"port": 80}]}});
+        assert_eq!(
+            strategic::generate(&before, &after, &options),
+            Some(json!({"spec": {"$setElementOrder/ports": [{"port": 443}, {"port": 80}]}}))
+        );
+
+        // Lists without merge keys are replaced, explicitly so if they have
+        // a merge strategy
+        let options = StrategicOptions::new().merge_key("/items", "id");
+        assert_eq!(
+            strategic::generate(
+                &json!({"items": [{"id": 1}], "tags": ["a"]}),
+                &json!({"items": [{"id": 1}, {"value": 2}], "tags": ["b"]}),
+                &options
+            ),
+            Some(json!({"items": [{"$patch": "replace"}, {"id": 1}, {"value": 2}], "tags": ["b"]}))
+        );
+    }
+
+    #[test]
+    fn test_ignore_removals() {
+        let options = Options::new().ignore_removals(true);
+        let before = json!({"a": 1, "b": {"c": 1, "d": 1}, "list": [{"x": 1, "y": 1}]});
+        let after = json!({"b": {"c": 2}, "list": [{"x": 1}]});
+        assert_eq!(
+            generate_with(&before, &after, &options),
+            Some(json!({"b": {"c": 2}}))
+        );
+        let after = json!({"list": [{"x": 2}]});
+        assert_eq!(
+            generate_with(&before, &after, &options),
+            Some(json!({"list": [{"x": 2}]}))
+        );
+        assert_eq!(
+            generate_with(&json!({"list": [1, 2]}), &json!({"list": [1]}), &options),
+            Some(json!({"list": [1]}))
+        );
+    }
+
+    #[test]
+    fn test_kubernetes_live_object() {
+        use strategic::StrategicOptions;
+
+        let live = json!({
+            "metadata": {
+                "name": "web",
+                "resourceVersion": "42",
+                "managedFields": [{"manager": "kubectl"}],
+            },
+            "spec": {
+                "replicas": 1,
+                "template": {"spec": {"containers": [
+                    {"name": "web", "image": "web:1", "imagePullPolicy": "IfNotPresent"},
+                    {"name": "injected", "image": "proxy:1"},
+                ]}},
+            },
+            "status": {"readyReplicas": 1},
+        });
+        let desired = json!({
+            "metadata": {"name": "web"},
+            "spec": {
+                "replicas": 1,
+                "template": {"spec": {"containers": [{"name": "web", "image": "web:1"}]}},
+            },
+        });
+        let options = StrategicOptions::kubernetes().options(Options::kubernetes());
+        assert_eq!(strategic::generate(&live, &desired, &options), None);
+
+        let desired = json!({"spec": {"replicas": 2}, "status": {}});
+        assert_eq!(
+            strategic::generate(&live, &desired, &options),
+            Some(json!({"spec": {"replicas": 2}}))
+        );
+    }
+
+    #[test]
+    fn test_document() {
+        use std::sync::{Arc, Mutex};
+
+        let mut document = Document::new(json!({"a": {"b": 1}, "list": [1]}));
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let subscription = document.subscribe({
+            let received = received.clone();
+            move |patch, value| {
+                received
+                    .lock()
+                    .unwrap()
+                    .push((patch.clone(), value.clone()))
+            }
+        });
+
+        assert_eq!(
+            document.set("/a/c/d", json!(2)).unwrap(),
+            Some(json!({"a": {"c": {"d": 2}}}))
+        );
+        assert_eq!(document.set("/a/b", json!(1)).unwrap(), None);
+        assert_eq!(
+            document.set("/list/0", json!(2)),
+            Err(Error::PointerThroughArray {
+                pointer: "/list/0".into()
+            })
+        );
+        assert_eq!(
+            document.remove("/a/b").unwrap(),
+            Some(json!({"a": {"b": null}}))
+        );
+        assert_eq!(document.remove("/missing").unwrap(), None);
+        assert_eq!(
+            document.apply(&json!({"a": {"c": {"d": 2}, "e": 3}})),
+            Some(json!({"a": {"e": 3}}))
+        );
+        assert_eq!(
+            document.replace(json!({"list": [1]})),
+            Some(json!({"a": null}))
+        );
+        assert_eq!(document.replace(json!({"list": [1]})), None);
+
+        assert!(document.unsubscribe(subscription));
+        assert!(!document.unsubscribe(subscription));
+        document.set("/x", json!(true)).unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 4);
+        assert_eq!(
+            received[0].1,
+            json!({"a": {"b": 1, "c": {"d": 2}}, "list": [1]})
+        );
+        assert_eq!(received[3], (json!({"a": null}), json!({"list": [1]})));
+        assert_eq!(document.value(), &json!({"list": [1], "x": true}));
+    }
+
+    #[test]
+    fn test_document_undo_redo() {
+        let mut document = Document::with_history(json!({"a": {"b": 1}, "c": [1]}), 2);
+        assert!(!document.can_undo());
+
+        document.set("/a/b", json!({"x": 1})).unwrap();
+        document.remove("/c").unwrap();
+        document.set("/d", json!("new")).unwrap();
+        assert_eq!(document.value(), &json!({"a": {"b": {"x": 1}}, "d": "new"}));
+
+        assert_eq!(document.undo(), Some(json!({"d": null})));
+        assert_eq!(document.undo(), Some(json!({"c": [1]})));
+        // Only the last 2 changes are kept
+        assert_eq!(document.undo(), None);
+        assert_eq!(document.value(), &json!({"a": {"b": {"x": 1}}, "c": [1]}));
+
+        assert!(document.can_redo());
+        assert_eq!(document.redo(), Some(json!({"c": null})));
+        document.replace(json!({"a": 1}));
+        assert!(!document.can_redo());
+        assert_eq!(document.undo(), Some(json!({"a": {"b": {"x": 1}}})));
+        assert_eq!(document.value(), &json!({"a": {"b": {"x": 1}}}));
+
+        let mut document = Document::new(json!({}));
+        document.set("/a", json!(1)).unwrap();
+        assert_eq!(document.undo(), None);
+    }
+
+    #[test]
+    fn test_patch_log() {
+        let mut log = PatchLog::new(json!({"a": {"b": 1}}));
+        assert_eq!(log.push(json!({"a": null})), 1);
+        assert_eq!(log.push(json!({"a": {"c": 2}})), 2);
+        log.push(json!({"d": true}));
+
+        assert_eq!(log.replay(1), Some(json!({})));
+        assert_eq!(log.latest(), json!({"a": {"c": 2}, "d": true}));
+        assert_eq!(log.replay(4), None);
+        assert_eq!(log.squash(0, 2), Some(json!({"a": {"b": null, "c": 2}})));
+        assert_eq!(log.squash(2, 2), None);
+
+        log.compact(2);
+        assert_eq!(log.snapshot(), &json!({"a": {"c": 2}}));
+        assert_eq!(log.snapshot_version(), 2);
+        assert_eq!(log.patches(), &[json!({"d": true})]);
+        assert_eq!(log.replay(1), None);
+        assert_eq!(log.latest(), json!({"a": {"c": 2}, "d": true}));
+
+        log.compact(10);
+        assert_eq!(log.version(), 3);
+        assert_eq!(log, PatchLog::from_parts(log.latest(), 3, Vec::new()));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_tokio_watch_patches() {
+        use ::tokio::sync::watch;
+        use futures_core::Stream;
+        use std::pin::Pin;
+        use std::task::{Context, Poll, Waker};
+
+        let (sender, receiver) = watch::channel(json!({"a": 1}));
+        let mut patches = tokio::watch_patches(receiver);
+        let mut next = || Pin::new(&mut patches).poll_next(&mut Context::from_waker(Waker::noop()));
+
+        assert_eq!(next(), Poll::Pending);
+        sender.send(json!({"a": 1})).unwrap();
+        assert_eq!(next(), Poll::Pending);
+        sender.send(json!({"a": 2, "b": true})).unwrap();
+        assert_eq!(next(), Poll::Ready(Some(json!({"a": 2, "b": true}))));
+        sender.send(json!({"b": true})).unwrap();
+        drop(sender);
+        assert_eq!(next(), Poll::Ready(Some(json!({"a": null}))));
+        assert_eq!(next(), Poll::Ready(None));
+        assert_eq!(patches.state(), Some(&json!({"b": true})));
+    }
+
+    #[test]
+    fn test_sync() {
+        use sync::{Message, ResyncNeeded, SyncClient, SyncServer, VersionedPatch};
+
+        let mut server = SyncServer::new(json!({"a": 1}));
+        let mut client = SyncClient::new();
+        let mut late = SyncClient::new();
+        assert_eq!(client.state(), None);
+
+        client.receive(server.snapshot()).unwrap();
+        let first = server.apply(&json!({"b": {"c": true}})).unwrap();
+        assert_eq!(server.update(json!({"a": 1, "b": {"c": true}})), None);
+        let second = server.update(json!({"b": {"c": false}})).unwrap();
+        assert_eq!(
+            second,
+            Message::Patch(VersionedPatch {
+                base_version: 1,
+                new_version: 2,
+                patch: json!({"a": null, "b": {"c": false}})
+            })
+        );
+
+        client.receive(first.clone()).unwrap();
+        client.receive(first.clone()).unwrap();
+        client.receive(second.clone()).unwrap();
+        assert_eq!(client.version(), Some(2));
+        assert_eq!(client.state(), Some(server.state()));
+
+        // A client that missed the first patch
+        late.receive(Message::Snapshot {
+            version: 0,
+            state: json!({"a": 1}),
+        })
+        .unwrap();
+        assert_eq!(
+            late.receive(second.clone()),
+            Err(ResyncNeeded {
+                version: Some(0),
+                base_version: 1
+            })
+        );
+        assert_eq!(late.state(), None);
+        assert!(late.receive(second).is_err());
+        late.receive(server.respond(&Message::Resync).unwrap())
+            .unwrap();
+        assert_eq!(late.state(), Some(server.state()));
+
+        let message: Message =
+            serde_json::from_str(&serde_json::to_string(&first).unwrap()).unwrap();
+        assert_eq!(message, first);
+        assert_eq!(
+            serde_json::to_value(Message::Resync).unwrap(),
+            json!({"type": "resync"})
+        );
+        assert!(serde_json::from_value::<Message>(json!({"type": "patch", "patch": {}})).is_err());
+    }
+
+    #[test]
+    fn test_versioned_patch() {
+        use sync::{Replica, ResyncNeeded, VersionedPatch};
+
+        let patch = |base_version, new_version, patch| VersionedPatch {
+            base_version,
+            new_version,
+            patch,
+        };
+        assert_eq!(
+            VersionedPatch::generate(&json!({"a": 1}), &json!({"a": 1}), 3),
+            None
+        );
+        assert_eq!(
+            VersionedPatch::generate(&json!({"a": 1}), &json!({"a": 2}), 3),
+            Some(patch(3, 4, json!({"a": 2})))
+        );
+
+        let mut replica = Replica::new(json!({"a": 1}), 3);
+        assert_eq!(replica.apply(&patch(3, 4, json!({"a": 2}))), Ok(true));
+        assert_eq!(replica.apply(&patch(3, 4, json!({"a": 2}))), Ok(false));
+        assert_eq!(replica.apply(&patch(2, 3, json!({"a": 0}))), Ok(false));
+        assert_eq!(
+            replica.apply(&patch(5, 6, json!({"a": 6}))),
+            Err(ResyncNeeded {
+                version: Some(4),
+                base_version: 5
+            })
+        );
+        // A squashed patch spanning several versions
+        assert_eq!(replica.apply(&patch(4, 7, json!({"b": true}))), Ok(true));
+        assert_eq!(replica.version(), 7);
+        assert_eq!(replica.state(), &json!({"a": 2, "b": true}));
+
+        let encoded = serde_json::to_value(patch(7, 8, json!({"b": null}))).unwrap();
+        assert_eq!(
+            encoded,
+            json!({"base_version": 7, "new_version": 8, "patch": {"b": null}})
+        );
+        assert_eq!(
+            serde_json::from_value::<VersionedPatch>(encoded).unwrap(),
+            patch(7, 8, json!({"b": null}))
+        );
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor() {
+        let before = cbor::to_vec(&json!({"a": 1, "b": [1.5, "x"], "c": {"d": null}}));
+        let after = cbor::to_vec(&json!({"a": 1, "b": [1.5, "y"], "c": {}}));
+        let patch = cbor::generate(&before, &after).unwrap().unwrap();
+        assert_eq!(
+            cbor::from_slice(&patch).unwrap(),
+            json!({"b": [1.5, "y"], "c": {"d": null}})
+        );
+        assert_eq!(cbor::generate(&before, &before).unwrap(), None);
+
+        // A map with an integer key, which JSON can't represent
+        assert!(cbor::generate(&[0xa1, 0x01, 0x02], &after).is_err());
+        assert!(cbor::from_slice(&[0x41, 0x00]).is_err());
+        assert!(cbor::from_slice(&[0xff]).is_err());
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_msgpack() {
+        let before = json!({"a": 1, "b": {"c": [true, 2.5]}});
+        let after = json!({"b": {"c": [true, 2.5], "d": "x"}});
+        let patch = msgpack::to_vec(&generate(&before, &after).unwrap());
+        assert_eq!(
+            msgpack::from_slice(&patch).unwrap(),
+            json!({"a": null, "b": {"d": "x"}})
+        );
+
+        let mut document = before.clone();
+        msgpack::apply(&mut document, &patch).unwrap();
+        assert_eq!(document, after);
+
+        // Binary data, which JSON can't represent
+        assert!(msgpack::apply(&mut document, &[0xc4, 0x01, 0x00]).is_err());
+        assert_eq!(document, after);
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    fn test_generate_yaml() {
+        let before = "name: web\nreplicas: 2\nports:\n  - 80\nselector:\n  tier: frontend\n";
+        let after = "name: web\nreplicas: 3\nports:\n  - 80\n";
+        let patch = generate_yaml(before, after).unwrap().unwrap();
+        assert_eq!(patch, json!({"replicas": 3, "selector": null}));
+        assert_eq!(to_yaml(&patch), "replicas: 3\nselector: null\n");
+        assert_eq!(generate_yaml(before, before).unwrap(), None);
+
+        let error = generate_yaml(before, "name: [web\n").unwrap_err();
+        assert!(matches!(
+            error,
+            Error::Yaml {
+                document: "after",
+                location: Some(_),
+                ..
+            }
+        ));
+        assert!(error.to_string().starts_with("after:"));
+        // A sequence as a mapping key, which JSON can't represent
+        assert!(matches!(
+            generate_yaml("? [a, b]\n: c\n", after),
+            Err(Error::Yaml {
+                document: "before",
+                ..
+            })
+        ));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_generate_toml() {
+        let before = "title = \"app\"\n\n[server]\nport = 80\nstarted = 1979-05-27T07:32:00Z\n";
+        let after = "title = \"app\"\n\n[server]\nport = 8080\nstarted = 1979-05-28T07:32:00Z\n";
+        let patch = generate_toml(before, after).unwrap().unwrap();
+        assert_eq!(
+            patch,
+            json!({"server": {"port": 8080, "started": "1979-05-28T07:32:00Z"}})
+        );
+        assert_eq!(
+            to_toml(&patch).unwrap(),
+            "[server]\nport = 8080\nstarted = \"1979-05-28T07:32:00Z\"\n"
+        );
+
+        let removal = generate_toml(before, "[server]\nport = 80\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            to_toml(&removal),
+            Err(Error::TomlRemoval {
+                pointer: "/server/started".into()
+            })
+        );
+
+        assert_eq!(
+            generate_toml(before, "title = \"app\"\nport = \n").unwrap_err(),
+            Error::Toml {
+                document: "after",
+                location: Some((2, 8)),
+                message: "string values must be quoted, expected literal string".into()
+            }
+        );
+        assert!(matches!(
+            generate_toml("x = nan\n", after),
+            Err(Error::Toml {
+                document: "before",
+                ..
+            })
+        ));
+    }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    fn test_generate_json5() {
+        let before = r#"{
+            // Compiler options
+            "compilerOptions": {
+                "strict": true,
+                "target": 'es2020',
+            },
+            include: ["src",],
+        }"#;
+        let after = r#"{"compilerOptions": {"target": "es2022"}, "include": ["src"]}"#;
+        assert_eq!(
+            generate_json5(before, after).unwrap(),
+            Some(json!({"compilerOptions": {"strict": null, "target": "es2022"}}))
+        );
+        assert_eq!(parse_json5("[0x10, .5, +1]").unwrap(), json!([16, 0.5, 1]));
+
+        assert!(matches!(
+            generate_json5(before, "{\"a\": }"),
+            Err(Error::Json5 {
+                document: "after",
+                location: Some((1, 7)),
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse_json5("{\"a\": Infinity}"),
+            Err(Error::Json5 {
+                document: "document",
+                ..
+            })
+        ));
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn test_ffi() {
+        use std::ffi::{c_char, CStr};
+        use std::ptr;
+
+        use ffi::{json_merge_patch_apply, json_merge_patch_free, json_merge_patch_generate};
+
+        unsafe fn take(string: *mut c_char) -> String {
+            let owned = CStr::from_ptr(string).to_str().unwrap().to_owned();
+            json_merge_patch_free(string);
+            owned
+        }
+
+        unsafe {
+            let mut error = ptr::null_mut();
+            let patch = json_merge_patch_generate(
+                c"{\"a\": 1, \"c\": 2}".as_ptr(),
+                c"{\"a\": 1, \"b\": 3}".as_ptr(),
+                &mut error,
+            );
+            assert!(error.is_null());
+            assert_eq!(take(patch), r#"{"b":3,"c":null}"#);
+
+            let patched = json_merge_patch_apply(
+                c"{\"a\": 1}".as_ptr(),
+                c"{\"a\": null, \"b\": [true]}".as_ptr(),
+                ptr::null_mut(),
+            );
+            assert_eq!(take(patched), r#"{"b":[true]}"#);
+
+            let equal = json_merge_patch_generate(c"{}".as_ptr(), c"{}".as_ptr(), &mut error);
+            assert_eq!(take(equal), "{}");
+
+            let failed = json_merge_patch_generate(c"{".as_ptr(), ptr::null(), &mut error);
+            assert!(failed.is_null());
+            assert!(take(error).starts_with("before: EOF"));
+            json_merge_patch_apply(c"{}".as_ptr(), ptr::null(), &mut error);
+            assert_eq!(take(error), "patch is NULL");
+            json_merge_patch_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_generate_interleaved_keys() {
+        let before = json!({ "a": 1, "c": 2, "d": 3, "f": { "x": 1 }, "h": 4 });
+        let after = json!({ "b": 1, "c": 2, "e": 3, "f": { "x": 2 }, "g": 4 });
+        let patch = generate(&before, &after).unwrap();
+        assert_eq!(
+            patch,
+            json!({ "a": null, "b": 1, "d": null, "e": 3, "f": { "x": 2 }, "g": 4, "h": null })
+        );
+        let options = Options::default().ignore_removals(true);
+        assert_eq!(
+            generate_with(&before, &after, &options),
+            Some(json!({ "b": 1, "e": 3, "f": { "x": 2 }, "g": 4 }))
+        );
+    }
+
+    #[test]
+    fn test_generate_identical() {
+        let document = json!({ "a": [1, 2], "b": { "c": null } });
+        assert_eq!(generate(&document, &document), None);
+        assert_eq!(generate(&document["a"], &document["a"]), None);
+
+        let after = json!({ "a": [1, 2], "b": { "c": 1 } });
+        let etag = etag::etag(&document);
+        assert_eq!(generate_hashed(&document, &etag, &after, &etag), None);
+        assert_eq!(
+            generate_hashed(&document, &etag, &after, &etag::etag(&after)),
+            Some(json!({ "b": { "c": 1 } }))
+        );
+        assert_eq!(
+            generate_hashed(&document, &1, &after, &2),
+            generate(&document, &after)
+        );
+    }
+
+    #[test]
+    fn test_empty_patch() {
+        let before = json!({ "a": 1, "b": { "c": [1] } });
+        let options = Options::new().empty_patch(EmptyPatchBehavior::EmptyObject);
+        assert_eq!(
+            generate_with(&before, &before.clone(), &options),
+            Some(json!({}))
+        );
+        assert_eq!(
+            generate_with(&before, &json!({ "a": 2, "b": { "c": [1] } }), &options),
+            Some(json!({ "a": 2 }))
+        );
+        assert_eq!(
+            generate_ref_with(&before, &before, &options).map(PatchRef::into_owned),
+            Some(json!({}))
+        );
+        assert_eq!(
+            generate_owned_with(before.clone(), before.clone(), &options),
+            Some(json!({}))
+        );
+        assert_eq!(
+            generate_at_with(&before, &before, "/b", &options),
+            Ok(Some(json!({})))
+        );
+        assert_eq!(
+            generate_hashed_with(&before, "1", &before, "1", &options),
+            Some(json!({}))
+        );
+
+        // Writers still report that the documents are equal
+        let mut patch = Vec::new();
+        let stream = br#"{"a": 1}"#.as_slice();
+        assert!(!generate_stream_with(stream, stream, &mut patch, &options).unwrap());
+        assert_eq!(patch, b"{}");
+        let mut patch = Vec::new();
+        let records = &b"{\"a\":1}\n{\"a\":1}\n"[..];
+        assert_eq!(
+            generate_ndjson_with(records, records, &mut patch, Unpaired::Error, &options).unwrap(),
+            0
+        );
+        assert_eq!(patch, b"{}\n{}\n");
+
+        let options = options.empty_patch(EmptyPatchBehavior::None);
+        assert_eq!(generate_with(&before, &before.clone(), &options), None);
+    }
+
+    #[test]
+    fn test_generate_checked() {
+        let before = json!({ "a": 1, "b": null, "c": [1], "d": 1, "e": 1 });
+        let after = json!({
+            "a": null,
+            "b": null,
+            "c": [null, { "x": null }],
+            "f": { "g": null, "h": { "i": null, "j": 1 } },
+            "e": 1,
+            "k~/": null
+        });
+        let (patch, lossy) = generate_checked(&before, &after);
+        assert_eq!(
+            patch,
+            Some(json!({
+                "a": null,
+                "c": [null, { "x": null }],
+                "d": null,
+                "f": { "g": null, "h": { "i": null, "j": 1 } },
+                "k~/": null
+            }))
+        );
+        assert_eq!(lossy, ["/a", "/f/g", "/f/h/i", "/k~0~1"]);
+
+        let (patch, lossy) = generate_checked(&json!({ "a": 1 }), &json!({ "a": 2, "b": null }));
+        assert_eq!(patch, Some(json!({ "a": 2, "b": null })));
+        assert_eq!(lossy, ["/b"]);
+        assert_eq!(generate_checked(&before, &before), (None, Vec::new()));
+        assert_eq!(
+            generate_checked(&json!(1), &json!(null)),
+            (Some(json!(null)), Vec::new())
+        );
+    }
+
+    #[test]
+    fn test_generate_strict() {
+        let before = json!({ "a": 1, "b": { "c": 1 } });
+        let after = json!({ "b": { "c": null, "d": [null] }, "e": null });
+        let error = generate_strict(&before, &after).unwrap_err();
+        assert_eq!(
+            error,
+            Error::NotRepresentable {
+                pointers: vec!["/b/c".to_string(), "/e".to_string()]
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            r#"a merge patch can't set the null members at ["/b/c", "/e"]"#
+        );
+
+        let after = json!({ "b": { "d": [null] } });
+        let patch = generate_strict(&before, &after).unwrap().unwrap();
+        let mut document = before.clone();
+        apply(&mut document, &patch);
+        assert_eq!(document, after);
+        assert_eq!(generate_strict(&before, &before), Ok(None));
+    }
+
+    #[cfg(feature = "testutil")]
+    #[test]
+    fn test_testutil() {
+        let message = |assertion: fn()| {
+            let error = std::panic::catch_unwind(assertion).unwrap_err();
+            error.downcast::<String>().unwrap()
+        };
+
+        let before = json!({ "a": 1, "b": { "c": 1 } });
+        let after = json!({ "a": 2, "b": { "c": 1 } });
+        assert_merge_patch!(before, after, json!({ "a": 2 }));
+        assert_merge_patch!(before, before, None);
+        assert_roundtrip!(before, after);
+
+        assert_eq!(
+            *message(|| assert_merge_patch!(
+                json!({ "a": 1 }),
+                json!({ "a": 2 }),
+                json!({ "a": 3 })
+            )),
+            "unexpected merge patch\nexpected:\n{\n  \"a\": 3\n}\nactual:\n{\n  \"a\": 2\n}\n\
+             changes from expected to actual:\n@@ /a @@\n-3\n+2\n"
+        );
+        assert_eq!(
+            *message(|| assert_merge_patch!(json!(1), json!(1), None)),
+            "unexpected merge patch\nexpected: none\nactual:\n1"
+        );
+        assert_eq!(
+            *message(|| assert_roundtrip!(json!({ "a": 1 }), json!({ "a": null }))),
+            "applying the merge patch doesn't yield after\npatch:\n{\n  \"a\": null\n}\n\
+             after:\n{\n  \"a\": null\n}\nresult:\n{}\n\
+             null members that merge patches can't set: [\"/a\"]\n"
+        );
     }
 
-    if before.is_array() {
-        if before != after {
-            return Some(after.clone());
+    #[cfg(feature = "proptest")]
+    ::proptest::proptest! {
+        #[test]
+        fn test_proptest_roundtrip((before, after) in crate::proptest::json_pair()) {
+            crate::proptest::check_roundtrip(&before, &after)?;
         }
-        return None;
     }
 
-    let mut patch = serde_json::json!({});
+    #[test]
+    fn test_apply_untrusted() {
+        let mut document = json!({ "a": 1 });
+        apply_untrusted(&mut document, br#"{"a": null, "b": [[1]]}"#, 3).unwrap();
+        assert_eq!(document, json!({ "b": [[1]] }));
+
+        let error = apply_untrusted(&mut document, br#"{"b": [[[1]]]}"#, 3).unwrap_err();
+        assert_eq!(error, Error::DepthLimitExceeded { limit: 3 });
+        assert_eq!(error.status_code(), 413);
+        let deep = [&[b'['; 200][..], &[b']'; 200][..]].concat();
+        assert_eq!(
+            parse_patch(&deep, 1000),
+            Err(Error::DepthLimitExceeded { limit: 128 })
+        );
 
-    // The .unwrap() calls are safe because we previously checked that the keys are objects
-    let before = before.as_object().unwrap();
-    let after = after.as_object().unwrap();
+        let error = apply_untrusted(&mut document, b"{\"b\":\n}", 10).unwrap_err();
+        assert_eq!(
+            error,
+            Error::InvalidPatch {
+                location: Some((2, 1)),
+                message: "expected value".to_string()
+            }
+        );
+        assert_eq!(error.to_string(), "invalid patch at 2:1: expected value");
+        assert_eq!(error.status_code(), 400);
+        assert_eq!(document, json!({ "b": [[1]] }));
 
-    // New elements
-    for (key, value) in after.iter() {
-        if !before.contains_key(key) {
-            patch[key] = value.clone();
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Settings {
+            timeout: u32,
         }
+        let mut settings = Settings { timeout: 30 };
+        apply_untrusted_to(&mut settings, br#"{"timeout": 60}"#, 10).unwrap();
+        assert_eq!(settings, Settings { timeout: 60 });
+        let error = apply_untrusted_to(&mut settings, br#"{"timeout": "x"}"#, 10).unwrap_err();
+        assert_eq!(
+            error,
+            Error::TypeMismatch {
+                message: "invalid type: string \"x\", expected u32".to_string()
+            }
+        );
+        assert_eq!(error.status_code(), 422);
+        assert_eq!(settings, Settings { timeout: 60 });
+    }
+
+    #[test]
+    fn test_apply_all() {
+        let patches = [r#"{"a": 1}"#, r#"{"b": 2}"#, "{", r#"{"a": null}"#];
+
+        let mut document = json!({});
+        let error = apply_all(&mut document, patches, 10, InvalidPatches::Stop).unwrap_err();
+        assert_eq!(error.index, 2);
+        assert_eq!(
+            error.to_string(),
+            "patch 2: invalid patch at 1:1: EOF while parsing an object"
+        );
+        assert_eq!(document, json!({ "a": 1, "b": 2 }));
+
+        let mut document = json!({});
+        let skipped = apply_all(&mut document, patches, 10, InvalidPatches::Skip).unwrap();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].index, 2);
+        assert_eq!(document, json!({ "b": 2 }));
+
+        let mut document = json!({ "a": 1 });
+        let patches = vec![b"[1]".to_vec(), b"{}".to_vec()];
+        assert_eq!(
+            apply_all(&mut document, &patches, 0, InvalidPatches::Skip)
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(document, json!({ "a": 1 }));
+    }
+
+    #[test]
+    fn test_squash() {
+        let patches = [
+            json!({ "a": 1, "b": { "c": 1, "d": 1 }, "e": [1] }),
+            json!({ "a": null, "b": { "c": null, "f": { "g": 1 } }, "h": {} }),
+            json!({ "e": { "i": 1 }, "b": { "d": 2, "f": { "g": null } } }),
+        ];
+        // The last one merges an object into a member that the first sets
+        assert_eq!(squash(&patches), None);
+
+        let patches = &patches[..2];
+        let squashed = squash(patches).unwrap();
+        assert_eq!(
+            squashed,
+            json!({ "a": null, "b": { "c": null, "d": 1, "f": { "g": 1 } }, "e": [1], "h": {} })
+        );
+        let document = json!({ "a": 0, "b": { "c": 0, "x": 0 }, "y": 0 });
+        let mut expected = document.clone();
+        apply_all(
+            &mut expected,
+            patches.iter().map(|patch| patch.to_string()),
+            10,
+            InvalidPatches::Stop,
+        )
+        .unwrap();
+        let mut result = document.clone();
+        apply(&mut result, &squashed);
+        assert_eq!(result, expected);
+
+        assert_eq!(squash(&[json!({ "e": null }), json!({ "e": {} })]), None);
+        assert_eq!(squash(&[json!([1]), json!({ "a": 1 })]), None);
+        assert_eq!(
+            squash(&[json!({ "a": {} }), json!([1]), json!(null)]),
+            Some(json!(null))
+        );
+        assert_eq!(squash(&[]), None);
     }
 
-    // Removed & modified elements
-    for (key, before_value) in before.iter() {
-        match after.get(key) {
-            None => {
-                patch[key] = serde_json::Value::Null;
+    #[test]
+    fn test_diff_patches() {
+        let a = json!({ "a": 1, "b": { "c": null, "d": [1] }, "e": {}, "f": 1 });
+        let b = json!({ "a": 1.0, "b": { "d": [1], "g": null }, "f": { "h": 1 }, "i/": 2 });
+        let pointers: Vec<_> = diff_patches(&a, &b)
+            .into_iter()
+            .map(|difference| (difference.pointer, difference.a, difference.b))
+            .collect();
+        assert_eq!(
+            pointers,
+            [
+                ("/a".to_string(), Some(&a["a"]), Some(&b["a"])),
+                ("/b/c".to_string(), Some(&Value::Null), None),
+                ("/b/g".to_string(), None, Some(&Value::Null)),
+                ("/e".to_string(), Some(&a["e"]), None),
+                ("/f".to_string(), Some(&a["f"]), Some(&b["f"])),
+                ("/i~1".to_string(), None, Some(&b["i/"])),
+            ]
+        );
+        assert_eq!(diff_patches(&a, &a.clone()), []);
+        assert_eq!(
+            diff_patches(&json!(null), &json!({})),
+            [PatchDifference {
+                pointer: String::new(),
+                a: Some(&Value::Null),
+                b: Some(&json!({}))
+            }]
+        );
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let a = json!({ "beta": null, "editor": { "font": 12, "tabs": 4 }, "theme": "dark" });
+        let b =
+            json!({ "beta": { "a": 1 }, "editor": { "font": 14, "wrap": true }, "theme": "dark" });
+        assert_eq!(
+            conflicts(&a, &b),
+            [
+                ConflictPath {
+                    pointer: "/beta".to_string(),
+                    a: &Value::Null,
+                    b: &b["beta"]
+                },
+                ConflictPath {
+                    pointer: "/editor/font".to_string(),
+                    a: &a["editor"]["font"],
+                    b: &b["editor"]["font"]
+                },
+            ]
+        );
+        assert_eq!(conflicts(&a, &json!({ "editor": { "wrap": true } })), []);
+        assert_eq!(conflicts(&json!([1]), &json!({})).len(), 1);
+    }
+
+    #[test]
+    fn test_rebase() {
+        let old_base = json!({
+            "title": "a",
+            "tags": ["x"],
+            "body": { "text": "b", "draft": true },
+            "meta": { "views": 1 },
+            "legacy": 1
+        });
+        let new_base = json!({
+            "title": "c",
+            "tags": ["x"],
+            "body": { "text": "b", "draft": false },
+            "author": "d"
+        });
+        let patch = json!({
+            "title": "e",
+            "tags": ["x", "y"],
+            "body": { "draft": false, "words": 2 },
+            "meta": { "views": 2 },
+            "legacy": null
+        });
+        let rebased = rebase(&patch, &old_base, &new_base);
+        assert_eq!(
+            rebased,
+            Rebased {
+                patch: Some(json!({ "title": "e", "tags": ["x", "y"], "body": { "words": 2 } })),
+                conflicts: vec!["/title".to_string()],
+                dropped: vec!["/meta/views".to_string()],
             }
-            Some(after_value) => {
-                if before_value.is_object() {
-                    let sub_patch = generate(before_value, after_value);
-                    if let Some(sub_patch) = sub_patch {
-                        patch[key] = sub_patch;
-                    }
-                } else if before_value != after_value {
-                    patch[key] = after_value.clone();
-                }
+        );
+
+        assert_eq!(
+            rebase(&patch, &old_base, &old_base).patch,
+            Some(patch.clone())
+        );
+        let mut after = old_base.clone();
+        apply(&mut after, &patch);
+        assert_eq!(rebase(&patch, &old_base, &after).patch, None);
+        assert_eq!(
+            rebase(&json!([1]), &old_base, &new_base),
+            Rebased {
+                patch: Some(json!([1])),
+                conflicts: vec![String::new()],
+                dropped: Vec::new(),
             }
-        }
+        );
     }
 
-    if patch.as_object().unwrap().is_empty() {
-        None
-    } else {
-        Some(patch)
+    #[test]
+    fn test_apply_partial() {
+        let mut document = json!({ "a": "x", "b": { "c": 1, "d": [1] }, "e": null, "f~": 1 });
+        let patch = json!({
+            "a": { "g": 1 },
+            "b": { "c": null, "d": { "h": 1 }, "i": { "j": 2 } },
+            "e": {},
+            "f~": 2,
+            "k": { "l": null }
+        });
+        let skipped = apply_partial(&mut document, &patch);
+        assert_eq!(skipped, ["/a", "/e", "/b/d"]);
+        assert_eq!(
+            document,
+            json!({ "a": "x", "b": { "d": [1], "i": { "j": 2 } }, "e": null, "f~": 2, "k": {} })
+        );
+
+        let mut document = json!([1]);
+        assert_eq!(apply_partial(&mut document, &json!({ "a": 1 })), [""]);
+        assert_eq!(document, json!([1]));
+        assert!(apply_partial(&mut document, &json!(2)).is_empty());
+        assert_eq!(document, json!(2));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde_json::json;
+    #[test]
+    fn test_canonical() {
+        let before = json!({ "a": 1, "b": { "c": 1 } });
+        let after = json!({ "a": 2, "b": { "c": 2, "d": { "f": [{ "h": 1, "g": 1 }], "e": 1 } } });
+        let options = Options::new().canonical(true);
+        let expected = r#"{"a":2,"b":{"c":2,"d":{"e":1,"f":[{"g":1,"h":1}]}}}"#;
+
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(patch.to_string(), expected);
+
+        #[cfg(feature = "std")]
+        {
+            let before = r#"{ "a": 1, "b": { "c": 1 } }"#;
+            let after =
+                r#"{ "a": 2, "b": { "c": 2, "d": { "f": [ { "h": 1, "g": 1 } ], "e": 1 } } }"#;
+            let mut patch = Vec::new();
+            assert!(generate_stream(before.as_bytes(), after.as_bytes(), &mut patch).unwrap());
+            assert_ne!(String::from_utf8(patch).unwrap(), expected);
+            let mut patch = Vec::new();
+            generate_stream_with(before.as_bytes(), after.as_bytes(), &mut patch, &options)
+                .unwrap();
+            assert_eq!(String::from_utf8(patch).unwrap(), expected);
+
+            #[cfg(feature = "raw_value")]
+            {
+                use serde_json::value::RawValue;
+
+                let before = RawValue::from_string(before.to_owned()).unwrap();
+                let after = RawValue::from_string(after.to_owned()).unwrap();
+                let patch = generate_raw_with(&before, &after, &options)
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(patch.get(), expected);
+            }
+        }
+    }
 
     #[test]
-    fn test_replace_attr() {
-        let before = json!({ "a": "b" });
-        let after = json!({ "a": "c" });
+    fn test_member_order() {
+        let before = json!({ "b": 1, "z": 1, "a": { "y": 1, "x": 1 }, "c": 1 });
+        let after = json!({ "d": 1, "c": 2, "b": 1, "a": { "x": 2, "w": 1, "y": 2 } });
         let patch = generate(&before, &after).unwrap();
-        assert_eq!(patch, json!({ "a": "c" }));
+        let expected = if cfg!(feature = "preserve_order") {
+            r#"{"d":1,"c":2,"a":{"x":2,"w":1,"y":2},"z":null}"#
+        } else {
+            r#"{"a":{"w":1,"x":2,"y":2},"c":2,"d":1,"z":null}"#
+        };
+        assert_eq!(patch.to_string(), expected);
+        assert_eq!(
+            generate_owned(before.clone(), after.clone())
+                .unwrap()
+                .to_string(),
+            expected
+        );
+        assert_eq!(
+            generate_with(&before, &after, &Options::new().canonical(true))
+                .unwrap()
+                .to_string(),
+            r#"{"a":{"w":1,"x":2,"y":2},"c":2,"d":1,"z":null}"#
+        );
     }
 
     #[test]
-    fn test_add_attr() {
-        let before = json!({ "a": "b" });
-        let after = json!({ "a": "b", "b": "c" });
-        let patch = generate(&before, &after).unwrap();
-        assert_eq!(patch, json!({ "b": "c" }));
+    fn test_generate_update() {
+        let before = json!({ "a": 1, "b": 2, "c": 3 });
+        let after = json!({ "d": 4 });
+        assert_eq!(
+            generate_update(&before, &after),
+            Some(Update::Replace(&after))
+        );
+        let after = json!({ "a": 1, "b": 2, "c": 4 });
+        assert_eq!(
+            generate_update(&before, &after),
+            Some(Update::Patch(json!({ "c": 4 })))
+        );
+        assert_eq!(generate_update(&before, &before), None);
+
+        // {"c":4} is 7 bytes, and the ratio is only checked if it's set
+        let options = Options::new().max_patch_size(6);
+        assert_eq!(
+            generate_update_with(&before, &after, &options),
+            Some(Update::Replace(&after))
+        );
+        let options = Options::new().max_patch_size(7);
+        assert_eq!(
+            generate_update_with(&before, &after, &options),
+            Some(Update::Patch(json!({ "c": 4 })))
+        );
+        let options = options.max_patch_ratio(0.2);
+        assert_eq!(
+            generate_update_with(&before, &after, &options),
+            Some(Update::Replace(&after))
+        );
+        let after = json!({ "d": 4 });
+        assert_eq!(
+            generate_update_with(&before, &after, &Options::new()),
+            Some(Update::Patch(
+                json!({ "a": null, "b": null, "c": null, "d": 4 })
+            ))
+        );
     }
 
     #[test]
-    fn test_del_attr() {
-        let before = json!({ "a": "b" });
-        let after = json!({});
-        let patch = generate(&before, &after).unwrap();
-        assert_eq!(patch, json!({ "a": null }));
+    fn test_replace_changed_objects() {
+        let before = json!({
+            "a": { "b": 1, "c": 1, "d": { "e": 1 }, "x": 1 },
+            "f": { "g": 1, "h": 1 },
+            "i": 1, "j": 1, "k": 1
+        });
+        let after = json!({
+            "a": { "b": 2, "c": 2, "d": {}, "x": 1 },
+            "f": { "g": 2, "h": 1 },
+            "i": 1, "j": 1, "k": 1
+        });
+        let a = json!({ "b": 2, "c": 2, "d": { "e": null }, "x": 1 });
+        let options = Options::new().replace_changed_objects(0.5);
+        assert_eq!(
+            generate_with(&before, &after, &options),
+            Some(json!({ "a": a, "f": { "g": 2 } }))
+        );
+        let options = Options::new().replace_changed_objects(0.4);
+        let patch = generate_with(&before, &after, &options).unwrap();
+        assert_eq!(patch, json!({ "a": a, "f": { "g": 2, "h": 1 } }));
+        let mut result = before.clone();
+        apply(&mut result, &patch);
+        assert_eq!(result, after);
+        assert_eq!(generate_with(&before, &before, &options), None);
+
+        // Excluded members stay out of the patch
+        assert_eq!(
+            generate_with(&before, &after, &options.ignore_path("/f/h")),
+            Some(json!({ "a": a, "f": { "g": 2 } }))
+        );
+        let options = Options::new()
+            .include_path("/a/b")
+            .include_path("/f")
+            .replace_changed_objects(0.0);
+        assert_eq!(
+            generate_with(&before, &after, &options),
+            Some(json!({ "a": { "b": 2 }, "f": { "g": 2, "h": 1 } }))
+        );
     }
 
     #[test]
-    fn test_del_attr_no_affect_others() {
-        let before = json!({ "a": "b", "b": "c" });
-        let after = json!({ "b": "c" });
-        let patch = generate(&before, &after).unwrap();
-        assert_eq!(patch, json!({ "a": null }));
+    fn test_estimate_patch_size() {
+        let before = json!({ "a": { "b": 1, "c\"\n\u{1}": [1, 2] }, "d": "x", "e": 1.5 });
+        let after = json!({ "a": { "b": 2, "f": { "g": "\u{e9}" } }, "d": "x", "h": null });
+        for (before, after) in [
+            (&before, &after),
+            (&after, &before),
+            (&before, &json!([1])),
+            (&json!({ "a": [1] }), &json!({ "a": [2] })),
+        ] {
+            let patch = generate(before, after).unwrap();
+            assert_eq!(estimate_patch_size(before, after), patch.to_string().len());
+        }
+        assert_eq!(estimate_patch_size(&before, &before), 0);
+        let options = Options::new().empty_patch(EmptyPatchBehavior::EmptyObject);
+        assert_eq!(estimate_patch_size_with(&before, &before, &options), 2);
+        let options = Options::new().ignore_path("/a/f/g");
+        assert_eq!(
+            estimate_patch_size_with(&before, &after, &options),
+            generate_with(&before, &after, &options)
+                .unwrap()
+                .to_string()
+                .len()
+        );
     }
 
     #[test]
-    fn test_replace_array_with_attr() {
-        let before = json!({ "a": ["b"] });
-        let after = json!({ "a": "c" });
-        let patch = generate(&before, &after).unwrap();
-        assert_eq!(patch, json!({ "a": "c" }));
+    fn test_has_changes() {
+        let before = json!({ "a": { "b": [1, { "c": 1 }], "d": 1 }, "e": "x" });
+        let cases = [
+            json!({ "a": { "b": [1, { "c": 1 }], "d": 1 }, "e": "x" }),
+            json!({ "a": { "b": [1, { "c": 2 }], "d": 1 }, "e": "x" }),
+            json!({ "a": { "b": [1, { "c": 1 }] }, "e": "x" }),
+            json!({ "a": { "b": [1, { "c": 1 }], "d": 1, "f": {} }, "e": "x" }),
+            json!({ "a": { "b": [1, { "c": 1 }], "d": 1 }, "e": "y" }),
+            json!([1]),
+            json!(1),
+        ];
+        let options = [
+            Options::new(),
+            Options::new().ignore_removals(true),
+            Options::new().ignore_path("/a/b"),
+            Options::new().include_path("/a/d").include_path("/e"),
+            Options::new().max_depth(1),
+            Options::new().replace_changed_objects(0.0),
+        ];
+        for after in &cases {
+            for options in &options {
+                assert_eq!(
+                    has_changes_with(&before, after, options),
+                    generate_with(&before, after, options).is_some(),
+                    "{after} with {options:?}"
+                );
+            }
+        }
+        assert!(!has_changes(&before, &before));
+        assert!(has_changes(&json!(1), &json!(1)));
+        assert!(!has_changes(&json!([1]), &json!([1])));
     }
 
     #[test]
-    fn test_replace_attr_with_array() {
-        let before = json!({ "a": "c" });
-        let after = json!({ "a": ["b"] });
-        let patch = generate(&before, &after).unwrap();
-        assert_eq!(patch, json!({ "a": ["b"] }));
+    fn test_generate_visited() {
+        #[derive(Default)]
+        struct Log {
+            changes: Vec<String>,
+        }
+
+        impl Visitor for Log {
+            fn on_added(&mut self, pointer: &str, after: &Value) -> bool {
+                self.changes.push(format!("+{pointer} {after}"));
+                true
+            }
+
+            fn on_removed(&mut self, pointer: &str, before: &Value) -> bool {
+                self.changes.push(format!("-{pointer} {before}"));
+                !pointer.starts_with("/keep")
+            }
+
+            fn on_modified(&mut self, pointer: &str, before: &Value, after: &Value) -> bool {
+                self.changes.push(format!("{pointer} {before} {after}"));
+                pointer != "/a/b"
+            }
+        }
+
+        let before = json!({ "a": { "b": 1, "c": [1], "d": 1 }, "keep": 1, "e~": { "f": 1 } });
+        let after = json!({ "a": { "b": 2, "c": [2], "d": 1, "g": {} }, "e~": "x" });
+        let mut log = Log::default();
+        assert_eq!(
+            generate_visited(&before, &after, &mut log),
+            Some(json!({ "a": { "c": [2], "g": {} }, "e~": "x" }))
+        );
+        assert_eq!(
+            log.changes,
+            [
+                "/a/b 1 2",
+                "/a/c [1] [2]",
+                "+/a/g {}",
+                "/e~0 {\"f\":1} \"x\"",
+                "-/keep 1"
+            ]
+        );
+
+        // Objects emitted as a whole are visited as one change
+        let mut log = Log::default();
+        let options = Options::new().max_depth(1);
+        assert_eq!(
+            generate_visited_with(&before, &after, &options, &mut log),
+            Some(json!({ "a": { "b": 2, "c": [2], "d": 1, "g": {} }, "e~": "x" }))
+        );
+        assert_eq!(
+            log.changes[0],
+            r#"/a {"b":1,"c":[1],"d":1} {"b":2,"c":[2],"d":1,"g":{}}"#
+        );
+        // Vetoed members stay out of objects replaced as a whole
+        let options = Options::new().replace_changed_objects(0.0);
+        assert_eq!(
+            generate_visited_with(&before, &after, &options, Log::default()),
+            Some(json!({ "a": { "c": [2], "g": {} }, "e~": "x" }))
+        );
+
+        let mut log = Log::default();
+        assert_eq!(
+            generate_visited(&json!(1), &json!(1), &mut log),
+            Some(json!(1))
+        );
+        assert_eq!(log.changes, [" 1 1"]);
     }
 
     #[test]
-    fn test_replace_obj_array_with_num_array() {
-        let before = json!({ "a": [{ "b": "c" }] });
-        let after = json!({ "a": [1] });
-        let patch = generate(&before, &after).unwrap();
-        assert_eq!(patch, json!({ "a": [1] }));
+    fn test_generate_redacted() {
+        let before = json!({ "user": "a", "credentials": { "password": "x" }, "token": "t" });
+        let after = json!({
+            "user": "b",
+            "credentials": { "password": "y", "keys": { "api": "k" } },
+            "token": null,
+            "audit": { "note": "n" }
+        });
+        let mut visited = Vec::new();
+        let patch = generate_redacted(&before, &after, |pointer, value| {
+            visited.push(pointer.to_owned());
+            match pointer {
+                "/credentials/password" | "/credentials/keys/api" => {
+                    Redaction::Replace(json!("***"))
+                }
+                "/audit" => Redaction::Drop,
+                _ if value.is_string() && value == "b" => Redaction::Replace(json!("?")),
+                _ => Redaction::Keep,
+            }
+        });
+        assert_eq!(
+            patch,
+            Some(json!({
+                "user": "?",
+                "credentials": { "password": "***", "keys": { "api": "***" } },
+                "token": null
+            }))
+        );
+        visited.sort();
+        assert_eq!(
+            visited,
+            [
+                "",
+                "/audit",
+                "/credentials",
+                "/credentials/keys",
+                "/credentials/keys/api",
+                "/credentials/password",
+                "/user"
+            ]
+        );
+
+        assert_eq!(
+            generate_redacted(&before, &after, |_, _| Redaction::Drop),
+            None
+        );
+        assert_eq!(
+            generate_redacted(&json!(1), &json!(2), |_, _| Redaction::Replace(json!(0))),
+            Some(json!(0))
+        );
+        assert_eq!(
+            generate_redacted(&before, &before, |_, _| unreachable!()),
+            None
+        );
     }
 
     #[test]
-    fn test_replace_arr_if_changed() {
-        let before = json!(["a", "b"]);
-        let after = json!(["c", "d"]);
-        let patch = generate(&before, &after).unwrap();
-        assert_eq!(patch, json!(["c", "d"]));
+    fn test_generate_normalized() {
+        fn normalize(pointer: &str, value: &mut Value) {
+            match value {
+                Value::String(string) if pointer.ends_with("/port") => {
+                    if let Ok(port) = string.trim().parse::<u16>() {
+                        *value = json!(port);
+                    }
+                }
+                Value::String(string) => *string = string.trim().to_lowercase(),
+                Value::Object(members) => {
+                    members.retain(|key, value| !(key == "retries" && *value == json!(3)))
+                }
+                _ => {}
+            }
+        }
+
+        let before = json!({ "server": { "host": "Example.com ", "port": "80" }, "modes": ["A"] });
+        let after = json!({
+            "server": { "host": "example.com", "port": 80, "retries": 3 },
+            "modes": [" a"]
+        });
+        assert_eq!(generate_normalized(&before, &after, normalize), None);
+
+        let after =
+            json!({ "server": { "host": " B", "port": 443, "retries": 5 }, "modes": ["a"] });
+        let mut pointers = Vec::new();
+        let patch = generate_normalized(&before, &after, |pointer, value| {
+            pointers.push(pointer.to_owned());
+            normalize(pointer, value);
+        });
+        assert_eq!(
+            patch,
+            Some(json!({ "server": { "host": "b", "port": 443, "retries": 5 } }))
+        );
+        pointers.sort();
+        pointers.dedup();
+        assert_eq!(
+            pointers,
+            [
+                "",
+                "/modes",
+                "/modes/0",
+                "/server",
+                "/server/host",
+                "/server/port",
+                "/server/retries"
+            ]
+        );
     }
 
     #[test]
-    fn test_replace_arr_if_elem_deleted() {
-        let before = json!(["a", "b"]);
-        let after = json!(["a"]);
-        let patch = generate(&before, &after).unwrap();
-        assert_eq!(patch, json!(["a"]));
+    fn test_ignore_keys() {
+        let before = json!({
+            "name": "a",
+            "created_at": 1,
+            "items": [{ "id": 1, "updatedAt": 1 }],
+            "meta": { "revision": 1, "lastSeen": 1, "at": 1, "x_at_y": 1 }
+        });
+        let after = json!({
+            "name": "a",
+            "created_at": 2,
+            "items": [{ "id": 1, "updatedAt": 2 }],
+            "meta": { "revision": 2, "lastSeen": 2, "at": 1, "x_at_y": 1 }
+        });
+        let options = Options::new().ignore_volatile_keys();
+        assert_eq!(generate_with(&before, &after, &options), None);
+
+        let mut after = after;
+        after["meta"]["at"] = json!(2);
+        after["meta"]["x_at_y"] = json!(2);
+        after["meta"]["lastSeen"] = Value::Null;
+        assert_eq!(
+            generate_with(&before, &after, &options),
+            Some(json!({ "meta": { "at": 2, "x_at_y": 2 } }))
+        );
+
+        let options = Options::new().ignore_key("x*a*_y").ignore_key("*Seen");
+        assert_eq!(
+            generate_with(&before, &after, &options),
+            Some(json!({
+                "created_at": 2,
+                "items": [{ "id": 1, "updatedAt": 2 }],
+                "meta": { "revision": 2, "at": 2 }
+            }))
+        );
+        for (glob, key, matches) in [
+            ("*", "", true),
+            ("a*", "a", true),
+            ("*a*a", "aa", true),
+            ("*a*a", "a", false),
+            ("a*b*c", "abbc", true),
+            ("a*b*c", "acb", false),
+            ("ab", "a", false),
+        ] {
+            let options = Options::new().ignore_key(glob);
+            let patch = generate_with(&json!({ key: 1 }), &json!({ key: 2 }), &options);
+            assert_eq!(patch.is_none(), matches, "{glob} {key}");
+        }
     }
 
     #[test]
-    fn test_replace_obj_with_array() {
-        let before = json!({ "a": "b" });
-        let after = json!(["c"]);
-        let patch = generate(&before, &after).unwrap();
-        assert_eq!(patch, json!(["c"]));
+    fn test_generate_map() {
+        use std::collections::{BTreeMap, HashMap};
+
+        #[derive(Debug, PartialEq, serde::Serialize)]
+        struct Device {
+            on: bool,
+            level: Option<u8>,
+            tags: Vec<u8>,
+        }
+
+        let device = |on, level| Device {
+            on,
+            level,
+            tags: vec![1],
+        };
+        let before = BTreeMap::from([
+            ("a".to_owned(), device(true, Some(1))),
+            ("b".to_owned(), device(true, Some(1))),
+            ("c".to_owned(), device(false, None)),
+        ]);
+        let after = BTreeMap::from([
+            ("a".to_owned(), device(true, Some(1))),
+            ("b".to_owned(), device(false, None)),
+            ("d".to_owned(), device(true, Some(2))),
+        ]);
+        let expected = generate(
+            &serde_json::to_value(&before).unwrap(),
+            &serde_json::to_value(&after).unwrap(),
+        );
+        assert_eq!(
+            expected,
+            Some(json!({
+                "b": { "on": false, "level": null },
+                "c": null,
+                "d": { "on": true, "level": 2, "tags": [1] }
+            }))
+        );
+        assert_eq!(generate_map(&before, &after).unwrap(), expected);
+        let (before, after): (HashMap<_, _>, HashMap<_, _>) =
+            (before.into_iter().collect(), after.into_iter().collect());
+        assert_eq!(generate_hash_map(&before, &after).unwrap(), expected);
+        assert_eq!(generate_hash_map(&before, &before).unwrap(), None);
+
+        for options in [
+            Options::new().ignore_path("/b/on").ignore_removals(true),
+            Options::new().include_path("/c/on").include_path("/d"),
+            Options::new()
+                .ignore_path("")
+                .empty_patch(EmptyPatchBehavior::EmptyObject),
+            Options::new().max_depth(0),
+            Options::new().max_depth(1),
+        ] {
+            assert_eq!(
+                generate_hash_map_with(&before, &after, &options).unwrap(),
+                generate_with(
+                    &serde_json::to_value(&before).unwrap(),
+                    &serde_json::to_value(&after).unwrap(),
+                    &options
+                ),
+                "{options:?}"
+            );
+        }
     }
 
     #[test]
-    fn test_replace_with_null() {
-        let before = json!({ "a": "b" });
-        let after = json!(null);
-        let patch = generate(&before, &after).unwrap();
-        assert_eq!(patch, json!(null));
+    fn test_generate_str() {
+        assert_eq!(
+            generate_str(r#"{"a": 1, "b": [1]}"#, r#"{"a": [2]}"#),
+            Ok(Some(r#"{"a":[2],"b":null}"#.to_owned()))
+        );
+        assert_eq!(generate_str("[1, 2]", " [1,2]\n"), Ok(None));
+
+        let error = generate_str("{}", "{\n  \"a\": 1,\n  \"b\" 2\n}").unwrap_err();
+        assert_eq!(
+            error,
+            Error::InvalidJson {
+                document: "after",
+                line: 3,
+                column: 7,
+                offset: 18,
+                message: "expected `:`".to_owned(),
+            }
+        );
+        assert_eq!(error.to_string(), "after:3:7 (byte 18): expected `:`");
+        assert_eq!(error.status_code(), 400);
+        assert!(matches!(
+            generate_str("{\"a\":", "{}"),
+            Err(Error::InvalidJson {
+                document: "before",
+                line: 1,
+                column: 5,
+                offset: 4,
+                ..
+            })
+        ));
     }
 
     #[test]
-    fn test_replace_with_string() {
-        let before = json!({ "a": "foo" });
-        let after = json!("bar");
-        let patch = generate(&before, &after).unwrap();
-        assert_eq!(patch, json!("bar"));
+    fn test_apply_stream() {
+        let mut document = Vec::new();
+        apply_stream(
+            r#"{ "a": { "b": 1, "c": 2 } }"#.as_bytes(),
+            r#"{ "a": { "c": null }, "d": [1] }"#.as_bytes(),
+            &mut document,
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&document).unwrap(),
+            json!({ "a": { "b": 1 }, "d": [1] })
+        );
+
+        assert!(apply_stream("{}".as_bytes(), "{ \"a\": ".as_bytes(), Vec::new()).is_err());
+        assert!(apply_stream("{} 1".as_bytes(), "{}".as_bytes(), Vec::new()).is_err());
+
+        // The patch from readers applies to the document read from them
+        let (before, after) = (r#"{ "a": 1, "b": [2] }"#, r#"{ "b": [3], "c": 4 }"#);
+        let mut patch = Vec::new();
+        assert!(generate_from_readers(before.as_bytes(), after.as_bytes(), &mut patch).unwrap());
+        let mut document = Vec::new();
+        apply_stream(before.as_bytes(), patch.as_slice(), &mut document).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&document).unwrap(),
+            serde_json::from_str::<serde_json::Value>(after).unwrap()
+        );
+        assert!(!generate_from_readers(after.as_bytes(), after.as_bytes(), Vec::new()).unwrap());
     }
 
     #[test]
-    fn test_keep_null_attrs() {
-        let before = json!({ "e": null });
-        let after = json!({ "e": null, "a": 1 });
-        let patch = generate(&before, &after).unwrap();
-        assert_eq!(patch, json!({ "a": 1 }));
+    fn test_diff_engine() {
+        let mut engine = DiffEngine::new(json!({ "a": 1, "b": { "c": 1 } }));
+        assert_eq!(
+            engine.push(json!({ "a": 1, "b": { "c": 2 } })),
+            Some(json!({ "b": { "c": 2 } }))
+        );
+        assert_eq!(engine.push(json!({ "a": 1, "b": { "c": 2 } })), None);
+        assert_eq!(
+            engine.push(json!({ "b": { "c": 2 } })),
+            Some(json!({ "a": null }))
+        );
+        assert_eq!(engine.state(), &json!({ "b": { "c": 2 } }));
+
+        engine.reset(json!({ "b": 1 }));
+        assert_eq!(engine.push(json!({ "b": 2 })), Some(json!({ "b": 2 })));
+        assert_eq!(engine.into_state(), json!({ "b": 2 }));
+
+        let mut engine =
+            DiffEngine::with_options(json!({ "a": 1 }), Options::new().ignore_path("/updated"));
+        assert_eq!(engine.push(json!({ "a": 1, "updated": 2 })), None);
     }
 
     #[test]
-    fn test_recursive() {
-        let before = json!({});
-        let after = json!({ "a": { "bb": {} } });
-        let patch = generate(&before, &after).unwrap();
-        assert_eq!(patch, json!({ "a": { "bb": {} } }));
+    fn test_generate_batch() {
+        use std::collections::BTreeMap;
+
+        let befores = [json!({ "a": 1 }), json!({ "a": 1 }), json!([1])];
+        let afters = [json!({ "a": 2 }), json!({ "a": 1 }), json!([2])];
+        assert_eq!(
+            generate_batch(&befores, &afters),
+            [Some(json!({ "a": 2 })), None, Some(json!([2]))]
+        );
+        assert_eq!(
+            generate_batch_with(&befores, &afters, &Options::new().ignore_path("/a")),
+            [None, None, Some(json!([2]))]
+        );
+
+        let befores = BTreeMap::from([
+            ("changed", json!({ "a": 1 })),
+            ("equal", json!({ "a": 1 })),
+            ("removed", json!({ "c": 1 })),
+        ]);
+        let afters = BTreeMap::from([
+            ("added", json!({ "b": null })),
+            ("changed", json!({ "a": 2 })),
+            ("equal", json!({ "a": 1 })),
+        ]);
+        let added = json!({ "b": null });
+        assert_eq!(
+            generate_batch_keyed(&befores, &afters),
+            BTreeMap::from([
+                (&"added", DocumentChange::Added(&added)),
+                (&"changed", DocumentChange::Patched(json!({ "a": 2 }))),
+                (&"removed", DocumentChange::Removed),
+            ])
+        );
+        assert_eq!(generate_batch_keyed(&befores, &befores), BTreeMap::new());
     }
 
+    #[cfg(feature = "rayon")]
     #[test]
-    fn test_recursive_replace() {
-        let before = json!({ "a": { "b": "c" } });
-        let after = json!({ "a": { "b": "d" } });
-        let patch = generate(&before, &after).unwrap();
-        assert_eq!(patch, json!({ "a": { "b": "d" } }));
+    fn test_par_generate_batch() {
+        use std::collections::BTreeMap;
+
+        let befores: Vec<_> = (0..1000).map(|i| json!({ "v": i })).collect();
+        let afters: Vec<_> = (0..1000).map(|i| json!({ "v": i % 3 })).collect();
+        assert_eq!(
+            par_generate_batch(&befores, &afters),
+            generate_batch(&befores, &afters)
+        );
+
+        let befores: BTreeMap<_, _> = befores.into_iter().enumerate().skip(10).collect();
+        let afters: BTreeMap<_, _> = afters.into_iter().enumerate().take(990).collect();
+        let options = Options::new().max_depth(0);
+        assert_eq!(
+            par_generate_batch_keyed_with(&befores, &afters, &options),
+            generate_batch_keyed_with(&befores, &afters, &options)
+        );
     }
 
+    #[cfg(feature = "metrics")]
     #[test]
-    fn test_recursive_add() {
-        let before = json!({ "a": { "b": "c" } });
-        let after = json!({ "a": { "b": "c", "d": "e" } });
+    fn test_metrics() {
+        use ::metrics::{
+            Counter, CounterFn, Gauge, Histogram, HistogramFn, Key, KeyName, Metadata, Recorder,
+            SharedString, Unit,
+        };
+        use std::sync::Mutex;
+
+        #[derive(Default)]
+        struct Recorded(Mutex<Vec<(String, f64)>>);
+
+        struct Handle(Arc<Recorded>, String);
+
+        impl CounterFn for Handle {
+            fn increment(&self, value: u64) {
+                self.0
+                     .0
+                    .lock()
+                    .unwrap()
+                    .push((self.1.clone(), value as f64));
+            }
+
+            fn absolute(&self, _: u64) {}
+        }
+
+        impl HistogramFn for Handle {
+            fn record(&self, value: f64) {
+                self.0 .0.lock().unwrap().push((self.1.clone(), value));
+            }
+        }
+
+        struct TestRecorder(Arc<Recorded>);
+
+        impl Recorder for TestRecorder {
+            fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+            fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+            fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+            fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+                Counter::from_arc(Arc::new(Handle(self.0.clone(), key.name().to_owned())))
+            }
+
+            fn register_gauge(&self, _: &Key, _: &Metadata<'_>) -> Gauge {
+                Gauge::noop()
+            }
+
+            fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+                Histogram::from_arc(Arc::new(Handle(self.0.clone(), key.name().to_owned())))
+            }
+        }
+
+        let recorded = Arc::new(Recorded::default());
+        ::metrics::with_local_recorder(&TestRecorder(recorded.clone()), || {
+            metrics::describe();
+            generate(
+                &json!({ "a": 1, "b": { "c": 1 } }),
+                &json!({ "b": { "c": 2, "d": {} } }),
+            );
+            generate(&json!({ "a": 1 }), &json!({ "a": 1 }));
+        });
+
+        let recorded = recorded.0.lock().unwrap();
+        let values = |name: &str| -> Vec<f64> {
+            recorded
+                .iter()
+                .filter(|(recorded, _)| recorded == name)
+                .map(|(_, value)| *value)
+                .collect()
+        };
+        assert_eq!(values(metrics::DOCUMENTS_DIFFED), [1.0, 1.0]);
+        assert_eq!(values(metrics::PATCH_CHANGES), [3.0]);
+        assert_eq!(
+            values(metrics::PATCH_BYTES),
+            [r#"{"a":null,"b":{"c":2,"d":{}}}"#.len() as f64]
+        );
+        assert_eq!(values(metrics::GENERATION_SECONDS).len(), 2);
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing() {
+        use std::sync::Mutex;
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        #[derive(Clone, Default)]
+        struct Capture(Arc<Mutex<Vec<String>>>);
+
+        struct Fields(String);
+
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn core::fmt::Debug) {
+                let separator = if self.0.is_empty() { "" } else { " " };
+                self.0 += &format!("{separator}{}={value:?}", field.name());
+            }
+        }
+
+        impl Capture {
+            fn push(&self, record: impl FnOnce(&mut Fields)) {
+                let mut fields = Fields(String::new());
+                record(&mut fields);
+                self.0.lock().unwrap().push(fields.0);
+            }
+        }
+
+        impl Subscriber for Capture {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                self.push(|fields| {
+                    fields.0 = span.metadata().name().to_owned();
+                    span.record(fields);
+                });
+                Id::from_u64(1)
+            }
+
+            fn record(&self, _: &Id, values: &Record<'_>) {
+                self.push(|fields| values.record(fields));
+            }
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, event: &Event<'_>) {
+                self.push(|fields| event.record(fields));
+            }
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        let capture = Capture::default();
+        tracing::subscriber::with_default(capture.clone(), || {
+            generate(
+                &json!({ "a": 1, "b": { "c": 1 } }),
+                &json!({ "b": { "c": 2 } }),
+            );
+        });
+        assert_eq!(
+            *capture.0.lock().unwrap(),
+            [
+                "generate_merge_patch",
+                "before_bytes=19",
+                "after_bytes=13",
+                r#"message=merge patch change pointer=/b/c change="modified""#,
+                r#"message=merge patch change pointer=/a change="removed""#,
+                "changes=2",
+                "message=generated merge patch changes=2",
+            ]
+        );
+    }
+
+    #[cfg(feature = "utoipa")]
+    #[test]
+    fn test_utoipa() {
+        // Out of the scope of this crate's utoipa module, which the paths
+        // of the derived code would refer to
+        #[allow(dead_code)]
+        mod types {
+            #[derive(utoipa::ToSchema)]
+            pub struct Settings {
+                timeout: u32,
+                name: Option<String>,
+                inner: Inner,
+                items: Vec<Inner>,
+            }
+
+            #[derive(utoipa::ToSchema)]
+            pub struct Inner {
+                enabled: bool,
+            }
+        }
+        use types::Settings;
+
+        let schema = serde_json::to_value(utoipa::schema::<Settings>()).unwrap();
+        assert_eq!(schema.get("required"), None);
+        assert_eq!(
+            schema["properties"],
+            json!({
+                "timeout": {"type": "integer", "format": "int32", "minimum": 0},
+                "name": {"type": ["string", "null"]},
+                "inner": {"$ref": "#/components/schemas/InnerMergePatch"},
+                "items": {"type": "array", "items": {"$ref": "#/components/schemas/Inner"}},
+            })
+        );
+
+        let mut schemas = Vec::new();
+        utoipa::schemas::<Settings>(&mut schemas);
+        let schemas: serde_json::Map<_, _> = schemas
+            .into_iter()
+            .map(|(name, schema)| (name, serde_json::to_value(schema).unwrap()))
+            .collect();
+        assert_eq!(schemas["Inner"]["required"], json!(["enabled"]));
+        assert_eq!(
+            schemas["InnerMergePatch"],
+            json!({"type": "object", "properties": {"enabled": {"type": "boolean"}}})
+        );
+
+        let body = serde_json::to_value(utoipa::request_body::<Settings>()).unwrap();
+        assert_eq!(body["required"], true);
+        assert_eq!(body["content"][MERGE_PATCH_MEDIA_TYPE]["schema"], schema);
+    }
+
+    #[test]
+    fn test_to_mongo_update() {
+        assert_eq!(
+            to_mongo_update(&json!({
+                "a": { "b": 1, "c": null, "d": { "e": [1, null] } },
+                "f": {},
+                "g": null,
+            })),
+            Ok(json!({
+                "$set": { "a.b": 1, "a.d.e": [1, null], "f": {} },
+                "$unset": { "a.c": "", "g": "" },
+            }))
+        );
+        assert_eq!(to_mongo_update(&json!({})), Ok(json!({})));
+
+        for (patch, pointer) in [
+            (json!({ "a": { "b.c": 1 } }), "/a/b.c"),
+            (json!({ "a/b": { "": null } }), "/a~1b/"),
+            (json!({ "$inc": 1 }), "/$inc"),
+            (json!([1]), ""),
+        ] {
+            assert!(matches!(
+                to_mongo_update(&patch),
+                Err(Error::MongoUpdate { pointer: error, .. }) if error == pointer
+            ));
+        }
+        assert_eq!(
+            to_mongo_update(&json!({ "a.b": 1 }))
+                .unwrap_err()
+                .to_string(),
+            r#"cannot translate "/a.b" to a MongoDB update: keys with dots can't be part of a dotted path"#
+        );
+    }
+
+    #[test]
+    fn test_to_redis_json_commands() {
+        let commands = to_redis_json_commands(&json!({
+            "a": { "b c": 1, "d": null },
+            "e": [1, null],
+            "f\"": {},
+        }));
+        let args: Vec<_> = commands.iter().map(|command| command.args("doc")).collect();
+        assert_eq!(
+            args,
+            [
+                vec!["JSON.SET", "doc", "$", "{}", "NX"],
+                vec!["JSON.SET", "doc", "$.a", "{}", "NX"],
+                vec!["JSON.SET", "doc", "$.e", "[1,null]"],
+                vec!["JSON.SET", "doc", r#"$["f\""]"#, "{}"],
+                vec!["JSON.SET", "doc", r#"$.a["b c"]"#, "1"],
+                vec!["JSON.DEL", "doc", "$.a.d"],
+            ]
+        );
+
+        assert_eq!(
+            to_redis_json_commands(&json!([1])),
+            [RedisJsonCommand::Set {
+                path: "$".to_owned(),
+                value: "[1]".to_owned(),
+                if_missing: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_render_plan() {
+        let before = json!({
+            "metadata": { "labels": { "app": "web", "debug": true } },
+            "spec": { "replicas": 2 },
+        });
+        let after = json!({
+            "metadata": { "labels": { "app": "web" } },
+            "spec": { "replicas": 3, "tolerations": [] },
+        });
+        assert_eq!(
+            render::plan_diff(&before, &after),
+            "- metadata.labels.debug\n\
+             ~ spec.replicas: 2 -> 3\n\
+             + spec.tolerations\n\
+             \n\
+             Plan: 1 to add, 1 to change, 1 to remove.\n"
+        );
+        assert_eq!(render::plan_diff(&before, &before), "No changes.\n");
+
         let patch = generate(&before, &after).unwrap();
-        assert_eq!(patch, json!({ "a": { "d": "e" } }));
+        assert_eq!(
+            render::plan_json(&before, &patch),
+            json!({
+                "changes": [
+                    {
+                        "action": "remove",
+                        "path": "metadata.labels.debug",
+                        "pointer": "/metadata/labels/debug",
+                        "before": true,
+                    },
+                    {
+                        "action": "change",
+                        "path": "spec.replicas",
+                        "pointer": "/spec/replicas",
+                        "before": 2,
+                        "after": 3,
+                    },
+                    {
+                        "action": "add",
+                        "path": "spec.tolerations",
+                        "pointer": "/spec/tolerations",
+                        "after": [],
+                    },
+                ],
+                "summary": { "add": 1, "change": 1, "remove": 1 },
+            })
+        );
     }
 
     #[test]
-    fn test_unchanged() {
-        let before = json!({ "a": "a" });
-        let after = json!({ "a": "a" });
-        let patch = generate(&before, &after);
-        assert_eq!(patch, None);
+    fn test_patch_envelope() {
+        let meta = PatchMeta {
+            author: Some("alice".to_string()),
+            base_revision: Some("3".to_string()),
+            ..PatchMeta::default()
+        };
+        let before = json!({ "name": "a", "size": 1 });
+        let after = json!({ "name": "b", "size": 1 });
+        let envelope = PatchEnvelope::generate(&before, &after, meta).unwrap();
+        assert_eq!(envelope.patch, json!({ "name": "b" }));
+        assert_eq!(
+            PatchEnvelope::generate(&before, &before, PatchMeta::default()),
+            None
+        );
+
+        let serialized = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(
+            serialized,
+            json!({
+                "meta": { "author": "alice", "base_revision": "3" },
+                "patch": { "name": "b" }
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<PatchEnvelope>(serialized).unwrap(),
+            envelope
+        );
+
+        // Metadata is optional, and unknown members are kept
+        let parsed: PatchEnvelope = serde_json::from_value(json!({
+            "meta": { "timestamp": "2024-05-01T12:00:00Z", "request_id": 7 },
+            "patch": null
+        }))
+        .unwrap();
+        assert_eq!(
+            parsed.meta.timestamp.as_deref(),
+            Some("2024-05-01T12:00:00Z")
+        );
+        assert_eq!(
+            parsed.meta.extra,
+            json!({ "request_id": 7 }).as_object().unwrap().clone()
+        );
+        assert_eq!(
+            serde_json::from_value::<PatchEnvelope>(json!({ "patch": {} }))
+                .unwrap()
+                .meta,
+            PatchMeta::default()
+        );
+        assert!(serde_json::from_value::<PatchEnvelope>(json!({ "meta": {} })).is_err());
+        assert!(serde_json::from_value::<PatchEnvelope>(
+            json!({ "meta": { "author": 1 }, "patch": {} })
+        )
+        .is_err());
+
+        let mut document = before.clone();
+        assert_eq!(
+            envelope.apply_at_revision(&mut document, "4"),
+            Err(Error::RevisionMismatch {
+                expected: "3".to_string(),
+                actual: "4".to_string()
+            })
+        );
+        assert_eq!(document, before);
+        envelope.apply_at_revision(&mut document, "3").unwrap();
+        assert_eq!(document, after);
     }
 
     #[test]
-    fn test_recursive_unchanged() {
-        let before = json!({ "a": { "b": "c" } });
-        let after = json!({ "a": { "b": "c" } });
-        let patch = generate(&before, &after);
-        assert_eq!(patch, None);
+    fn test_apply_checked() {
+        let before = json!({ "a": 1, "b": { "c": 2, "d": 3 } });
+        let after = json!({ "a": 1, "b": { "c": 4, "d": 3 } });
+        let patch = generate_with_checksum(&before, &after).unwrap();
+        assert_eq!(patch.patch, json!({ "b": { "c": 4 } }));
+        assert_eq!(patch.checksum, checksum(&before));
+        assert_eq!(generate_with_checksum(&before, &before), None);
+
+        // The order of the members doesn't matter
+        let reordered: Value = serde_json::from_str(r#"{"b":{"d":3,"c":2},"a":1}"#).unwrap();
+        assert_eq!(checksum(&reordered), checksum(&before));
+        assert_ne!(checksum(&after), checksum(&before));
+
+        let mut document = reordered;
+        apply_checked(&mut document, &patch).unwrap();
+        assert_eq!(document, after);
+
+        // Applying it again would apply it to the wrong document
+        assert_eq!(
+            apply_checked(&mut document, &patch),
+            Err(Error::ChecksumMismatch {
+                expected: checksum(&before),
+                actual: checksum(&after)
+            })
+        );
+        assert_eq!(document, after);
     }
 
+    #[cfg(feature = "rayon")]
     #[test]
-    fn test_unchanged_array() {
-        let before = json!([1, 2, 3]);
-        let after = json!([1, 2, 3]);
-        let patch = generate(&before, &after);
-        assert_eq!(patch, None);
+    fn test_par_generate_canonical() {
+        let before: serde_json::Value =
+            serde_json::from_str(r#"{"z":0,"a":0,"n":{"y":1,"b":1},"c":3}"#).unwrap();
+        let after: serde_json::Value =
+            serde_json::from_str(r#"{"z":1,"a":2,"n":{"y":2,"b":2},"c":3}"#).unwrap();
+
+        let options = Options::new().canonical(true);
+        let patch = par_generate_with(&before, &after, &options).unwrap();
+        assert_eq!(
+            serde_json::to_string(&patch).unwrap(),
+            r#"{"a":2,"n":{"b":2,"y":2},"z":1}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&patch).unwrap(),
+            serde_json::to_string(&generate_with(&before, &after, &options).unwrap()).unwrap()
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_generate_replace_changed_objects() {
+        let before = json!({ "a": 1, "b": 2, "c": 3 });
+        let after = json!({ "a": 10, "b": 20, "c": 3 });
+        let options = Options::new().replace_changed_objects(0.5);
+        assert_eq!(
+            par_generate_with(&before, &after, &options),
+            generate_with(&before, &after, &options)
+        );
+        assert_eq!(par_generate_with(&before, &after, &options), Some(after));
     }
 }
@@ -0,0 +1,96 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+#[cfg(feature = "std")]
+use core::hash::{BuildHasher, Hash};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::diff::Diff;
+use crate::filter::{self, Visibility};
+use crate::pointer::Segment;
+use crate::Options;
+
+/**
+ * A map whose entries are diffed as the members of an object.
+ */
+pub(crate) trait Entries<K, V>: Serialize {
+    fn entries<'m>(&'m self) -> impl Iterator<Item = (&'m K, &'m V)>
+    where
+        K: 'm,
+        V: 'm;
+    fn lookup(&self, key: &K) -> Option<&V>;
+}
+
+impl<K: Ord + Serialize, V: Serialize> Entries<K, V> for BTreeMap<K, V> {
+    fn entries<'m>(&'m self) -> impl Iterator<Item = (&'m K, &'m V)>
+    where
+        K: 'm,
+        V: 'm,
+    {
+        self.iter()
+    }
+
+    fn lookup(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: Eq + Hash + Serialize, V: Serialize, S: BuildHasher> Entries<K, V> for HashMap<K, V, S> {
+    fn entries<'m>(&'m self) -> impl Iterator<Item = (&'m K, &'m V)>
+    where
+        K: 'm,
+        V: 'm,
+    {
+        self.iter()
+    }
+
+    fn lookup(&self, key: &K) -> Option<&V> {
+        self.get(key)
+    }
+}
+
+pub(crate) fn generate_map<K: AsRef<str>, V: Serialize + PartialEq>(
+    before: &impl Entries<K, V>,
+    after: &impl Entries<K, V>,
+    options: &Options,
+) -> Result<Option<Value>, serde_json::Error> {
+    // These look at the root object as a whole
+    if options.max_depth == Some(0) || options.replace_changed_objects.is_some() {
+        let (before, after) = (serde_json::to_value(before)?, serde_json::to_value(after)?);
+        return Ok(crate::generate_with(&before, &after, options));
+    }
+    if filter::visibility(&options.ignore, &options.include, &[]) == Visibility::Excluded {
+        return Ok(options.finish(None));
+    }
+
+    let mut patch = Map::new();
+    let mut member = |key: &K, before: Option<&V>, after: Option<&V>| {
+        let before = before.map(serde_json::to_value).transpose()?;
+        let after = after.map(serde_json::to_value).transpose()?;
+        let key = key.as_ref();
+        let member = Diff::at(options, vec![Segment::Key(key)])
+            .member_patch::<Value, Value>(before.as_ref(), after.as_ref());
+        if let Some(member) = member {
+            patch.insert(key.into(), member);
+        }
+        Ok::<_, serde_json::Error>(())
+    };
+
+    for (key, value) in after.entries() {
+        match before.lookup(key) {
+            Some(before) if before == value => {}
+            before => member(key, before, Some(value))?,
+        }
+    }
+    for (key, value) in before.entries() {
+        if after.lookup(key).is_none() {
+            member(key, Some(value), None)?;
+        }
+    }
+
+    Ok(options.finish((!patch.is_empty()).then_some(Value::Object(patch))))
+}
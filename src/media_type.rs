@@ -0,0 +1,15 @@
+/**
+ * The media type of JSON Merge Patches (RFC 7386 section 4), for the
+ * `Content-Type` of requests with merge patch bodies.
+ */
+pub const MERGE_PATCH_MEDIA_TYPE: &str = "application/merge-patch+json";
+
+/**
+ * Whether the value of a `Content-Type` header is the merge patch media type,
+ * ignoring parameters like `charset`.
+ */
+#[cfg(any(feature = "actix-web", feature = "axum", feature = "http"))]
+pub(crate) fn is_merge_patch(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default();
+    essence.trim().eq_ignore_ascii_case(MERGE_PATCH_MEDIA_TYPE)
+}
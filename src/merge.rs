@@ -0,0 +1,116 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use serde_json::{Map, Value};
+
+use crate::changes::equal;
+use crate::copy::deep_clone;
+use crate::{pointer, Error};
+
+/**
+ * Members of the patches of both sides for an object that both change.
+ */
+struct Frame<'a> {
+    /** Key of the object in its parent, `None` for the root */
+    key: Option<&'a str>,
+    pointer: String,
+    base: &'a Map<String, Value>,
+    members: vec::IntoIter<(&'a str, Option<&'a Value>, Option<&'a Value>)>,
+    patch: Map<String, Value>,
+}
+
+pub(crate) fn merge(base: &Value, ours: &Value, theirs: &Value) -> Result<Value, Error> {
+    let mut merged = deep_clone(base);
+    match (crate::generate(base, ours), crate::generate(base, theirs)) {
+        (None, None) => {}
+        (Some(patch), None) | (None, Some(patch)) => crate::apply(&mut merged, &patch),
+        (Some(ours), Some(theirs)) => {
+            crate::apply(&mut merged, &merge_patches(base, &ours, &theirs)?)
+        }
+    }
+    Ok(merged)
+}
+
+/**
+ * Merges the patches of both sides into one, failing if they change the same
+ * value differently.
+ */
+fn merge_patches(base: &Value, ours: &Value, theirs: &Value) -> Result<Value, Error> {
+    let (Value::Object(base), Value::Object(ours), Value::Object(theirs)) = (base, ours, theirs)
+    else {
+        return match equal(ours, theirs) {
+            true => Ok(deep_clone(ours)),
+            false => Err(Error::MergeConflict {
+                pointers: vec![String::new()],
+            }),
+        };
+    };
+
+    let mut conflicts = Vec::new();
+    let mut stack = vec![frame(None, String::new(), base, ours, theirs)];
+    loop {
+        // The .unwrap() calls are safe because the loop returns once the stack is empty
+        let frame = stack.last_mut().unwrap();
+        if let Some((key, ours, theirs)) = frame.members.next() {
+            let pointer = [frame.pointer.as_str(), "/", &pointer::escape(key)].concat();
+            match (frame.base.get(key), ours, theirs) {
+                (_, Some(patch), None) | (_, None, Some(patch)) => {
+                    frame.patch.insert(key.into(), deep_clone(patch));
+                }
+                (
+                    Some(Value::Object(base)),
+                    Some(Value::Object(ours)),
+                    Some(Value::Object(theirs)),
+                ) => {
+                    let frame = self::frame(Some(key), pointer, base, ours, theirs);
+                    stack.push(frame);
+                }
+                (_, Some(ours), Some(theirs)) if equal(ours, theirs) => {
+                    frame.patch.insert(key.into(), deep_clone(ours));
+                }
+                _ => conflicts.push(pointer),
+            }
+            continue;
+        }
+
+        let frame = stack.pop().unwrap();
+        match (stack.last_mut(), frame.key) {
+            (Some(parent), Some(key)) => {
+                parent.patch.insert(key.into(), Value::Object(frame.patch));
+            }
+            _ if conflicts.is_empty() => return Ok(Value::Object(frame.patch)),
+            _ => {
+                return Err(Error::MergeConflict {
+                    pointers: conflicts,
+                })
+            }
+        }
+    }
+}
+
+fn frame<'a>(
+    key: Option<&'a str>,
+    pointer: String,
+    base: &'a Map<String, Value>,
+    ours: &'a Map<String, Value>,
+    theirs: &'a Map<String, Value>,
+) -> Frame<'a> {
+    let mut members: Vec<_> = ours
+        .iter()
+        .map(|(key, ours)| (key.as_str(), Some(ours), theirs.get(key)))
+        .collect();
+    members.extend(
+        theirs
+            .iter()
+            .filter(|(key, _)| !ours.contains_key(*key))
+            .map(|(key, theirs)| (key.as_str(), None, Some(theirs))),
+    );
+    Frame {
+        key,
+        pointer,
+        base,
+        members: members.into_iter(),
+        patch: Map::new(),
+    }
+}
@@ -0,0 +1,89 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+
+use serde_json::Value;
+
+/**
+ * A structured JSON Merge Patch.
+ *
+ * Converts to and from the plain JSON representation with [`From`]
+ * conversions. In JSON, an object patch is always merged into its target, so
+ * a `Replace` of an object is encoded as that object and reads back as a
+ * `Merge`.
+ *
+ * Unlike [`generate`](crate::generate), conversions and [`Drop`] of this type
+ * recurse on the nesting depth of the patch. The members of merged objects
+ * are always sorted by key, even with serde_json's `preserve_order` feature.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergePatch {
+    /** The target is left as-is */
+    Unchanged,
+    /** The target is replaced with the value, or removed if it's `null` */
+    Replace(Value),
+    /** The members of the target object are patched individually */
+    Merge(BTreeMap<String, MergePatch>),
+}
+
+impl MergePatch {
+    /**
+     * Whether applying the patch leaves the target as-is.
+     */
+    pub fn is_unchanged(&self) -> bool {
+        match self {
+            MergePatch::Unchanged => true,
+            MergePatch::Replace(_) => false,
+            MergePatch::Merge(members) => members.values().all(MergePatch::is_unchanged),
+        }
+    }
+
+    /**
+     * The patch in the representation returned by [`generate`](crate::generate),
+     * i.e. `None` if the patch leaves the target as-is.
+     */
+    pub fn into_value(self) -> Option<Value> {
+        match self {
+            MergePatch::Unchanged => None,
+            MergePatch::Replace(value) => Some(value),
+            MergePatch::Merge(members) => {
+                let members: serde_json::Map<_, _> = members
+                    .into_iter()
+                    .filter_map(|(key, patch)| patch.into_value().map(|patch| (key, patch)))
+                    .collect();
+                (!members.is_empty()).then_some(Value::Object(members))
+            }
+        }
+    }
+}
+
+impl From<Value> for MergePatch {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Object(members) => MergePatch::Merge(
+                members
+                    .into_iter()
+                    .map(|(key, value)| (key, MergePatch::from(value)))
+                    .collect(),
+            ),
+            value => MergePatch::Replace(value),
+        }
+    }
+}
+
+impl From<Option<Value>> for MergePatch {
+    fn from(value: Option<Value>) -> Self {
+        value.map_or(MergePatch::Unchanged, MergePatch::from)
+    }
+}
+
+impl From<MergePatch> for Value {
+    /**
+     * An unchanged patch is encoded as the empty object, which is the merge
+     * patch that leaves any target as-is.
+     */
+    fn from(patch: MergePatch) -> Self {
+        patch
+            .into_value()
+            .unwrap_or_else(|| Value::Object(serde_json::Map::new()))
+    }
+}
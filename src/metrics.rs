@@ -0,0 +1,61 @@
+/*!
+ * Instrumentation of patch generation through the
+ * [metrics](https://docs.rs/metrics) facade, recorded by
+ * [`generate_with`](crate::generate_with), the functions built on it, like
+ * [`generate`](crate::generate), and [`par_generate_with`](crate::par_generate_with).
+ *
+ * Nothing is recorded until a recorder, e.g. a Prometheus exporter, is
+ * installed. [`describe`] registers the descriptions of the metrics with it.
+ */
+
+use std::time::Instant;
+
+use ::metrics::{counter, describe_counter, describe_histogram, histogram, Unit};
+
+use crate::value::{patch_changes, JsonLike};
+
+/** Counter of the pairs of documents compared */
+pub const DOCUMENTS_DIFFED: &str = "json_merge_patch.documents_diffed";
+/** Histogram of the values that each patch sets or removes */
+pub const PATCH_CHANGES: &str = "json_merge_patch.patch_changes";
+/** Histogram of the size of each patch as compact JSON */
+pub const PATCH_BYTES: &str = "json_merge_patch.patch_bytes";
+/** Histogram of the time taken to compare each pair of documents */
+pub const GENERATION_SECONDS: &str = "json_merge_patch.generation_seconds";
+
+/**
+ * Describes the metrics to the installed recorder.
+ */
+pub fn describe() {
+    describe_counter!(DOCUMENTS_DIFFED, "Pairs of JSON documents compared");
+    describe_histogram!(
+        PATCH_CHANGES,
+        "Values set or removed by each generated merge patch"
+    );
+    describe_histogram!(
+        PATCH_BYTES,
+        Unit::Bytes,
+        "Size of each generated merge patch as compact JSON"
+    );
+    describe_histogram!(
+        GENERATION_SECONDS,
+        Unit::Seconds,
+        "Time taken to compare each pair of JSON documents"
+    );
+}
+
+/**
+ * Records the comparison that started at `start`. The patch's size is only
+ * recorded for values that tell it, see [`JsonLike::serialized_size`].
+ */
+pub(crate) fn record<V: JsonLike>(start: Instant, patch: Option<&V>) {
+    histogram!(GENERATION_SECONDS).record(start.elapsed());
+    counter!(DOCUMENTS_DIFFED).increment(1);
+    let Some(patch) = patch else {
+        return;
+    };
+    histogram!(PATCH_CHANGES).record(patch_changes(patch) as f64);
+    if let Some(size) = patch.serialized_size() {
+        histogram!(PATCH_BYTES).record(size as f64);
+    }
+}
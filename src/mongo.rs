@@ -0,0 +1,68 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use serde_json::{Map, Value};
+
+use crate::{pointer, Error};
+
+pub(crate) fn to_mongo_update(patch: &Value) -> Result<Value, Error> {
+    let Value::Object(members) = patch else {
+        return Err(Error::MongoUpdate {
+            pointer: String::new(),
+            reason: "the patch replaces the whole document",
+        });
+    };
+
+    let mut set = Map::new();
+    let mut unset = Map::new();
+    // Members left to translate, with their dotted paths and JSON Pointers
+    let mut stack = vec![(String::new(), String::new(), members)];
+    while let Some((path, parent, members)) = stack.pop() {
+        let mut objects = Vec::new();
+        for (key, value) in members {
+            let pointer = || [parent.as_str(), "/", &pointer::escape(key)].concat();
+            let reason = if key.is_empty() {
+                Some("empty keys can't be part of a dotted path")
+            } else if key.contains('.') {
+                Some("keys with dots can't be part of a dotted path")
+            } else if key.starts_with('$') {
+                Some("keys starting with `$` are operators")
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                return Err(Error::MongoUpdate {
+                    pointer: pointer(),
+                    reason,
+                });
+            }
+
+            let path = match path.is_empty() {
+                true => key.clone(),
+                false => [path.as_str(), ".", key].concat(),
+            };
+            match value {
+                Value::Null => {
+                    unset.insert(path, Value::String(String::new()));
+                }
+                Value::Object(members) if !members.is_empty() => {
+                    objects.push((path, pointer(), members));
+                }
+                value => {
+                    set.insert(path, value.clone());
+                }
+            }
+        }
+        stack.extend(objects.into_iter().rev());
+    }
+
+    let mut update = Map::new();
+    if !set.is_empty() {
+        update.insert("$set".into(), Value::Object(set));
+    }
+    if !unset.is_empty() {
+        update.insert("$unset".into(), Value::Object(unset));
+    }
+    Ok(Value::Object(update))
+}
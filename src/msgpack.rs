@@ -0,0 +1,55 @@
+/*!
+ * Encoding patches as [MessagePack](https://msgpack.org) to send them, and
+ * decoding and applying the ones received, without going through JSON text.
+ *
+ * The `null`s of patches, which remove members, are encoded as `nil`. Only
+ * documents that JSON can represent are supported: maps need string keys,
+ * and binary data doesn't decode.
+ */
+
+use std::fmt;
+
+use serde_json::Value;
+
+/**
+ * The bytes aren't a MessagePack encoded JSON document.
+ */
+#[derive(Debug)]
+pub struct DecodeError(rmp_serde::decode::Error);
+
+/**
+ * Decodes a MessagePack encoded document, e.g. a patch.
+ */
+pub fn from_slice(bytes: &[u8]) -> Result<Value, DecodeError> {
+    rmp_serde::from_slice(bytes).map_err(DecodeError)
+}
+
+/**
+ * Encodes a document, e.g. a generated patch, as MessagePack.
+ */
+pub fn to_vec(value: &Value) -> Vec<u8> {
+    // The .unwrap() is safe because writing to a Vec can't fail and every
+    // JSON value can be encoded
+    rmp_serde::to_vec(value).unwrap()
+}
+
+/**
+ * Decodes a MessagePack encoded patch and applies it to `document`, which is
+ * left unchanged if the patch doesn't decode.
+ */
+pub fn apply(document: &mut Value, patch: &[u8]) -> Result<(), DecodeError> {
+    crate::apply(document, &from_slice(patch)?);
+    Ok(())
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to decode MessagePack: {}", self.0)
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
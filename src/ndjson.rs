@@ -0,0 +1,150 @@
+use std::io::{BufRead, BufWriter, Write};
+
+use serde::de::Error as _;
+use serde_json::{Map, Value};
+
+use crate::diff::Diff;
+use crate::{EmptyPatchBehavior, Options};
+
+/**
+ * What to do with the records of the longer stream when two NDJSON streams
+ * have a different number of records, see [`generate_ndjson`].
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Unpaired {
+    /** Fail on the first unpaired record */
+    #[default]
+    Error,
+    /** Stop at the end of the shorter stream */
+    Skip,
+    /** Compare unpaired records with the empty object */
+    Empty,
+}
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) for each pair of records of two
+ * NDJSON (JSON Lines) streams, and writes them to `writer` one per line. Pairs
+ * of equal records are written as `null`, or `{}` as set by
+ * [`Options::empty_patch`]. Blank lines are skipped.
+ *
+ * Returns the number of pairs that differ.
+ */
+pub fn generate_ndjson(
+    before: impl BufRead,
+    after: impl BufRead,
+    writer: impl Write,
+    unpaired: Unpaired,
+) -> Result<usize, serde_json::Error> {
+    generate_ndjson_with(before, after, writer, unpaired, &Options::default())
+}
+
+/**
+ * [`generate_ndjson`] using the given [`Options`].
+ */
+pub fn generate_ndjson_with(
+    before: impl BufRead,
+    after: impl BufRead,
+    writer: impl Write,
+    unpaired: Unpaired,
+    options: &Options,
+) -> Result<usize, serde_json::Error> {
+    let mut before = Records::new(before, "before");
+    let mut after = Records::new(after, "after");
+    let mut writer = BufWriter::new(writer);
+    let mut changed = 0;
+
+    loop {
+        let (before, after) = match (before.next()?, after.next()?) {
+            (None, None) => break,
+            (Some(before), Some(after)) => (before, after),
+            (before, after) => match unpaired {
+                Unpaired::Error => {
+                    let (side, line) = match before {
+                        Some((_, line)) => ("before", line),
+                        None => ("after", after.map_or(0, |(_, line)| line)),
+                    };
+                    return Err(serde_json::Error::custom(format!(
+                        "record at line {line} of {side} has no pair"
+                    )));
+                }
+                Unpaired::Skip => break,
+                Unpaired::Empty => {
+                    let empty = || (Value::Object(Map::new()), 0);
+                    (before.unwrap_or_else(empty), after.unwrap_or_else(empty))
+                }
+            },
+        };
+
+        match Diff::new(options).generate::<Value, Value>(&before.0, &after.0) {
+            Some(mut patch) => {
+                options.canonicalize(&mut patch);
+                changed += 1;
+                serde_json::to_writer(&mut writer, &patch)?;
+            }
+            None => {
+                let unchanged: &[u8] = match options.empty_patch {
+                    EmptyPatchBehavior::None => b"null",
+                    EmptyPatchBehavior::EmptyObject => b"{}",
+                };
+                writer.write_all(unchanged).map_err(serde_json::Error::io)?;
+            }
+        }
+        writer.write_all(b"\n").map_err(serde_json::Error::io)?;
+    }
+
+    writer.flush().map_err(serde_json::Error::io)?;
+    Ok(changed)
+}
+
+/**
+ * The records of an NDJSON stream, with their line numbers.
+ */
+struct Records<R> {
+    reader: R,
+    side: &'static str,
+    line: usize,
+    buffer: String,
+}
+
+impl<R: BufRead> Records<R> {
+    fn new(reader: R, side: &'static str) -> Self {
+        Self {
+            reader,
+            side,
+            line: 0,
+            buffer: String::new(),
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<(Value, usize)>, serde_json::Error> {
+        loop {
+            self.buffer.clear();
+            let read = self
+                .reader
+                .read_line(&mut self.buffer)
+                .map_err(serde_json::Error::io)?;
+            if read == 0 {
+                return Ok(None);
+            }
+            self.line += 1;
+            if self.buffer.trim().is_empty() {
+                continue;
+            }
+
+            return match serde_json::from_str(&self.buffer) {
+                Ok(record) => Ok(Some((record, self.line))),
+                Err(error) => {
+                    let message = error.to_string();
+                    let suffix = format!(" at line {} column {}", error.line(), error.column());
+                    Err(serde_json::Error::custom(format!(
+                        "{} at line {} column {} of {}",
+                        message.strip_suffix(&suffix).unwrap_or(&message),
+                        self.line,
+                        error.column(),
+                        self.side
+                    )))
+                }
+            };
+        }
+    }
+}
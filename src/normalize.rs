@@ -0,0 +1,33 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+
+use serde_json::Value;
+
+use crate::copy::deep_clone;
+use crate::pointer;
+
+/**
+ * A copy of `document` with `normalize` applied to each of its values, from
+ * the root down, so that it sees the members of objects as it left them.
+ */
+pub(crate) fn normalized(document: &Value, normalize: &mut impl FnMut(&str, &mut Value)) -> Value {
+    let mut document = deep_clone(document);
+    let mut stack = vec![(String::new(), &mut document)];
+    while let Some((path, value)) = stack.pop() {
+        normalize(&path, value);
+        match value {
+            Value::Array(values) => {
+                stack.extend(values.iter_mut().enumerate().map(|(index, value)| {
+                    ([path.as_str(), "/", &index.to_string()].concat(), value)
+                }));
+            }
+            Value::Object(members) => {
+                stack.extend(members.iter_mut().map(|(key, value)| {
+                    ([path.as_str(), "/", &pointer::escape(key)].concat(), value)
+                }));
+            }
+            _ => {}
+        }
+    }
+    document
+}
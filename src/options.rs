@@ -0,0 +1,290 @@
+use alloc::vec::Vec;
+
+use crate::filter::PathPattern;
+use crate::JsonLike;
+
+/**
+ * Options controlling how [`generate_with`](crate::generate_with) compares the
+ * two documents.
+ *
+ * The defaults match [`generate`](crate::generate): values are compared by their
+ * JSON representation.
+ */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Options {
+    pub(crate) numeric_equality: bool,
+    pub(crate) float_epsilon: Option<f64>,
+    pub(crate) ignore: Vec<PathPattern>,
+    pub(crate) include: Vec<PathPattern>,
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) replace_changed_objects: Option<f64>,
+    pub(crate) ignore_removals: bool,
+    pub(crate) empty_patch: EmptyPatchBehavior,
+    pub(crate) canonical: bool,
+    pub(crate) max_patch_ratio: Option<f64>,
+    pub(crate) max_patch_size: Option<usize>,
+    #[cfg(feature = "unicode")]
+    pub(crate) unicode_normalization: Option<UnicodeNormalization>,
+}
+
+/**
+ * What generating a patch between equal documents returns, see
+ * [`Options::empty_patch`].
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EmptyPatchBehavior {
+    /** `None`, so that there's nothing to send */
+    #[default]
+    None,
+    /** An empty object, which is a patch that changes nothing */
+    EmptyObject,
+}
+
+/**
+ * Unicode normalization form applied to strings before comparing them.
+ */
+#[cfg(feature = "unicode")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnicodeNormalization {
+    /** Canonical composition, e.g. `"e\u{301}"` equals `"\u{e9}"` */
+    Nfc,
+    /** Compatibility composition, additionally e.g. `"\u{fb01}"` equals `"fi"` */
+    Nfkc,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * Compare numbers by their numeric value instead of their JSON
+     * representation, so that e.g. `1` and `1.0` are considered equal.
+     */
+    pub fn numeric_equality(mut self, enabled: bool) -> Self {
+        self.numeric_equality = enabled;
+        self
+    }
+
+    /**
+     * Consider two numbers equal if they differ by at most `epsilon`.
+     *
+     * Setting an epsilon implies [`numeric_equality`](Self::numeric_equality).
+     */
+    pub fn float_epsilon(mut self, epsilon: f64) -> Self {
+        self.numeric_equality = true;
+        self.float_epsilon = Some(epsilon);
+        self
+    }
+
+    /**
+     * Normalize string values to the given Unicode normalization form before
+     * comparing them, so that canonically (or compatibly) equivalent strings
+     * don't produce a patch.
+     *
+     * Only the comparison is affected: patches contain strings exactly as they
+     * appear in the `after` document. Object keys are always compared as-is.
+     */
+    #[cfg(feature = "unicode")]
+    pub fn unicode_normalization(mut self, form: UnicodeNormalization) -> Self {
+        self.unicode_normalization = Some(form);
+        self
+    }
+
+    // Line comments, as the example patterns would open nested block comments
+    /// Exclude the values at `pattern` from diffing. Changes only under ignored
+    /// paths don't produce a patch, and ignored values never appear in one.
+    ///
+    /// The pattern is a JSON Pointer (RFC 6901) where the reference token `*`
+    /// matches any single object key or array index, and `**` matches any
+    /// number of them, e.g. `/metadata/updatedAt`, `/items/*/etag` or
+    /// `/**/etag`.
+    pub fn ignore_path(mut self, pattern: &str) -> Self {
+        self.ignore.push(PathPattern::parse(pattern));
+        self
+    }
+
+    /**
+     * Exclude the object members at any depth whose key matches `pattern`
+     * from diffing, like [`ignore_path`](Self::ignore_path), where `*`
+     * matches any number of characters, e.g. `*_at`.
+     */
+    pub fn ignore_key(mut self, pattern: &str) -> Self {
+        self.ignore.push(PathPattern::key_glob(pattern));
+        self
+    }
+
+    /**
+     * Keys of members that change without the change mattering, like
+     * timestamps and revision counters, see
+     * [`ignore_volatile_keys`](Self::ignore_volatile_keys).
+     */
+    pub const VOLATILE_KEYS: &'static [&'static str] = &[
+        "*_at",
+        "updatedAt",
+        "modifiedAt",
+        "lastModified",
+        "lastSeen",
+        "last_seen",
+        "revision",
+    ];
+
+    /**
+     * [`ignore_key`](Self::ignore_key) for each of the
+     * [`VOLATILE_KEYS`](Self::VOLATILE_KEYS), e.g. to only patch documents
+     * whose contents changed.
+     */
+    pub fn ignore_volatile_keys(self) -> Self {
+        Self::VOLATILE_KEYS
+            .iter()
+            .fold(self, |options, key| options.ignore_key(key))
+    }
+
+    /**
+     * Restrict diffing to the values at `pattern`, using the same syntax as
+     * [`ignore_path`](Self::ignore_path). May be given multiple times.
+     *
+     * Everything outside of the included paths is treated as unchanged. When
+     * an ancestor of an included path is not an object in one of the
+     * documents, it's treated as an empty object so that the patch still only
+     * touches the included paths.
+     */
+    pub fn include_path(mut self, pattern: &str) -> Self {
+        self.include.push(PathPattern::parse(pattern));
+        self
+    }
+
+    /**
+     * Stop diffing objects member by member below `max_depth` levels of
+     * nesting (the root object being at depth 0).
+     *
+     * A changed object at the depth limit is emitted as a whole: the patch
+     * contains the entire `after` object, plus `null` for every member that was
+     * removed at any depth below it, so applying it still yields `after`. This
+     * trades patch size for generation cost, as unchanged members are no
+     * longer pruned from the patch, and no per-member patches need to be
+     * built for deeply nested documents.
+     */
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /**
+     * Emit a changed object as a whole, like at [`Options::max_depth`], when
+     * more than `fraction` of its members were added, removed or changed,
+     * e.g. `0.5`, for when most of it would be sent anyway. The patch of an
+     * object is then its entire `after` version, plus `null` for every member
+     * removed at any depth below it.
+     *
+     * Not supported by `generate_raw` (see the `raw_value` feature) and
+     * [`generate_stream`](crate::generate_stream). `par_generate` (see the
+     * `rayon` feature) compares the documents sequentially with it.
+     */
+    pub fn replace_changed_objects(mut self, fraction: f64) -> Self {
+        self.replace_changed_objects = Some(fraction);
+        self
+    }
+
+    /**
+     * Leave the members that are only in `before` as they are instead of
+     * removing them, so that `after` only needs the members it sets. Objects in
+     * arrays are compared the same way, so that arrays only differing by
+     * members missing from `after` are equal.
+     */
+    pub fn ignore_removals(mut self, enabled: bool) -> Self {
+        self.ignore_removals = enabled;
+        self
+    }
+
+    /**
+     * What to return when the documents are equal, e.g. `{}` to have a patch
+     * to acknowledge every poll with. Defaults to [`EmptyPatchBehavior::None`].
+     *
+     * Functions that write patches write `{}` for
+     * [`EmptyPatchBehavior::EmptyObject`], and still report that the
+     * documents are equal.
+     */
+    pub fn empty_patch(mut self, behavior: EmptyPatchBehavior) -> Self {
+        self.empty_patch = behavior;
+        self
+    }
+
+    /**
+     * Sort the members of the objects in patches by key, recursively, and
+     * write patches compactly, so that equal documents always give
+     * byte-identical patches, e.g. to hash or sign them.
+     *
+     * Otherwise objects keep the order of `after` where their type preserves
+     * it (see serde_json's `preserve_order` feature), and `generate_raw` (see
+     * the `raw_value` feature) and [`generate_stream`](crate::generate_stream)
+     * copy changed values from `after` as they're written there.
+     */
+    pub fn canonical(mut self, enabled: bool) -> Self {
+        self.canonical = enabled;
+        self
+    }
+
+    /**
+     * Have [`generate_update_with`](crate::generate_update_with) replace the
+     * whole document instead of patching it when the serialized patch would be
+     * larger than `ratio` times the serialized `after`, e.g. `1.0` to send
+     * whichever is smaller.
+     */
+    pub fn max_patch_ratio(mut self, ratio: f64) -> Self {
+        self.max_patch_ratio = Some(ratio);
+        self
+    }
+
+    /**
+     * Have [`generate_update_with`](crate::generate_update_with) replace the
+     * whole document instead of patching it when the serialized patch would be
+     * larger than `bytes`.
+     */
+    pub fn max_patch_size(mut self, bytes: usize) -> Self {
+        self.max_patch_size = Some(bytes);
+        self
+    }
+
+    /**
+     * `patch`, or an empty object in place of `None` if that's requested, with
+     * its keys sorted if that's requested.
+     */
+    pub(crate) fn finish<V: JsonLike>(&self, patch: Option<V>) -> Option<V> {
+        let mut patch = match self.empty_patch {
+            EmptyPatchBehavior::EmptyObject if patch.is_none() => Some(V::object(V::new_object())),
+            _ => patch,
+        };
+        if let Some(patch) = &mut patch {
+            self.canonicalize(patch);
+        }
+        patch
+    }
+
+    pub(crate) fn canonicalize<V: JsonLike>(&self, patch: &mut V) {
+        if self.canonical {
+            patch.sort_keys();
+        }
+    }
+
+    /**
+     * Options for patches from a live Kubernetes object (`before`) to a desired
+     * manifest (`after`), ignoring what the API server populates: removals,
+     * which include defaulted fields, `status` and the server-managed fields
+     * of `metadata`, like `managedFields` and `resourceVersion`.
+     */
+    pub fn kubernetes() -> Self {
+        [
+            "/status",
+            "/metadata/managedFields",
+            "/metadata/resourceVersion",
+            "/metadata/uid",
+            "/metadata/generation",
+            "/metadata/creationTimestamp",
+            "/metadata/selfLink",
+            "/metadata/annotations/kubectl.kubernetes.io~1last-applied-configuration",
+        ]
+        .into_iter()
+        .fold(Self::default().ignore_removals(true), Self::ignore_path)
+    }
+}
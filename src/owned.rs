@@ -0,0 +1,104 @@
+use alloc::borrow::{Cow, ToOwned};
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use serde_json::{Map, Value};
+
+use crate::diff::Output;
+
+/**
+ * Where each value of a patch comes from, without borrowing the documents.
+ */
+pub(crate) enum Plan {
+    /** Moved out of the `after` document at the same path */
+    Take,
+    /** Built during generation, e.g. `null` for removed members */
+    Value(Value),
+    Merge(Vec<(String, Plan)>),
+}
+
+impl<'a> Output<'a, Value> for Plan {
+    type Members = Vec<(String, Plan)>;
+
+    fn members() -> Self::Members {
+        Vec::new()
+    }
+
+    fn insert(members: &mut Self::Members, key: &'a str, patch: Self) {
+        members.push((key.to_owned(), patch));
+    }
+
+    fn is_empty(members: &Self::Members) -> bool {
+        members.is_empty()
+    }
+
+    fn merge(members: Self::Members) -> Self {
+        Plan::Merge(members)
+    }
+
+    fn replace(value: Cow<'a, Value>) -> Self {
+        match value {
+            Cow::Borrowed(_) => Plan::Take,
+            Cow::Owned(value) => Plan::Value(value),
+        }
+    }
+}
+
+impl Plan {
+    /**
+     * Builds the patch by moving values out of `after`.
+     */
+    pub(crate) fn execute(self, after: Value) -> Value {
+        /** A merge whose members are being collected */
+        struct Node {
+            key: Option<String>,
+            members: vec::IntoIter<(String, Plan)>,
+            after: Map<String, Value>,
+            patch: Map<String, Value>,
+        }
+
+        let node = |key, members: Vec<_>, after: Option<Value>| Node {
+            key,
+            members: members.into_iter(),
+            after: match after {
+                Some(Value::Object(after)) => after,
+                _ => Map::new(),
+            },
+            patch: Map::new(),
+        };
+
+        let mut stack = match self {
+            Plan::Take => return after,
+            Plan::Value(value) => return value,
+            Plan::Merge(members) => vec![node(None, members, Some(after))],
+        };
+
+        loop {
+            // The .unwrap() calls are safe because the loop returns once the stack is empty
+            let top = stack.last_mut().unwrap();
+            match top.members.next() {
+                Some((key, Plan::Take)) => {
+                    let value = top.after.remove(&key).unwrap_or(Value::Null);
+                    top.patch.insert(key, value);
+                }
+                Some((key, Plan::Value(value))) => {
+                    top.patch.insert(key, value);
+                }
+                Some((key, Plan::Merge(members))) => {
+                    let after = top.after.remove(&key);
+                    stack.push(node(Some(key), members, after));
+                }
+                None => {
+                    let node = stack.pop().unwrap();
+                    match (stack.last_mut(), node.key) {
+                        (Some(parent), Some(key)) => {
+                            parent.patch.insert(key, Value::Object(node.patch));
+                        }
+                        _ => return Value::Object(node.patch),
+                    }
+                }
+            }
+        }
+    }
+}
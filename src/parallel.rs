@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+
+use rayon::prelude::*;
+use serde_json::{Map, Value};
+
+use crate::batch::{self, DocumentChange};
+use crate::diff::Diff;
+use crate::filter::{self, Visibility};
+use crate::pointer::Segment;
+use crate::Options;
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`](crate::generate),
+ * comparing the members of the root object in parallel on the rayon thread
+ * pool.
+ *
+ * The result is identical to the sequential version, which is also used when
+ * the documents aren't both objects, and with
+ * [`Options::replace_changed_objects`], which may replace the root object as
+ * a whole.
+ */
+pub fn par_generate(before: &Value, after: &Value) -> Option<Value> {
+    par_generate_with(before, after, &Options::default())
+}
+
+/**
+ * [`par_generate`] using the given [`Options`].
+ */
+pub fn par_generate_with(before: &Value, after: &Value, options: &Options) -> Option<Value> {
+    let (Some(before_object), Some(after_object)) = (before.as_object(), after.as_object()) else {
+        return crate::generate_with(before, after, options);
+    };
+    // The root object is emitted as a whole at depth 0, or may be replaced
+    if options.max_depth == Some(0) || options.replace_changed_objects.is_some() {
+        return crate::generate_with(before, after, options);
+    }
+
+    #[cfg(feature = "metrics")]
+    let start = std::time::Instant::now();
+    let patch = generate_members(before_object, after_object, options);
+    #[cfg(feature = "metrics")]
+    crate::metrics::record(start, patch.as_ref());
+    patch
+}
+
+fn generate_members(
+    before: &Map<String, Value>,
+    after: &Map<String, Value>,
+    options: &Options,
+) -> Option<Value> {
+    if filter::visibility(&options.ignore, &options.include, &[]) == Visibility::Excluded {
+        return options.finish(None);
+    }
+
+    // Same order of members as the sequential generation
+    let members: Vec<_> = after
+        .iter()
+        .map(|(key, after_value)| (key, before.get(key), Some(after_value)))
+        .chain(
+            before
+                .iter()
+                .filter(|(key, _)| !after.contains_key(*key))
+                .map(|(key, before_value)| (key, Some(before_value), None)),
+        )
+        .collect();
+
+    let patches: Vec<_> = members
+        .into_par_iter()
+        .filter_map(|(key, before_value, after_value)| {
+            Diff::at(options, vec![Segment::Key(key)])
+                .member_patch(before_value, after_value)
+                .map(|patch| (key.clone(), patch))
+        })
+        .collect();
+
+    if patches.is_empty() {
+        options.finish(None)
+    } else {
+        options.finish(Some(Value::Object(patches.into_iter().collect())))
+    }
+}
+
+/**
+ * [`generate_batch`](crate::generate_batch), diffing the documents in parallel
+ * on the rayon thread pool.
+ */
+pub fn par_generate_batch(befores: &[Value], afters: &[Value]) -> Vec<Option<Value>> {
+    par_generate_batch_with(befores, afters, &Options::default())
+}
+
+/**
+ * [`par_generate_batch`] using the given [`Options`].
+ */
+pub fn par_generate_batch_with(
+    befores: &[Value],
+    afters: &[Value],
+    options: &Options,
+) -> Vec<Option<Value>> {
+    assert_eq!(befores.len(), afters.len(), "batches of different lengths");
+    befores
+        .par_iter()
+        .zip(afters)
+        .map(|(before, after)| crate::generate_with(before, after, options))
+        .collect()
+}
+
+/**
+ * [`generate_batch_keyed`](crate::generate_batch_keyed), diffing the
+ * documents in parallel on the rayon thread pool.
+ */
+pub fn par_generate_batch_keyed<'a, K: Ord + Sync>(
+    befores: &'a BTreeMap<K, Value>,
+    afters: &'a BTreeMap<K, Value>,
+) -> BTreeMap<&'a K, DocumentChange<'a>> {
+    par_generate_batch_keyed_with(befores, afters, &Options::default())
+}
+
+/**
+ * [`par_generate_batch_keyed`] using the given [`Options`].
+ */
+pub fn par_generate_batch_keyed_with<'a, K: Ord + Sync>(
+    befores: &'a BTreeMap<K, Value>,
+    afters: &'a BTreeMap<K, Value>,
+    options: &Options,
+) -> BTreeMap<&'a K, DocumentChange<'a>> {
+    let documents: Vec<_> = batch::keyed(befores, afters).collect();
+    documents
+        .into_par_iter()
+        .filter_map(|(key, before, after)| {
+            batch::document_change(before, after, options).map(|change| (key, change))
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .collect()
+}
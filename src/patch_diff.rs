@@ -0,0 +1,80 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use serde_json::Value;
+
+use crate::changes::equal;
+use crate::pointer;
+
+/**
+ * A path that two patches touch differently, see
+ * [`diff_patches`](crate::diff_patches).
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchDifference<'a> {
+    /** JSON Pointer (RFC 6901) to the value */
+    pub pointer: String,
+    /** The value of the first patch there, `None` if it leaves it as it is */
+    pub a: Option<&'a Value>,
+    /** The value of the second patch there, `None` if it leaves it as it is */
+    pub b: Option<&'a Value>,
+}
+
+/**
+ * A path that two patches set to different values, or that one removes and
+ * the other sets, see [`conflicts`](crate::conflicts).
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConflictPath<'a> {
+    /** JSON Pointer (RFC 6901) to the value */
+    pub pointer: String,
+    /** The value of the first patch there, `null` if it removes it */
+    pub a: &'a Value,
+    /** The value of the second patch there, `null` if it removes it */
+    pub b: &'a Value,
+}
+
+pub(crate) fn conflicts<'a>(a: &'a Value, b: &'a Value) -> Vec<ConflictPath<'a>> {
+    // Paths that only one of them touches are merged without a conflict
+    diff_patches(a, b)
+        .into_iter()
+        .filter_map(|difference| {
+            Some(ConflictPath {
+                pointer: difference.pointer,
+                a: difference.a?,
+                b: difference.b?,
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn diff_patches<'a>(a: &'a Value, b: &'a Value) -> Vec<PatchDifference<'a>> {
+    let mut differences = Vec::new();
+    // Values left to compare, last first
+    let mut stack = vec![(String::new(), Some(a), Some(b))];
+
+    while let Some((path, a, b)) = stack.pop() {
+        match (a, b) {
+            (Some(Value::Object(a)), Some(Value::Object(b))) => {
+                let members = a.iter().map(|(key, a)| (key, Some(a), b.get(key))).chain(
+                    b.iter()
+                        .filter(|(key, _)| !a.contains_key(*key))
+                        .map(|(key, b)| (key, None, Some(b))),
+                );
+                let start = stack.len();
+                stack.extend(members.map(|(key, a, b)| {
+                    ([path.as_str(), "/", &pointer::escape(key)].concat(), a, b)
+                }));
+                stack[start..].reverse();
+            }
+            (Some(a), Some(b)) if equal(a, b) => {}
+            _ => differences.push(PatchDifference {
+                pointer: path,
+                a,
+                b,
+            }),
+        }
+    }
+    differences
+}
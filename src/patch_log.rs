@@ -0,0 +1,118 @@
+use alloc::vec::Vec;
+
+use serde_json::Value;
+
+use crate::copy::deep_clone;
+
+/**
+ * A document stored as a snapshot and the patches made to it since, for event
+ * sourcing.
+ *
+ * Versions count the patches from the very first snapshot, so they stay the
+ * same as old patches are [compacted](Self::compact) into the snapshot.
+ *
+ * Merge patches can't be squashed into one without the document they apply
+ * to, e.g. a patch removing a member followed by one setting it to an object
+ * would merge that object into the member if combined, which
+ * [`crate::squash`] refuses. Compaction applies the patches to the snapshot
+ * instead, and [`squash`](Self::squash) generates a patch from the documents.
+ */
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PatchLog {
+    snapshot: Value,
+    snapshot_version: usize,
+    patches: Vec<Value>,
+}
+
+impl PatchLog {
+    /**
+     * A log starting at `snapshot`, as version 0.
+     */
+    pub fn new(snapshot: Value) -> Self {
+        Self::from_parts(snapshot, 0, Vec::new())
+    }
+
+    /**
+     * A log restored from its [`snapshot`](Self::snapshot) at
+     * `snapshot_version` and the [`patches`](Self::patches) made since.
+     */
+    pub fn from_parts(snapshot: Value, snapshot_version: usize, patches: Vec<Value>) -> Self {
+        PatchLog {
+            snapshot,
+            snapshot_version,
+            patches,
+        }
+    }
+
+    /**
+     * Records `patch` as the next version, returning its number.
+     */
+    pub fn push(&mut self, patch: Value) -> usize {
+        self.patches.push(patch);
+        self.version()
+    }
+
+    /** The latest version */
+    pub fn version(&self) -> usize {
+        self.snapshot_version + self.patches.len()
+    }
+
+    pub fn snapshot(&self) -> &Value {
+        &self.snapshot
+    }
+
+    /** The version of the snapshot, the oldest that can be replayed */
+    pub fn snapshot_version(&self) -> usize {
+        self.snapshot_version
+    }
+
+    /** The patches since the snapshot, in order */
+    pub fn patches(&self) -> &[Value] {
+        &self.patches
+    }
+
+    /**
+     * The document at `version`, or `None` if it's compacted away or not
+     * recorded yet.
+     */
+    pub fn replay(&self, version: usize) -> Option<Value> {
+        let count = version.checked_sub(self.snapshot_version)?;
+        let patches = self.patches.get(..count)?;
+        let mut document = deep_clone(&self.snapshot);
+        for patch in patches {
+            crate::apply(&mut document, patch);
+        }
+        Some(document)
+    }
+
+    /** The document at the latest version */
+    pub fn latest(&self) -> Value {
+        // The .unwrap() is safe because the latest version is always recorded
+        self.replay(self.version()).unwrap()
+    }
+
+    /**
+     * The patch from the document at version `from` to the one at `to`, or
+     * `None` if it doesn't change anything or a version can't be replayed.
+     */
+    pub fn squash(&self, from: usize, to: usize) -> Option<Value> {
+        let before = self.replay(from)?;
+        let after = self.replay(to)?;
+        match crate::changes::equal(&before, &after) {
+            true => None,
+            false => crate::generate(&before, &after),
+        }
+    }
+
+    /**
+     * Applies the oldest `count` patches (or all, if there are fewer) to the
+     * snapshot and drops them.
+     */
+    pub fn compact(&mut self, count: usize) {
+        let count = count.min(self.patches.len());
+        for patch in self.patches.drain(..count) {
+            crate::apply(&mut self.snapshot, &patch);
+        }
+        self.snapshot_version += count;
+    }
+}
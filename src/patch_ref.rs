@@ -0,0 +1,85 @@
+use alloc::borrow::{Cow, ToOwned};
+use alloc::collections::BTreeMap;
+
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::Value;
+
+use crate::diff::Output;
+
+/**
+ * A JSON Merge Patch whose values borrow from the documents it was generated
+ * from, see [`generate_ref`](crate::generate_ref).
+ *
+ * Serializes to the same JSON as the patch returned by
+ * [`generate`](crate::generate), except that the members of merged objects
+ * are always sorted by key, even with serde_json's `preserve_order` feature.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchRef<'a> {
+    /** The target is replaced with the value, or removed if it's `null` */
+    Replace(Cow<'a, Value>),
+    /** The members of the target object are patched individually */
+    Merge(BTreeMap<&'a str, PatchRef<'a>>),
+}
+
+impl PatchRef<'_> {
+    /**
+     * Converts the patch into an owned [`Value`], cloning borrowed values.
+     */
+    pub fn into_owned(self) -> Value {
+        match self {
+            PatchRef::Replace(value) => Value::replace(value),
+            PatchRef::Merge(members) => Value::Object(
+                members
+                    .into_iter()
+                    .map(|(key, patch)| (key.to_owned(), patch.into_owned()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<PatchRef<'_>> for Value {
+    fn from(patch: PatchRef<'_>) -> Self {
+        patch.into_owned()
+    }
+}
+
+impl Serialize for PatchRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            PatchRef::Replace(value) => value.serialize(serializer),
+            PatchRef::Merge(members) => {
+                let mut map = serializer.serialize_map(Some(members.len()))?;
+                for (key, patch) in members {
+                    map.serialize_entry(key, patch)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'a> Output<'a, Value> for PatchRef<'a> {
+    type Members = BTreeMap<&'a str, PatchRef<'a>>;
+
+    fn members() -> Self::Members {
+        BTreeMap::new()
+    }
+
+    fn insert(members: &mut Self::Members, key: &'a str, patch: Self) {
+        members.insert(key, patch);
+    }
+
+    fn is_empty(members: &Self::Members) -> bool {
+        members.is_empty()
+    }
+
+    fn merge(members: Self::Members) -> Self {
+        PatchRef::Merge(members)
+    }
+
+    fn replace(value: Cow<'a, Value>) -> Self {
+        PatchRef::Replace(value)
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::BTreeSet;
+
+#[cfg(feature = "schemars")]
+use schemars::{JsonSchema, Schema, SchemaGenerator};
+use serde_json::{json, Map, Value};
+
+/**
+ * Suffix of the names of definitions for patches of the definition without it.
+ */
+pub(crate) const SUFFIX: &str = "MergePatch";
+
+#[cfg(feature = "schemars")]
+pub(crate) fn merge_patch_schema<T: JsonSchema + ?Sized>() -> Schema {
+    to_merge_patch_schema(&SchemaGenerator::default().into_root_schema_for::<T>())
+}
+
+#[cfg(feature = "schemars")]
+pub(crate) fn to_merge_patch_schema(schema: &Schema) -> Schema {
+    let mut root = schema.as_value().clone();
+    let mut referenced = BTreeSet::new();
+    let patch = patch(&root, &mut referenced);
+
+    // Definitions are only patched where a patch refers to them, as they're
+    // still used as they are elsewhere, e.g. for array items
+    let mut patched = BTreeSet::new();
+    while let Some(reference) = referenced.pop_first() {
+        if !patched.insert(reference.clone()) {
+            continue;
+        }
+        let Some((definitions, name)) = split_reference(&reference) else {
+            continue;
+        };
+        let Some(definition) = root.pointer(&reference[1..]) else {
+            continue;
+        };
+        let definition = self::patch(definition, &mut referenced);
+        if let Some(Value::Object(definitions)) = root.get_mut(definitions) {
+            definitions.insert([name, SUFFIX].concat(), definition);
+        }
+    }
+
+    match (&mut root, patch) {
+        (Value::Object(root), Value::Object(patch)) => {
+            let definitions = ["$defs", "definitions"].map(|key| (key, root.remove(key)));
+            *root = patch;
+            for (key, definitions) in definitions {
+                if let Some(definitions) = definitions {
+                    root.insert(key.into(), definitions);
+                }
+            }
+        }
+        (root, patch) => *root = patch,
+    }
+    // The .unwrap() is safe because patches of schemas are objects or booleans
+    Schema::try_from(root).unwrap()
+}
+
+/**
+ * Schema for the patches of the values of `schema`, collecting the references
+ * to definitions whose patches it uses.
+ */
+pub(crate) fn patch(schema: &Value, referenced: &mut BTreeSet<String>) -> Value {
+    let Value::Object(schema) = schema else {
+        return schema.clone();
+    };
+    let mut patch = schema.clone();
+    patch.remove("$defs");
+    patch.remove("definitions");
+
+    if let Some(Value::String(reference)) = schema.get("$ref") {
+        if let Some((definitions, name)) = split_reference(reference) {
+            referenced.insert(reference.clone());
+            patch.insert(
+                "$ref".into(),
+                format!("#/{definitions}/{name}{SUFFIX}").into(),
+            );
+        }
+    }
+
+    // A patch matches one of the patches of the alternatives, possibly more
+    // than one as they're less strict
+    for key in ["allOf", "anyOf", "oneOf"] {
+        if let Some(Value::Array(alternatives)) = patch.remove(key) {
+            let alternatives = alternatives
+                .iter()
+                .map(|alternative| self::patch(alternative, referenced))
+                .collect();
+            let key = if key == "oneOf" { "anyOf" } else { key };
+            patch.insert(key.into(), Value::Array(alternatives));
+        }
+    }
+
+    if is_object(schema) {
+        let required: BTreeSet<&str> = match schema.get("required") {
+            Some(Value::Array(required)) => required.iter().filter_map(Value::as_str).collect(),
+            _ => BTreeSet::new(),
+        };
+        patch.remove("required");
+        patch.remove("minProperties");
+        patch.remove("maxProperties");
+        if let Some(Value::Object(properties)) = patch.get_mut("properties") {
+            for (key, property) in properties.iter_mut() {
+                let patched = self::patch(property, referenced);
+                *property = match required.contains(key.as_str()) {
+                    true => patched,
+                    false => nullable(patched),
+                };
+            }
+        }
+        for key in ["additionalProperties", "patternProperties"] {
+            match patch.get_mut(key) {
+                Some(Value::Object(properties)) if key == "patternProperties" => {
+                    for property in properties.values_mut() {
+                        *property = nullable(self::patch(property, referenced));
+                    }
+                }
+                Some(property @ Value::Object(_)) => {
+                    *property = nullable(self::patch(property, referenced));
+                }
+                _ => {}
+            }
+        }
+    }
+    Value::Object(patch)
+}
+
+/**
+ * Whether `schema` is for objects, whose patches are merged into them.
+ */
+fn is_object(schema: &Map<String, Value>) -> bool {
+    match schema.get("type") {
+        Some(Value::String(kind)) => kind == "object",
+        Some(Value::Array(kinds)) => kinds.iter().any(|kind| kind == "object"),
+        _ => schema.contains_key("properties"),
+    }
+}
+
+/**
+ * `schema` also allowing `null`, which removes members.
+ */
+fn nullable(schema: Value) -> Value {
+    let Value::Object(mut schema) = schema else {
+        return schema;
+    };
+    if schema.contains_key("enum") || schema.contains_key("const") {
+        return json!({"anyOf": [schema, {"type": "null"}]});
+    }
+    match schema.get_mut("type") {
+        Some(Value::Array(kinds)) => {
+            if !kinds.iter().any(|kind| kind == "null") {
+                kinds.push("null".into());
+            }
+        }
+        Some(kind) if kind.is_string() => {
+            if kind != "null" {
+                *kind = json!([kind.take(), "null"]);
+            }
+        }
+        _ => return json!({"anyOf": [schema, {"type": "null"}]}),
+    }
+    Value::Object(schema)
+}
+
+/**
+ * Splits a reference to a definition of the root schema, like `#/$defs/Name`,
+ * or to a schema of the components of an OpenAPI document, like
+ * `#/components/schemas/Name`, into the path of the definitions and the name.
+ */
+pub(crate) fn split_reference(reference: &str) -> Option<(&str, &str)> {
+    let (definitions, name) = reference.strip_prefix("#/")?.rsplit_once('/')?;
+    matches!(definitions, "$defs" | "definitions" | "components/schemas")
+        .then_some((definitions, name))
+}
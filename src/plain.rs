@@ -0,0 +1,37 @@
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use serde_json::Value;
+
+/**
+ * Serializes a value with its numbers as primitives, rather than as the
+ * private struct that serde_json uses for them with `arbitrary_precision`,
+ * for formats other than JSON and serializers into other languages' values.
+ */
+pub struct PlainNumbers<'a>(pub &'a Value);
+
+impl Serialize for PlainNumbers<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            Value::Number(number) => match (number.as_u64(), number.as_i64()) {
+                (Some(n), _) => serializer.serialize_u64(n),
+                (None, Some(n)) => serializer.serialize_i64(n),
+                // as_f64() only fails for numbers that are out of range of f64
+                (None, None) => serializer.serialize_f64(number.as_f64().unwrap_or(f64::NAN)),
+            },
+            Value::Array(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for value in values {
+                    seq.serialize_element(&PlainNumbers(value))?;
+                }
+                seq.end()
+            }
+            Value::Object(members) => {
+                let mut map = serializer.serialize_map(Some(members.len()))?;
+                for (key, value) in members {
+                    map.serialize_entry(key, &PlainNumbers(value))?;
+                }
+                map.end()
+            }
+            value => value.serialize(serializer),
+        }
+    }
+}
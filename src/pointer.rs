@@ -0,0 +1,73 @@
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+
+/**
+ * A single reference token of a JSON Pointer (RFC 6901)
+ * <https://datatracker.ietf.org/doc/html/rfc6901>
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+impl Segment<'_> {
+    /**
+     * Whether this segment is addressed by the unescaped reference token `token`.
+     */
+    pub(crate) fn matches(&self, token: &str) -> bool {
+        match self {
+            Segment::Key(key) => *key == token,
+            Segment::Index(index) => token.parse::<usize>().is_ok_and(|t| t == *index),
+        }
+    }
+}
+
+/**
+ * Unescapes `~1` and `~0` in a reference token. Unknown escape sequences are
+ * kept as-is.
+ */
+pub(crate) fn unescape(token: &str) -> Cow<'_, str> {
+    if token.contains('~') {
+        Cow::Owned(token.replace("~1", "/").replace("~0", "~"))
+    } else {
+        Cow::Borrowed(token)
+    }
+}
+
+/**
+ * Escapes `~` and `/` in a key to make it a reference token.
+ */
+pub(crate) fn escape(key: &str) -> Cow<'_, str> {
+    if key.contains(['~', '/']) {
+        Cow::Owned(key.replace('~', "~0").replace('/', "~1"))
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
+/**
+ * Splits a JSON Pointer into its unescaped reference tokens. The leading `/`
+ * is optional, and the empty pointer refers to the whole document.
+ */
+pub(crate) fn tokens(pointer: &str) -> impl Iterator<Item = Cow<'_, str>> {
+    let rest = (!pointer.is_empty()).then(|| pointer.strip_prefix('/').unwrap_or(pointer));
+    rest.into_iter()
+        .flat_map(|rest| rest.split('/'))
+        .map(unescape)
+}
+
+/**
+ * The JSON Pointer of `path`.
+ */
+pub(crate) fn format(path: &[Segment]) -> String {
+    let mut pointer = String::new();
+    for segment in path {
+        pointer.push('/');
+        match segment {
+            Segment::Key(key) => pointer.push_str(&escape(key)),
+            Segment::Index(index) => pointer.push_str(&index.to_string()),
+        }
+    }
+    pointer
+}
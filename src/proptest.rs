@@ -0,0 +1,92 @@
+/*!
+ * [proptest](https://docs.rs/proptest) strategies for JSON documents and
+ * pairs of them, and the property that a generated patch turns one into the
+ * other, for fuzzing code that generates or applies merge patches.
+ *
+ * ```
+ * use json_merge_patch_gen::proptest::{check_roundtrip, json_pair};
+ * use proptest::proptest;
+ *
+ * proptest!(|((before, after) in json_pair())| {
+ *     check_roundtrip(&before, &after)?;
+ * });
+ * ```
+ */
+
+use ::proptest::collection::{btree_map, vec};
+use ::proptest::prelude::*;
+use ::proptest::sample::select;
+use serde_json::{Number, Value};
+
+use crate::pointer;
+
+/**
+ * Arbitrary JSON documents, nested a few levels deep. Object keys come from a
+ * small set including ones that need escaping in JSON Pointers, so that
+ * objects often share members.
+ */
+pub fn json() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i64>().prop_map(Value::from),
+        any::<f64>()
+            .prop_filter_map("not finite", Number::from_f64)
+            .prop_map(Value::Number),
+        vec(any::<char>(), 0..8).prop_map(|chars| Value::String(chars.into_iter().collect())),
+    ];
+    leaf.prop_recursive(4, 64, 6, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..6).prop_map(Value::Array),
+            btree_map(key(), inner, 0..6)
+                .prop_map(|members| Value::Object(members.into_iter().collect())),
+        ]
+    })
+}
+
+/**
+ * Pairs of documents: unrelated ones, as well as ones where the second is the
+ * first with another document applied to it as a patch, which only changes
+ * some of its members.
+ */
+pub fn json_pair() -> impl Strategy<Value = (Value, Value)> {
+    prop_oneof![
+        (json(), json()),
+        (json(), json()).prop_map(|(before, patch)| {
+            let mut after = before.clone();
+            crate::apply(&mut after, &patch);
+            (before, after)
+        }),
+    ]
+}
+
+/**
+ * Checks that applying the patch from `before` to `after` to `before` yields
+ * `after`, except for the `null` members of `after` that merge patches can't
+ * set (see [`generate_checked`](crate::generate_checked)), which are removed
+ * instead.
+ */
+pub fn check_roundtrip(before: &Value, after: &Value) -> Result<(), TestCaseError> {
+    let (patch, lossy) = crate::generate_checked(before, after);
+    let mut result = before.clone();
+    if let Some(patch) = &patch {
+        crate::apply(&mut result, patch);
+    }
+
+    let mut expected = after.clone();
+    for lossy in &lossy {
+        // The .unwrap()s are safe because the pointers are to members of `after`
+        let (parent, key) = lossy.rsplit_once('/').unwrap();
+        let parent = expected.pointer_mut(parent).unwrap();
+        parent
+            .as_object_mut()
+            .unwrap()
+            .remove(&*pointer::unescape(key));
+    }
+    prop_assert_eq!(result, expected, "patch: {:?}", patch);
+    Ok(())
+}
+
+fn key() -> impl Strategy<Value = String> {
+    select(&["a", "b", "c", "", "~1/"][..]).prop_map(String::from)
+}
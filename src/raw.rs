@@ -0,0 +1,261 @@
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use serde_json::value::RawValue;
+use serde_json::Value;
+
+use crate::diff::Diff;
+use crate::filter::{self, Visibility};
+use crate::pointer::Segment;
+use crate::{EmptyPatchBehavior, Options};
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`](crate::generate),
+ * from unparsed documents.
+ *
+ * Objects are only parsed one level at a time, and members whose text is
+ * identical in both documents are skipped without parsing them. Other values
+ * are parsed and compared as usual, so the result is the same as with
+ * [`generate`](crate::generate). Added members are copied into the patch as-is,
+ * including their formatting, while the members of objects that are compared
+ * are sorted by key, even with serde_json's `preserve_order` feature.
+ *
+ * Fails if a value nests deeper than serde_json's recursion limit.
+ */
+pub fn generate_raw(
+    before: &RawValue,
+    after: &RawValue,
+) -> Result<Option<Box<RawValue>>, serde_json::Error> {
+    generate_raw_with(before, after, &Options::default())
+}
+
+/**
+ * [`generate_raw`] using the given [`Options`].
+ */
+pub fn generate_raw_with(
+    before: &RawValue,
+    after: &RawValue,
+    options: &Options,
+) -> Result<Option<Box<RawValue>>, serde_json::Error> {
+    let descend = match filter::visibility(&options.ignore, &options.include, &[]) {
+        Visibility::Excluded => None,
+        Visibility::Ancestor => Some(is_object(before) && is_object(after)),
+        Visibility::Included => {
+            Some(is_object(before) && is_object(after) && options.max_depth != Some(0))
+        }
+    };
+    let patch = match descend {
+        None => None,
+        Some(false) => {
+            let (before, after): (Value, Value) = (parse(before)?, parse(after)?);
+            Diff::new(options)
+                .generate::<Value, Value>(&before, &after)
+                .map(|patch| serde_json::to_string(&patch))
+                .transpose()?
+        }
+        Some(true) => RawDiff {
+            options,
+            path: Vec::new(),
+        }
+        .run(before, after)?,
+    };
+    let patch = match patch {
+        // Values are copied from `after` as they're written there
+        Some(patch) if options.canonical => {
+            let mut patch: Value = serde_json::from_str(&patch)?;
+            options.canonicalize(&mut patch);
+            Some(serde_json::to_string(&patch)?)
+        }
+        patch => patch,
+    };
+
+    match patch {
+        None if options.empty_patch == EmptyPatchBehavior::EmptyObject => Some("{}".to_string()),
+        patch => patch,
+    }
+    .map(RawValue::from_string)
+    .transpose()
+}
+
+/**
+ * Outcome of comparing a pair of members: either their patch is known right
+ * away, or they're objects whose members need to be compared.
+ */
+enum Step<'a> {
+    Done(Option<String>),
+    Descend(&'a RawValue, &'a RawValue),
+}
+
+/**
+ * An object whose members are being compared.
+ */
+struct Frame<'a> {
+    /** Key of the object in its parent, `None` for the root */
+    key: Option<String>,
+    /** Length of the path to the object */
+    depth: usize,
+    members: vec::IntoIter<(String, Option<&'a RawValue>, Option<&'a RawValue>)>,
+    /** Text of the patches of the members */
+    patch: BTreeMap<String, String>,
+}
+
+struct RawDiff<'o> {
+    options: &'o Options,
+    path: Vec<String>,
+}
+
+impl<'o> RawDiff<'o> {
+    /**
+     * Text of the patch for two objects.
+     */
+    fn run<'a>(
+        &mut self,
+        before: &'a RawValue,
+        after: &'a RawValue,
+    ) -> Result<Option<String>, serde_json::Error> {
+        let mut stack = vec![self.frame(None, before, after)?];
+
+        loop {
+            // The .unwrap() calls are safe because the loop returns once the stack is empty
+            let frame = stack.last_mut().unwrap();
+            self.path.truncate(frame.depth);
+
+            if let Some((key, before, after)) = frame.members.next() {
+                self.path.push(key);
+                match self.member_step(before, after)? {
+                    Step::Done(None) => {}
+                    Step::Done(Some(patch)) => {
+                        let key = self.path.last().unwrap().clone();
+                        stack.last_mut().unwrap().patch.insert(key, patch);
+                    }
+                    Step::Descend(before, after) => {
+                        let key = self.path.last().unwrap().clone();
+                        let frame = self.frame(Some(key), before, after)?;
+                        stack.push(frame);
+                    }
+                }
+                continue;
+            }
+
+            let frame = stack.pop().unwrap();
+            let patch = (!frame.patch.is_empty())
+                .then(|| object(frame.patch))
+                .transpose()?;
+            match (stack.last_mut(), frame.key) {
+                (Some(parent), Some(key)) => {
+                    if let Some(patch) = patch {
+                        parent.patch.insert(key, patch);
+                    }
+                }
+                _ => return Ok(patch),
+            }
+        }
+    }
+
+    fn frame<'a>(
+        &self,
+        key: Option<String>,
+        before: &'a RawValue,
+        after: &'a RawValue,
+    ) -> Result<Frame<'a>, serde_json::Error> {
+        let mut before: BTreeMap<String, &RawValue> = parse(before)?;
+        let after: BTreeMap<String, &RawValue> = parse(after)?;
+
+        // New & modified members, then removed ones
+        let mut members: Vec<_> = after
+            .into_iter()
+            .map(|(key, after)| {
+                let before = before.remove(&key);
+                (key, before, Some(after))
+            })
+            .collect();
+        members.extend(
+            before
+                .into_iter()
+                .map(|(key, before)| (key, Some(before), None)),
+        );
+
+        Ok(Frame {
+            key,
+            depth: self.path.len(),
+            members: members.into_iter(),
+            patch: BTreeMap::new(),
+        })
+    }
+
+    /**
+     * Compares the object member at the current path, where `None` means that
+     * the member is absent.
+     */
+    fn member_step<'a>(
+        &self,
+        before: Option<&'a RawValue>,
+        after: Option<&'a RawValue>,
+    ) -> Result<Step<'a>, serde_json::Error> {
+        let path: Vec<_> = self.path.iter().map(|key| Segment::Key(key)).collect();
+        let visibility = filter::visibility(&self.options.ignore, &self.options.include, &path);
+        let max_depth = self
+            .options
+            .max_depth
+            .is_some_and(|max_depth| path.len() >= max_depth);
+
+        match (visibility, before, after) {
+            (Visibility::Excluded, _, _) => Ok(Step::Done(None)),
+            (_, Some(before), Some(after)) if before.get() == after.get() => Ok(Step::Done(None)),
+            (Visibility::Ancestor, Some(before), Some(after))
+                if is_object(before) && is_object(after) =>
+            {
+                Ok(Step::Descend(before, after))
+            }
+            (Visibility::Included, Some(before), Some(after))
+                if is_object(before) && is_object(after) && !max_depth =>
+            {
+                Ok(Step::Descend(before, after))
+            }
+            (Visibility::Included, Some(_), None) if !self.options.ignore_removals => {
+                Ok(Step::Done(Some("null".to_string())))
+            }
+            (Visibility::Included, None, Some(after)) if self.options.ignore.is_empty() => {
+                Ok(Step::Done(Some(after.get().to_string())))
+            }
+            // Anything else is parsed and compared as usual
+            _ => {
+                let before: Option<Value> = before.map(parse).transpose()?;
+                let after: Option<Value> = after.map(parse).transpose()?;
+                Diff::at(self.options, path)
+                    .member_patch::<Value, Value>(before.as_ref(), after.as_ref())
+                    .map(|patch| serde_json::to_string(&patch))
+                    .transpose()
+                    .map(Step::Done)
+            }
+        }
+    }
+}
+
+fn is_object(value: &RawValue) -> bool {
+    value.get().trim_start().starts_with('{')
+}
+
+fn parse<'a, T: serde::Deserialize<'a>>(value: &'a RawValue) -> Result<T, serde_json::Error> {
+    serde_json::from_str(value.get())
+}
+
+/**
+ * Text of an object with the given members, whose values are JSON text.
+ */
+fn object(members: BTreeMap<String, String>) -> Result<String, serde_json::Error> {
+    let mut text = String::from("{");
+    for (index, (key, value)) in members.iter().enumerate() {
+        if index > 0 {
+            text.push(',');
+        }
+        text.push_str(&serde_json::to_string(key)?);
+        text.push(':');
+        text.push_str(value);
+    }
+    text.push('}');
+    Ok(text)
+}
@@ -0,0 +1,83 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use serde_json::{Map, Value};
+
+use crate::changes::equal;
+use crate::copy::deep_clone;
+use crate::pointer;
+
+/**
+ * A patch rebased onto a new document, see [`rebase`](crate::rebase).
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rebased {
+    /** The rebased patch, `None` if nothing of it is left to apply */
+    pub patch: Option<Value>,
+    /**
+     * JSON Pointers to the values that the patch changes, but that changed in
+     * the new document too. The patch still changes them, overwriting the
+     * concurrent change.
+     */
+    pub conflicts: Vec<String>,
+    /**
+     * JSON Pointers to the values that the patch changes, but whose parent is
+     * no longer an object in the new document. They're left out of the patch.
+     */
+    pub dropped: Vec<String>,
+}
+
+pub(crate) fn rebase(patch: &Value, old_base: &Value, new_base: &Value) -> Rebased {
+    let mut rebased = Rebased {
+        patch: None,
+        conflicts: Vec::new(),
+        dropped: Vec::new(),
+    };
+    let mut members = Map::new();
+
+    for change in crate::changes(old_base, patch) {
+        let current = new_base.pointer(&change.pointer);
+        if option_equal(current, change.after) {
+            continue;
+        }
+        let parent = change.pointer.rsplit_once('/').map(|(parent, _)| parent);
+        if parent.is_some_and(|parent| !new_base.pointer(parent).is_some_and(Value::is_object)) {
+            rebased.dropped.push(change.pointer);
+            continue;
+        }
+        if !option_equal(current, change.before) {
+            rebased.conflicts.push(change.pointer.clone());
+        }
+        let value = change.after.map_or(Value::Null, deep_clone);
+
+        // A change to the whole document, which is the only one then
+        if parent.is_none() {
+            rebased.patch = Some(value);
+            return rebased;
+        }
+
+        // Parents are objects in both documents, so they're merged
+        let mut tokens: Vec<_> = pointer::tokens(&change.pointer).collect();
+        // The .unwrap()s are safe because the pointer has a parent, and
+        // this only creates objects
+        let key = tokens.pop().unwrap();
+        let object = tokens.into_iter().fold(&mut members, |object, token| {
+            object
+                .entry(token)
+                .or_insert_with(|| Value::Object(Map::new()))
+                .as_object_mut()
+                .unwrap()
+        });
+        object.insert(key.into_owned(), value);
+    }
+
+    rebased.patch = (!members.is_empty()).then_some(Value::Object(members));
+    rebased
+}
+
+fn option_equal(a: Option<&Value>, b: Option<&Value>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => equal(a, b),
+        (a, b) => a.is_none() && b.is_none(),
+    }
+}
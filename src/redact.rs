@@ -0,0 +1,68 @@
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use serde_json::Value;
+
+use crate::pointer;
+
+/**
+ * What to do with a value of a patch, see
+ * [`generate_redacted`](crate::generate_redacted).
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Redaction {
+    /** Keep the value, and then look at its members */
+    Keep,
+    /** Put another value in its place, e.g. `"***"` */
+    Replace(Value),
+    /** Leave it out of the patch, so that the target keeps its value */
+    Drop,
+}
+
+pub(crate) fn redact(
+    patch: Option<Value>,
+    mut redact: impl FnMut(&str, &Value) -> Redaction,
+) -> Option<Value> {
+    let mut patch = patch?;
+    match redact("", &patch) {
+        Redaction::Keep => {}
+        Redaction::Replace(value) => return Some(value),
+        Redaction::Drop => return None,
+    }
+
+    // Objects whose members are left to look at, with their pointers
+    let mut stack = vec![(String::new(), &mut patch)];
+    while let Some((path, value)) = stack.pop() {
+        let Value::Object(members) = value else {
+            continue;
+        };
+        // Pointers of the members to look into, in the order of the object
+        let mut kept = Vec::with_capacity(members.len());
+        let mut dropped = Vec::new();
+        for (key, value) in members.iter_mut() {
+            if value.is_null() {
+                kept.push(None);
+                continue;
+            }
+            let pointer = [path.as_str(), "/", &pointer::escape(key)].concat();
+            match redact(&pointer, value) {
+                Redaction::Keep => kept.push(Some(pointer)),
+                Redaction::Replace(replacement) => {
+                    *value = replacement;
+                    kept.push(None);
+                }
+                Redaction::Drop => dropped.push(key.clone()),
+            }
+        }
+        if !dropped.is_empty() {
+            members.retain(|key, _| !dropped.contains(key));
+        }
+        stack.extend(
+            kept.into_iter()
+                .zip(members.values_mut())
+                .filter_map(|(pointer, value)| Some((pointer?, value))),
+        );
+    }
+    Some(patch)
+}
@@ -0,0 +1,103 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use serde_json::Value;
+
+/**
+ * A RedisJSON command of the translation of a merge patch, see
+ * [`to_redis_json_commands`](crate::to_redis_json_commands).
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RedisJsonCommand {
+    /**
+     * `JSON.SET` the value, as JSON text, at the JSONPath, only if there's
+     * none yet with `NX`
+     */
+    Set {
+        path: String,
+        value: String,
+        if_missing: bool,
+    },
+    /** `JSON.DEL` the value at the JSONPath */
+    Del { path: String },
+}
+
+impl RedisJsonCommand {
+    /**
+     * The command and the arguments to send for the document at `key`, e.g.
+     * `["JSON.SET", key, "$.a", "1"]`.
+     */
+    pub fn args<'a>(&'a self, key: &'a str) -> Vec<&'a str> {
+        match self {
+            RedisJsonCommand::Set {
+                path,
+                value,
+                if_missing,
+            } => {
+                let mut args = vec!["JSON.SET", key, path, value];
+                if *if_missing {
+                    args.push("NX");
+                }
+                args
+            }
+            RedisJsonCommand::Del { path } => vec!["JSON.DEL", key, path],
+        }
+    }
+}
+
+pub(crate) fn to_redis_json_commands(patch: &Value) -> Vec<RedisJsonCommand> {
+    let Value::Object(members) = patch else {
+        return vec![set("$".into(), patch)];
+    };
+
+    // Objects are merged into the ones at their paths, created if missing
+    let mut commands = vec![RedisJsonCommand::Set {
+        path: "$".into(),
+        value: "{}".into(),
+        if_missing: true,
+    }];
+    let mut stack = vec![(String::from("$"), members)];
+    while let Some((parent, members)) = stack.pop() {
+        let mut objects = Vec::new();
+        for (key, value) in members {
+            let path = path(&parent, key);
+            match value {
+                Value::Null => commands.push(RedisJsonCommand::Del { path }),
+                Value::Object(members) if !members.is_empty() => {
+                    commands.push(RedisJsonCommand::Set {
+                        path: path.clone(),
+                        value: "{}".into(),
+                        if_missing: true,
+                    });
+                    objects.push((path, members));
+                }
+                value => commands.push(set(path, value)),
+            }
+        }
+        stack.extend(objects.into_iter().rev());
+    }
+    commands
+}
+
+fn set(path: String, value: &Value) -> RedisJsonCommand {
+    RedisJsonCommand::Set {
+        path,
+        value: value.to_string(),
+        if_missing: false,
+    }
+}
+
+/**
+ * The JSONPath of the member `key` of the object at `parent`: `.key` for
+ * identifiers, `["key"]` with the key as a JSON string otherwise.
+ */
+fn path(parent: &str, key: &str) -> String {
+    let identifier = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if identifier {
+        [parent, ".", key].concat()
+    } else {
+        [parent, "[", &Value::from(key).to_string(), "]"].concat()
+    }
+}
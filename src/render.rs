@@ -0,0 +1,384 @@
+/*!
+ * Textual representations of the changes that a patch makes to a document.
+ */
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use serde_json::{Map, Value};
+
+use crate::{pointer, Change};
+
+/**
+ * Renders the changes that `patch` makes to `before` like a unified diff: a
+ * `@@ pointer @@` header for each changed value, followed by the indented
+ * JSON of the value before the change prefixed with `-` and after it with `+`.
+ * The whole document is `(root)`.
+ *
+ * ```text
+ * @@ /settings/timeout @@
+ * -30
+ * +60
+ * @@ /features/beta @@
+ * -true
+ * ```
+ */
+pub fn unified(before: &Value, patch: &Value) -> String {
+    let mut text = String::new();
+    for Change {
+        pointer,
+        before,
+        after,
+    } in crate::changes(before, patch)
+    {
+        text.push_str("@@ ");
+        text.push_str(display_pointer(&pointer));
+        text.push_str(" @@\n");
+        for (prefix, value) in [('-', before), ('+', after)] {
+            if let Some(value) = value {
+                lines(&mut text, prefix, value);
+            }
+        }
+    }
+    text
+}
+
+/**
+ * [`unified`] for the patch from `before` to `after`, empty if they're equal.
+ */
+pub fn unified_diff(before: &Value, after: &Value) -> String {
+    crate::generate(before, after)
+        .map(|patch| unified(before, &patch))
+        .unwrap_or_default()
+}
+
+/**
+ * Renders the changes that `patch` makes to `before` as a Markdown list, for
+ * changelogs and notifications. Paths are written as JSON Pointers, so that
+ * keys with dots in them stay unambiguous, in code spans fenced with more
+ * backticks than they contain, and values as compact JSON.
+ *
+ * ```markdown
+ * - `/settings/timeout`: 30 → 60
+ * - removed `/features/beta`
+ * - added `/features/gamma`: true
+ * ```
+ */
+pub fn markdown(before: &Value, patch: &Value) -> String {
+    let mut text = String::new();
+    for change in crate::changes(before, patch) {
+        let path = code(display_pointer(&change.pointer));
+        let line = match (change.before, change.after) {
+            (Some(before), Some(after)) => format!("- {path}: {before} → {after}\n"),
+            (None, Some(after)) => format!("- added {path}: {after}\n"),
+            (_, None) => format!("- removed {path}\n"),
+        };
+        text.push_str(&line);
+    }
+    text
+}
+
+/**
+ * [`markdown`] for the patch from `before` to `after`, empty if they're equal.
+ */
+pub fn markdown_diff(before: &Value, after: &Value) -> String {
+    crate::generate(before, after)
+        .map(|patch| markdown(before, &patch))
+        .unwrap_or_default()
+}
+
+/**
+ * Renders the changes that `patch` makes to `before` as a plan of the changes
+ * to apply, like Terraform's: a line for each changed path, with its keys
+ * separated by dots, and values as compact JSON, followed by a summary.
+ *
+ * ```text
+ * ~ spec.replicas: 2 -> 3
+ * - metadata.labels.debug
+ * + spec.tolerations
+ *
+ * Plan: 1 to add, 1 to change, 1 to remove.
+ * ```
+ *
+ * See [`plan_json`] for a machine-readable version.
+ */
+pub fn plan(before: &Value, patch: &Value) -> String {
+    let mut text = String::new();
+    let mut counts = [0; 3];
+    for change in crate::changes(before, patch) {
+        let path = dotted(&change.pointer);
+        let (line, action) = match (change.before, change.after) {
+            (Some(before), Some(after)) => (format!("~ {path}: {before} -> {after}\n"), 1),
+            (None, Some(_)) => (format!("+ {path}\n"), 0),
+            (_, None) => (format!("- {path}\n"), 2),
+        };
+        text.push_str(&line);
+        counts[action] += 1;
+    }
+    if counts == [0; 3] {
+        text.push_str("No changes.\n");
+    } else {
+        let [add, change, remove] = counts;
+        text.push_str(&format!(
+            "\nPlan: {add} to add, {change} to change, {remove} to remove.\n"
+        ));
+    }
+    text
+}
+
+/**
+ * [`plan`] for the patch from `before` to `after`.
+ */
+pub fn plan_diff(before: &Value, after: &Value) -> String {
+    match crate::generate(before, after) {
+        Some(patch) => plan(before, &patch),
+        None => String::from("No changes.\n"),
+    }
+}
+
+/**
+ * [`plan`] as JSON, for tools: the changes with their `action`, `"add"`,
+ * `"change"` or `"remove"`, their dotted `path` and JSON `pointer`, and their
+ * values `before` and `after` where there are any, and the counts of the
+ * actions.
+ *
+ * ```json
+ * {
+ *   "changes": [
+ *     {"action": "change", "path": "spec.replicas", "pointer": "/spec/replicas", "before": 2, "after": 3}
+ *   ],
+ *   "summary": {"add": 0, "change": 1, "remove": 0}
+ * }
+ * ```
+ */
+pub fn plan_json(before: &Value, patch: &Value) -> Value {
+    let mut summary = [("add", 0), ("change", 0), ("remove", 0)];
+    let changes: Vec<Value> = crate::changes(before, patch)
+        .into_iter()
+        .map(|change| {
+            let action = match (change.before, change.after) {
+                (Some(_), Some(_)) => 1,
+                (None, Some(_)) => 0,
+                (_, None) => 2,
+            };
+            summary[action].1 += 1;
+            let mut entry = Map::new();
+            entry.insert("action".into(), summary[action].0.into());
+            entry.insert("path".into(), dotted(&change.pointer).into());
+            entry.insert("pointer".into(), change.pointer.into());
+            for (key, value) in [("before", change.before), ("after", change.after)] {
+                if let Some(value) = value {
+                    entry.insert(key.into(), value.clone());
+                }
+            }
+            Value::Object(entry)
+        })
+        .collect();
+    let summary = summary
+        .into_iter()
+        .map(|(action, count)| (action.into(), count.into()))
+        .collect();
+    let mut plan = Map::new();
+    plan.insert("changes".into(), Value::Array(changes));
+    plan.insert("summary".into(), Value::Object(summary));
+    Value::Object(plan)
+}
+
+/**
+ * The keys of `pointer` separated by dots.
+ */
+fn dotted(pointer: &str) -> String {
+    match pointer {
+        "" => String::from("(root)"),
+        pointer => pointer::tokens(pointer).collect::<Vec<_>>().join("."),
+    }
+}
+
+/**
+ * `pointer` for headers, where the empty pointer would read as nothing.
+ */
+fn display_pointer(pointer: &str) -> &str {
+    match pointer {
+        "" => "(root)",
+        pointer => pointer,
+    }
+}
+
+/**
+ * `text` as a Markdown code span, fenced with one backtick more than its
+ * longest run of them, and padded where it starts or ends with one.
+ */
+fn code(text: &str) -> String {
+    let longest = text
+        .split(|char| char != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest + 1);
+    let padding = match text.starts_with('`') || text.ends_with('`') {
+        true => " ",
+        false => "",
+    };
+    format!("{fence}{padding}{text}{padding}{fence}")
+}
+
+fn lines(text: &mut String, prefix: char, value: &Value) {
+    // The .unwrap() is safe because values with string keys always serialize
+    let json = serde_json::to_string_pretty(value).unwrap();
+    for line in json.lines() {
+        text.push(prefix);
+        text.push_str(line);
+        text.push('\n');
+    }
+}
+
+/**
+ * Renders the changes that `patch` makes to `before` as a standalone HTML
+ * fragment: a tree of the changed members, where modified objects can be
+ * collapsed and added, removed and modified values are styled by the
+ * `jmp-added`, `jmp-removed` and `jmp-modified` classes of their list items.
+ * The fragment includes a `<style>` element with default styles for these.
+ */
+#[cfg(feature = "html")]
+pub fn html(before: &Value, patch: &Value) -> String {
+    html::render(before, patch)
+}
+
+/**
+ * [`html`] for the patch from `before` to `after`.
+ */
+#[cfg(feature = "html")]
+pub fn html_diff(before: &Value, after: &Value) -> String {
+    match crate::generate(before, after) {
+        Some(patch) => html::render(before, &patch),
+        // A fragment without changes
+        None => html::render(&Value::Null, &Value::Null),
+    }
+}
+
+#[cfg(feature = "html")]
+mod html {
+    use alloc::string::{String, ToString};
+    use alloc::vec;
+
+    use serde_json::Value;
+
+    use crate::changes::equal;
+
+    const STYLE: &str = "<style>\
+        .jmp-diff ul{list-style:none;margin:0;padding-left:1.5em;font-family:monospace}\
+        .jmp-diff summary{cursor:pointer}\
+        .jmp-added{color:#1a7f37}\
+        .jmp-removed{color:#cf222e}\
+        .jmp-modified{color:#9a6700}\
+        .jmp-diff del{background:#ffebe9}\
+        .jmp-diff ins{background:#dafbe1;text-decoration:none}\
+        </style>";
+
+    pub(super) fn render(before: &Value, patch: &Value) -> String {
+        let mut html = String::from("<div class=\"jmp-diff\">");
+        html.push_str(STYLE);
+
+        let (Value::Object(before), Value::Object(patch)) = (before, patch) else {
+            let after = applied(patch);
+            if !equal(before, &after) {
+                html.push_str("<ul><li class=\"jmp-modified\">");
+                replacement(&mut html, before, &after);
+                html.push_str("</li></ul>");
+            }
+            html.push_str("</div>");
+            return html;
+        };
+
+        html.push_str("<ul>");
+        let mut stack = vec![(before, patch.iter())];
+        while let Some((before, members)) = stack.last_mut() {
+            let before = *before;
+            let Some((key, patch)) = members.next() else {
+                stack.pop();
+                html.push_str(match stack.is_empty() {
+                    true => "</ul>",
+                    false => "</ul></details></li>",
+                });
+                continue;
+            };
+
+            match (before.get(key), patch) {
+                (None, Value::Null) => {}
+                (Some(before), _) if equal(before, patch) => {}
+                (None, patch) => {
+                    item(&mut html, "jmp-added", key);
+                    value(&mut html, "ins", &applied(patch));
+                    html.push_str("</li>");
+                }
+                (Some(before), Value::Null) => {
+                    item(&mut html, "jmp-removed", key);
+                    value(&mut html, "del", before);
+                    html.push_str("</li>");
+                }
+                (Some(Value::Object(before)), Value::Object(patch)) => {
+                    html.push_str("<li class=\"jmp-modified\"><details open><summary>");
+                    escape(&mut html, key);
+                    html.push_str("</summary><ul>");
+                    stack.push((before, patch.iter()));
+                }
+                (Some(before), after) => {
+                    item(&mut html, "jmp-modified", key);
+                    replacement(&mut html, before, &applied(after));
+                    html.push_str("</li>");
+                }
+            }
+        }
+        html.push_str("</div>");
+        html
+    }
+
+    /**
+     * The value that `patch` replaces a member that isn't an object with,
+     * which is the patch without its `null` members.
+     */
+    fn applied(patch: &Value) -> Value {
+        let mut after = Value::Null;
+        crate::apply(&mut after, patch);
+        after
+    }
+
+    /** Opens the list item of a member that isn't expanded */
+    fn item(html: &mut String, class: &str, key: &str) {
+        html.push_str("<li class=\"");
+        html.push_str(class);
+        html.push_str("\">");
+        escape(html, key);
+        html.push_str(": ");
+    }
+
+    fn replacement(html: &mut String, before: &Value, after: &Value) {
+        value(html, "del", before);
+        html.push(' ');
+        value(html, "ins", after);
+    }
+
+    fn value(html: &mut String, tag: &str, value: &Value) {
+        html.push('<');
+        html.push_str(tag);
+        html.push('>');
+        escape(html, &value.to_string());
+        html.push_str("</");
+        html.push_str(tag);
+        html.push('>');
+    }
+
+    fn escape(html: &mut String, text: &str) {
+        for char in text.chars() {
+            match char {
+                '&' => html.push_str("&amp;"),
+                '<' => html.push_str("&lt;"),
+                '>' => html.push_str("&gt;"),
+                '"' => html.push_str("&quot;"),
+                '\'' => html.push_str("&#39;"),
+                char => html.push(char),
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+/*!
+ * Sending generated patches as HTTP `PATCH` requests with
+ * [reqwest](https://docs.rs/reqwest).
+ */
+
+use core::fmt;
+
+use ::reqwest::header::CONTENT_TYPE;
+use ::reqwest::{Client, IntoUrl, Response};
+use serde_json::Value;
+
+use crate::MERGE_PATCH_MEDIA_TYPE;
+
+/**
+ * Sends the patch from `before` to `after` to `url` as a `PATCH` request with
+ * the `application/merge-patch+json` content type, or nothing if they're
+ * equal, returning `None` then.
+ *
+ * Responses with error statuses are returned like others, as by reqwest.
+ */
+pub async fn patch(
+    client: &Client,
+    url: impl IntoUrl,
+    before: &Value,
+    after: &Value,
+) -> Result<Option<Response>, ::reqwest::Error> {
+    let Some(patch) = crate::generate(before, after) else {
+        return Ok(None);
+    };
+    // The .unwrap() is safe because values with string keys always serialize
+    let body = serde_json::to_vec(&patch).unwrap();
+    let request = client
+        .patch(url)
+        .header(CONTENT_TYPE, MERGE_PATCH_MEDIA_TYPE)
+        .body(body);
+    request.send().await.map(Some)
+}
+
+/**
+ * [`patch`], then replaces `before` with the document in the response, or
+ * with `after` if the response has no body. Returns whether a request was
+ * sent, and fails for responses with error statuses.
+ */
+pub async fn patch_and_refresh(
+    client: &Client,
+    url: impl IntoUrl,
+    before: &mut Value,
+    after: &Value,
+) -> Result<bool, Error> {
+    let Some(response) = patch(client, url, before, after).await? else {
+        return Ok(false);
+    };
+    let body = response.error_for_status()?.bytes().await?;
+    *before = match body.is_empty() {
+        true => after.clone(),
+        false => serde_json::from_slice(&body).map_err(Error::InvalidJson)?,
+    };
+    Ok(true)
+}
+
+/**
+ * Errors of [`patch_and_refresh`].
+ */
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /** The request failed or the response has an error status */
+    Request(::reqwest::Error),
+    /** The body of the response isn't JSON */
+    InvalidJson(serde_json::Error),
+}
+
+impl From<::reqwest::Error> for Error {
+    fn from(error: ::reqwest::Error) -> Self {
+        Error::Request(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Request(error) => write!(f, "{error}"),
+            Error::InvalidJson(error) => write!(f, "failed to parse the response: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Request(error) => Some(error),
+            Error::InvalidJson(error) => Some(error),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use serde_json::Value;
+
+use crate::copy::deep_clone;
+use crate::Error;
+
+/**
+ * A way in which a patched document doesn't match the JSON Schema, see
+ * [`apply_validated`](crate::apply_validated).
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /** JSON Pointer (RFC 6901) to the offending value of the document */
+    pub pointer: String,
+    pub message: String,
+}
+
+pub(crate) fn apply_validated(
+    document: &mut Value,
+    patch: &Value,
+    schema: &jsonschema::Validator,
+) -> Result<(), Error> {
+    let mut patched = deep_clone(document);
+    crate::apply(&mut patched, patch);
+
+    let violations: Vec<_> = schema
+        .iter_errors(&patched)
+        .map(|error| SchemaViolation {
+            pointer: error.instance_path().as_str().to_string(),
+            message: error.to_string(),
+        })
+        .collect();
+    if !violations.is_empty() {
+        return Err(Error::SchemaViolation { violations });
+    }
+
+    *document = patched;
+    Ok(())
+}
@@ -0,0 +1,183 @@
+/*!
+ * Patch generation for [simd-json](https://docs.rs/simd-json) values, without
+ * converting them to [`serde_json::Value`] first.
+ */
+
+use ::simd_json::prelude::*;
+use ::simd_json::{borrowed, owned, BorrowedValue, OwnedValue, StaticNode};
+
+use crate::diff::Diff;
+use crate::value::{JsonLike, Kind};
+use crate::Options;
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`](crate::generate),
+ * for [`OwnedValue`]s.
+ */
+pub fn generate(before: &OwnedValue, after: &OwnedValue) -> Option<OwnedValue> {
+    generate_with(before, after, &Options::default())
+}
+
+/**
+ * [`generate`] using the given [`Options`].
+ */
+pub fn generate_with(
+    before: &OwnedValue,
+    after: &OwnedValue,
+    options: &Options,
+) -> Option<OwnedValue> {
+    options.finish(Diff::new(options).generate(before, after))
+}
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`](crate::generate),
+ * for [`BorrowedValue`]s.
+ */
+pub fn generate_borrowed<'v>(
+    before: &BorrowedValue<'v>,
+    after: &BorrowedValue<'v>,
+) -> Option<BorrowedValue<'v>> {
+    generate_borrowed_with(before, after, &Options::default())
+}
+
+/**
+ * [`generate_borrowed`] using the given [`Options`].
+ */
+pub fn generate_borrowed_with<'v>(
+    before: &BorrowedValue<'v>,
+    after: &BorrowedValue<'v>,
+    options: &Options,
+) -> Option<BorrowedValue<'v>> {
+    options.finish(Diff::new(options).generate(before, after))
+}
+
+impl JsonLike for OwnedValue {
+    type Object = owned::Object;
+    type Members<'a> = std::iter::Map<
+        <&'a owned::Object as IntoIterator>::IntoIter,
+        fn((&'a String, &'a OwnedValue)) -> (&'a str, &'a OwnedValue),
+    >;
+
+    fn kind(&self) -> Kind<'_, Self> {
+        match self {
+            OwnedValue::Static(StaticNode::Null) => Kind::Null,
+            OwnedValue::Static(StaticNode::Bool(_)) => Kind::Bool,
+            OwnedValue::Static(_) => Kind::Number,
+            OwnedValue::String(string) => Kind::String(string),
+            OwnedValue::Array(array) => Kind::Array(array),
+            OwnedValue::Object(object) => Kind::Object(&**object),
+        }
+    }
+
+    fn members(object: &owned::Object) -> Self::Members<'_> {
+        object.iter().map(|(key, value)| (key.as_str(), value))
+    }
+
+    fn get<'a>(object: &'a owned::Object, key: &str) -> Option<&'a Self> {
+        object.get(key)
+    }
+
+    fn len(object: &owned::Object) -> usize {
+        object.len()
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        ValueAsScalar::as_i64(self)
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        ValueAsScalar::as_u64(self)
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        self.cast_f64()
+    }
+
+    fn null() -> Self {
+        OwnedValue::Static(StaticNode::Null)
+    }
+
+    fn array(values: Vec<Self>) -> Self {
+        OwnedValue::Array(Box::new(values))
+    }
+
+    fn new_object() -> owned::Object {
+        owned::Object::default()
+    }
+
+    fn insert(object: &mut owned::Object, key: &str, value: Self) {
+        object.insert(key.to_owned(), value);
+    }
+
+    fn object(object: owned::Object) -> Self {
+        OwnedValue::Object(Box::new(object))
+    }
+}
+
+impl<'v> JsonLike for BorrowedValue<'v> {
+    type Object = borrowed::Object<'v>;
+    type Members<'a>
+        = std::iter::Map<
+        <&'a borrowed::Object<'v> as IntoIterator>::IntoIter,
+        fn(
+            (&'a ::simd_json::cow::Cow<'v, str>, &'a BorrowedValue<'v>),
+        ) -> (&'a str, &'a BorrowedValue<'v>),
+    >
+    where
+        Self: 'a;
+
+    fn kind(&self) -> Kind<'_, Self> {
+        match self {
+            BorrowedValue::Static(StaticNode::Null) => Kind::Null,
+            BorrowedValue::Static(StaticNode::Bool(_)) => Kind::Bool,
+            BorrowedValue::Static(_) => Kind::Number,
+            BorrowedValue::String(string) => Kind::String(string),
+            BorrowedValue::Array(array) => Kind::Array(array),
+            BorrowedValue::Object(object) => Kind::Object(&**object),
+        }
+    }
+
+    fn members<'a>(object: &'a borrowed::Object<'v>) -> Self::Members<'a> {
+        object.iter().map(|(key, value)| (key.as_ref(), value))
+    }
+
+    fn get<'a>(object: &'a borrowed::Object<'v>, key: &str) -> Option<&'a Self> {
+        object.get(key)
+    }
+
+    fn len(object: &borrowed::Object<'v>) -> usize {
+        object.len()
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        ValueAsScalar::as_i64(self)
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        ValueAsScalar::as_u64(self)
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        self.cast_f64()
+    }
+
+    fn null() -> Self {
+        BorrowedValue::Static(StaticNode::Null)
+    }
+
+    fn array(values: Vec<Self>) -> Self {
+        BorrowedValue::Array(Box::new(values))
+    }
+
+    fn new_object() -> borrowed::Object<'v> {
+        borrowed::Object::default()
+    }
+
+    fn insert(object: &mut borrowed::Object<'v>, key: &str, value: Self) {
+        object.insert(key.to_owned().into(), value);
+    }
+
+    fn object(object: borrowed::Object<'v>) -> Self {
+        BorrowedValue::Object(Box::new(object))
+    }
+}
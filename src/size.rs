@@ -0,0 +1,106 @@
+use alloc::borrow::Cow;
+use alloc::vec;
+use core::fmt::{self, Write};
+
+use serde_json::Value;
+
+use crate::diff::{Diff, Output};
+use crate::{EmptyPatchBehavior, Options};
+
+/**
+ * The size of a patch as compact JSON, built in place of the patch.
+ */
+struct Size(usize);
+
+/** Members of an object patch: how many there are, and the size of each but the commas */
+struct Members {
+    count: usize,
+    size: usize,
+}
+
+impl<'a> Output<'a, Value> for Size {
+    type Members = Members;
+
+    fn members() -> Members {
+        Members { count: 0, size: 0 }
+    }
+
+    fn insert(members: &mut Members, key: &'a str, patch: Self) {
+        members.count += 1;
+        members.size += string_size(key) + 1 + patch.0;
+    }
+
+    fn is_empty(members: &Members) -> bool {
+        members.count == 0
+    }
+
+    fn merge(members: Members) -> Self {
+        Size(2 + members.size + members.count.saturating_sub(1))
+    }
+
+    fn replace(value: Cow<'a, Value>) -> Self {
+        Size(serialized_size(&value))
+    }
+}
+
+pub(crate) fn estimate_patch_size(before: &Value, after: &Value, options: &Options) -> usize {
+    match Diff::new(options).generate::<Value, Size>(before, after) {
+        Some(size) => size.0,
+        None if options.empty_patch == EmptyPatchBehavior::EmptyObject => 2,
+        None => 0,
+    }
+}
+
+/**
+ * Length of the compact JSON of `value`, without allocating it.
+ */
+pub(crate) fn serialized_size(value: &Value) -> usize {
+    struct Counter(usize);
+
+    impl Write for Counter {
+        fn write_str(&mut self, text: &str) -> fmt::Result {
+            self.0 += text.len();
+            Ok(())
+        }
+    }
+
+    let mut size = 0;
+    let mut stack = vec![value];
+    while let Some(value) = stack.pop() {
+        size += match value {
+            Value::String(string) => string_size(string),
+            // Brackets and commas, with the members added as they're popped
+            Value::Array(values) => {
+                stack.extend(values);
+                2 + values.len().saturating_sub(1)
+            }
+            Value::Object(members) => {
+                stack.extend(members.values());
+                let keys: usize = members.keys().map(|key| string_size(key) + 1).sum();
+                2 + members.len().saturating_sub(1) + keys
+            }
+            scalar => {
+                let mut counter = Counter(0);
+                // The Display of values only fails if the writer does
+                let _ = write!(counter, "{scalar}");
+                counter.0
+            }
+        };
+    }
+    size
+}
+
+/**
+ * Length of `string` as a JSON string, with serde_json's escapes.
+ */
+fn string_size(string: &str) -> usize {
+    let escapes: usize = string
+        .bytes()
+        .map(|byte| match byte {
+            b'"' | b'\\' | b'\x08' | b'\x0c' | b'\n' | b'\r' | b'\t' => 1,
+            0..=0x1f => 5,
+            _ => 0,
+        })
+        .sum();
+    string.len() + escapes + 2
+}
@@ -0,0 +1,60 @@
+use alloc::vec;
+
+use serde_json::{Map, Value};
+
+use crate::copy::deep_clone;
+
+pub(crate) fn squash(patches: &[Value]) -> Option<Value> {
+    let (first, rest) = patches.split_first()?;
+    let mut squashed = deep_clone(first);
+    for patch in rest {
+        if !compose(&mut squashed, patch) {
+            return None;
+        }
+    }
+    Some(squashed)
+}
+
+/**
+ * Merges `patch` into `squashed`, so that applying it has the effect of
+ * applying both, or returns `false` if it can't have.
+ */
+fn compose(squashed: &mut Value, patch: &Value) -> bool {
+    let mut stack = vec![(squashed, patch)];
+
+    while let Some((target, patch)) = stack.pop() {
+        let Value::Object(patch) = patch else {
+            *target = deep_clone(patch);
+            continue;
+        };
+        // An object patch after anything else would be merged into the value
+        // before both patches, which the earlier patch replaced
+        let Value::Object(target) = target else {
+            return false;
+        };
+
+        // Objects are merged like in `apply`, into empty ones for members the
+        // earlier patch doesn't touch
+        for (key, patch) in patch {
+            match patch {
+                Value::Object(_) => {
+                    let member = target
+                        .entry(key)
+                        .or_insert_with(|| Value::Object(Map::new()));
+                    if !member.is_object() {
+                        return false;
+                    }
+                }
+                _ => {
+                    target.insert(key.clone(), deep_clone(patch));
+                }
+            }
+        }
+        for (key, target) in target.iter_mut() {
+            if let Some(patch @ Value::Object(_)) = patch.get(key) {
+                stack.push((target, patch));
+            }
+        }
+    }
+    true
+}
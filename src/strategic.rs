@@ -0,0 +1,421 @@
+/*!
+ * Kubernetes strategic merge patches, like `kubectl patch --type=strategic`
+ * takes: JSON Merge Patches, except that lists whose elements are objects
+ * identified by a merge key, like the `name` of containers, are patched
+ * element by element instead of replaced.
+ *
+ * The patch of such a list lists the changed elements with their merge key
+ * and only their changed members, the added elements as a whole, and the
+ * removed ones as `{"<merge key>": ..., "$patch": "delete"}`. If the order of
+ * the elements changes, the object holding the list also gets a
+ * `$setElementOrder/<member>` list of the merge keys in their new order.
+ */
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use serde_json::{json, Map, Value};
+
+use crate::diff::Diff;
+use crate::filter::{self, PathPattern, Visibility};
+use crate::pointer::Segment;
+use crate::Options;
+
+/**
+ * Options of [`generate`]: the merge keys of the lists that are patched
+ * element by element, and the [`Options`] the other values are compared with.
+ */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StrategicOptions {
+    options: Options,
+    merge_keys: Vec<(PathPattern, String)>,
+}
+
+impl StrategicOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * The merge keys of the lists in the built-in Kubernetes workload,
+     * service and metadata types, like containers by `name` and volume mounts
+     * by `mountPath`.
+     */
+    pub fn kubernetes() -> Self {
+        [
+            ("/**/containers", "name"),
+            ("/**/initContainers", "name"),
+            ("/**/ephemeralContainers", "name"),
+            ("/**/env", "name"),
+            ("/**/containers/*/ports", "containerPort"),
+            ("/**/initContainers/*/ports", "containerPort"),
+            ("/**/volumeMounts", "mountPath"),
+            ("/**/volumeDevices", "devicePath"),
+            ("/**/volumes", "name"),
+            ("/**/imagePullSecrets", "name"),
+            ("/**/hostAliases", "ip"),
+            ("/spec/ports", "port"),
+            ("/metadata/ownerReferences", "uid"),
+            ("/status/conditions", "type"),
+        ]
+        .into_iter()
+        .fold(Self::default(), |options, (pattern, key)| {
+            options.merge_key(pattern, key)
+        })
+    }
+
+    /**
+     * Compares values with `options`, whose path filters also apply to the
+     * elements of merged lists by their index in `after`. With
+     * [`Options::ignore_removals`], elements missing from merged lists in
+     * `after` are kept too.
+     */
+    pub fn options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    // Line comments, as the example patterns would open nested block comments
+    /// Patches the lists at `pattern` element by element, identifying their
+    /// elements by their `key` member. The pattern uses the syntax of
+    /// [`Options::ignore_path`], e.g. `/spec/template/spec/containers` or
+    /// `/**/containers`.
+    ///
+    /// Lists whose elements aren't all objects with distinct values of `key`
+    /// are replaced instead, with a `{"$patch": "replace"}` first element.
+    pub fn merge_key(mut self, pattern: &str, key: &str) -> Self {
+        self.merge_keys
+            .push((PathPattern::parse(pattern), String::from(key)));
+        self
+    }
+}
+
+/**
+ * Generates a strategic merge patch from `before` to `after`, or `None` if
+ * they're equal.
+ */
+pub fn generate(before: &Value, after: &Value, options: &StrategicOptions) -> Option<Value> {
+    Strategic {
+        options,
+        path: Vec::new(),
+    }
+    .generate(before, after)
+}
+
+struct Strategic<'a> {
+    options: &'a StrategicOptions,
+    path: Vec<Segment<'a>>,
+}
+
+/**
+ * Outcome of comparing a pair of values, like in the generation of merge
+ * patches, except that lists with a merge key are descended into too.
+ */
+enum Step<'a> {
+    Done(Option<Value>),
+    Object(&'a Map<String, Value>, &'a Map<String, Value>),
+    List(&'a str, &'a [Value], &'a [Value]),
+}
+
+/**
+ * Where the patch of a frame goes in the patch of its parent.
+ */
+#[derive(Clone, Copy)]
+enum Slot<'a> {
+    Root,
+    /** Under the key of the object member */
+    Member(&'a str),
+    /** Into the list, with the merge key and its value added */
+    Element(&'a str, &'a Value),
+    /** Into the list as it is */
+    Item,
+}
+
+enum Entry<'a> {
+    Changed {
+        index: usize,
+        key: &'a Value,
+        before: &'a Value,
+        after: &'a Value,
+    },
+    Added(usize, &'a Value),
+    Removed(&'a Value),
+}
+
+enum Node<'a> {
+    Object {
+        members: vec::IntoIter<(&'a str, Option<&'a Value>, Option<&'a Value>)>,
+        patch: Map<String, Value>,
+    },
+    List {
+        merge_key: &'a str,
+        entries: vec::IntoIter<Entry<'a>>,
+        patch: Vec<Value>,
+        /** `$setElementOrder` of the list, if its order changes */
+        order: Option<Value>,
+    },
+}
+
+struct Frame<'a> {
+    slot: Slot<'a>,
+    /** Length of the path to the object or list */
+    depth: usize,
+    node: Node<'a>,
+}
+
+impl<'a> Strategic<'a> {
+    fn generate(&mut self, before: &'a Value, after: &'a Value) -> Option<Value> {
+        let mut stack = match self.step(Some(before), Some(after), false) {
+            Step::Object(before, after) => vec![self.object(Slot::Root, before, after)],
+            Step::Done(patch) => return patch,
+            Step::List(..) => unreachable!("lists are only merged in objects"),
+        };
+
+        loop {
+            // The .unwrap() calls are safe because the loop returns once the stack is empty
+            let frame = stack.last_mut().unwrap();
+            self.path.truncate(frame.depth);
+
+            let next = match &mut frame.node {
+                Node::Object { members, .. } => members.next().map(|(key, before, after)| {
+                    self.path.push(Segment::Key(key));
+                    (Slot::Member(key), self.step(before, after, true))
+                }),
+                Node::List {
+                    merge_key, entries, ..
+                } => {
+                    let merge_key = *merge_key;
+                    entries.next().map(|entry| match entry {
+                        Entry::Changed {
+                            index,
+                            key,
+                            before,
+                            after,
+                        } => {
+                            self.path.push(Segment::Index(index));
+                            let step = self.step(Some(before), Some(after), false);
+                            (Slot::Element(merge_key, key), step)
+                        }
+                        Entry::Added(index, after) => {
+                            self.path.push(Segment::Index(index));
+                            (Slot::Item, Step::Done(self.leaf(None, Some(after))))
+                        }
+                        Entry::Removed(key) => {
+                            let deletion = json!({ merge_key: key, "$patch": "delete" });
+                            (Slot::Item, Step::Done(Some(deletion)))
+                        }
+                    })
+                }
+            };
+
+            if let Some((slot, step)) = next {
+                match step {
+                    Step::Done(patch) => deliver(stack.last_mut().unwrap(), slot, patch, None),
+                    Step::Object(before, after) => stack.push(self.object(slot, before, after)),
+                    Step::List(merge_key, before, after) => {
+                        stack.push(self.list(slot, merge_key, before, after));
+                    }
+                }
+                continue;
+            }
+
+            let frame = stack.pop().unwrap();
+            let (patch, order) = match frame.node {
+                Node::Object { patch, .. } => {
+                    ((!patch.is_empty()).then_some(Value::Object(patch)), None)
+                }
+                Node::List { patch, order, .. } => {
+                    ((!patch.is_empty()).then_some(Value::Array(patch)), order)
+                }
+            };
+            match stack.last_mut() {
+                Some(parent) => deliver(parent, frame.slot, patch, order),
+                None => {
+                    self.path.truncate(frame.depth);
+                    return patch;
+                }
+            }
+        }
+    }
+
+    fn step(
+        &mut self,
+        before: Option<&'a Value>,
+        after: Option<&'a Value>,
+        member: bool,
+    ) -> Step<'a> {
+        let options = &self.options.options;
+        if filter::visibility(&options.ignore, &options.include, &self.path) == Visibility::Excluded
+        {
+            return Step::Done(None);
+        }
+        match (before, after) {
+            (Some(Value::Object(before)), Some(Value::Object(after))) => {
+                Step::Object(before, after)
+            }
+            (Some(Value::Array(list)), Some(Value::Array(new_list))) if member => {
+                match self.merge_key() {
+                    Some(key) if keyed(list, key) && keyed(new_list, key) => {
+                        Step::List(key, list, new_list)
+                    }
+                    // Without the directive the server would merge the lists
+                    Some(_) => Step::Done(self.leaf(before, after).map(|patch| match patch {
+                        Value::Array(mut items) => {
+                            items.insert(0, json!({"$patch": "replace"}));
+                            Value::Array(items)
+                        }
+                        patch => patch,
+                    })),
+                    None => Step::Done(self.leaf(before, after)),
+                }
+            }
+            _ => Step::Done(self.leaf(before, after)),
+        }
+    }
+
+    /**
+     * Merge patch of values that aren't merged here, honouring the options.
+     */
+    fn leaf(&self, before: Option<&'a Value>, after: Option<&'a Value>) -> Option<Value> {
+        Diff::at(&self.options.options, self.path.clone()).member_patch(before, after)
+    }
+
+    /**
+     * Merge key of the list at the current path.
+     */
+    fn merge_key(&self) -> Option<&'a str> {
+        self.options
+            .merge_keys
+            .iter()
+            .find(|(pattern, _)| pattern.matches(&self.path))
+            .map(|(_, key)| key.as_str())
+    }
+
+    fn object(
+        &self,
+        slot: Slot<'a>,
+        before: &'a Map<String, Value>,
+        after: &'a Map<String, Value>,
+    ) -> Frame<'a> {
+        let mut members: Vec<_> = after
+            .iter()
+            .map(|(key, after)| (key.as_str(), before.get(key), Some(after)))
+            .collect();
+        members.extend(
+            before
+                .iter()
+                .filter(|(key, _)| !after.contains_key(*key))
+                .map(|(key, before)| (key.as_str(), Some(before), None)),
+        );
+        Frame {
+            slot,
+            depth: self.path.len(),
+            node: Node::Object {
+                members: members.into_iter(),
+                patch: Map::new(),
+            },
+        }
+    }
+
+    fn list(
+        &self,
+        slot: Slot<'a>,
+        merge_key: &'a str,
+        before: &'a [Value],
+        after: &'a [Value],
+    ) -> Frame<'a> {
+        let key = |element: &'a Value| &element[merge_key];
+        let find =
+            |list: &'a [Value], value: &Value| list.iter().find(|element| key(element) == value);
+
+        let mut entries = Vec::new();
+        let mut removed = Vec::new();
+        // The server keeps the order of the elements that remain and appends
+        // the added ones, which only needs to be overridden if that's wrong
+        let mut order: Vec<&Value> = Vec::new();
+        for element in before {
+            match find(after, key(element)) {
+                Some(_) => order.push(key(element)),
+                None if self.options.options.ignore_removals => {}
+                None => removed.push(Entry::Removed(key(element))),
+            }
+        }
+        for (index, element) in after.iter().enumerate() {
+            match find(before, key(element)) {
+                Some(before) => entries.push(Entry::Changed {
+                    index,
+                    key: key(element),
+                    before,
+                    after: element,
+                }),
+                None => {
+                    order.push(key(element));
+                    entries.push(Entry::Added(index, element));
+                }
+            }
+        }
+        entries.extend(removed);
+
+        let reordered = after.iter().map(key).ne(order.iter().copied());
+        let order = reordered.then(|| {
+            Value::Array(
+                after
+                    .iter()
+                    .map(|element| json!({ merge_key: key(element) }))
+                    .collect(),
+            )
+        });
+        Frame {
+            slot,
+            depth: self.path.len(),
+            node: Node::List {
+                merge_key,
+                entries: entries.into_iter(),
+                patch: Vec::new(),
+                order,
+            },
+        }
+    }
+}
+
+/**
+ * Adds the patch of a finished frame, or of a value, to its parent.
+ */
+fn deliver(parent: &mut Frame<'_>, slot: Slot<'_>, patch: Option<Value>, order: Option<Value>) {
+    match (&mut parent.node, slot) {
+        (Node::Object { patch: members, .. }, Slot::Member(key)) => {
+            if let Some(patch) = patch {
+                members.insert(String::from(key), patch);
+            }
+            if let Some(order) = order {
+                members.insert(format!("$setElementOrder/{key}"), order);
+            }
+        }
+        (Node::List { patch: items, .. }, Slot::Element(merge_key, key)) => match patch {
+            Some(Value::Object(mut patch)) => {
+                patch.insert(String::from(merge_key), key.clone());
+                items.push(Value::Object(patch));
+            }
+            Some(patch) => items.push(patch),
+            None => {}
+        },
+        (Node::List { patch: items, .. }, Slot::Item) => items.extend(patch),
+        _ => unreachable!("patches go into the kind of node that they're for"),
+    }
+}
+
+/**
+ * Whether the elements of `list` are all objects with distinct values of
+ * their `key` member.
+ */
+fn keyed(list: &[Value], key: &str) -> bool {
+    list.iter().enumerate().all(|(index, element)| {
+        element.get(key).is_some_and(|value| {
+            list[..index]
+                .iter()
+                .all(|other| other.get(key) != Some(value))
+        })
+    })
+}
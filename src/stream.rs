@@ -0,0 +1,643 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+
+use serde::de::Error as _;
+use serde_json::Value;
+
+use crate::diff::Diff;
+use crate::filter::{self, Visibility};
+use crate::pointer::Segment;
+use crate::{EmptyPatchBehavior, Options};
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`](crate::generate),
+ * reading the documents from `before` and `after` and writing the patch to
+ * `writer`. Returns whether a patch was written, where `false` means that the
+ * documents are equal, even if [`Options::empty_patch`] wrote `{}`.
+ *
+ * Objects are read member by member. As long as the members of both documents
+ * come in the same order, only one member of each object needs to be kept in
+ * memory, and objects in both documents are compared without reading them as
+ * a whole. Members whose text is identical in both documents are skipped
+ * without parsing them. Out of order members are kept until the other document
+ * catches up, and values other than objects are read as a whole, as are
+ * documents that aren't objects.
+ *
+ * The members of the patch are written in the order they're found, which may
+ * differ from the order of [`generate`](crate::generate), unless
+ * [`Options::canonical`] is set.
+ */
+pub fn generate_stream(
+    before: impl Read,
+    after: impl Read,
+    writer: impl Write,
+) -> Result<bool, serde_json::Error> {
+    generate_stream_with(before, after, writer, &Options::default())
+}
+
+/**
+ * [`generate_stream`] for documents read from the same kind of source, e.g.
+ * two files or sockets, writing the patch to `out`.
+ */
+pub fn generate_from_readers<R: Read>(
+    before: R,
+    after: R,
+    out: impl Write,
+) -> Result<bool, serde_json::Error> {
+    generate_stream(before, after, out)
+}
+
+/**
+ * [`generate_stream`] using the given [`Options`].
+ */
+pub fn generate_stream_with(
+    before: impl Read,
+    after: impl Read,
+    writer: impl Write,
+    options: &Options,
+) -> Result<bool, serde_json::Error> {
+    let mut before = Reader::new(before);
+    let mut after = Reader::new(after);
+    let mut writer = BufWriter::new(writer);
+
+    let stream = match filter::visibility(&options.ignore, &options.include, &[]) {
+        Visibility::Excluded => return finish(writer, false, options),
+        Visibility::Ancestor => true,
+        Visibility::Included => options.max_depth != Some(0),
+    } && before.peek_value()? == Some(b'{')
+        && after.peek_value()? == Some(b'{');
+
+    let written = if stream && options.canonical {
+        // Values are copied from `after` as they're written there, so the
+        // patch is parsed again to sort and format it
+        let mut patch = Vec::new();
+        let written = Stream {
+            options,
+            before: &mut before,
+            after: &mut after,
+            writer: &mut patch,
+            stack: Vec::new(),
+        }
+        .run()?;
+        if written {
+            let mut patch: Value = serde_json::from_slice(&patch)?;
+            options.canonicalize(&mut patch);
+            serde_json::to_writer(&mut writer, &patch)?;
+        }
+        written
+    } else if stream {
+        Stream {
+            options,
+            before: &mut before,
+            after: &mut after,
+            writer: &mut writer,
+            stack: Vec::new(),
+        }
+        .run()?
+    } else {
+        let before: Value = before.read_document()?;
+        let after: Value = after.read_document()?;
+        match Diff::new(options).generate::<Value, Value>(&before, &after) {
+            Some(mut patch) => {
+                options.canonicalize(&mut patch);
+                serde_json::to_writer(&mut writer, &patch)?;
+                true
+            }
+            None => false,
+        }
+    };
+
+    finish(writer, written, options)
+}
+
+/**
+ * Applies a JSON Merge Patch (RFC 7386) like [`apply`](crate::apply), the
+ * counterpart of [`generate_stream`], reading the document from `document`
+ * and the patch from `patch` and writing the patched document to `writer`.
+ *
+ * Unlike [`generate_stream`], both are read as a whole.
+ */
+pub fn apply_stream(
+    document: impl Read,
+    patch: impl Read,
+    writer: impl Write,
+) -> Result<(), serde_json::Error> {
+    let mut document: Value = serde_json::from_reader(BufReader::new(document))?;
+    let patch: Value = serde_json::from_reader(BufReader::new(patch))?;
+    crate::apply(&mut document, &patch);
+
+    let mut writer = BufWriter::new(writer);
+    serde_json::to_writer(&mut writer, &document)?;
+    writer.flush().map_err(serde_json::Error::io)
+}
+
+/**
+ * Writes an empty patch if nothing was written and that's requested, and
+ * flushes the writer.
+ */
+fn finish(
+    mut writer: BufWriter<impl Write>,
+    written: bool,
+    options: &Options,
+) -> Result<bool, serde_json::Error> {
+    if !written && options.empty_patch == EmptyPatchBehavior::EmptyObject {
+        writer.write_all(b"{}").map_err(serde_json::Error::io)?;
+    }
+    writer.flush().map_err(serde_json::Error::io)?;
+    Ok(written)
+}
+
+/**
+ * The text of a value, with its position in the document for errors.
+ */
+struct Raw {
+    text: Vec<u8>,
+    line: usize,
+    column: usize,
+}
+
+impl Raw {
+    fn parse<T: serde::de::DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_slice(&self.text).map_err(|error| {
+            serde_json::Error::custom(format!(
+                "{error} in the value at line {} column {}",
+                self.line, self.column
+            ))
+        })
+    }
+}
+
+/**
+ * A patch for a member, either parsed or copied from `after`.
+ */
+enum Patch {
+    Value(Value),
+    Raw(Raw),
+}
+
+/**
+ * An object whose members are being compared.
+ */
+struct Frame {
+    /** Key of the object in its parent, `None` for the root */
+    key: Option<String>,
+    /** Whether the patch for the object has been started in the output */
+    opened: bool,
+    /** Whether any members have been written to the patch */
+    written: bool,
+    before_done: bool,
+    after_done: bool,
+    /** Members of one document that haven't been found in the other one yet */
+    before_pending: BTreeMap<String, Raw>,
+    after_pending: BTreeMap<String, Raw>,
+}
+
+impl Frame {
+    fn new(key: Option<String>) -> Self {
+        Self {
+            key,
+            opened: false,
+            written: false,
+            before_done: false,
+            after_done: false,
+            before_pending: BTreeMap::new(),
+            after_pending: BTreeMap::new(),
+        }
+    }
+}
+
+struct Stream<'s, B, A, W> {
+    options: &'s Options,
+    before: &'s mut Reader<B>,
+    after: &'s mut Reader<A>,
+    writer: &'s mut W,
+    stack: Vec<Frame>,
+}
+
+impl<B: Read, A: Read, W: Write> Stream<'_, B, A, W> {
+    /**
+     * Compares the root objects, returning whether a patch was written.
+     */
+    fn run(&mut self) -> Result<bool, serde_json::Error> {
+        self.before.enter_object()?;
+        self.after.enter_object()?;
+        self.stack.push(Frame::new(None));
+
+        loop {
+            // The .unwrap() calls are safe because the loop returns once the stack is empty
+            let frame = self.stack.last_mut().unwrap();
+
+            if !frame.before_done || !frame.after_done {
+                let before = match frame.before_done {
+                    true => None,
+                    false => self.before.next_key()?,
+                };
+                frame.before_done = before.is_none();
+                let after = match frame.after_done {
+                    true => None,
+                    false => self.after.next_key()?,
+                };
+                frame.after_done = after.is_none();
+
+                match (before, after) {
+                    (Some(before), Some(after)) if before == after => self.member(before)?,
+                    (before, after) => {
+                        if let Some(key) = before {
+                            self.pending(key, true)?;
+                        }
+                        if let Some(key) = after {
+                            self.pending(key, false)?;
+                        }
+                    }
+                }
+                continue;
+            }
+
+            // Members that are only in one of the documents
+            let frame = self.stack.last_mut().unwrap();
+            let removed = std::mem::take(&mut frame.before_pending);
+            let added = std::mem::take(&mut frame.after_pending);
+            for (key, before) in removed {
+                self.compare(key, Some(before), None)?;
+            }
+            for (key, after) in added {
+                self.compare(key, None, Some(after))?;
+            }
+
+            let frame = self.stack.pop().unwrap();
+            if frame.opened {
+                self.write(b"}")?;
+            }
+            if self.stack.is_empty() {
+                self.before.finish()?;
+                self.after.finish()?;
+                return Ok(frame.opened);
+            }
+        }
+    }
+
+    /**
+     * Compares a member found at the same time in both documents.
+     */
+    fn member(&mut self, key: String) -> Result<(), serde_json::Error> {
+        let depth = self.stack.len();
+        let visibility = self.visibility(&key);
+        let objects =
+            self.before.peek_value()? == Some(b'{') && self.after.peek_value()? == Some(b'{');
+        let descend = objects
+            && match visibility {
+                Visibility::Excluded => false,
+                Visibility::Ancestor => true,
+                Visibility::Included => self
+                    .options
+                    .max_depth
+                    .is_none_or(|max_depth| depth < max_depth),
+            };
+
+        if descend {
+            self.before.enter_object()?;
+            self.after.enter_object()?;
+            self.stack.push(Frame::new(Some(key)));
+        } else if visibility == Visibility::Excluded {
+            self.before.skip_value()?;
+            self.after.skip_value()?;
+        } else {
+            let before = self.before.read_value()?;
+            let after = self.after.read_value()?;
+            self.compare(key, Some(before), Some(after))?;
+        }
+        Ok(())
+    }
+
+    /**
+     * Reads the value of a member found in one document, and compares it if
+     * the other document already had it.
+     */
+    fn pending(&mut self, key: String, is_before: bool) -> Result<(), serde_json::Error> {
+        if self.visibility(&key) == Visibility::Excluded {
+            return match is_before {
+                true => self.before.skip_value(),
+                false => self.after.skip_value(),
+            };
+        }
+
+        let frame = self.stack.last_mut().unwrap();
+        if is_before {
+            let before = self.before.read_value()?;
+            match frame.after_pending.remove(&key) {
+                Some(after) => self.compare(key, Some(before), Some(after)),
+                None => {
+                    frame.before_pending.insert(key, before);
+                    Ok(())
+                }
+            }
+        } else {
+            let after = self.after.read_value()?;
+            match frame.before_pending.remove(&key) {
+                Some(before) => self.compare(key, Some(before), Some(after)),
+                None => {
+                    frame.after_pending.insert(key, after);
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    /**
+     * Compares a member of the current object, where `None` means that the
+     * member is absent, and writes its patch.
+     */
+    fn compare(
+        &mut self,
+        key: String,
+        before: Option<Raw>,
+        after: Option<Raw>,
+    ) -> Result<(), serde_json::Error> {
+        if let (Some(before), Some(after)) = (&before, &after) {
+            if before.text == after.text {
+                return Ok(());
+            }
+        }
+
+        let patch = match (self.visibility(&key), before, after) {
+            (Visibility::Excluded, _, _) => None,
+            (Visibility::Included, Some(_), None) if !self.options.ignore_removals => {
+                Some(Patch::Value(Value::Null))
+            }
+            (Visibility::Included, None, Some(after)) if self.options.ignore.is_empty() => {
+                after.parse::<serde::de::IgnoredAny>()?;
+                Some(Patch::Raw(after))
+            }
+            (_, before, after) => {
+                let before: Option<Value> = before.map(|before| before.parse()).transpose()?;
+                let after: Option<Value> = after.map(|after| after.parse()).transpose()?;
+                let keys = self.path();
+                let mut path: Vec<_> = keys.iter().map(|key| Segment::Key(key)).collect();
+                path.push(Segment::Key(&key));
+                Diff::at(self.options, path)
+                    .member_patch::<Value, Value>(before.as_ref(), after.as_ref())
+                    .map(Patch::Value)
+            }
+        };
+
+        match patch {
+            Some(patch) => self.write_member(&key, patch),
+            None => Ok(()),
+        }
+    }
+
+    /**
+     * Writes a member of the patch for the current object, starting the
+     * patches of the objects leading to it if needed.
+     */
+    fn write_member(&mut self, key: &str, patch: Patch) -> Result<(), serde_json::Error> {
+        for index in 0..self.stack.len() {
+            if self.stack[index].opened {
+                continue;
+            }
+            if index > 0 {
+                let key = self.stack[index].key.clone().unwrap_or_default();
+                self.write_key(index - 1, &key)?;
+            }
+            self.write(b"{")?;
+            self.stack[index].opened = true;
+        }
+
+        self.write_key(self.stack.len() - 1, key)?;
+        match patch {
+            Patch::Value(value) => serde_json::to_writer(&mut *self.writer, &value),
+            Patch::Raw(raw) => self.write(&raw.text),
+        }
+    }
+
+    /**
+     * Writes the key of a member of the patch of the object at `index` in the
+     * stack.
+     */
+    fn write_key(&mut self, index: usize, key: &str) -> Result<(), serde_json::Error> {
+        if std::mem::replace(&mut self.stack[index].written, true) {
+            self.write(b",")?;
+        }
+        serde_json::to_writer(&mut *self.writer, key)?;
+        self.write(b":")
+    }
+
+    fn write(&mut self, text: &[u8]) -> Result<(), serde_json::Error> {
+        self.writer.write_all(text).map_err(serde_json::Error::io)
+    }
+
+    /**
+     * Keys of the objects leading to the current one.
+     */
+    fn path(&self) -> Vec<String> {
+        self.stack
+            .iter()
+            .filter_map(|frame| frame.key.clone())
+            .collect()
+    }
+
+    /**
+     * Visibility of the member `key` of the current object.
+     */
+    fn visibility(&self, key: &str) -> Visibility {
+        let keys = self.path();
+        let mut path: Vec<_> = keys.iter().map(|key| Segment::Key(key)).collect();
+        path.push(Segment::Key(key));
+        filter::visibility(&self.options.ignore, &self.options.include, &path)
+    }
+}
+
+/**
+ * Reads a JSON document piece by piece. Only the structure of the objects
+ * being read is checked here, values are checked by parsing them.
+ */
+struct Reader<R> {
+    reader: BufReader<R>,
+    line: usize,
+    column: usize,
+    /** For each object being read, whether no member has been read yet */
+    objects: Vec<bool>,
+}
+
+impl<R: Read> Reader<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            line: 1,
+            column: 0,
+            objects: Vec::new(),
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>, serde_json::Error> {
+        let buffer = self.reader.fill_buf().map_err(serde_json::Error::io)?;
+        Ok(buffer.first().copied())
+    }
+
+    fn next(&mut self) -> Result<Option<u8>, serde_json::Error> {
+        let byte = self.peek()?;
+        if let Some(byte) = byte {
+            self.reader.consume(1);
+            if byte == b'\n' {
+                self.line += 1;
+                self.column = 0;
+            } else {
+                self.column += 1;
+            }
+        }
+        Ok(byte)
+    }
+
+    fn error(&self, expected: &str) -> serde_json::Error {
+        serde_json::Error::custom(format!(
+            "expected {expected} at line {} column {}",
+            self.line,
+            self.column + 1
+        ))
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), serde_json::Error> {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek()? {
+            self.next()?;
+        }
+        Ok(())
+    }
+
+    fn expect(&mut self, byte: u8, expected: &str) -> Result<(), serde_json::Error> {
+        match self.next()? {
+            Some(next) if next == byte => Ok(()),
+            _ => Err(self.error(expected)),
+        }
+    }
+
+    /**
+     * First byte of the next value.
+     */
+    fn peek_value(&mut self) -> Result<Option<u8>, serde_json::Error> {
+        self.skip_whitespace()?;
+        self.peek()
+    }
+
+    fn enter_object(&mut self) -> Result<(), serde_json::Error> {
+        self.skip_whitespace()?;
+        self.expect(b'{', "`{`")?;
+        self.objects.push(true);
+        Ok(())
+    }
+
+    /**
+     * Key of the next member of the current object, or `None` once the object
+     * ends. The value of the member needs to be read before the next key.
+     */
+    fn next_key(&mut self) -> Result<Option<String>, serde_json::Error> {
+        self.skip_whitespace()?;
+        if self.peek()? == Some(b'}') {
+            self.next()?;
+            self.objects.pop();
+            return Ok(None);
+        }
+
+        let first = self
+            .objects
+            .last_mut()
+            .map(|first| std::mem::replace(first, false));
+        if first == Some(false) {
+            self.expect(b',', "`,` or `}`")?;
+            self.skip_whitespace()?;
+        }
+        if self.peek()? != Some(b'"') {
+            return Err(self.error("a string key"));
+        }
+        let key = self.read_value()?.parse()?;
+        self.skip_whitespace()?;
+        self.expect(b':', "`:`")?;
+        Ok(Some(key))
+    }
+
+    fn read_value(&mut self) -> Result<Raw, serde_json::Error> {
+        self.skip_whitespace()?;
+        let mut text = Vec::new();
+        let (line, column) = (self.line, self.column + 1);
+        self.scan_value(Some(&mut text))?;
+        Ok(Raw { text, line, column })
+    }
+
+    fn skip_value(&mut self) -> Result<(), serde_json::Error> {
+        self.skip_whitespace()?;
+        self.scan_value(None)
+    }
+
+    /**
+     * Reads the next value, collecting its text into `text`.
+     */
+    fn scan_value(&mut self, mut text: Option<&mut Vec<u8>>) -> Result<(), serde_json::Error> {
+        let mut push = |byte| {
+            if let Some(text) = text.as_mut() {
+                text.push(byte);
+            }
+        };
+
+        let mut empty = true;
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+        loop {
+            let Some(byte) = self.peek()? else {
+                return Err(self.error("a value"));
+            };
+            let scalar_end = matches!(byte, b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r');
+            if depth == 0 && !in_string && scalar_end {
+                // The end of a number or literal, left for the caller
+                return match empty {
+                    true => Err(self.error("a value")),
+                    false => Ok(()),
+                };
+            }
+            self.next()?;
+            push(byte);
+            empty = false;
+
+            if in_string {
+                match byte {
+                    _ if escaped => escaped = false,
+                    b'\\' => escaped = true,
+                    b'"' => in_string = false,
+                    _ => {}
+                }
+                if !in_string && depth == 0 {
+                    return Ok(());
+                }
+                continue;
+            }
+            match byte {
+                b'"' => in_string = true,
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' => {
+                    depth = depth.checked_sub(1).ok_or_else(|| self.error("a value"))?;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /**
+     * Checks that nothing but whitespace follows the document.
+     */
+    fn finish(&mut self) -> Result<(), serde_json::Error> {
+        self.skip_whitespace()?;
+        match self.peek()? {
+            None => Ok(()),
+            Some(_) => Err(self.error("the end of the document")),
+        }
+    }
+
+    /**
+     * Reads the rest of the input as a single document.
+     */
+    fn read_document<T: serde::de::DeserializeOwned>(self) -> Result<T, serde_json::Error> {
+        serde_json::from_reader(self.reader)
+    }
+}
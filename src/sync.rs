@@ -0,0 +1,352 @@
+/*!
+ * A small protocol for keeping clients in sync with a document on a server,
+ * e.g. over WebSockets or server-sent events: a client gets a snapshot of the
+ * document when it connects, then the patches of its changes as
+ * [`VersionedPatch`]es. A client that misses a patch asks for a new snapshot
+ * with a resync message.
+ *
+ * Messages are JSON objects with a `type` of `"snapshot"`, `"patch"` or
+ * `"resync"`:
+ *
+ * ```text
+ * {"type": "snapshot", "version": 3, "state": {"temperature": 21}}
+ * {"type": "patch", "base_version": 3, "new_version": 4, "patch": {"temperature": 22}}
+ * {"type": "resync"}
+ * ```
+ */
+
+use alloc::string::String;
+use core::fmt;
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{json, Map, Value};
+
+use crate::changes::equal;
+use crate::copy::deep_clone;
+
+/**
+ * A message of the protocol.
+ */
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Message {
+    /** The whole document at a version, from the server */
+    Snapshot { version: u64, state: Value },
+    /** A change of the document, from the server */
+    Patch(VersionedPatch),
+    /** A request for a snapshot, from a client */
+    Resync,
+}
+
+/**
+ * A merge patch from the document at `base_version` to the one at
+ * `new_version`.
+ *
+ * Applying a plain merge patch to the wrong document silently corrupts it,
+ * e.g. after a dropped message. A [`Replica`] only applies versioned patches
+ * to the version they were made from.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionedPatch {
+    pub base_version: u64,
+    pub new_version: u64,
+    pub patch: Value,
+}
+
+impl VersionedPatch {
+    /**
+     * The patch from `before`, at `base_version`, to `after`, as the next
+     * version, or `None` if they're equal.
+     */
+    pub fn generate(before: &Value, after: &Value, base_version: u64) -> Option<Self> {
+        if equal(before, after) {
+            return None;
+        }
+        Some(VersionedPatch {
+            base_version,
+            new_version: base_version + 1,
+            patch: crate::generate(before, after)?,
+        })
+    }
+}
+
+/**
+ * A copy of a document at a known version, changed only by the
+ * [`VersionedPatch`]es that follow it.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replica {
+    version: u64,
+    state: Value,
+}
+
+/**
+ * A patch wasn't made from the version of the document it was meant for,
+ * which needs a new snapshot, e.g. by sending [`Message::Resync`] to the
+ * server.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResyncNeeded {
+    /** The version of the document, `None` before the first snapshot */
+    pub version: Option<u64>,
+    /** The version the patch was made from */
+    pub base_version: u64,
+}
+
+impl Replica {
+    pub fn new(state: Value, version: u64) -> Self {
+        Replica { version, state }
+    }
+
+    pub fn state(&self) -> &Value {
+        &self.state
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn into_state(self) -> Value {
+        self.state
+    }
+
+    /**
+     * Applies `patch` if it was made from the current version, returning
+     * whether it did. Patches to versions that the replica already has, e.g.
+     * duplicates or ones arriving out of order, are ignored, and any other
+     * patch fails, leaving the replica unchanged.
+     */
+    pub fn apply(&mut self, patch: &VersionedPatch) -> Result<bool, ResyncNeeded> {
+        if patch.new_version <= self.version {
+            return Ok(false);
+        }
+        if patch.base_version != self.version {
+            return Err(ResyncNeeded {
+                version: Some(self.version),
+                base_version: patch.base_version,
+            });
+        }
+        crate::apply(&mut self.state, &patch.patch);
+        self.version = patch.new_version;
+        Ok(true)
+    }
+}
+
+/**
+ * The server side: the document, its version and the messages for clients.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncServer {
+    version: u64,
+    state: Value,
+}
+
+impl SyncServer {
+    /**
+     * A server for `state`, as version 0.
+     */
+    pub fn new(state: Value) -> Self {
+        SyncServer { version: 0, state }
+    }
+
+    pub fn state(&self) -> &Value {
+        &self.state
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /**
+     * The message for clients that connect or resync.
+     */
+    pub fn snapshot(&self) -> Message {
+        Message::Snapshot {
+            version: self.version,
+            state: deep_clone(&self.state),
+        }
+    }
+
+    /**
+     * Replaces the document with `after`, returning the message to broadcast
+     * to the clients, or `None` if nothing changed.
+     */
+    pub fn update(&mut self, after: Value) -> Option<Message> {
+        let patch = VersionedPatch::generate(&self.state, &after, self.version)?;
+        self.state = after;
+        self.version = patch.new_version;
+        Some(Message::Patch(patch))
+    }
+
+    /**
+     * Applies `patch` to the document, see [`update`](Self::update).
+     */
+    pub fn apply(&mut self, patch: &Value) -> Option<Message> {
+        let mut after = deep_clone(&self.state);
+        crate::apply(&mut after, patch);
+        self.update(after)
+    }
+
+    /**
+     * The response to a message from a client: a snapshot for a resync.
+     */
+    pub fn respond(&self, message: &Message) -> Option<Message> {
+        match message {
+            Message::Resync => Some(self.snapshot()),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * The client side: the document as of the last message received, if it's in
+ * sync.
+ */
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SyncClient {
+    replica: Option<Replica>,
+}
+
+impl SyncClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /**
+     * The document, or `None` before the first snapshot or after a missed
+     * patch.
+     */
+    pub fn state(&self) -> Option<&Value> {
+        self.replica.as_ref().map(Replica::state)
+    }
+
+    pub fn version(&self) -> Option<u64> {
+        self.replica.as_ref().map(Replica::version)
+    }
+
+    /**
+     * Handles a message from the server, see [`Replica::apply`]. A missed
+     * patch puts the client out of sync until the next snapshot.
+     */
+    pub fn receive(&mut self, message: Message) -> Result<(), ResyncNeeded> {
+        match message {
+            Message::Snapshot { version, state } => {
+                self.replica = Some(Replica::new(state, version))
+            }
+            Message::Patch(patch) => {
+                let result = match &mut self.replica {
+                    Some(replica) => replica.apply(&patch),
+                    None => Err(ResyncNeeded {
+                        version: None,
+                        base_version: patch.base_version,
+                    }),
+                };
+                if let Err(error) = result {
+                    self.replica = None;
+                    return Err(error);
+                }
+            }
+            Message::Resync => {}
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ResyncNeeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.version {
+            Some(version) => write!(
+                f,
+                "received a patch from version {} at version {version}",
+                self.base_version
+            ),
+            None => write!(
+                f,
+                "received a patch from version {} before a snapshot",
+                self.base_version
+            ),
+        }
+    }
+}
+
+impl core::error::Error for ResyncNeeded {}
+
+impl VersionedPatch {
+    fn to_json(&self) -> Map<String, Value> {
+        let mut object = Map::new();
+        object.insert("base_version".into(), self.base_version.into());
+        object.insert("new_version".into(), self.new_version.into());
+        object.insert("patch".into(), self.patch.clone());
+        object
+    }
+
+    fn from_json<E: serde::de::Error>(object: &mut Map<String, Value>) -> Result<Self, E> {
+        let version = |object: &Map<String, Value>, name| {
+            object
+                .get(name)
+                .and_then(Value::as_u64)
+                .ok_or_else(|| E::missing_field(name))
+        };
+        Ok(VersionedPatch {
+            base_version: version(object, "base_version")?,
+            new_version: version(object, "new_version")?,
+            patch: object
+                .remove("patch")
+                .ok_or_else(|| E::missing_field("patch"))?,
+        })
+    }
+}
+
+impl Serialize for VersionedPatch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for VersionedPatch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        VersionedPatch::from_json(&mut Map::deserialize(deserializer)?)
+    }
+}
+
+impl Serialize for Message {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Message::Snapshot { version, state } => {
+                json!({"type": "snapshot", "version": version, "state": state})
+            }
+            Message::Patch(patch) => {
+                let mut object = patch.to_json();
+                object.insert("type".into(), "patch".into());
+                Value::Object(object)
+            }
+            Message::Resync => json!({"type": "resync"}),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Message {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut message = Map::deserialize(deserializer)?;
+        match message.get("type").and_then(Value::as_str) {
+            Some("snapshot") => Ok(Message::Snapshot {
+                version: message
+                    .get("version")
+                    .and_then(Value::as_u64)
+                    .ok_or_else(|| D::Error::missing_field("version"))?,
+                state: message
+                    .remove("state")
+                    .ok_or_else(|| D::Error::missing_field("state"))?,
+            }),
+            Some("patch") => VersionedPatch::from_json(&mut message).map(Message::Patch),
+            Some("resync") => Ok(Message::Resync),
+            Some(other) => Err(D::Error::unknown_variant(
+                other,
+                &["snapshot", "patch", "resync"],
+            )),
+            None => Err(D::Error::missing_field("type")),
+        }
+    }
+}
@@ -0,0 +1,117 @@
+/*!
+ * Assertions for tests of code that generates or applies merge patches, which
+ * on failure show what differs between the values rather than their debug
+ * representations.
+ *
+ * ```
+ * use json_merge_patch_gen::{assert_merge_patch, assert_roundtrip};
+ * use serde_json::json;
+ *
+ * let before = json!({ "a": 1, "b": [1, 2] });
+ * let after = json!({ "a": 1, "b": [2], "c": true });
+ * assert_merge_patch!(before, after, json!({ "b": [2], "c": true }));
+ * assert_merge_patch!(before, before, None);
+ * assert_roundtrip!(before, after);
+ * ```
+ */
+
+use alloc::format;
+use alloc::string::String;
+
+use serde_json::Value;
+
+use crate::render;
+
+/**
+ * Asserts that the merge patch from `before` to `after` is `expected`, where
+ * `None` means that there's no patch. See [`assert_merge_patch`].
+ */
+#[track_caller]
+pub fn assert_merge_patch(before: &Value, after: &Value, expected: Option<&Value>) {
+    let patch = crate::generate(before, after);
+    if patch.as_ref() == expected {
+        return;
+    }
+    let message = match (expected, &patch) {
+        (Some(expected), Some(patch)) => format!(
+            "expected:\n{}\nactual:\n{}\nchanges from expected to actual:\n{}",
+            pretty(expected),
+            pretty(patch),
+            render::unified_diff(expected, patch),
+        ),
+        (Some(expected), None) => format!("expected:\n{}\nactual: none", pretty(expected)),
+        (None, Some(patch)) => format!("expected: none\nactual:\n{}", pretty(patch)),
+        (None, None) => unreachable!(),
+    };
+    panic!("unexpected merge patch\n{message}");
+}
+
+/**
+ * Asserts that applying the merge patch from `before` to `after` to `before`
+ * yields `after`. See [`assert_roundtrip`].
+ */
+#[track_caller]
+pub fn assert_roundtrip(before: &Value, after: &Value) {
+    let patch = crate::generate(before, after);
+    let mut result = crate::copy::deep_clone(before);
+    if let Some(patch) = &patch {
+        crate::apply(&mut result, patch);
+    }
+    if result == *after {
+        return;
+    }
+
+    let mut message = format!(
+        "applying the merge patch doesn't yield after\npatch:\n{}\nafter:\n{}\nresult:\n{}\n",
+        patch.as_ref().map_or_else(|| String::from("none"), pretty),
+        pretty(after),
+        pretty(&result),
+    );
+    let (_, lossy) = crate::generate_checked(before, after);
+    if !lossy.is_empty() {
+        message.push_str(&format!(
+            "null members that merge patches can't set: {lossy:?}\n"
+        ));
+    }
+    panic!("{message}");
+}
+
+fn pretty(value: &Value) -> String {
+    // The .unwrap() is safe because values with string keys always serialize
+    serde_json::to_string_pretty(value).unwrap()
+}
+
+/**
+ * Asserts that the merge patch from `before` to `after` is `expected`, or that
+ * there's none if `expected` is `None`. On failure, shows both patches and the
+ * changes between them.
+ *
+ * The arguments are [`serde_json::Value`]s, borrowed by the macro.
+ */
+#[macro_export]
+macro_rules! assert_merge_patch {
+    ($before:expr, $after:expr, None $(,)?) => {
+        $crate::testutil::assert_merge_patch(&$before, &$after, ::core::option::Option::None)
+    };
+    ($before:expr, $after:expr, $expected:expr $(,)?) => {
+        $crate::testutil::assert_merge_patch(
+            &$before,
+            &$after,
+            ::core::option::Option::Some(&$expected),
+        )
+    };
+}
+
+/**
+ * Asserts that applying the merge patch from `before` to `after` to `before`
+ * yields `after`. On failure, shows the patch, `after` and the result, and the
+ * `null` members of `after` that merge patches can't set.
+ *
+ * The arguments are [`serde_json::Value`]s, borrowed by the macro.
+ */
+#[macro_export]
+macro_rules! assert_roundtrip {
+    ($before:expr, $after:expr $(,)?) => {
+        $crate::testutil::assert_roundtrip(&$before, &$after)
+    };
+}
@@ -0,0 +1,54 @@
+use alloc::string::{String, ToString};
+
+use serde_json::Value;
+
+use crate::{Error, Options};
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`](crate::generate)
+ * between two JSON documents given as text, and serializes it, e.g. for
+ * documents taken off a queue.
+ *
+ * Invalid documents fail with [`Error::InvalidJson`], which tells which of
+ * them it is and where the error is.
+ */
+pub fn generate_str(before: &str, after: &str) -> Result<Option<String>, Error> {
+    generate_str_with(before, after, &Options::default())
+}
+
+/**
+ * [`generate_str`] using the given [`Options`].
+ */
+pub fn generate_str_with(
+    before: &str,
+    after: &str,
+    options: &Options,
+) -> Result<Option<String>, Error> {
+    let before = parse("before", before)?;
+    let after = parse("after", after)?;
+    Ok(crate::generate_with(&before, &after, options).map(|patch| patch.to_string()))
+}
+
+fn parse(document: &'static str, text: &str) -> Result<Value, Error> {
+    serde_json::from_str(text).map_err(|error| {
+        let (line, column) = (error.line(), error.column());
+        // serde_json counts columns in bytes, from 1 once a line has started
+        let offset = text
+            .split_inclusive('\n')
+            .take(line.saturating_sub(1))
+            .map(str::len)
+            .sum::<usize>()
+            + column.saturating_sub(1);
+        let message = error.to_string();
+        Error::InvalidJson {
+            document,
+            line,
+            column,
+            offset: offset.min(text.len()),
+            message: match message.rsplit_once(" at line ") {
+                Some((message, _)) => message.to_string(),
+                None => message,
+            },
+        }
+    })
+}
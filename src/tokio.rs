@@ -0,0 +1,86 @@
+/*!
+ * Streams of the patches between consecutive states, e.g. of a
+ * [tokio](https://docs.rs/tokio) `watch` channel.
+ */
+
+use core::pin::Pin;
+use core::task::{ready, Context, Poll};
+
+use ::tokio::sync::watch;
+use ::tokio_stream::wrappers::WatchStream;
+use futures_core::Stream;
+use serde_json::Value;
+
+use crate::changes::equal;
+use crate::Options;
+
+/**
+ * The patches between the states of a `watch` channel, from its current
+ * state on. Ends when the sender is dropped.
+ *
+ * As `watch` channels only keep the latest state, states that are replaced
+ * before the stream is polled are skipped, and the patches lead from one
+ * observed state to the next.
+ */
+pub fn watch_patches(receiver: watch::Receiver<Value>) -> PatchStream<WatchStream<Value>> {
+    PatchStream::new(WatchStream::new(receiver))
+}
+
+/**
+ * A [`Stream`] of the patches between consecutive states of a stream of
+ * states, where the first state is the starting point. States equal to the
+ * previous one don't produce a patch.
+ */
+#[derive(Debug)]
+pub struct PatchStream<S> {
+    states: S,
+    options: Options,
+    state: Option<Value>,
+}
+
+impl<S> PatchStream<S> {
+    pub fn new(states: S) -> Self {
+        Self::with_options(states, Options::default())
+    }
+
+    /**
+     * A stream generating the patches with the given [`Options`].
+     */
+    pub fn with_options(states: S, options: Options) -> Self {
+        PatchStream {
+            states,
+            options,
+            state: None,
+        }
+    }
+
+    /**
+     * The last state received, which the next patch applies to.
+     */
+    pub fn state(&self) -> Option<&Value> {
+        self.state.as_ref()
+    }
+}
+
+impl<S: Stream<Item = Value> + Unpin> Stream for PatchStream<S> {
+    type Item = Value;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Value>> {
+        let this = self.get_mut();
+        loop {
+            let Some(state) = ready!(Pin::new(&mut this.states).poll_next(cx)) else {
+                return Poll::Ready(None);
+            };
+            let patch = match this.state.as_ref() {
+                Some(previous) if !equal(previous, &state) => {
+                    crate::generate_with(previous, &state, &this.options)
+                }
+                _ => None,
+            };
+            this.state = Some(state);
+            if let Some(patch) = patch {
+                return Poll::Ready(Some(patch));
+            }
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use serde_json::Value;
+
+use crate::plain::PlainNumbers;
+use crate::{pointer, Error, Options};
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`](crate::generate),
+ * between two TOML documents. Datetimes are compared as their text, like
+ * strings.
+ *
+ * The patch can be rendered as JSON with serde_json, or as TOML with
+ * [`to_toml`] unless it removes members.
+ */
+pub fn generate_toml(before: &str, after: &str) -> Result<Option<Value>, Error> {
+    generate_toml_with(before, after, &Options::default())
+}
+
+/**
+ * [`generate_toml`] using the given [`Options`].
+ */
+pub fn generate_toml_with(
+    before: &str,
+    after: &str,
+    options: &Options,
+) -> Result<Option<Value>, Error> {
+    let before = parse("before", before)?;
+    let after = parse("after", after)?;
+    Ok(crate::generate_with(&before, &after, options))
+}
+
+/**
+ * Renders a patch, or any object, as TOML.
+ *
+ * TOML has no null, so patches that remove members can't be rendered: this
+ * fails with [`Error::TomlRemoval`] for the first such member.
+ */
+pub fn to_toml(value: &Value) -> Result<String, Error> {
+    let mut stack = vec![(String::new(), value)];
+    while let Some((path, value)) = stack.pop() {
+        match value {
+            Value::Null => return Err(Error::TomlRemoval { pointer: path }),
+            Value::Array(values) => stack.extend(
+                values
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(index, value)| (format!("{path}/{index}"), value)),
+            ),
+            Value::Object(members) => stack.extend(
+                members
+                    .iter()
+                    .rev()
+                    .map(|(key, value)| (format!("{path}/{}", pointer::escape(key)), value)),
+            ),
+            _ => {}
+        }
+    }
+    ::toml::to_string(&PlainNumbers(value)).map_err(|error| Error::Toml {
+        document: "patch",
+        location: None,
+        message: error.to_string(),
+    })
+}
+
+fn parse(document: &'static str, text: &str) -> Result<Value, Error> {
+    let mut table: ::toml::Table = ::toml::from_str(text).map_err(|error| {
+        let location = error.span().map(|span| {
+            let before = &text[..span.start];
+            let line = before.matches('\n').count() + 1;
+            let column = before.len() - before.rfind('\n').map_or(0, |index| index + 1) + 1;
+            (line, column)
+        });
+        Error::Toml {
+            document,
+            location,
+            message: error.message().trim_end().to_owned(),
+        }
+    })?;
+
+    // Datetimes would otherwise become objects with a private key, and JSON
+    // numbers can't be NaN or infinite
+    let mut stack: Vec<&mut ::toml::Value> = table.iter_mut().map(|(_, value)| value).collect();
+    while let Some(value) = stack.pop() {
+        match value {
+            ::toml::Value::Datetime(datetime) => {
+                *value = ::toml::Value::String(datetime.to_string())
+            }
+            ::toml::Value::Float(float) if !float.is_finite() => {
+                return Err(Error::Toml {
+                    document,
+                    location: None,
+                    message: format!("{float} can't be represented as JSON"),
+                })
+            }
+            ::toml::Value::Array(values) => stack.extend(values.iter_mut()),
+            ::toml::Value::Table(members) => {
+                stack.extend(members.iter_mut().map(|(_, value)| value))
+            }
+            _ => {}
+        }
+    }
+    // The .unwrap() is safe because the remaining values are all representable
+    Ok(serde_json::to_value(table).unwrap())
+}
@@ -0,0 +1,46 @@
+use tracing::field::Empty;
+use tracing::{debug, debug_span, trace, Level, Span};
+
+use crate::pointer::{self, Segment};
+use crate::value::{patch_changes, JsonLike};
+
+/**
+ * The span of a call generating a patch, with the sizes of the documents as
+ * compact JSON for values that tell them, see [`JsonLike::serialized_size`].
+ */
+pub(crate) fn span<V: JsonLike>(before: &V, after: &V) -> Span {
+    let span = debug_span!(
+        "generate_merge_patch",
+        before_bytes = Empty,
+        after_bytes = Empty,
+        changes = Empty,
+    );
+    if !span.is_disabled() {
+        span.record("before_bytes", before.serialized_size());
+        span.record("after_bytes", after.serialized_size());
+    }
+    span
+}
+
+/**
+ * Records the number of values that `patch` sets or removes on the span of
+ * the call that generated it.
+ */
+pub(crate) fn generated<V: JsonLike>(span: &Span, patch: Option<&V>) {
+    if span.is_disabled() {
+        return;
+    }
+    let changes = patch.map_or(0, patch_changes);
+    span.record("changes", changes);
+    debug!(changes, "generated merge patch");
+}
+
+/**
+ * Emits a trace event for a change found at `path`, where `change` is
+ * `"added"`, `"removed"` or `"modified"`.
+ */
+pub(crate) fn change(change: &'static str, path: &[Segment<'_>]) {
+    if tracing::enabled!(Level::TRACE) {
+        trace!(pointer = %pointer::format(path), change, "merge patch change");
+    }
+}
@@ -0,0 +1,173 @@
+/*!
+ * Merge patches of Rust types.
+ */
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+/**
+ * Types with typed merge patches, usually implemented with
+ * `#[derive(JsonMergePatch)]` (see the `derive` feature).
+ *
+ * The patch converts to its JSON representation with [`Into`] and back with
+ * [`TryFrom`], which fails for JSON that isn't a patch of the type.
+ */
+pub trait JsonMergePatch {
+    /** The patch, where every member is optional */
+    type Patch: Default + Into<Value> + TryFrom<Value, Error = serde_json::Error>;
+
+    /**
+     * Applies `patch`, leaving the members that it doesn't set as they are.
+     */
+    fn apply_patch(&mut self, patch: Self::Patch);
+}
+
+/**
+ * A JSON Merge Patch of a `T`, like the body of an
+ * `application/merge-patch+json` request.
+ *
+ * Deserializes from any JSON and keeps it as it is, so unlike a struct of
+ * `Option`s it tells members set to `null`, which removes them, from absent
+ * members, which are left as they are. It's only checked against `T` when
+ * it's applied.
+ *
+ * ```
+ * use json_merge_patch_gen::typed::MergePatch;
+ * use serde::{Deserialize, Serialize};
+ *
+ * #[derive(Serialize, Deserialize)]
+ * struct User {
+ *     name: String,
+ *     email: Option<String>,
+ * }
+ *
+ * let patch: MergePatch<User> = serde_json::from_str(r#"{"email": null}"#).unwrap();
+ * assert!(patch.removes("/email"));
+ * assert!(!patch.contains("/name"));
+ *
+ * let mut user = User { name: "ann".into(), email: Some("ann@example.com".into()) };
+ * patch.apply(&mut user).unwrap();
+ * assert_eq!(user.email, None);
+ * ```
+ */
+pub struct MergePatch<T> {
+    patch: Value,
+    target: PhantomData<fn() -> T>,
+}
+
+impl<T> MergePatch<T> {
+    /**
+     * The patch with the JSON representation `patch`.
+     */
+    pub fn new(patch: Value) -> Self {
+        MergePatch {
+            patch,
+            target: PhantomData,
+        }
+    }
+
+    /** The JSON representation of the patch */
+    pub fn as_value(&self) -> &Value {
+        &self.patch
+    }
+
+    /** The JSON representation of the patch */
+    pub fn into_value(self) -> Value {
+        self.patch
+    }
+
+    /**
+     * Whether the patch sets the member at the JSON Pointer `pointer`, including
+     * to `null`.
+     */
+    pub fn contains(&self, pointer: &str) -> bool {
+        self.patch.pointer(pointer).is_some()
+    }
+
+    /**
+     * Whether the patch removes the member at the JSON Pointer `pointer`, by
+     * setting it to `null`.
+     */
+    pub fn removes(&self, pointer: &str) -> bool {
+        self.patch.pointer(pointer).is_some_and(Value::is_null)
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> MergePatch<T> {
+    /**
+     * Applies the patch to `target` through its JSON representation, leaving
+     * it as it is if the patched JSON isn't a `T`.
+     */
+    pub fn apply(&self, target: &mut T) -> Result<(), serde_json::Error> {
+        *target = self.patched(target)?;
+        Ok(())
+    }
+
+    /**
+     * The result of applying the patch to `target`.
+     */
+    pub fn patched(&self, target: &T) -> Result<T, serde_json::Error> {
+        let mut document = serde_json::to_value(target)?;
+        crate::apply(&mut document, &self.patch);
+        serde_json::from_value(document)
+    }
+
+    /**
+     * The patch from `before` to `after`, which is empty if they're equal.
+     */
+    pub fn diff(before: &T, after: &T) -> Result<Self, serde_json::Error> {
+        let (before, after) = (serde_json::to_value(before)?, serde_json::to_value(after)?);
+        let patch =
+            crate::generate(&before, &after).unwrap_or_else(|| Value::Object(Default::default()));
+        Ok(MergePatch::new(patch))
+    }
+}
+
+// Implemented by hand as the derives would require the traits of `T`
+
+impl<T> Clone for MergePatch<T> {
+    fn clone(&self) -> Self {
+        MergePatch::new(self.patch.clone())
+    }
+}
+
+impl<T> fmt::Debug for MergePatch<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("MergePatch").field(&self.patch).finish()
+    }
+}
+
+impl<T> PartialEq for MergePatch<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.patch == other.patch
+    }
+}
+
+impl<T> Default for MergePatch<T> {
+    /** The empty patch, which leaves the target as it is */
+    fn default() -> Self {
+        MergePatch::new(Value::Object(Default::default()))
+    }
+}
+
+impl<T> From<MergePatch<T>> for Value {
+    fn from(patch: MergePatch<T>) -> Self {
+        patch.patch
+    }
+}
+
+impl<T> Serialize for MergePatch<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.patch.serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for MergePatch<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Value::deserialize(deserializer).map(MergePatch::new)
+    }
+}
@@ -0,0 +1,112 @@
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::Error;
+
+/** Nesting depth at which serde_json stops parsing */
+const RECURSION_LIMIT: usize = 128;
+
+/**
+ * What [`apply_all`](crate::apply_all) does with patches that fail to parse.
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InvalidPatches {
+    /** Fail on the first one, leaving the patches before it applied */
+    #[default]
+    Stop,
+    /** Apply the patches after it, and report it along with the others skipped */
+    Skip,
+}
+
+/**
+ * A patch of a sequence that failed to parse, see [`apply_all`](crate::apply_all).
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchError {
+    /** Position of the patch in the sequence, from 0 */
+    pub index: usize,
+    pub error: Error,
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "patch {}: {}", self.index, self.error)
+    }
+}
+
+impl core::error::Error for PatchError {}
+
+pub(crate) fn parse_patch(json: &[u8], max_depth: usize) -> Result<Value, Error> {
+    let patch: Value = serde_json::from_slice(json).map_err(|error| {
+        let message = error.to_string();
+        if message.starts_with("recursion limit exceeded") {
+            return Error::DepthLimitExceeded {
+                limit: max_depth.min(RECURSION_LIMIT),
+            };
+        }
+        Error::InvalidPatch {
+            location: (error.line() > 0).then(|| (error.line(), error.column())),
+            message: match message.rsplit_once(" at line ") {
+                Some((message, _)) => message.to_string(),
+                None => message,
+            },
+        }
+    })?;
+
+    // Arrays and objects left to check, with the number of their ancestors
+    let mut stack = vec![(&patch, 0)];
+    while let Some((value, depth)) = stack.pop() {
+        match value {
+            Value::Array(_) | Value::Object(_) if depth == max_depth => {
+                return Err(Error::DepthLimitExceeded { limit: max_depth });
+            }
+            Value::Array(values) => stack.extend(values.iter().map(|value| (value, depth + 1))),
+            Value::Object(members) => {
+                stack.extend(members.values().map(|value| (value, depth + 1)))
+            }
+            _ => {}
+        }
+    }
+    Ok(patch)
+}
+
+pub(crate) fn apply_to<T: Serialize + DeserializeOwned>(
+    target: &mut T,
+    patch: &Value,
+) -> Result<(), Error> {
+    let mismatch = |error: serde_json::Error| Error::TypeMismatch {
+        message: error.to_string(),
+    };
+    let mut document = serde_json::to_value(&*target).map_err(mismatch)?;
+    crate::apply(&mut document, patch);
+    *target = serde_json::from_value(document).map_err(mismatch)?;
+    Ok(())
+}
+
+pub(crate) fn apply_all<P: AsRef<[u8]>>(
+    document: &mut Value,
+    patches: impl IntoIterator<Item = P>,
+    max_depth: usize,
+    invalid: InvalidPatches,
+) -> Result<Vec<PatchError>, PatchError> {
+    let mut skipped = Vec::new();
+    for (index, patch) in patches.into_iter().enumerate() {
+        match parse_patch(patch.as_ref(), max_depth) {
+            Ok(patch) => crate::apply(document, &patch),
+            Err(error) => {
+                let error = PatchError { index, error };
+                match invalid {
+                    InvalidPatches::Stop => return Err(error),
+                    InvalidPatches::Skip => skipped.push(error),
+                }
+            }
+        }
+    }
+    Ok(skipped)
+}
@@ -0,0 +1,34 @@
+use serde_json::Value;
+
+use crate::size::serialized_size;
+use crate::Options;
+
+/**
+ * How to bring a copy of a document up to date, see
+ * [`generate_update`](crate::generate_update).
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Update<'a> {
+    /** A merge patch to apply to the copy */
+    Patch(Value),
+    /** The `after` document, to replace the copy with, e.g. with a `PUT` */
+    Replace(&'a Value),
+}
+
+pub(crate) fn generate_update<'a>(
+    before: &Value,
+    after: &'a Value,
+    options: &Options,
+) -> Option<Update<'a>> {
+    let patch = crate::generate_with(before, after, options)?;
+    let size = serialized_size(&patch);
+    let too_large = options.max_patch_size.is_some_and(|max| size > max)
+        || options
+            .max_patch_ratio
+            .is_some_and(|ratio| size as f64 > ratio * serialized_size(after) as f64);
+    Some(if too_large {
+        Update::Replace(after)
+    } else {
+        Update::Patch(patch)
+    })
+}
@@ -0,0 +1,100 @@
+/*!
+ * OpenAPI documentation of `PATCH` endpoints taking merge patches, generated
+ * with [utoipa](https://docs.rs/utoipa) from the schema of the type being
+ * patched, like `merge_patch_schema` does for schemars (see the `schemars`
+ * feature).
+ *
+ * ```
+ * use json_merge_patch_gen::utoipa::{request_body, schemas};
+ * use utoipa::openapi::{ComponentsBuilder, OpenApiBuilder, PathItem, PathsBuilder, HttpMethod};
+ * use utoipa::openapi::path::OperationBuilder;
+ * use utoipa::ToSchema;
+ *
+ * #[derive(ToSchema)]
+ * struct Settings {
+ *     timeout: u32,
+ *     name: Option<String>,
+ * }
+ *
+ * let mut components = Vec::new();
+ * schemas::<Settings>(&mut components);
+ * let openapi = OpenApiBuilder::new()
+ *     .paths(PathsBuilder::new().path(
+ *         "/settings",
+ *         PathItem::new(
+ *             HttpMethod::Patch,
+ *             OperationBuilder::new().request_body(Some(request_body::<Settings>())),
+ *         ),
+ *     ))
+ *     .components(Some(ComponentsBuilder::new().schemas_from_iter(components).build()))
+ *     .build();
+ * ```
+ */
+
+use std::collections::BTreeSet;
+
+use ::utoipa::openapi::request_body::{RequestBody, RequestBodyBuilder};
+use ::utoipa::openapi::{Content, RefOr, Required, Schema};
+use ::utoipa::ToSchema;
+use serde_json::Value;
+
+use crate::patch_schema::{self, SUFFIX};
+use crate::MERGE_PATCH_MEDIA_TYPE;
+
+/**
+ * The schema of the merge patches of `T`: one for objects whose members are
+ * all optional, and nullable to remove them unless they're required. It
+ * refers to the schemas that [`schemas`] adds to the components, with a
+ * `MergePatch` suffix, for the patches of the types it refers to.
+ */
+pub fn schema<T: ToSchema>() -> RefOr<Schema> {
+    patch(&T::schema(), &mut BTreeSet::new())
+}
+
+/**
+ * Adds the schemas that [`schema`] refers to to `schemas`, for the components
+ * of an OpenAPI document: the ones that `T` refers to, and the schemas of the
+ * patches of the types whose members its patches merge into.
+ */
+pub fn schemas<T: ToSchema>(schemas: &mut Vec<(String, RefOr<Schema>)>) {
+    let mut components = Vec::new();
+    T::schemas(&mut components);
+
+    let mut referenced = BTreeSet::new();
+    patch(&T::schema(), &mut referenced);
+    // Components are only patched where a patch refers to them, as they're
+    // still used as they are elsewhere, e.g. for array items
+    let mut patched = BTreeSet::new();
+    while let Some(reference) = referenced.pop_first() {
+        if !patched.insert(reference.clone()) {
+            continue;
+        }
+        let Some((_, name)) = patch_schema::split_reference(&reference) else {
+            continue;
+        };
+        if let Some((_, component)) = components.iter().find(|(key, _)| key == name) {
+            let component = patch(component, &mut referenced);
+            schemas.push(([name, SUFFIX].concat(), component));
+        }
+    }
+    schemas.extend(components);
+}
+
+/**
+ * A required `application/merge-patch+json` request body with the [`schema`]
+ * of the patches of `T`.
+ */
+pub fn request_body<T: ToSchema>() -> RequestBody {
+    RequestBodyBuilder::new()
+        .content(MERGE_PATCH_MEDIA_TYPE, Content::new(Some(schema::<T>())))
+        .required(Some(Required::True))
+        .build()
+}
+
+fn patch(schema: &RefOr<Schema>, referenced: &mut BTreeSet<String>) -> RefOr<Schema> {
+    // The .unwrap()s are safe because utoipa's schemas serialize to JSON
+    // objects, which their patches are too
+    let schema = serde_json::to_value(schema).unwrap();
+    let patch: Value = patch_schema::patch(&schema, referenced);
+    serde_json::from_value(patch).unwrap()
+}
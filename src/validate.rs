@@ -0,0 +1,633 @@
+use alloc::borrow::ToOwned;
+use alloc::collections::BTreeSet;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use serde::ser::{self, Impossible, Serialize};
+use serde_json::Value;
+
+use crate::changes::equal;
+use crate::pointer;
+
+/**
+ * Why a patch isn't valid JSON, see [`validate_patch`](crate::validate_patch).
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValidationError {
+    /** An object at the JSON Pointer has a key that isn't a string */
+    NonStringKey { pointer: String },
+    /** An object at the JSON Pointer has the same key more than once */
+    DuplicateKey { pointer: String, key: String },
+    /** The number at the JSON Pointer is infinite or NaN */
+    NonFiniteNumber { pointer: String },
+    /** The patch failed to serialize */
+    Custom { message: String },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::NonStringKey { pointer } => {
+                write!(
+                    f,
+                    "object at JSON Pointer {pointer:?} has a key that isn't a string"
+                )
+            }
+            ValidationError::DuplicateKey { pointer, key } => {
+                write!(
+                    f,
+                    "object at JSON Pointer {pointer:?} has the key {key:?} more than once"
+                )
+            }
+            ValidationError::NonFiniteNumber { pointer } => {
+                write!(f, "number at JSON Pointer {pointer:?} isn't finite")
+            }
+            ValidationError::Custom { message } => f.write_str(message),
+        }
+    }
+}
+
+impl core::error::Error for ValidationError {}
+
+impl ser::Error for ValidationError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        ValidationError::Custom {
+            message: message.to_string(),
+        }
+    }
+}
+
+pub(crate) fn validate_patch<T: Serialize + ?Sized>(patch: &T) -> Result<(), ValidationError> {
+    patch.serialize(Checker {
+        path: &mut Vec::new(),
+    })
+}
+
+pub(crate) fn validate_applicability(document: &Value, patch: &Value) -> Vec<String> {
+    let mut no_ops = Vec::new();
+    let (Value::Object(document), Value::Object(patch)) = (document, patch) else {
+        if equal(document, patch) {
+            no_ops.push(String::new());
+        }
+        return no_ops;
+    };
+
+    let mut stack = vec![(String::new(), document, patch.iter())];
+    while let Some((path, document, members)) = stack.last_mut() {
+        let document = *document;
+        let Some((key, patch)) = members.next() else {
+            stack.pop();
+            continue;
+        };
+        let pointer = [path.as_str(), "/", &pointer::escape(key)].concat();
+
+        match (document.get(key), patch) {
+            (None, Value::Null) => no_ops.push(pointer),
+            (Some(Value::Object(before)), Value::Object(patch)) => {
+                stack.push((pointer, before, patch.iter()));
+            }
+            (Some(before), _) if equal(before, patch) => no_ops.push(pointer),
+            _ => {}
+        }
+    }
+    no_ops
+}
+
+pub(crate) fn lossy_nulls(after: &Value, patch: &Value) -> Vec<String> {
+    let mut lossy = Vec::new();
+    let (Value::Object(after), Value::Object(patch)) = (after, patch) else {
+        return lossy;
+    };
+
+    // Objects in the patch are merged, so any `null` in them removes a member,
+    // whether they're set as a whole or member by member
+    let mut stack = vec![(String::new(), Some(after), patch.iter())];
+    while let Some((path, after, members)) = stack.last_mut() {
+        let after = *after;
+        let Some((key, patch)) = members.next() else {
+            stack.pop();
+            continue;
+        };
+        let pointer = [path.as_str(), "/", &pointer::escape(key)].concat();
+        let after = after.and_then(|after| after.get(key));
+
+        match patch {
+            Value::Null if after.is_some() => lossy.push(pointer),
+            Value::Object(patch) => {
+                stack.push((pointer, after.and_then(Value::as_object), patch.iter()));
+            }
+            _ => {}
+        }
+    }
+    lossy
+}
+
+/**
+ * Serializer that checks that a value is valid JSON, keeping track of the
+ * reference tokens of the path to it.
+ */
+struct Checker<'p> {
+    path: &'p mut Vec<String>,
+}
+
+impl Checker<'_> {
+    fn pointer(&self) -> String {
+        self.path
+            .iter()
+            .map(|token| ["/", token].concat())
+            .collect()
+    }
+
+    fn reborrow(&mut self) -> Checker<'_> {
+        Checker { path: self.path }
+    }
+
+    /** Checks `value` as the member `key` of the current value */
+    fn member<T: Serialize + ?Sized>(
+        &mut self,
+        key: &str,
+        value: &T,
+    ) -> Result<(), ValidationError> {
+        self.path.push(pointer::escape(key).into_owned());
+        value.serialize(self.reborrow())?;
+        self.path.pop();
+        Ok(())
+    }
+}
+
+type Result<T, E = ValidationError> = core::result::Result<T, E>;
+
+impl<'p> ser::Serializer for Checker<'p> {
+    type Ok = ();
+    type Error = ValidationError;
+    type SerializeSeq = Seq<'p>;
+    type SerializeTuple = Seq<'p>;
+    type SerializeTupleStruct = Seq<'p>;
+    type SerializeTupleVariant = Seq<'p>;
+    type SerializeMap = Object<'p>;
+    type SerializeStruct = Object<'p>;
+    type SerializeStructVariant = Object<'p>;
+
+    fn serialize_bool(self, _: bool) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_i8(self, _: i8) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_i16(self, _: i16) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_i32(self, _: i32) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_i64(self, _: i64) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_i128(self, _: i128) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_u8(self, _: u8) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_u16(self, _: u16) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_u32(self, _: u32) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_u64(self, _: u64) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_u128(self, _: u128) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_f32(self, value: f32) -> Result<()> {
+        self.serialize_f64(value.into())
+    }
+
+    fn serialize_f64(self, value: f64) -> Result<()> {
+        if !value.is_finite() {
+            return Err(ValidationError::NonFiniteNumber {
+                pointer: self.pointer(),
+            });
+        }
+        Ok(())
+    }
+
+    fn serialize_char(self, _: char) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_str(self, _: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_bytes(self, _: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, _: &'static str, _: u32, _: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        mut self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.member(variant, value)
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Seq<'p>> {
+        Ok(Seq {
+            checker: self,
+            index: 0,
+            variant: false,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Seq<'p>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, len: usize) -> Result<Seq<'p>> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Seq<'p>> {
+        self.path.push(pointer::escape(variant).into_owned());
+        Ok(Seq {
+            checker: self,
+            index: 0,
+            variant: true,
+        })
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Object<'p>> {
+        Ok(Object {
+            checker: self,
+            keys: BTreeSet::new(),
+            key: None,
+            variant: false,
+        })
+    }
+
+    fn serialize_struct(self, _: &'static str, len: usize) -> Result<Object<'p>> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<Object<'p>> {
+        self.path.push(pointer::escape(variant).into_owned());
+        Ok(Object {
+            checker: self,
+            keys: BTreeSet::new(),
+            key: None,
+            variant: true,
+        })
+    }
+}
+
+struct Seq<'p> {
+    checker: Checker<'p>,
+    index: usize,
+    /** Whether the array is wrapped in an object of an enum variant */
+    variant: bool,
+}
+
+impl Seq<'_> {
+    fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.checker.path.push(self.index.to_string());
+        value.serialize(self.checker.reborrow())?;
+        self.checker.path.pop();
+        self.index += 1;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        if self.variant {
+            self.checker.path.pop();
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for Seq<'_> {
+    type Ok = ();
+    type Error = ValidationError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for Seq<'_> {
+    type Ok = ();
+    type Error = ValidationError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for Seq<'_> {
+    type Ok = ();
+    type Error = ValidationError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for Seq<'_> {
+    type Ok = ();
+    type Error = ValidationError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+struct Object<'p> {
+    checker: Checker<'p>,
+    keys: BTreeSet<String>,
+    /** Key of the member whose value is serialized next */
+    key: Option<String>,
+    /** Whether the object is wrapped in an object of an enum variant */
+    variant: bool,
+}
+
+impl Object<'_> {
+    fn field<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<()> {
+        if !self.keys.insert(key.to_owned()) {
+            return Err(ValidationError::DuplicateKey {
+                pointer: self.checker.pointer(),
+                key: key.to_owned(),
+            });
+        }
+        self.checker.member(key, value)
+    }
+
+    fn finish(self) -> Result<()> {
+        if self.variant {
+            self.checker.path.pop();
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for Object<'_> {
+    type Ok = ();
+    type Error = ValidationError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        match key.serialize(KeySerializer) {
+            Ok(key) => {
+                self.key = Some(key);
+                Ok(())
+            }
+            Err(_) => Err(ValidationError::NonStringKey {
+                pointer: self.checker.pointer(),
+            }),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| ser::Error::custom("map value without a key"))?;
+        self.field(&key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for Object<'_> {
+    type Ok = ();
+    type Error = ValidationError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for Object<'_> {
+    type Ok = ();
+    type Error = ValidationError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.field(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+/**
+ * Serializer for object keys, which only accepts strings.
+ */
+struct KeySerializer;
+
+macro_rules! not_a_string {
+    ($($method:ident($($arg:ty),*)),* $(,)?) => {
+        $(fn $method(self, $(_: $arg),*) -> Result<String> {
+            Err(ser::Error::custom("key isn't a string"))
+        })*
+    };
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = ValidationError;
+    type SerializeSeq = Impossible<String, ValidationError>;
+    type SerializeTuple = Impossible<String, ValidationError>;
+    type SerializeTupleStruct = Impossible<String, ValidationError>;
+    type SerializeTupleVariant = Impossible<String, ValidationError>;
+    type SerializeMap = Impossible<String, ValidationError>;
+    type SerializeStruct = Impossible<String, ValidationError>;
+    type SerializeStructVariant = Impossible<String, ValidationError>;
+
+    fn serialize_str(self, value: &str) -> Result<String> {
+        Ok(value.to_owned())
+    }
+
+    fn serialize_char(self, value: char) -> Result<String> {
+        Ok(value.to_string())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<String> {
+        Ok(variant.to_owned())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<String> {
+        value.serialize(self)
+    }
+
+    not_a_string! {
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(&'static str),
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _: &T) -> Result<String> {
+        Err(ser::Error::custom("key isn't a string"))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<String> {
+        Err(ser::Error::custom("key isn't a string"))
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(ser::Error::custom("key isn't a string"))
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple> {
+        Err(ser::Error::custom("key isn't a string"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(ser::Error::custom("key isn't a string"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(ser::Error::custom("key isn't a string"))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(ser::Error::custom("key isn't a string"))
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeStruct> {
+        Err(ser::Error::custom("key isn't a string"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(ser::Error::custom("key isn't a string"))
+    }
+}
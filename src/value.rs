@@ -0,0 +1,216 @@
+use alloc::borrow::ToOwned;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use serde_json::{Map, Value};
+
+/**
+ * The shape of a JSON value, as seen by the patch generation.
+ */
+pub enum Kind<'a, V: JsonLike> {
+    Null,
+    Bool,
+    Number,
+    String(&'a str),
+    Array(&'a [V]),
+    Object(&'a V::Object),
+}
+
+/**
+ * A JSON value representation that patches can be generated for, with
+ * [`generate`](crate::generate) and [`generate_with`](crate::generate_with).
+ *
+ * Implemented for [`serde_json::Value`], and for the values of other JSON
+ * crates behind their Cargo features. Scalars of the same kind are compared
+ * with [`PartialEq`] unless the [`Options`](crate::Options) ask for numeric or
+ * Unicode-aware equality.
+ */
+pub trait JsonLike: Clone + PartialEq + Sized {
+    /** The object representation, usually a map from keys to values */
+    type Object;
+    /** Iterator over the members of an object */
+    type Members<'a>: Iterator<Item = (&'a str, &'a Self)>
+    where
+        Self: 'a;
+
+    fn kind(&self) -> Kind<'_, Self>;
+
+    fn members(object: &Self::Object) -> Self::Members<'_>;
+    fn get<'a>(object: &'a Self::Object, key: &str) -> Option<&'a Self>;
+    fn len(object: &Self::Object) -> usize;
+
+    /** The number as `i64`, if it's an integer in range */
+    fn as_i64(&self) -> Option<i64>;
+    /** The number as `u64`, if it's an integer in range */
+    fn as_u64(&self) -> Option<u64>;
+    /** Any number as `f64`, even if that loses precision */
+    fn as_f64(&self) -> Option<f64>;
+
+    /**
+     * The exact decimal text of a number, for representations that keep it.
+     * Only used with the `arbitrary_precision` feature.
+     */
+    fn number_text(&self) -> Option<String> {
+        None
+    }
+
+    /**
+     * The length of the value as compact JSON, for representations that can
+     * tell without serializing it. Only used with the `metrics` feature.
+     */
+    fn serialized_size(&self) -> Option<usize> {
+        None
+    }
+
+    fn null() -> Self;
+    fn array(values: Vec<Self>) -> Self;
+    fn new_object() -> Self::Object;
+    /** Adds a member to an object built for a patch; keys are never repeated */
+    fn insert(object: &mut Self::Object, key: &str, value: Self);
+    fn object(object: Self::Object) -> Self;
+
+    /**
+     * A copy of the value to put in a patch. Defaults to a deep clone that
+     * doesn't recurse on the nesting depth; types whose clones share their
+     * contents can return a plain clone instead.
+     */
+    fn copy_for_patch(&self) -> Self {
+        crate::copy::deep_clone(self)
+    }
+
+    /**
+     * Whether the two values are known to be equal without comparing them,
+     * e.g. because they share their contents. Defaults to `false`.
+     */
+    fn shares_with(&self, other: &Self) -> bool {
+        let _ = other;
+        false
+    }
+
+    /**
+     * Sorts the members of the objects in the value by key, recursively, for
+     * [`Options::canonical`](crate::Options::canonical). Defaults to doing
+     * nothing, for types whose objects are always sorted or can't be.
+     */
+    fn sort_keys(&mut self) {}
+
+    fn is_object(&self) -> bool {
+        matches!(self.kind(), Kind::Object(_))
+    }
+
+    fn as_object(&self) -> Option<&Self::Object> {
+        match self.kind() {
+            Kind::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+}
+
+impl JsonLike for Value {
+    type Object = Map<String, Value>;
+    type Members<'a> = core::iter::Map<
+        serde_json::map::Iter<'a>,
+        fn((&'a String, &'a Value)) -> (&'a str, &'a Value),
+    >;
+
+    fn kind(&self) -> Kind<'_, Self> {
+        match self {
+            Value::Null => Kind::Null,
+            Value::Bool(_) => Kind::Bool,
+            Value::Number(_) => Kind::Number,
+            Value::String(string) => Kind::String(string),
+            Value::Array(array) => Kind::Array(array),
+            Value::Object(object) => Kind::Object(object),
+        }
+    }
+
+    fn members(object: &Map<String, Value>) -> Self::Members<'_> {
+        object.iter().map(|(key, value)| (key.as_str(), value))
+    }
+
+    fn get<'a>(object: &'a Map<String, Value>, key: &str) -> Option<&'a Self> {
+        object.get(key)
+    }
+
+    fn len(object: &Map<String, Value>) -> usize {
+        object.len()
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        Value::as_i64(self)
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        Value::as_u64(self)
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        Value::as_f64(self)
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    fn number_text(&self) -> Option<String> {
+        match self {
+            Value::Number(number) => Some(number.as_str().to_owned()),
+            _ => None,
+        }
+    }
+
+    fn serialized_size(&self) -> Option<usize> {
+        Some(crate::size::serialized_size(self))
+    }
+
+    fn null() -> Self {
+        Value::Null
+    }
+
+    fn array(values: Vec<Self>) -> Self {
+        Value::Array(values)
+    }
+
+    fn new_object() -> Map<String, Value> {
+        Map::new()
+    }
+
+    fn insert(object: &mut Map<String, Value>, key: &str, value: Self) {
+        object.insert(key.to_owned(), value);
+    }
+
+    fn object(object: Map<String, Value>) -> Self {
+        Value::Object(object)
+    }
+
+    fn sort_keys(&mut self) {
+        let mut stack = vec![self];
+        while let Some(value) = stack.pop() {
+            match value {
+                Value::Array(values) => stack.extend(values.iter_mut()),
+                Value::Object(members) => {
+                    members.sort_keys();
+                    stack.extend(members.values_mut());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/**
+ * The number of values that the patch sets or removes: its members that
+ * aren't objects, and empty objects.
+ */
+#[cfg(any(feature = "metrics", feature = "tracing"))]
+pub(crate) fn patch_changes<V: JsonLike>(patch: &V) -> usize {
+    let mut changes = 0;
+    let mut stack = vec![patch];
+    while let Some(value) = stack.pop() {
+        match value.kind() {
+            Kind::Object(object) if V::len(object) > 0 => {
+                stack.extend(V::members(object).map(|(_, value)| value))
+            }
+            _ => changes += 1,
+        }
+    }
+    changes
+}
@@ -0,0 +1,49 @@
+use crate::JsonLike;
+
+/**
+ * Callbacks for the changes found while generating a patch, see
+ * [`generate_visited`](crate::generate_visited), e.g. to log or count them.
+ * Each is called with the JSON Pointer of the change and returns whether to
+ * keep it in the patch, which the defaults do.
+ *
+ * Changes are reported as they end up in the patch: an array that changed is
+ * a modification of the whole array, and so is an object emitted as a whole
+ * at [`Options::max_depth`](crate::Options::max_depth). A document that isn't
+ * an object is always reported as modified, as its patch is `after`. Objects
+ * are only replaced as a whole for
+ * [`Options::replace_changed_objects`](crate::Options::replace_changed_objects)
+ * if none of their changes were left out.
+ */
+pub trait Visitor<V: JsonLike = serde_json::Value> {
+    /** A member that's only in `after` */
+    fn on_added(&mut self, pointer: &str, after: &V) -> bool {
+        let _ = (pointer, after);
+        true
+    }
+
+    /** A member that's only in `before`, which the patch sets to `null` */
+    fn on_removed(&mut self, pointer: &str, before: &V) -> bool {
+        let _ = (pointer, before);
+        true
+    }
+
+    /** A value that's replaced by `after` */
+    fn on_modified(&mut self, pointer: &str, before: &V, after: &V) -> bool {
+        let _ = (pointer, before, after);
+        true
+    }
+}
+
+impl<V: JsonLike, T: Visitor<V> + ?Sized> Visitor<V> for &mut T {
+    fn on_added(&mut self, pointer: &str, after: &V) -> bool {
+        (**self).on_added(pointer, after)
+    }
+
+    fn on_removed(&mut self, pointer: &str, before: &V) -> bool {
+        (**self).on_removed(pointer, before)
+    }
+
+    fn on_modified(&mut self, pointer: &str, before: &V, after: &V) -> bool {
+        (**self).on_modified(pointer, before, after)
+    }
+}
@@ -0,0 +1,48 @@
+/*!
+ * JavaScript bindings through [wasm-bindgen](https://docs.rs/wasm-bindgen),
+ * so browsers and Node.js get the same patches as Rust. Documents and patches
+ * are plain JavaScript values, as from `JSON.parse`.
+ *
+ * The `wasm` crate in the repository builds them into an npm package with
+ * wasm-pack.
+ */
+
+use serde::Serialize;
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+use crate::plain::PlainNumbers;
+
+/**
+ * The merge patch that turns `before` into `after`, like
+ * [`generate`](crate::generate), or `undefined` if they're equal.
+ */
+#[wasm_bindgen]
+pub fn generate(before: JsValue, after: JsValue) -> Result<JsValue, JsValue> {
+    let before: Value = serde_wasm_bindgen::from_value(before)?;
+    let after: Value = serde_wasm_bindgen::from_value(after)?;
+    match crate::generate(&before, &after) {
+        Some(patch) => to_js(&patch),
+        None => Ok(JsValue::UNDEFINED),
+    }
+}
+
+/**
+ * A copy of `document` with `patch` applied, like [`apply`](crate::apply).
+ */
+#[wasm_bindgen]
+pub fn apply(document: JsValue, patch: JsValue) -> Result<JsValue, JsValue> {
+    let mut document: Value = serde_wasm_bindgen::from_value(document)?;
+    let patch: Value = serde_wasm_bindgen::from_value(patch)?;
+    crate::apply(&mut document, &patch);
+    to_js(&document)
+}
+
+/**
+ * Converts to plain objects rather than `Map`s, which serde-wasm-bindgen
+ * produces by default.
+ */
+fn to_js(value: &Value) -> Result<JsValue, JsValue> {
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    Ok(PlainNumbers(value).serialize(&serializer)?)
+}
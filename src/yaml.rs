@@ -0,0 +1,60 @@
+use serde_json::Value;
+
+use crate::plain::PlainNumbers;
+use crate::{Error, Options};
+
+/**
+ * Generates a JSON Merge Patch (RFC 7386) like [`generate`](crate::generate),
+ * between two YAML documents. The patch can be rendered as JSON with
+ * serde_json, or as YAML with [`to_yaml`].
+ *
+ * The documents must be representable as JSON, e.g. mapping keys must be
+ * scalars, which become strings.
+ */
+pub fn generate_yaml(before: &str, after: &str) -> Result<Option<Value>, Error> {
+    generate_yaml_with(before, after, &Options::default())
+}
+
+/**
+ * [`generate_yaml`] using the given [`Options`].
+ */
+pub fn generate_yaml_with(
+    before: &str,
+    after: &str,
+    options: &Options,
+) -> Result<Option<Value>, Error> {
+    let before = parse("before", before)?;
+    let after = parse("after", after)?;
+    Ok(crate::generate_with(&before, &after, options))
+}
+
+/**
+ * Renders a patch, or any document, as YAML. Numbers that don't fit a
+ * primitive type, kept with the `arbitrary_precision` feature, are rounded to
+ * the nearest `f64`.
+ */
+pub fn to_yaml(value: &Value) -> String {
+    // The .unwrap() is safe because every JSON value can be rendered as YAML
+    serde_yaml::to_string(&PlainNumbers(value)).unwrap()
+}
+
+fn parse(document: &'static str, yaml: &str) -> Result<Value, Error> {
+    serde_yaml::from_str(yaml).map_err(|error| {
+        let location = error
+            .location()
+            .map(|location| (location.line(), location.column()));
+        let message = error.to_string();
+        let message = match location {
+            Some((line, column)) => {
+                let suffix = format!(" at line {line} column {column}");
+                message.strip_suffix(&suffix).unwrap_or(&message).to_owned()
+            }
+            None => message,
+        };
+        Error::Yaml {
+            document,
+            location,
+            message,
+        }
+    })
+}
@@ -0,0 +1,6 @@
+/*!
+ * The npm package of the JavaScript bindings of json_merge_patch_gen, built
+ * with `wasm-pack build wasm`.
+ */
+
+pub use json_merge_patch_gen::wasm::{apply, generate};